@@ -1,11 +1,14 @@
 use anyhow::{Context, Result};
 use clap::Args;
 use colored::Colorize;
-use llm_test_bench_core::benchmarks::{BenchmarkConfig, BenchmarkRunner, CsvExporter};
+use llm_test_bench_core::benchmarks::{BenchmarkConfig, BenchmarkRunner, CsvExporter, PromptCacheResults};
 use llm_test_bench_core::config::ConfigLoader;
-use llm_test_bench_core::providers::ProviderFactory;
+use llm_test_bench_core::providers::{BatchProvider, CachedProvider, CassetteMode, ProviderFactory};
 use llm_test_bench_datasets::loader::DatasetLoader;
 use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::cassette::{self, CassetteArgs};
 
 #[derive(Args, Debug)]
 pub struct BenchArgs {
@@ -41,6 +44,10 @@ pub struct BenchArgs {
     #[arg(long)]
     pub delay: Option<u64>,
 
+    /// Stream responses and record time to first token and throughput
+    #[arg(long)]
+    pub stream: bool,
+
     /// Path to custom configuration file
     #[arg(long)]
     pub config: Option<PathBuf>,
@@ -60,6 +67,29 @@ pub struct BenchArgs {
     /// Generate HTML dashboard after benchmark
     #[arg(long)]
     pub dashboard: bool,
+
+    /// Cache responses to temperature 0 requests on disk and reuse them on later runs
+    #[arg(long)]
+    pub cache: bool,
+
+    /// Also cache sampled responses (temperature > 0 or the provider default); implies --cache
+    #[arg(long)]
+    pub cache_all: bool,
+
+    #[command(flatten)]
+    pub cassette: CassetteArgs,
+
+    /// Run through the providers' batch APIs (openai, anthropic): cheaper,
+    /// but results can take up to 24 hours. An interrupted run resumes its
+    /// batch when started again with the same output directory
+    #[arg(long, conflicts_with_all = ["stream", "cache", "cache_all", "delay", "record", "replay"])]
+    pub batch: bool,
+
+    /// Measure prompt caching: send every test twice after the contents of
+    /// this file as a shared, cacheable system prompt, and compare the cold
+    /// and warm latency and cost
+    #[arg(long, value_name = "PREFIX_FILE", conflicts_with_all = ["batch", "cache", "cache_all"])]
+    pub prompt_cache: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, clap::ValueEnum)]
@@ -96,6 +126,9 @@ pub async fn execute(args: BenchArgs, verbose: bool) -> Result<()> {
         if args.dashboard {
             println!("  Generate dashboard: Yes");
         }
+        if let Some(ref prefix) = args.prompt_cache {
+            println!("  Prompt cache prefix: {}", prefix.display());
+        }
         println!();
     }
 
@@ -124,6 +157,24 @@ pub async fn execute(args: BenchArgs, verbose: bool) -> Result<()> {
     let config = config_loader.load()
         .context("Failed to load configuration")?;
 
+    let session = args.cassette.open()?;
+    if let Some(ref session) = session {
+        let action = match session.mode() {
+            CassetteMode::Record => "Recording to",
+            CassetteMode::Replay => "Replaying from",
+        };
+        println!("  {} {} cassette {}", "ℹ".blue(), action, session.cassette().path().display());
+        println!();
+    }
+
+    let prefix = match args.prompt_cache {
+        Some(ref path) => Some(
+            std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read prompt cache prefix: {}", path.display()))?,
+        ),
+        None => None,
+    };
+
     // Step 3: Create output directory
     std::fs::create_dir_all(&args.output)
         .context("Failed to create output directory")?;
@@ -141,13 +192,7 @@ pub async fn execute(args: BenchArgs, verbose: bool) -> Result<()> {
         let provider_config = config.providers.get(provider_name)
             .ok_or_else(|| anyhow::anyhow!("Provider '{}' not found in configuration", provider_name))?;
 
-        // Create provider instance
-        let factory = ProviderFactory::new();
-        let provider = factory.create_shared(provider_name, provider_config)
-            .context(format!("Failed to create provider: {}", provider_name))?;
-
         if verbose {
-            println!("  Provider: {}", provider.name());
             println!("  Default model: {}", provider_config.default_model);
         }
 
@@ -159,6 +204,8 @@ pub async fn execute(args: BenchArgs, verbose: bool) -> Result<()> {
             continue_on_failure: args.continue_on_failure,
             random_seed: None,
             request_delay_ms: args.delay,
+            streaming: args.stream,
+            ..BenchmarkConfig::default()
         };
 
         // Validate benchmark configuration
@@ -171,9 +218,37 @@ pub async fn execute(args: BenchArgs, verbose: bool) -> Result<()> {
             .context("Failed to create provider output directory")?;
 
         // Run benchmark
+        let factory = ProviderFactory::new();
         let runner = BenchmarkRunner::new(bench_config);
-        let results = runner.run(&dataset, provider).await
-            .context(format!("Benchmark failed for provider: {}", provider_name))?;
+        let results = if args.batch {
+            let provider: Arc<dyn BatchProvider> = factory.create_batch(provider_name, provider_config)
+                .context(format!("Failed to create batch provider: {}", provider_name))?
+                .into();
+            runner.run_batch(&dataset, provider).await
+        } else {
+            let mut provider = cassette::create_provider(session.as_ref(), provider_name, || {
+                Ok(factory.create_with_fallbacks(provider_name, &config.providers)?)
+            })
+            .context(format!("Failed to create provider: {}", provider_name))?;
+            if args.cache || args.cache_all {
+                provider = Box::new(CachedProvider::new(provider).force(args.cache_all));
+            }
+
+            if let Some(ref prefix) = prefix {
+                let results = runner.run_prompt_cache(&dataset, provider.into(), prefix).await
+                    .context(format!("Benchmark failed for provider: {}", provider_name))?;
+
+                export_results(&results.cold, &args.output, &format!("{}-cold", provider_name), &args.export)?;
+                export_results(&results.warm, &args.output, &format!("{}-warm", provider_name), &args.export)?;
+                print_summary(&results.cold, &format!("{} (cold)", provider_name));
+                print_summary(&results.warm, &format!("{} (warm)", provider_name));
+                print_prompt_cache_summary(&results);
+                println!();
+                continue;
+            }
+            runner.run(&dataset, provider.into()).await
+        }
+        .context(format!("Benchmark failed for provider: {}", provider_name))?;
 
         // Export results
         export_results(&results, &args.output, provider_name, &args.export)?;
@@ -183,6 +258,10 @@ pub async fn execute(args: BenchArgs, verbose: bool) -> Result<()> {
         println!();
     }
 
+    if let Some(ref session) = session {
+        session.finish()?;
+    }
+
     // Run evaluations if metrics specified
     if let Some(ref metrics) = args.metrics {
         println!();
@@ -305,6 +384,23 @@ fn print_summary(
         summary.p99_duration_ms
     );
 
+    if let Some(streaming) = &summary.streaming {
+        println!("  {} Avg TTFT:     {:.0}ms (p50 {:.0}ms, p95 {:.0}ms)",
+            "⏱".cyan(),
+            streaming.avg_ttft_ms,
+            streaming.p50_ttft_ms,
+            streaming.p95_ttft_ms
+        );
+        println!("  {} Inter-token:  {:.1}ms",
+            "ℹ".blue(),
+            streaming.avg_inter_token_latency_ms
+        );
+        println!("  {} Throughput:   {:.1} tokens/s",
+            "ℹ".blue(),
+            streaming.avg_tokens_per_second
+        );
+    }
+
     println!();
 
     // Token usage and cost
@@ -312,6 +408,24 @@ fn print_summary(
         "💰".to_string(),
         summary.total_tokens.to_string().yellow()
     );
+    if summary.total_reasoning_tokens > 0 {
+        println!("  {} Reasoning:    {}",
+            "💰".to_string(),
+            summary.total_reasoning_tokens.to_string().yellow()
+        );
+    }
+    if summary.total_cached_tokens > 0 {
+        println!("  {} Cached:       {}",
+            "💰".to_string(),
+            summary.total_cached_tokens.to_string().yellow()
+        );
+    }
+    if summary.total_cache_write_tokens > 0 {
+        println!("  {} Cache Writes: {}",
+            "💰".to_string(),
+            summary.total_cache_write_tokens.to_string().yellow()
+        );
+    }
     println!("  {} Est. Cost:    ${:.4}",
         "💰".to_string(),
         summary.total_cost.to_string().green()
@@ -320,6 +434,45 @@ fn print_summary(
     println!("{}", "─".repeat(60).dimmed());
 }
 
+/// Print how warm requests compare to cold ones
+fn print_prompt_cache_summary(results: &PromptCacheResults) {
+    let summary = &results.summary;
+
+    println!();
+    println!("{}", "Prompt caching:".bold());
+    println!("{}", "─".repeat(60).dimmed());
+
+    println!("  {} Prefix:       ~{} tokens",
+        "ℹ".blue(),
+        results.prefix_tokens
+    );
+    println!("  {} Cache Hits:   {:.1}% of warm prompt tokens",
+        "ℹ".blue(),
+        summary.cache_hit_rate * 100.0
+    );
+    println!("  {} P50 Latency:  {:.0}ms cold → {:.0}ms warm ({:.1}% faster)",
+        "⏱".cyan(),
+        summary.cold_p50_ms,
+        summary.warm_p50_ms,
+        summary.latency_reduction * 100.0
+    );
+    if let (Some(cold), Some(warm)) = (summary.cold_p50_ttft_ms, summary.warm_p50_ttft_ms) {
+        println!("  {} P50 TTFT:     {:.0}ms cold → {:.0}ms warm",
+            "⏱".cyan(),
+            cold,
+            warm
+        );
+    }
+    println!("  {} Est. Cost:    ${:.4} cold → ${:.4} warm ({:.1}% cheaper)",
+        "💰".to_string(),
+        summary.cold_cost,
+        summary.warm_cost,
+        summary.cost_reduction * 100.0
+    );
+
+    println!("{}", "─".repeat(60).dimmed());
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -335,7 +488,17 @@ mod tests {
             continue_on_failure: true,
             save_responses: true,
             delay: None,
+            stream: false,
             config: None,
+            metrics: None,
+            judge_model: None,
+            judge_provider: None,
+            dashboard: false,
+            cache: false,
+            cache_all: false,
+            cassette: CassetteArgs::default(),
+            batch: false,
+            prompt_cache: None,
         };
 
         assert_eq!(args.concurrency, 5);
@@ -0,0 +1,120 @@
+//! `--record` / `--replay` options shared by commands that call providers
+
+use anyhow::{Context, Result};
+use clap::Args;
+use llm_test_bench_core::providers::{Cassette, CassetteMode, CassetteProvider, Provider};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct CassetteArgs {
+    /// Record provider requests and responses to this cassette file
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Answer provider requests from this cassette file without network access
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+}
+
+impl CassetteArgs {
+    /// Opens the cassette named on the command line, if any
+    pub fn open(&self) -> Result<Option<CassetteSession>> {
+        let session = match (&self.record, &self.replay) {
+            (Some(path), _) => CassetteSession {
+                mode: CassetteMode::Record,
+                cassette: Arc::new(
+                    Cassette::open(path)
+                        .with_context(|| format!("Failed to open cassette: {}", path.display()))?,
+                ),
+            },
+            (None, Some(path)) => CassetteSession {
+                mode: CassetteMode::Replay,
+                cassette: Arc::new(
+                    Cassette::load(path)
+                        .with_context(|| format!("Failed to load cassette: {}", path.display()))?,
+                ),
+            },
+            (None, None) => return Ok(None),
+        };
+
+        Ok(Some(session))
+    }
+}
+
+/// A cassette opened for the duration of one command
+pub struct CassetteSession {
+    mode: CassetteMode,
+    cassette: Arc<Cassette>,
+}
+
+impl CassetteSession {
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    pub fn cassette(&self) -> &Arc<Cassette> {
+        &self.cassette
+    }
+
+    /// Writes the recordings made during the command
+    pub fn finish(&self) -> Result<()> {
+        self.cassette.save().context("Failed to save cassette")
+    }
+}
+
+/// Creates the provider called `name`, recording or replaying it when a
+/// cassette is in use. `create` is not called in replay mode, so replays
+/// need neither network access nor API keys.
+pub fn create_provider<F>(
+    session: Option<&CassetteSession>,
+    name: &str,
+    create: F,
+) -> Result<Box<dyn Provider>>
+where
+    F: FnOnce() -> Result<Box<dyn Provider>>,
+{
+    match session {
+        Some(session) if session.mode == CassetteMode::Replay => Ok(Box::new(CassetteProvider::replay(
+            name,
+            Arc::clone(&session.cassette),
+        ))),
+        Some(session) => Ok(Box::new(CassetteProvider::record(
+            name,
+            create()?,
+            Arc::clone(&session.cassette),
+        ))),
+        None => create(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser, Debug)]
+    struct TestCli {
+        #[command(flatten)]
+        cassette: CassetteArgs,
+    }
+
+    #[test]
+    fn test_record_conflicts_with_replay() {
+        let result = TestCli::try_parse_from(["test", "--record", "a.json", "--replay", "b.json"]);
+        assert!(result.is_err());
+
+        let cli = TestCli::try_parse_from(["test", "--replay", "b.json"]).unwrap();
+        assert_eq!(cli.cassette.replay, Some(PathBuf::from("b.json")));
+    }
+
+    #[test]
+    fn test_replay_requires_existing_cassette() {
+        let args = CassetteArgs {
+            record: None,
+            replay: Some(PathBuf::from("/nonexistent/cassette.json")),
+        };
+        assert!(args.open().is_err());
+        assert!(CassetteArgs::default().open().unwrap().is_none());
+    }
+}
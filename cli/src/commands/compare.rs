@@ -3,6 +3,7 @@ use clap::Args;
 use colored::Colorize;
 use llm_test_bench_core::config::{Config, ConfigLoader};
 use llm_test_bench_core::providers::{ProviderFactory, CompletionRequest};
+use super::cassette::{self, CassetteArgs, CassetteSession};
 use llm_test_bench_datasets::loader::DatasetLoader;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
@@ -50,6 +51,9 @@ pub struct CompareArgs {
     /// Maximum concurrent comparisons
     #[arg(long, default_value = "5")]
     pub concurrency: usize,
+
+    #[command(flatten)]
+    pub cassette: CassetteArgs,
 }
 
 #[derive(Debug, Clone, clap::ValueEnum)]
@@ -120,14 +124,20 @@ pub async fn execute(args: CompareArgs, verbose: bool) -> Result<()> {
     // Parse model specifications
     let model_specs = parse_model_specs(&args.models)?;
 
+    // Open the cassette once so every provider shares it
+    let session = args.cassette.open()?;
+
     // Run comparison based on input type
     let reports = if let Some(ref prompt) = args.prompt {
-        vec![run_single_comparison(prompt, &model_specs, &args, &config, verbose).await?]
+        vec![run_single_comparison(prompt, &model_specs, &args, &config, session.as_ref(), verbose).await?]
     } else if let Some(ref dataset_path) = args.dataset {
-        run_batch_comparison(dataset_path, &model_specs, &args, &config, verbose).await?
+        run_batch_comparison(dataset_path, &model_specs, &args, &config, session.as_ref(), verbose).await?
     } else {
         unreachable!()
     };
+    if let Some(session) = session {
+        session.finish()?;
+    }
 
     // Output results
     display_results(&reports, &args, verbose)?;
@@ -176,6 +186,7 @@ async fn run_single_comparison(
     model_specs: &[(String, String)],
     args: &CompareArgs,
     config: &Config,
+    session: Option<&CassetteSession>,
     verbose: bool,
 ) -> Result<ComparisonReport> {
     println!("{} Running comparison for prompt...", "▶".green());
@@ -198,16 +209,11 @@ async fn run_single_comparison(
 
         let start = Instant::now();
 
-        // Get provider configuration
-        let provider_config = config
-            .providers
-            .get(provider_name)
-            .ok_or_else(|| anyhow::anyhow!("Provider '{}' not found in configuration", provider_name))?;
-
-        // Create provider instance
-        let provider = factory
-            .create_shared(provider_name, provider_config)
-            .context(format!("Failed to create provider: {}", provider_name))?;
+        // Create provider instance with its fallback chain
+        let provider = cassette::create_provider(session, provider_name, || {
+            Ok(factory.create_with_fallbacks(provider_name, &config.providers)?)
+        })
+        .context(format!("Failed to create provider: {}", provider_name))?;
 
         // Execute request
         let request = CompletionRequest {
@@ -218,6 +224,21 @@ async fn run_single_comparison(
             top_p: None,
             stop: None,
             stream: false,
+            system: None,
+            messages: Vec::new(),
+            tools: Vec::new(),
+            tool_choice: None,
+            response_format: None,
+            logprobs: None,
+            seed: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+            top_k: None,
+            n: None,
+            logit_bias: std::collections::HashMap::new(),
+            reasoning_effort: None,
+            thinking_budget: None,
+            cache_points: Vec::new(),
         };
 
         let result = match provider.complete(request).await {
@@ -291,6 +312,7 @@ async fn run_batch_comparison(
     model_specs: &[(String, String)],
     args: &CompareArgs,
     config: &Config,
+    session: Option<&CassetteSession>,
     verbose: bool,
 ) -> Result<Vec<ComparisonReport>> {
     println!("{} Loading dataset...", "▶".green());
@@ -311,6 +333,7 @@ async fn run_batch_comparison(
             model_specs,
             args,
             config,
+            session,
             false, // Don't be verbose in batch mode
         )
         .await?;
@@ -622,6 +645,7 @@ mod tests {
             dashboard: false,
             config: None,
             concurrency: 5,
+            cassette: CassetteArgs::default(),
         };
 
         // Should have at least 2 models
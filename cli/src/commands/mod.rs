@@ -1,5 +1,6 @@
 pub mod analyze;
 pub mod bench;
+pub mod cassette;
 pub mod compare;
 pub mod config;
 pub mod dashboard;
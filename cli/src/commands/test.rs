@@ -4,22 +4,28 @@ use futures::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
 use llm_test_bench_core::config::ConfigLoader;
 use llm_test_bench_core::providers::{
-    AnthropicProvider, CompletionRequest, OpenAIProvider, Provider, ProviderError,
+    CompletionRequest, Provider, ProviderError, ProviderFactory, ReasoningEffort, ResponseFormat, StreamEventKind,
 };
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::time::Instant;
 
+use super::cassette::{self, CassetteArgs};
 use crate::output::{display_error, display_response, OutputFormat, StreamingOutput};
 
 #[derive(Args, Debug)]
 pub struct TestArgs {
-    /// LLM provider to use (openai, anthropic)
+    /// LLM provider to use (openai, anthropic, mock, ...)
     pub provider: String,
 
     /// Prompt to send to the LLM
     #[arg(short, long)]
     pub prompt: String,
 
+    /// System prompt to send ahead of the user prompt
+    #[arg(long)]
+    pub system: Option<String>,
+
     /// Model to use for the test
     #[arg(short, long)]
     pub model: Option<String>,
@@ -40,10 +46,26 @@ pub struct TestArgs {
     #[arg(long)]
     pub stop: Option<Vec<String>>,
 
+    /// Reasoning effort for reasoning models (low, medium, high)
+    #[arg(long)]
+    pub reasoning_effort: Option<ReasoningEffort>,
+
+    /// Maximum tokens a reasoning model may spend thinking
+    #[arg(long)]
+    pub thinking_budget: Option<u32>,
+
     /// Enable streaming mode
     #[arg(short, long)]
     pub stream: bool,
 
+    /// Require the response to be a JSON object
+    #[arg(long, conflicts_with = "json_schema")]
+    pub json: bool,
+
+    /// Require the response to match the JSON Schema in this file
+    #[arg(long, value_name = "FILE")]
+    pub json_schema: Option<PathBuf>,
+
     /// Output format
     #[arg(short, long, value_enum, default_value = "pretty")]
     pub output_format: OutputFormat,
@@ -51,6 +73,9 @@ pub struct TestArgs {
     /// Path to configuration file
     #[arg(short, long)]
     pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub cassette: CassetteArgs,
 }
 
 /// Create a provider instance from configuration
@@ -66,29 +91,10 @@ fn create_provider(
 
     let config = loader.load().context("Failed to load configuration")?;
 
-    // Get provider config
-    let provider_config = config
-        .providers
-        .get(provider_name)
-        .ok_or_else(|| anyhow!("Provider '{}' not found in configuration", provider_name))?;
-
-    // Get API key from environment
-    let api_key = std::env::var(&provider_config.api_key_env)
-        .with_context(|| format!("Environment variable '{}' not set", provider_config.api_key_env))?;
-
-    // Create provider instance
-    match provider_name {
-        "openai" => {
-            let provider = OpenAIProvider::with_base_url(api_key, provider_config.base_url.clone())
-                .context("Failed to create OpenAI provider")?;
-            Ok(Box::new(provider))
-        }
-        "anthropic" => {
-            let provider = AnthropicProvider::with_base_url(api_key, provider_config.base_url.clone());
-            Ok(Box::new(provider))
-        }
-        _ => Err(anyhow!("Unknown provider: {}", provider_name)),
-    }
+    // Build the provider with its fallback chain, if any
+    ProviderFactory::new()
+        .create_with_fallbacks(provider_name, &config.providers)
+        .map_err(map_provider_error)
 }
 
 /// Build a completion request from command arguments
@@ -121,6 +127,22 @@ fn build_completion_request(
         ));
     }
 
+    let response_format = match args.json_schema {
+        Some(ref path) => {
+            let schema = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read JSON schema: {}", path.display()))?;
+            let schema = serde_json::from_str(&schema)
+                .with_context(|| format!("Invalid JSON schema: {}", path.display()))?;
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "response".to_string());
+            Some(ResponseFormat::json_schema(name, schema))
+        }
+        None if args.json => Some(ResponseFormat::JsonObject),
+        None => None,
+    };
+
     Ok(CompletionRequest {
         prompt: args.prompt.clone(),
         model,
@@ -129,6 +151,21 @@ fn build_completion_request(
         top_p: args.top_p,
         stop: args.stop.clone(),
         stream: args.stream,
+        system: args.system.clone(),
+        messages: Vec::new(),
+        tools: Vec::new(),
+        tool_choice: None,
+        response_format,
+        logprobs: None,
+        seed: None,
+        presence_penalty: None,
+        frequency_penalty: None,
+        top_k: None,
+        n: None,
+        logit_bias: HashMap::new(),
+        reasoning_effort: args.reasoning_effort,
+        thinking_budget: args.thinking_budget,
+        cache_points: Vec::new(),
     })
 }
 
@@ -186,17 +223,22 @@ async fn execute_streaming(
     output.display_header(&model);
 
     let mut is_first = true;
+    let mut finish_reason = None;
 
-    while let Some(chunk_result) = stream.next().await {
-        let chunk = chunk_result.map_err(map_provider_error)?;
+    while let Some(event_result) = stream.next().await {
+        let event = event_result.map_err(map_provider_error)?;
 
-        if !chunk.is_empty() {
-            output.display_chunk(&chunk, is_first)?;
-            is_first = false;
+        match event.kind {
+            StreamEventKind::ContentDelta { text } if !text.is_empty() => {
+                output.display_chunk(&text, is_first)?;
+                is_first = false;
+            }
+            StreamEventKind::Finish { reason, .. } => finish_reason = Some(reason.to_string()),
+            _ => {}
         }
     }
 
-    output.display_footer(None);
+    output.display_footer(finish_reason.as_deref());
 
     Ok(())
 }
@@ -253,8 +295,11 @@ pub async fn execute(args: TestArgs, verbose: bool) -> Result<()> {
     }
 
     // Create provider
-    let provider = create_provider(&args.provider, &args.config)
-        .with_context(|| format!("Failed to initialize {} provider", args.provider))?;
+    let session = args.cassette.open()?;
+    let provider = cassette::create_provider(session.as_ref(), &args.provider, || {
+        create_provider(&args.provider, &args.config)
+    })
+    .with_context(|| format!("Failed to initialize {} provider", args.provider))?;
 
     // Build request
     let request = build_completion_request(&args, &provider)?;
@@ -270,6 +315,10 @@ pub async fn execute(args: TestArgs, verbose: bool) -> Result<()> {
         execute_non_streaming(&provider, request, args.output_format).await?;
     }
 
+    if let Some(session) = session {
+        session.finish()?;
+    }
+
     Ok(())
 }
 
@@ -295,4 +344,43 @@ mod tests {
         assert!(!(0.0..=1.0).contains(&1.1));
         assert!(!(0.0..=1.0).contains(&-0.1));
     }
+
+    #[tokio::test]
+    async fn test_create_provider_follows_fallback_chain() {
+        use llm_test_bench_core::providers::fallback::SERVED_BY;
+
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("flaky.yaml");
+        std::fs::write(&script, "errors: { rate_limit: 1.0 }\n").unwrap();
+        let config = dir.path().join("config.toml");
+        std::fs::write(
+            &config,
+            format!(
+                r#"
+[providers.flaky]
+kind = "mock"
+script = "{}"
+base_url = "http://localhost"
+default_model = "mock-model"
+timeout_seconds = 30
+max_retries = 0
+fallback = ["backup"]
+
+[providers.backup]
+kind = "mock"
+base_url = "http://localhost"
+default_model = "backup-model"
+timeout_seconds = 30
+max_retries = 0
+"#,
+                script.display()
+            ),
+        )
+        .unwrap();
+
+        let provider = create_provider("flaky", &Some(config)).unwrap();
+        let response = provider.complete(CompletionRequest::new("mock-model", "Hi")).await.unwrap();
+        assert_eq!(response.metadata.get(SERVED_BY).map(String::as_str), Some("backup"));
+        assert_eq!(response.model, "backup-model");
+    }
 }
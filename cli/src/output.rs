@@ -63,7 +63,23 @@ fn display_pretty(response: &CompletionResponse) -> Result<()> {
         response.usage.completion_tokens.to_string().yellow(),
         response.usage.total_tokens.to_string().green().bold()
     );
+    let usage = &response.usage;
+    if usage.reasoning_tokens > 0 || usage.cached_tokens > 0 || usage.cache_write_tokens > 0 {
+        println!(
+            "{} {} reasoning, {} cached prompt, {} written to cache",
+            "Token Details:".bright_cyan(),
+            usage.reasoning_tokens.to_string().yellow(),
+            usage.cached_tokens.to_string().yellow(),
+            usage.cache_write_tokens.to_string().yellow()
+        );
+    }
     println!("{} {}", "Created:".bright_cyan(), response.created_at.format("%Y-%m-%d %H:%M:%S UTC"));
+    if !response.schema_violations.is_empty() {
+        println!("{}", "Schema Violations:".bright_red());
+        for violation in &response.schema_violations {
+            println!("  - {}", violation);
+        }
+    }
     println!("{}", "━".repeat(80).bright_blue());
     println!();
 
@@ -191,6 +207,7 @@ pub fn display_error(error: &str, suggestion: Option<&str>) {
 mod tests {
     use super::*;
     use llm_test_bench_core::providers::{FinishReason, TokenUsage};
+    use std::collections::HashMap;
 
     fn create_test_response() -> CompletionResponse {
         CompletionResponse {
@@ -201,10 +218,18 @@ mod tests {
                 prompt_tokens: 10,
                 completion_tokens: 5,
                 total_tokens: 15,
+                reasoning_tokens: 0,
+                cached_tokens: 0,
+                cache_write_tokens: 0,
             },
             finish_reason: FinishReason::Stop,
             created_at: chrono::Utc::now(),
-            metadata: serde_json::Value::Null,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
         }
     }
 
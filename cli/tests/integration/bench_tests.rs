@@ -1,6 +1,7 @@
 // Integration tests for bench command
 use assert_cmd::Command;
 use predicates::prelude::*;
+use std::collections::HashMap;
 use std::fs;
 use tempfile::TempDir;
 
@@ -264,6 +265,7 @@ mod result_tests {
     };
     use llm_test_bench_core::providers::{CompletionResponse, FinishReason, TokenUsage};
     use chrono::Utc;
+    use std::collections::HashMap;
     use std::time::Duration;
 
     fn create_test_response() -> CompletionResponse {
@@ -274,6 +276,12 @@ mod result_tests {
             usage: TokenUsage::new(10, 5),
             finish_reason: FinishReason::Stop,
             created_at: Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         }
     }
 
@@ -343,6 +351,12 @@ fn test_csv_exporter() {
         usage: TokenUsage::new(10, 5),
         finish_reason: FinishReason::Stop,
         created_at: Utc::now(),
+        tool_calls: Vec::new(),
+        schema_violations: Vec::new(),
+        logprobs: None,
+        prompt_logprobs: None,
+        choices: Vec::new(),
+        metadata: HashMap::new(),
     };
 
     let results = vec![TestResult::success(
@@ -99,6 +99,11 @@ fn create_demo_benchmark_results() -> BenchmarkResults {
             usage: TokenUsage::new(prompt_tokens, completion_tokens),
             finish_reason: FinishReason::Stop,
             created_at: chrono::Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            metadata: HashMap::new(),
         };
 
         results.push(TestResult::success(
@@ -169,6 +174,11 @@ fn create_model_results(
             usage: TokenUsage::new(prompt_tokens, prompt_tokens / 2),
             finish_reason: FinishReason::Stop,
             created_at: chrono::Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            metadata: HashMap::new(),
         };
 
         results.push(TestResult::success(
@@ -581,6 +581,12 @@ mod tests {
             usage: TokenUsage::new(prompt_tokens, completion_tokens),
             finish_reason: FinishReason::Stop,
             created_at: Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         };
 
         TestResult::success(
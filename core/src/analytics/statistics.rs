@@ -701,6 +701,7 @@ fn interpret_results(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
 
     #[test]
     fn test_mean() {
@@ -842,6 +843,12 @@ mod tests {
             usage: TokenUsage::new(10, 5),
             finish_reason: FinishReason::Stop,
             created_at: Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         };
 
         let result = TestResult::success(
@@ -15,6 +15,7 @@ mod tests {
     use crate::benchmarks::results::{BenchmarkResults, TestResult};
     use crate::providers::{CompletionResponse, FinishReason, TokenUsage};
     use chrono::Utc;
+    use std::collections::HashMap;
     use std::time::Duration;
 
     fn create_benchmark_with_config(
@@ -33,6 +34,12 @@ mod tests {
                     usage: TokenUsage::new(prompt_tokens, completion_tokens),
                     finish_reason: FinishReason::Stop,
                     created_at: Utc::now(),
+                    tool_calls: Vec::new(),
+                    schema_violations: Vec::new(),
+                    logprobs: None,
+                    prompt_logprobs: None,
+                    choices: Vec::new(),
+                    metadata: HashMap::new(),
                 };
 
                 TestResult::success(
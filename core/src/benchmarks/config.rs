@@ -27,6 +27,9 @@ use std::path::PathBuf;
 ///     continue_on_failure: true,
 ///     random_seed: Some(42),
 ///     request_delay_ms: Some(100),
+///     streaming: false,
+///     batch_poll_interval_ms: 60_000,
+///     batch_max_wait_ms: 86_400_000,
 /// };
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,6 +69,38 @@ pub struct BenchmarkConfig {
     /// Adds a fixed delay between consecutive requests to avoid rate limiting.
     /// Default: None (no delay)
     pub request_delay_ms: Option<u64>,
+
+    /// Whether to stream completions.
+    ///
+    /// If true, each test is run through the provider's streaming API and its
+    /// time to first token, inter-token latency and throughput are recorded.
+    /// Default: false
+    #[serde(default)]
+    pub streaming: bool,
+
+    /// How often to poll a batch job for completion, in milliseconds.
+    ///
+    /// Only used by [`BenchmarkRunner::run_batch`](super::BenchmarkRunner::run_batch).
+    /// Batches take minutes to hours, so frequent polling gains little.
+    /// Default: 60000
+    #[serde(default = "default_batch_poll_interval_ms")]
+    pub batch_poll_interval_ms: u64,
+
+    /// How long to wait for a batch job to end, in milliseconds.
+    ///
+    /// When the wait runs out the job is left running and its state file is
+    /// kept, so a later run resumes polling it. Default: 86400000 (24 hours,
+    /// the providers' completion window)
+    #[serde(default = "default_batch_max_wait_ms")]
+    pub batch_max_wait_ms: u64,
+}
+
+fn default_batch_poll_interval_ms() -> u64 {
+    60_000
+}
+
+fn default_batch_max_wait_ms() -> u64 {
+    24 * 60 * 60 * 1000
 }
 
 impl Default for BenchmarkConfig {
@@ -77,6 +112,9 @@ impl Default for BenchmarkConfig {
             continue_on_failure: true,
             random_seed: None,
             request_delay_ms: None,
+            streaming: false,
+            batch_poll_interval_ms: default_batch_poll_interval_ms(),
+            batch_max_wait_ms: default_batch_max_wait_ms(),
         }
     }
 }
@@ -190,6 +228,51 @@ impl BenchmarkConfig {
         self
     }
 
+    /// Sets whether to stream completions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use llm_test_bench_core::benchmarks::BenchmarkConfig;
+    ///
+    /// let config = BenchmarkConfig::new().with_streaming(true);
+    /// assert!(config.streaming);
+    /// ```
+    pub fn with_streaming(mut self, streaming: bool) -> Self {
+        self.streaming = streaming;
+        self
+    }
+
+    /// Sets how often batch jobs are polled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use llm_test_bench_core::benchmarks::BenchmarkConfig;
+    ///
+    /// let config = BenchmarkConfig::new().with_batch_poll_interval_ms(5_000);
+    /// assert_eq!(config.batch_poll_interval_ms, 5_000);
+    /// ```
+    pub fn with_batch_poll_interval_ms(mut self, interval_ms: u64) -> Self {
+        self.batch_poll_interval_ms = interval_ms;
+        self
+    }
+
+    /// Sets how long to wait for a batch job to end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use llm_test_bench_core::benchmarks::BenchmarkConfig;
+    ///
+    /// let config = BenchmarkConfig::new().with_batch_max_wait_ms(3_600_000);
+    /// assert_eq!(config.batch_max_wait_ms, 3_600_000);
+    /// ```
+    pub fn with_batch_max_wait_ms(mut self, max_wait_ms: u64) -> Self {
+        self.batch_max_wait_ms = max_wait_ms;
+        self
+    }
+
     /// Validates the configuration.
     ///
     /// Returns an error if the configuration has invalid values.
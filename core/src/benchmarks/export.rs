@@ -214,6 +214,7 @@ mod tests {
     use crate::benchmarks::runner::{BenchmarkResults, TestResult};
     use crate::providers::{CompletionResponse, FinishReason, TokenUsage};
     use chrono::Utc;
+    use std::collections::HashMap;
     use std::fs;
     use std::time::Duration;
     use tempfile::TempDir;
@@ -226,6 +227,12 @@ mod tests {
             usage: TokenUsage::new(100, 50),
             finish_reason: FinishReason::Stop,
             created_at: Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         }
     }
 
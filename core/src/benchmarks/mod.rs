@@ -114,7 +114,10 @@ pub mod storage;
 
 pub use config::BenchmarkConfig;
 pub use reporter::BenchmarkReporter;
-pub use runner::{BenchmarkResults, BenchmarkRunner, ResultSummary, TestResult, TestStatus};
+pub use runner::{
+    BenchmarkResults, BenchmarkRunner, PromptCacheResults, PromptCacheSummary, ResultSummary, StreamingSummary,
+    TestResult, TestStatus,
+};
 pub use export::CsvExporter;
 pub use storage::ResultStorage;
 // Re-export the calculate_percentile utility function
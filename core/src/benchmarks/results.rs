@@ -34,6 +34,10 @@
 //!         usage: TokenUsage::new(10, 5),
 //!         finish_reason: FinishReason::Stop,
 //!         created_at: Utc::now(),
+//!         tool_calls: Vec::new(),
+//!         schema_violations: Vec::new(),
+//!         logprobs: None,
+//!         prompt_logprobs: None,
 //!     },
 //!     std::time::Duration::from_millis(1234),
 //! );
@@ -281,6 +285,10 @@ impl TestResult {
     ///     usage: TokenUsage::new(5, 2),
     ///     finish_reason: FinishReason::Stop,
     ///     created_at: Utc::now(),
+    ///     tool_calls: Vec::new(),
+    ///     schema_violations: Vec::new(),
+    ///     logprobs: None,
+    ///     prompt_logprobs: None,
     /// };
     ///
     /// let result = TestResult::success(
@@ -549,6 +557,7 @@ pub fn calculate_percentile(durations: &[u64], percentile: f64) -> f64 {
 mod tests {
     use super::*;
     use crate::providers::{FinishReason, TokenUsage};
+    use std::collections::HashMap;
 
     fn create_test_response(tokens: (usize, usize)) -> CompletionResponse {
         CompletionResponse {
@@ -558,6 +567,12 @@ mod tests {
             usage: TokenUsage::new(tokens.0, tokens.1),
             finish_reason: FinishReason::Stop,
             created_at: Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         }
     }
 
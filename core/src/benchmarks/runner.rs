@@ -8,12 +8,17 @@
 
 use super::config::BenchmarkConfig;
 use super::{BenchmarkError, BenchmarkResult};
-use crate::providers::{CompletionRequest, CompletionResponse, Provider};
+use crate::providers::{
+    models, BatchProvider, BatchRequest, BatchStatus, CachePoint, CompletionRequest, CompletionResponse, Provider, StreamAccumulator,
+    StreamMetrics,
+};
 use chrono::{DateTime, Utc};
 use futures::stream::{self, StreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
 use llm_test_bench_datasets::{Dataset, TestCase};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::Semaphore;
@@ -76,7 +81,11 @@ impl BenchmarkResults {
             max_duration_ms: 0,
             total_tokens: 0,
             avg_tokens_per_request: 0.0,
+            total_reasoning_tokens: 0,
+            total_cached_tokens: 0,
+            total_cache_write_tokens: 0,
             total_cost: 0.0,
+            streaming: None,
         };
 
         Self {
@@ -161,15 +170,32 @@ impl BenchmarkResults {
             0.0
         };
 
-        // Estimate total cost (using average pricing)
-        // GPT-4: ~$0.03/1K prompt, ~$0.06/1K completion
+        let total_reasoning_tokens: usize = results
+            .iter()
+            .filter_map(|r| r.response.as_ref())
+            .map(|resp| resp.usage.reasoning_tokens)
+            .sum();
+
+        let total_cached_tokens: usize = results
+            .iter()
+            .filter_map(|r| r.response.as_ref())
+            .map(|resp| resp.usage.cached_tokens)
+            .sum();
+
+        let total_cache_write_tokens: usize = results
+            .iter()
+            .filter_map(|r| r.response.as_ref())
+            .map(|resp| resp.usage.cache_write_tokens)
+            .sum();
+
+        // Estimate total cost from the model's list prices, falling back to
+        // average pricing (GPT-4: ~$0.03/1K prompt, ~$0.06/1K completion)
         let total_cost: f64 = results
             .iter()
             .filter_map(|r| r.response.as_ref())
             .map(|resp| {
-                let prompt_cost = (resp.usage.prompt_tokens as f64 / 1000.0) * 0.03;
-                let completion_cost = (resp.usage.completion_tokens as f64 / 1000.0) * 0.06;
-                prompt_cost + completion_cost
+                models::estimate_cost(&resp.model, &resp.usage)
+                    .unwrap_or_else(|| resp.usage.calculate_cost(0.03, 0.06))
             })
             .sum();
 
@@ -188,9 +214,46 @@ impl BenchmarkResults {
             max_duration_ms,
             total_tokens,
             avg_tokens_per_request,
+            total_reasoning_tokens,
+            total_cached_tokens,
+            total_cache_write_tokens,
             total_cost,
+            streaming: Self::compute_streaming_summary(results),
         }
     }
+
+    /// Computes streaming latency statistics, or `None` if no test was streamed.
+    fn compute_streaming_summary(results: &[TestResult]) -> Option<StreamingSummary> {
+        use super::results::calculate_percentile;
+
+        let metrics: Vec<&StreamMetrics> = results.iter().filter_map(|r| r.stream_metrics.as_ref()).collect();
+        if metrics.is_empty() {
+            return None;
+        }
+
+        let average = |values: Vec<f64>| {
+            if values.is_empty() {
+                None
+            } else {
+                Some(values.iter().sum::<f64>() / values.len() as f64)
+            }
+        };
+
+        let ttfts: Vec<f64> = metrics.iter().filter_map(|m| m.time_to_first_token_ms).collect();
+        let mut sorted_ttfts: Vec<u64> = ttfts.iter().map(|ms| ms.round() as u64).collect();
+        sorted_ttfts.sort_unstable();
+
+        Some(StreamingSummary {
+            streamed: metrics.len(),
+            p50_ttft_ms: calculate_percentile(&sorted_ttfts, 50.0),
+            p95_ttft_ms: calculate_percentile(&sorted_ttfts, 95.0),
+            avg_ttft_ms: average(ttfts).unwrap_or(0.0),
+            avg_inter_token_latency_ms: average(metrics.iter().filter_map(|m| m.inter_token_latency_ms).collect())
+                .unwrap_or(0.0),
+            avg_tokens_per_second: average(metrics.iter().filter_map(|m| m.tokens_per_second).collect())
+                .unwrap_or(0.0),
+        })
+    }
 }
 
 /// Result of a single test case execution.
@@ -216,6 +279,10 @@ pub struct TestResult {
 
     /// When the test was executed
     pub timestamp: DateTime<Utc>,
+
+    /// Streaming latency metrics (if the test was run with streaming)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_metrics: Option<StreamMetrics>,
 }
 
 /// Status of a test execution.
@@ -291,8 +358,131 @@ pub struct ResultSummary {
     /// Average tokens per request
     pub avg_tokens_per_request: f64,
 
+    /// Reasoning tokens across all tests, included in `total_tokens`
+    #[serde(default)]
+    pub total_reasoning_tokens: usize,
+
+    /// Prompt tokens read from the provider's cache across all tests
+    #[serde(default)]
+    pub total_cached_tokens: usize,
+
+    /// Prompt tokens written to the provider's cache across all tests
+    #[serde(default)]
+    pub total_cache_write_tokens: usize,
+
     /// Estimated total cost in USD
     pub total_cost: f64,
+
+    /// Streaming latency statistics (if tests were run with streaming)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub streaming: Option<StreamingSummary>,
+}
+
+/// Latency statistics for streamed tests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamingSummary {
+    /// Number of tests with streaming metrics
+    pub streamed: usize,
+
+    /// Average time to first token in milliseconds
+    pub avg_ttft_ms: f64,
+
+    /// Median time to first token in milliseconds
+    pub p50_ttft_ms: f64,
+
+    /// 95th percentile time to first token in milliseconds
+    pub p95_ttft_ms: f64,
+
+    /// Average time between generated tokens in milliseconds
+    pub avg_inter_token_latency_ms: f64,
+
+    /// Average generation throughput in tokens per second
+    pub avg_tokens_per_second: f64,
+}
+
+/// Results of a prompt caching benchmark.
+///
+/// Every test case was sent twice with the same long prefix: `cold` holds
+/// the first requests, which write the prefix to the provider's cache, and
+/// `warm` the repeats, which read it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptCacheResults {
+    /// Estimated length of the shared prefix in tokens
+    pub prefix_tokens: usize,
+
+    /// First request for each test case
+    pub cold: BenchmarkResults,
+
+    /// Repeated request for each test case
+    pub warm: BenchmarkResults,
+
+    /// Comparison of the cold and warm requests
+    pub summary: PromptCacheSummary,
+}
+
+/// How much warm requests save over cold ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptCacheSummary {
+    /// Median latency of cold requests (milliseconds)
+    pub cold_p50_ms: f64,
+
+    /// Median latency of warm requests (milliseconds)
+    pub warm_p50_ms: f64,
+
+    /// Fraction of the cold median latency saved by warm requests
+    pub latency_reduction: f64,
+
+    /// Median time to first token of cold requests, if streamed (milliseconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cold_p50_ttft_ms: Option<f64>,
+
+    /// Median time to first token of warm requests, if streamed (milliseconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warm_p50_ttft_ms: Option<f64>,
+
+    /// Estimated cost of the cold requests in USD
+    pub cold_cost: f64,
+
+    /// Estimated cost of the warm requests in USD
+    pub warm_cost: f64,
+
+    /// Fraction of the cold cost saved by warm requests
+    pub cost_reduction: f64,
+
+    /// Fraction of warm prompt tokens read from the cache (0.0 to 1.0)
+    pub cache_hit_rate: f64,
+}
+
+impl PromptCacheSummary {
+    /// Compares the summaries of the cold and warm runs.
+    pub fn compare(cold: &BenchmarkResults, warm: &BenchmarkResults) -> Self {
+        let reduction = |cold: f64, warm: f64| if cold > 0.0 { 1.0 - warm / cold } else { 0.0 };
+        let p50_ttft = |results: &BenchmarkResults| results.summary.streaming.as_ref().map(|s| s.p50_ttft_ms);
+
+        let warm_prompt_tokens: usize = warm
+            .results
+            .iter()
+            .filter_map(|r| r.response.as_ref())
+            .map(|resp| resp.usage.prompt_tokens)
+            .sum();
+        let cache_hit_rate = if warm_prompt_tokens > 0 {
+            warm.summary.total_cached_tokens as f64 / warm_prompt_tokens as f64
+        } else {
+            0.0
+        };
+
+        Self {
+            cold_p50_ms: cold.summary.p50_duration_ms,
+            warm_p50_ms: warm.summary.p50_duration_ms,
+            latency_reduction: reduction(cold.summary.p50_duration_ms, warm.summary.p50_duration_ms),
+            cold_p50_ttft_ms: p50_ttft(cold),
+            warm_p50_ttft_ms: p50_ttft(warm),
+            cold_cost: cold.summary.total_cost,
+            warm_cost: warm.summary.total_cost,
+            cost_reduction: reduction(cold.summary.total_cost, warm.summary.total_cost),
+            cache_hit_rate,
+        }
+    }
 }
 
 impl TestResult {
@@ -311,6 +501,7 @@ impl TestResult {
             error: None,
             duration_ms: duration.as_millis() as u64,
             timestamp: Utc::now(),
+            stream_metrics: None,
         }
     }
 
@@ -324,6 +515,7 @@ impl TestResult {
             error: Some(error),
             duration_ms: duration.as_millis() as u64,
             timestamp: Utc::now(),
+            stream_metrics: None,
         }
     }
 
@@ -337,9 +529,16 @@ impl TestResult {
             error: Some("Request timed out".to_string()),
             duration_ms: duration.as_millis() as u64,
             timestamp: Utc::now(),
+            stream_metrics: None,
         }
     }
 
+    /// Attaches streaming latency metrics.
+    pub fn with_stream_metrics(mut self, metrics: StreamMetrics) -> Self {
+        self.stream_metrics = Some(metrics);
+        self
+    }
+
     /// Creates a skipped test result.
     pub fn skipped(test_id: String, category: Option<String>) -> Self {
         Self {
@@ -350,6 +549,7 @@ impl TestResult {
             error: None,
             duration_ms: 0,
             timestamp: Utc::now(),
+            stream_metrics: None,
         }
     }
 }
@@ -501,27 +701,293 @@ impl BenchmarkRunner {
         })
     }
 
+    /// Runs the benchmark through the provider's batch API.
+    ///
+    /// All test cases are submitted as one batch job, which is polled every
+    /// `batch_poll_interval_ms` until it ends. While the job runs its ID is
+    /// kept in a state file in the output directory, so a run that is
+    /// interrupted picks the same job up again when restarted for the same
+    /// dataset and provider instead of submitting a new one. Polling stops
+    /// after `batch_max_wait_ms`; the job and its state file are left in
+    /// place so a later run can resume waiting for it.
+    ///
+    /// Batches report no per-request latency, so every test's duration is
+    /// zero; `total_duration_ms` covers the whole job.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The dataset has no test cases
+    /// - The output directory or state file cannot be written
+    /// - The job cannot be submitted, polled or its results fetched
+    /// - The job has not ended within `batch_max_wait_ms`
+    pub async fn run_batch(
+        &self,
+        dataset: &Dataset,
+        provider: Arc<dyn BatchProvider>,
+    ) -> Result<BenchmarkResults, BenchmarkError> {
+        let start_time = Instant::now();
+        let started_at = Utc::now();
+
+        if dataset.test_cases.is_empty() {
+            return Err(BenchmarkError::InvalidConfiguration(
+                "Dataset has no test cases".to_string(),
+            ));
+        }
+
+        std::fs::create_dir_all(&self.config.output_dir).map_err(|e| {
+            BenchmarkError::ExecutionFailed(format!("Failed to create output directory: {}", e))
+        })?;
+
+        let state_path = BatchState::path(&self.config.output_dir, &dataset.name, provider.name());
+        let state = match BatchState::load(&state_path)? {
+            Some(state) => {
+                tracing::info!("Resuming batch {} submitted at {}", state.batch_id, state.submitted_at);
+                state
+            }
+            None => {
+                let model = default_model(provider.as_ref());
+                let requests = dataset
+                    .test_cases
+                    .iter()
+                    .enumerate()
+                    .map(|(i, test_case)| {
+                        BatchRequest::new(BatchState::custom_id(i), CompletionRequest::new(&model, &test_case.prompt))
+                    })
+                    .collect();
+                let job = provider.submit_batch(requests).await.map_err(|e| {
+                    BenchmarkError::ExecutionFailed(format!("Failed to submit batch: {}", e))
+                })?;
+
+                let state = BatchState {
+                    batch_id: job.id,
+                    test_ids: dataset.test_cases.iter().map(|t| t.id.clone()).collect(),
+                    submitted_at: Utc::now(),
+                };
+                state.save(&state_path)?;
+                state
+            }
+        };
+
+        let pb = Self::create_progress_bar(state.test_ids.len());
+        pb.set_message(format!("Batch {}", state.batch_id));
+        let deadline = start_time + Duration::from_millis(self.config.batch_max_wait_ms);
+        let job = loop {
+            let job = provider.batch_status(&state.batch_id).await.map_err(|e| {
+                BenchmarkError::ExecutionFailed(format!("Failed to poll batch {}: {}", state.batch_id, e))
+            })?;
+            pb.set_position((job.counts.completed + job.counts.failed) as u64);
+            if job.status.is_terminal() {
+                break job;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                // Keep the state file so the next run resumes this job
+                pb.abandon_with_message(format!("Batch {}", BatchStatus::Expired));
+                return Err(BenchmarkError::ExecutionFailed(format!(
+                    "Batch {} did not end within {} ms (status: {}); rerun to resume waiting",
+                    state.batch_id, self.config.batch_max_wait_ms, job.status
+                )));
+            }
+            let interval = Duration::from_millis(self.config.batch_poll_interval_ms);
+            tokio::time::sleep(interval.min(deadline - now)).await;
+        };
+        pb.finish_with_message(format!("Batch {}", job.status));
+
+        let mut outcomes: HashMap<String, Result<CompletionResponse, String>> = provider
+            .batch_results(&job.id)
+            .await
+            .map_err(|e| BenchmarkError::ExecutionFailed(format!("Failed to fetch batch results: {}", e)))?
+            .into_iter()
+            .map(|result| (result.custom_id, result.response))
+            .collect();
+
+        let categories: HashMap<&str, &Option<String>> =
+            dataset.test_cases.iter().map(|t| (t.id.as_str(), &t.category)).collect();
+        let results: Vec<TestResult> = state
+            .test_ids
+            .iter()
+            .enumerate()
+            .map(|(i, test_id)| {
+                let category = categories.get(test_id.as_str()).and_then(|c| (*c).clone());
+                match outcomes.remove(&BatchState::custom_id(i)) {
+                    Some(Ok(response)) => {
+                        if self.config.save_responses {
+                            if let Err(e) = Self::save_response(test_id, &response, &self.config) {
+                                tracing::warn!("Failed to save response for {}: {}", test_id, e);
+                            }
+                        }
+                        TestResult::success(test_id.clone(), category, response, Duration::ZERO)
+                    }
+                    Some(Err(error)) => TestResult::failure(test_id.clone(), category, error, Duration::ZERO),
+                    None => TestResult::failure(
+                        test_id.clone(),
+                        category,
+                        format!("No result in batch {} ({})", job.id, job.status),
+                        Duration::ZERO,
+                    ),
+                }
+            })
+            .collect();
+
+        // The job has ended, so the next run submits a new one
+        if let Err(e) = std::fs::remove_file(&state_path) {
+            tracing::warn!("Failed to remove batch state {}: {}", state_path.display(), e);
+        }
+
+        let summary = BenchmarkResults::compute_summary(&results);
+
+        Ok(BenchmarkResults {
+            dataset_name: dataset.name.clone(),
+            provider_name: provider.name().to_string(),
+            total_tests: results.len(),
+            results,
+            started_at,
+            completed_at: Utc::now(),
+            total_duration_ms: start_time.elapsed().as_millis() as u64,
+            summary,
+        })
+    }
+
+    /// Measures prompt caching by sending every test case twice.
+    ///
+    /// Each test's prompt is sent after `prefix`, a long shared system
+    /// prompt marked as a [`CachePoint`]. The first, cold request writes the
+    /// prefix to the provider's cache and the repeat, sent once the first
+    /// has finished, reads it back. The prefix starts with a marker unique
+    /// to the run and test case, so no cold request hits an entry cached by
+    /// another test or an earlier run.
+    ///
+    /// Saved responses go to `cold/` and `warm/` in the output directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The dataset has no test cases
+    /// - The output directories cannot be created
+    pub async fn run_prompt_cache(
+        &self,
+        dataset: &Dataset,
+        provider: Arc<dyn Provider>,
+        prefix: &str,
+    ) -> Result<PromptCacheResults, BenchmarkError> {
+        let start_time = Instant::now();
+        let started_at = Utc::now();
+
+        let total = dataset.test_cases.len();
+        if total == 0 {
+            return Err(BenchmarkError::InvalidConfiguration(
+                "Dataset has no test cases".to_string(),
+            ));
+        }
+
+        let model = default_model(provider.as_ref());
+        let prefix_tokens = provider.estimate_tokens(prefix, &model).unwrap_or(0);
+        if prefix_tokens < MIN_CACHEABLE_PREFIX_TOKENS {
+            tracing::warn!(
+                "The prefix is about {} tokens; providers only cache prefixes of at least {}",
+                prefix_tokens,
+                MIN_CACHEABLE_PREFIX_TOKENS
+            );
+        }
+
+        let cold_config = BenchmarkConfig { output_dir: self.config.output_dir.join("cold"), ..self.config.clone() };
+        let warm_config = BenchmarkConfig { output_dir: self.config.output_dir.join("warm"), ..self.config.clone() };
+        if self.config.save_responses {
+            for dir in [&cold_config.output_dir, &warm_config.output_dir] {
+                std::fs::create_dir_all(dir).map_err(|e| {
+                    BenchmarkError::ExecutionFailed(format!("Failed to create output directory: {}", e))
+                })?;
+            }
+        }
+
+        let run_id = uuid::Uuid::new_v4();
+        let pb = Self::create_progress_bar(total);
+        let semaphore = Arc::new(Semaphore::new(self.config.concurrency));
+
+        let (cold, warm): (Vec<TestResult>, Vec<TestResult>) = stream::iter(&dataset.test_cases)
+            .map(|test_case| {
+                let provider = Arc::clone(&provider);
+                let semaphore = Arc::clone(&semaphore);
+                let pb = pb.clone();
+                let (model, cold_config, warm_config) = (&model, &cold_config, &warm_config);
+
+                async move {
+                    let _permit = semaphore.acquire().await.unwrap();
+
+                    pb.set_message(format!("Testing: {}", test_case.id));
+
+                    if let Some(delay) = cold_config.request_delay_ms {
+                        tokio::time::sleep(Duration::from_millis(delay)).await;
+                    }
+
+                    let request = CompletionRequest::new(model.clone(), test_case.prompt.clone())
+                        .with_system(format!("[{} {}]\n\n{}", run_id, test_case.id, prefix))
+                        .with_cache_point(CachePoint::System);
+                    let cold = Self::run_request(test_case, request.clone(), &provider, cold_config).await;
+                    let warm = Self::run_request(test_case, request, &provider, warm_config).await;
+
+                    pb.inc(1);
+                    (cold, warm)
+                }
+            })
+            .buffer_unordered(self.config.concurrency)
+            .unzip()
+            .await;
+
+        pb.finish_with_message("Benchmark complete");
+
+        let completed_at = Utc::now();
+        let total_duration_ms = start_time.elapsed().as_millis() as u64;
+        let results = |results: Vec<TestResult>| BenchmarkResults {
+            dataset_name: dataset.name.clone(),
+            provider_name: provider.name().to_string(),
+            total_tests: total,
+            summary: BenchmarkResults::compute_summary(&results),
+            results,
+            started_at,
+            completed_at,
+            total_duration_ms,
+        };
+        let (cold, warm) = (results(cold), results(warm));
+
+        Ok(PromptCacheResults {
+            prefix_tokens,
+            summary: PromptCacheSummary::compare(&cold, &warm),
+            cold,
+            warm,
+        })
+    }
+
     /// Executes a single test case.
     async fn run_test_case(
         test_case: &TestCase,
         provider: &Arc<dyn Provider>,
         config: &BenchmarkConfig,
     ) -> TestResult {
-        let start = Instant::now();
+        let request = CompletionRequest::new(default_model(provider.as_ref()), test_case.prompt.clone());
+        Self::run_request(test_case, request, provider, config).await
+    }
 
-        // Build completion request
-        let request = CompletionRequest::new(
-            // Use default model from provider (can be enhanced later with dataset config)
-            provider.supported_models().first().map(|m| m.id.clone()).unwrap_or_else(|| "default".to_string()),
-            test_case.prompt.clone(),
-        );
+    /// Sends the request for a test case and records the outcome.
+    async fn run_request(
+        test_case: &TestCase,
+        request: CompletionRequest,
+        provider: &Arc<dyn Provider>,
+        config: &BenchmarkConfig,
+    ) -> TestResult {
+        let start = Instant::now();
 
         // Execute request
-        let result = provider.complete(request).await;
+        let result = if config.streaming {
+            Self::stream_completion(provider, request).await
+        } else {
+            provider.complete(request).await.map(|response| (response, None))
+        };
         let duration = start.elapsed();
 
         match result {
-            Ok(response) => {
+            Ok((response, stream_metrics)) => {
                 // Save raw response if configured
                 if config.save_responses {
                     if let Err(e) = Self::save_response(&test_case.id, &response, config) {
@@ -529,12 +995,16 @@ impl BenchmarkRunner {
                     }
                 }
 
-                TestResult::success(
+                let result = TestResult::success(
                     test_case.id.clone(),
                     test_case.category.clone(),
                     response,
                     duration,
-                )
+                );
+                match stream_metrics {
+                    Some(metrics) => result.with_stream_metrics(metrics),
+                    None => result,
+                }
             }
             Err(e) => {
                 let error_msg = e.to_string();
@@ -548,6 +1018,23 @@ impl BenchmarkRunner {
         }
     }
 
+    /// Streams a completion, assembling the response and its latency metrics.
+    async fn stream_completion(
+        provider: &Arc<dyn Provider>,
+        request: CompletionRequest,
+    ) -> Result<(CompletionResponse, Option<StreamMetrics>), crate::providers::ProviderError> {
+        let model = request.model.clone();
+        let mut accumulator = StreamAccumulator::new(model);
+
+        let mut events = provider.stream(request.with_streaming()).await?;
+        while let Some(event) = events.next().await {
+            accumulator.push(&event?);
+        }
+
+        let metrics = accumulator.metrics();
+        Ok((accumulator.into_response(), Some(metrics)))
+    }
+
     /// Creates a progress bar for tracking benchmark execution.
     fn create_progress_bar(total: usize) -> ProgressBar {
         let pb = ProgressBar::new(total as u64);
@@ -579,11 +1066,69 @@ impl Default for BenchmarkRunner {
     }
 }
 
+/// The shortest prefix providers cache (OpenAI and most Claude models).
+const MIN_CACHEABLE_PREFIX_TOKENS: usize = 1024;
+
+/// The model tests run against: the provider's first supported model.
+fn default_model(provider: &dyn Provider) -> String {
+    // Can be enhanced later with dataset config
+    provider.supported_models().first().map(|m| m.id.clone()).unwrap_or_else(|| "default".to_string())
+}
+
+/// A submitted batch job, persisted so an interrupted run can resume it.
+///
+/// Test case `i` is submitted under the custom ID `test-{i}`, which stays
+/// within the characters every batch API accepts; `test_ids` maps the IDs
+/// back even if the dataset file changes in the meantime.
+#[derive(Debug, Serialize, Deserialize)]
+struct BatchState {
+    batch_id: String,
+    test_ids: Vec<String>,
+    submitted_at: DateTime<Utc>,
+}
+
+impl BatchState {
+    fn custom_id(index: usize) -> String {
+        format!("test-{}", index)
+    }
+
+    /// The state file for a dataset and provider in `output_dir`.
+    fn path(output_dir: &Path, dataset_name: &str, provider_name: &str) -> PathBuf {
+        let sanitize = |name: &str| -> String {
+            name.chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c.to_ascii_lowercase() } else { '_' })
+                .collect()
+        };
+        output_dir.join(format!("batch-{}-{}.json", sanitize(provider_name), sanitize(dataset_name)))
+    }
+
+    fn load(path: &Path) -> Result<Option<Self>, BenchmarkError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            BenchmarkError::ExecutionFailed(format!("Failed to read batch state {}: {}", path.display(), e))
+        })?;
+        serde_json::from_str(&json).map(Some).map_err(|e| {
+            BenchmarkError::ExecutionFailed(format!("Invalid batch state {}: {}", path.display(), e))
+        })
+    }
+
+    fn save(&self, path: &Path) -> Result<(), BenchmarkError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| BenchmarkError::ExecutionFailed(format!("Failed to serialize batch state: {}", e)))?;
+        std::fs::write(path, json).map_err(|e| {
+            BenchmarkError::ExecutionFailed(format!("Failed to write batch state {}: {}", path.display(), e))
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::providers::{FinishReason, ModelInfo, ProviderError, TokenUsage};
     use async_trait::async_trait;
+    use std::collections::HashMap;
     use std::path::PathBuf;
 
     // Mock provider for testing
@@ -625,14 +1170,21 @@ mod tests {
                 usage: TokenUsage::new(10, 20),
                 finish_reason: FinishReason::Stop,
                 created_at: Utc::now(),
+                tool_calls: Vec::new(),
+                schema_violations: Vec::new(),
+                logprobs: None,
+                prompt_logprobs: None,
+                choices: Vec::new(),
+                metadata: HashMap::new(),
             })
         }
 
         async fn stream(
             &self,
-            _request: CompletionRequest,
+            request: CompletionRequest,
         ) -> Result<crate::providers::ResponseStream, ProviderError> {
-            unimplemented!("Stream not needed for tests")
+            let response = self.complete(request).await?;
+            Ok(crate::providers::streaming::from_response(&response))
         }
 
         fn supported_models(&self) -> Vec<ModelInfo> {
@@ -656,6 +1208,72 @@ mod tests {
         }
     }
 
+    /// Caches system prompts: the first request with a system prompt is
+    /// slow and writes it, repeats are fast and read it
+    struct CachingMockProvider {
+        cached: std::sync::Mutex<std::collections::HashSet<String>>,
+    }
+
+    #[async_trait]
+    impl Provider for CachingMockProvider {
+        async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+            assert_eq!(request.cache_points, vec![CachePoint::System]);
+            let system = request.system.unwrap_or_default();
+            let hit = !self.cached.lock().unwrap().insert(system);
+
+            let usage = if hit {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                TokenUsage::new(2010, 20).with_cached_tokens(2000)
+            } else {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                TokenUsage::new(2010, 20).with_cache_write_tokens(2000)
+            };
+
+            Ok(CompletionResponse {
+                id: "mock-123".to_string(),
+                model: "claude-3-5-sonnet-20241022".to_string(),
+                content: "Mock response".to_string(),
+                usage,
+                finish_reason: FinishReason::Stop,
+                created_at: Utc::now(),
+                tool_calls: Vec::new(),
+                schema_violations: Vec::new(),
+                logprobs: None,
+                prompt_logprobs: None,
+                choices: Vec::new(),
+                metadata: HashMap::new(),
+            })
+        }
+
+        async fn stream(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<crate::providers::ResponseStream, ProviderError> {
+            let response = self.complete(request).await?;
+            Ok(crate::providers::streaming::from_response(&response))
+        }
+
+        fn supported_models(&self) -> Vec<ModelInfo> {
+            vec![ModelInfo::new("claude-3-5-sonnet-20241022", "Claude 3.5 Sonnet", 200_000, true, false)]
+        }
+
+        fn max_context_length(&self, _model: &str) -> Option<usize> {
+            Some(200_000)
+        }
+
+        fn name(&self) -> &str {
+            "caching-mock"
+        }
+
+        async fn validate_config(&self) -> Result<(), ProviderError> {
+            Ok(())
+        }
+
+        fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
+            Ok(text.split_whitespace().count())
+        }
+    }
+
     fn create_test_dataset(num_cases: usize) -> Dataset {
         let mut dataset = Dataset::new("test".to_string(), "Test dataset".to_string());
         for i in 0..num_cases {
@@ -688,6 +1306,35 @@ mod tests {
         assert_eq!(results.summary.succeeded, 5);
         assert_eq!(results.summary.failed, 0);
         assert_eq!(results.summary.success_rate, 1.0);
+        assert!(results.summary.streaming.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_run_benchmark_streaming() {
+        let config = BenchmarkConfig::new()
+            .with_concurrency(2)
+            .with_save_responses(false)
+            .with_streaming(true);
+        let runner = BenchmarkRunner::new(config);
+
+        let dataset = create_test_dataset(3);
+        let provider = Arc::new(MockProvider::new("mock"));
+
+        let results = runner.run(&dataset, provider).await.unwrap();
+
+        assert_eq!(results.summary.succeeded, 3);
+        for result in &results.results {
+            let metrics = result.stream_metrics.unwrap();
+            // The mock waits 10ms before the first token
+            assert!(metrics.time_to_first_token_ms.unwrap() >= 10.0);
+            assert_eq!(metrics.completion_tokens, 20);
+            assert_eq!(result.response.as_ref().unwrap().content, "Mock response");
+        }
+
+        let streaming = results.summary.streaming.unwrap();
+        assert_eq!(streaming.streamed, 3);
+        assert!(streaming.avg_ttft_ms >= 10.0);
+        assert!(streaming.avg_tokens_per_second > 0.0);
     }
 
     #[tokio::test]
@@ -750,6 +1397,12 @@ mod tests {
                     usage: TokenUsage::new(10, 20),
                     finish_reason: FinishReason::Stop,
                     created_at: Utc::now(),
+                    tool_calls: Vec::new(),
+                    schema_violations: Vec::new(),
+                    logprobs: None,
+                    prompt_logprobs: None,
+                    choices: Vec::new(),
+                    metadata: HashMap::new(),
                 },
                 Duration::from_millis(100),
             ),
@@ -769,6 +1422,12 @@ mod tests {
                     usage: TokenUsage::new(15, 25),
                     finish_reason: FinishReason::Stop,
                     created_at: Utc::now(),
+                    tool_calls: Vec::new(),
+                    schema_violations: Vec::new(),
+                    logprobs: None,
+                    prompt_logprobs: None,
+                    choices: Vec::new(),
+                    metadata: HashMap::new(),
                 },
                 Duration::from_millis(150),
             ),
@@ -797,6 +1456,12 @@ mod tests {
             usage: TokenUsage::new(10, 20),
             finish_reason: FinishReason::Stop,
             created_at: Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         };
 
         let result = TestResult::success(
@@ -851,6 +1516,12 @@ mod tests {
             usage: TokenUsage::new(10, 20),
             finish_reason: FinishReason::Stop,
             created_at: Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         };
 
         std::fs::create_dir_all(&config.output_dir).unwrap();
@@ -920,7 +1591,11 @@ mod tests {
                 max_duration_ms: 100,
                 total_tokens: 30,
                 avg_tokens_per_request: 30.0,
+                total_reasoning_tokens: 0,
+                total_cached_tokens: 0,
+                total_cache_write_tokens: 0,
                 total_cost: 0.0,
+                streaming: None,
             },
         };
 
@@ -928,4 +1603,52 @@ mod tests {
         let deserialized: BenchmarkResults = serde_json::from_str(&json).unwrap();
         assert_eq!(deserialized.dataset_name, results.dataset_name);
     }
+
+    #[tokio::test]
+    async fn test_run_prompt_cache() {
+        let config = BenchmarkConfig::new()
+            .with_concurrency(2)
+            .with_save_responses(false);
+        let runner = BenchmarkRunner::new(config);
+
+        let dataset = create_test_dataset(3);
+        let provider = Arc::new(CachingMockProvider { cached: Default::default() });
+        let prefix = "word ".repeat(2000);
+
+        let results = runner.run_prompt_cache(&dataset, provider, &prefix).await.unwrap();
+
+        assert_eq!(results.prefix_tokens, 2000);
+        assert_eq!(results.cold.summary.succeeded, 3);
+        assert_eq!(results.warm.summary.succeeded, 3);
+
+        // Each test's cold request misses, even though they share the prefix
+        assert_eq!(results.cold.summary.total_cache_write_tokens, 6000);
+        assert_eq!(results.cold.summary.total_cached_tokens, 0);
+        assert_eq!(results.warm.summary.total_cached_tokens, 6000);
+        assert_eq!(results.warm.summary.total_cache_write_tokens, 0);
+
+        let summary = &results.summary;
+        assert!(summary.warm_p50_ms < summary.cold_p50_ms);
+        assert!(summary.latency_reduction > 0.0);
+        assert!(summary.warm_cost < summary.cold_cost);
+        assert!(summary.cost_reduction > 0.0);
+        assert!((summary.cache_hit_rate - 2000.0 / 2010.0).abs() < 1e-9);
+        assert!(summary.cold_p50_ttft_ms.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_run_prompt_cache_empty_dataset() {
+        let runner = BenchmarkRunner::new(BenchmarkConfig::new().with_save_responses(false));
+        let dataset = create_test_dataset(0);
+        let provider = Arc::new(CachingMockProvider { cached: Default::default() });
+
+        let result = runner.run_prompt_cache(&dataset, provider, "prefix").await;
+        assert!(matches!(result, Err(BenchmarkError::InvalidConfiguration(_))));
+    }
+
+    #[test]
+    fn test_batch_state_path() {
+        let path = BatchState::path(Path::new("out"), "My Dataset/v2", "OpenAI");
+        assert_eq!(path, PathBuf::from("out/batch-openai-my_dataset_v2.json"));
+    }
 }
@@ -291,6 +291,7 @@ mod tests {
     use crate::benchmarks::runner::TestStatus;
     use crate::providers::{CompletionResponse, FinishReason, TokenUsage};
     use chrono::Utc;
+    use std::collections::HashMap;
     use std::time::Duration;
     use tempfile::TempDir;
 
@@ -302,6 +303,12 @@ mod tests {
             usage: TokenUsage::new(50, 25),
             finish_reason: FinishReason::Stop,
             created_at: Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         }
     }
 
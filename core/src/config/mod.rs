@@ -36,8 +36,8 @@ use std::path::{Path, PathBuf};
 
 // Re-export all public types from models module
 pub use models::{
-    AnalyticsConfig, BenchmarkConfig, Config, DashboardConfig, EvaluationConfig, Metric,
-    OrchestrationConfig, ProviderConfig,
+    AnalyticsConfig, BenchmarkConfig, CircuitBreakerConfig, Config, DashboardConfig,
+    EvaluationConfig, Metric, OrchestrationConfig, ProviderConfig,
 };
 
 /// Default configuration file name
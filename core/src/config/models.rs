@@ -57,11 +57,25 @@ impl Default for Config {
             "openai".to_string(),
             ProviderConfig {
                 api_key_env: "OPENAI_API_KEY".to_string(),
+                api_key_file: None,
+                api_key_cmd: None,
+                api_keys_env: Vec::new(),
+                key_rotation: KeyRotation::default(),
+                azure_ad: false,
                 base_url: "https://api.openai.com/v1".to_string(),
                 default_model: "gpt-4o".to_string(),  // Updated to latest GPT-4o
                 timeout_seconds: 30,
                 max_retries: 3,
                 rate_limit_rpm: None,
+                rate_limit_tpm: None,
+                fallback: Vec::new(),
+                circuit_breaker: None,
+                kind: None,
+                headers: HashMap::new(),
+                models: Vec::new(),
+                context_length: None,
+                script: None,
+                transport: TransportConfig::default(),
             },
         );
 
@@ -70,11 +84,25 @@ impl Default for Config {
             "anthropic".to_string(),
             ProviderConfig {
                 api_key_env: "ANTHROPIC_API_KEY".to_string(),
+                api_key_file: None,
+                api_key_cmd: None,
+                api_keys_env: Vec::new(),
+                key_rotation: KeyRotation::default(),
+                azure_ad: false,
                 base_url: "https://api.anthropic.com/v1".to_string(),
                 default_model: "claude-3-5-sonnet-latest".to_string(),  // Updated to Claude 3.5 Sonnet
                 timeout_seconds: 30,
                 max_retries: 3,
                 rate_limit_rpm: None,
+                rate_limit_tpm: None,
+                fallback: Vec::new(),
+                circuit_breaker: None,
+                kind: None,
+                headers: HashMap::new(),
+                models: Vec::new(),
+                context_length: None,
+                script: None,
+                transport: TransportConfig::default(),
             },
         );
 
@@ -83,11 +111,52 @@ impl Default for Config {
             "google".to_string(),
             ProviderConfig {
                 api_key_env: "GOOGLE_API_KEY".to_string(),
+                api_key_file: None,
+                api_key_cmd: None,
+                api_keys_env: Vec::new(),
+                key_rotation: KeyRotation::default(),
+                azure_ad: false,
                 base_url: "https://generativelanguage.googleapis.com/v1beta".to_string(),
                 default_model: "gemini-1.5-pro".to_string(),  // Added Google Gemini
                 timeout_seconds: 30,
                 max_retries: 3,
                 rate_limit_rpm: None,
+                rate_limit_tpm: None,
+                fallback: Vec::new(),
+                circuit_breaker: None,
+                kind: None,
+                headers: HashMap::new(),
+                models: Vec::new(),
+                context_length: None,
+                script: None,
+                transport: TransportConfig::default(),
+            },
+        );
+
+        // Scripted mock provider: needs no API key or network access
+        providers.insert(
+            "mock".to_string(),
+            ProviderConfig {
+                api_key_env: "MOCK_API_KEY".to_string(),
+                api_key_file: None,
+                api_key_cmd: None,
+                api_keys_env: Vec::new(),
+                key_rotation: KeyRotation::default(),
+                azure_ad: false,
+                base_url: "mock://localhost".to_string(),
+                default_model: "mock-model".to_string(),
+                timeout_seconds: 30,
+                max_retries: 0,
+                rate_limit_rpm: None,
+                rate_limit_tpm: None,
+                fallback: Vec::new(),
+                circuit_breaker: None,
+                kind: None,
+                headers: HashMap::new(),
+                models: Vec::new(),
+                context_length: None,
+                script: None,
+                transport: TransportConfig::default(),
             },
         );
 
@@ -111,10 +180,51 @@ impl Default for Config {
 pub struct ProviderConfig {
     /// Environment variable name containing the API key
     ///
+    /// Not needed when `api_key_file`, `api_key_cmd` or `api_keys_env` is set
+    ///
     /// Example: "OPENAI_API_KEY", "ANTHROPIC_API_KEY"
-    #[validate(min_length = 1)]
+    #[serde(default)]
     pub api_key_env: String,
 
+    /// File holding the API key, read when the provider is created
+    ///
+    /// Surrounding whitespace is ignored
+    ///
+    /// Example: `api_key_file = "/run/secrets/openai"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_file: Option<PathBuf>,
+
+    /// Shell command printing the API key, run when the provider is created
+    /// (and again for new tokens with `azure_ad`)
+    ///
+    /// Takes precedence over `api_key_file`, `api_keys_env` and `api_key_env`
+    ///
+    /// Example: `api_key_cmd = "op read op://Private/OpenAI/credential"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_cmd: Option<String>,
+
+    /// Environment variables holding several API keys to rotate between
+    ///
+    /// Takes precedence over `api_key_env`. See `key_rotation`
+    ///
+    /// Example: `api_keys_env = ["OPENAI_KEY_1", "OPENAI_KEY_2"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub api_keys_env: Vec<String>,
+
+    /// How requests are spread over the keys in `api_keys_env`
+    ///
+    /// Default: "round-robin"
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub key_rotation: KeyRotation,
+
+    /// Send the key as an Azure AD bearer token (azure-openai only)
+    ///
+    /// Combine with an `api_key_cmd` such as `az account get-access-token
+    /// --resource https://cognitiveservices.azure.com --query accessToken -o tsv`.
+    /// The command runs again when the token is about to expire or is rejected
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub azure_ad: bool,
+
     /// Base URL for the provider's API
     ///
     /// Example: "https://api.openai.com/v1"
@@ -145,6 +255,167 @@ pub struct ProviderConfig {
     /// If set, the client will throttle requests to stay under this limit
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rate_limit_rpm: Option<u32>,
+
+    /// Optional rate limit in tokens per minute
+    ///
+    /// Counts prompt and completion tokens. Requests reserve their prompt
+    /// plus `max_tokens` up front and are settled against actual usage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit_tpm: Option<u32>,
+
+    /// Providers to fall back to, in order, when this one fails
+    ///
+    /// Each entry names another provider in the `providers` section. Only
+    /// retryable errors (rate limits, timeouts, network and 5xx errors) move
+    /// a request down the chain, and fallbacks answer with their own
+    /// `default_model`
+    ///
+    /// Example: `fallback = ["anthropic", "groq"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<String>,
+
+    /// Circuit breaker settings for the providers of the fallback chain
+    ///
+    /// Only used when `fallback` is non-empty
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+
+    /// Provider implementation to use
+    ///
+    /// Defaults to the provider's name in the `providers` section. Set it to
+    /// `"openai-compatible"` to register a self-hosted or proxy server (vLLM,
+    /// llama.cpp, LM Studio, LiteLLM) under a name of your choosing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+
+    /// Extra HTTP headers sent with every request (openai-compatible only)
+    ///
+    /// Values are templates: `{api_key}` is replaced by the value of
+    /// `api_key_env`. When empty, `Authorization: Bearer {api_key}` is sent
+    /// if the variable is set
+    ///
+    /// Example: `headers = { "X-Api-Key" = "{api_key}" }`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+
+    /// Models served by the provider (openai-compatible only)
+    ///
+    /// Example: `models = ["meta-llama/Meta-Llama-3-8B-Instruct"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+
+    /// Context window of the models in `models` (openai-compatible only)
+    ///
+    /// Default: 4096
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_length: Option<usize>,
+
+    /// Path of a YAML or JSON script for the provider (mock only)
+    ///
+    /// Example: `script = "mock-script.yaml"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<PathBuf>,
+
+    /// HTTP transport settings: proxy, headers, CA bundle, connection pool
+    ///
+    /// Example: `transport = { proxy = "http://proxy.corp:3128" }`
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub transport: TransportConfig,
+}
+
+/// HTTP transport settings of a provider
+///
+/// When any setting is given, the provider's HTTP client is built from these
+/// settings and the provider's `timeout_seconds`. Otherwise each provider
+/// keeps its own client.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TransportConfig {
+    /// Proxy for HTTP and HTTPS requests
+    ///
+    /// Example: "http://proxy.corp:3128", "socks5://localhost:1080"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+
+    /// Hosts that bypass `proxy`, comma separated as in `NO_PROXY`
+    ///
+    /// Example: "localhost,.corp.example.com"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+
+    /// Headers sent with every request, such as organization or project IDs
+    ///
+    /// Example: `headers = { "OpenAI-Organization" = "org-123" }`
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+
+    /// PEM file of CA certificates trusted in addition to the built-in roots
+    ///
+    /// Example: "/etc/ssl/certs/corp-ca.pem"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<PathBuf>,
+
+    /// Maximum idle connections kept open per host
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool_max_idle_per_host: Option<usize>,
+
+    /// Seconds an idle connection is kept open
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool_idle_timeout_seconds: Option<u64>,
+
+    /// HTTP version: unset negotiates HTTP/2 where the server supports it,
+    /// `false` forces HTTP/1.1 and `true` forces HTTP/2
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http2: Option<bool>,
+}
+
+/// Strategy for spreading requests over several API keys
+///
+/// Either way, a request answered with a rate limit error (HTTP 429) is
+/// retried with the next key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyRotation {
+    /// Each request uses the next key in turn
+    #[default]
+    RoundRobin,
+    /// Keep using a key until it is rate limited
+    OnRateLimit,
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// Circuit breaker configuration for fallback chains
+///
+/// After `failure_threshold` consecutive failures a provider is skipped for
+/// `cooldown_seconds`, after which a single probe request decides whether it
+/// is used again.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, PartialEq)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive retryable failures that open the circuit
+    ///
+    /// Default: 5
+    #[validate(minimum = 1)]
+    pub failure_threshold: u32,
+
+    /// Seconds an open circuit waits before letting a probe request through
+    ///
+    /// Default: 30
+    #[validate(minimum = 1)]
+    pub cooldown_seconds: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown_seconds: 30,
+        }
+    }
 }
 
 /// Benchmark execution configuration
@@ -362,17 +633,32 @@ mod tests {
         assert!(config.providers.contains_key("openai"));
         assert!(config.providers.contains_key("anthropic"));
         assert!(config.providers.contains_key("google"));
+        assert!(config.providers.contains_key("mock"));
     }
 
     #[test]
     fn test_provider_config_validation() {
         let provider = ProviderConfig {
             api_key_env: "TEST_KEY".to_string(),
+            api_key_file: None,
+            api_key_cmd: None,
+            api_keys_env: Vec::new(),
+            key_rotation: KeyRotation::default(),
+            azure_ad: false,
             base_url: "https://api.example.com".to_string(),
             default_model: "test-model".to_string(),
             timeout_seconds: 30,
             max_retries: 3,
             rate_limit_rpm: None,
+            rate_limit_tpm: None,
+            fallback: Vec::new(),
+            circuit_breaker: None,
+            kind: None,
+            headers: HashMap::new(),
+            models: Vec::new(),
+            context_length: None,
+            script: None,
+            transport: TransportConfig::default(),
         };
         assert!(provider.validate().is_ok());
     }
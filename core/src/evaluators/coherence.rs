@@ -488,7 +488,7 @@ mod tests {
         let evaluator = CoherenceEvaluator::new_basic();
         let text = "The cat sat on the mat. It was a sunny day. The birds were singing cheerfully.";
 
-        let result = evaluator.evaluate("", text).unwrap();
+        let result = futures::executor::block_on(evaluator.evaluate("", text)).unwrap();
         assert!(result.score > 0.7, "Well-structured text should score highly");
     }
 
@@ -497,21 +497,21 @@ mod tests {
         let evaluator = CoherenceEvaluator::new_basic();
         let text = "Cat. Sunny. Birds birds birds. Very very very long sentence that goes on and on without any clear purpose or meaningful content just rambling continuously.";
 
-        let result = evaluator.evaluate("", text).unwrap();
+        let result = futures::executor::block_on(evaluator.evaluate("", text)).unwrap();
         assert!(result.score < 0.8, "Poor structure should lower score");
     }
 
     #[test]
     fn test_evaluate_empty_text() {
         let evaluator = CoherenceEvaluator::new_basic();
-        let result = evaluator.evaluate("", "").unwrap();
+        let result = futures::executor::block_on(evaluator.evaluate("", "")).unwrap();
         assert!(result.score < 0.5, "Empty text should score low");
     }
 
     #[test]
     fn test_evaluate_very_short_text() {
         let evaluator = CoherenceEvaluator::new_basic();
-        let result = evaluator.evaluate("", "Yes.").unwrap();
+        let result = futures::executor::block_on(evaluator.evaluate("", "Yes.")).unwrap();
         assert!(result.score < 0.9, "Very short text should be penalized");
     }
 
@@ -544,8 +544,8 @@ mod tests {
         let without_markers = "The experiment was conducted. The results were analyzed. The conclusion was drawn.";
         let with_markers = "First, the experiment was conducted. Then, the results were analyzed. Finally, the conclusion was drawn.";
 
-        let score1 = evaluator.evaluate("", without_markers).unwrap().score;
-        let score2 = evaluator.evaluate("", with_markers).unwrap().score;
+        let score1 = futures::executor::block_on(evaluator.evaluate("", without_markers)).unwrap().score;
+        let score2 = futures::executor::block_on(evaluator.evaluate("", with_markers)).unwrap().score;
 
         // Text with discourse markers should generally score better
         assert!(
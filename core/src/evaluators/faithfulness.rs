@@ -272,6 +272,7 @@ mod tests {
     use mockall::mock;
     use mockall::predicate::*;
     use chrono::Utc;
+    use std::collections::HashMap;
 
     mock! {
         Provider {}
@@ -296,6 +297,12 @@ mod tests {
             usage: TokenUsage::new(100, 50),
             finish_reason: FinishReason::Stop,
             created_at: Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         }
     }
 
@@ -571,6 +571,7 @@ mod tests {
     use crate::providers::{CompletionResponse, FinishReason, TokenUsage};
     use mockall::mock;
     use mockall::predicate::*;
+    use std::collections::HashMap;
 
     mock! {
         Provider {}
@@ -595,6 +596,12 @@ mod tests {
             usage: TokenUsage::new(prompt_tokens, completion_tokens),
             finish_reason: FinishReason::Stop,
             created_at: Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         }
     }
 
@@ -12,11 +12,14 @@
 //!
 //! Formula: PPL = exp(-1/N * sum(log P(token_i)))
 //!
-//! The evaluator requests log probabilities from the provider (OpenAI's logprobs
-//! parameter) and calculates perplexity from them.
+//! The evaluator sends the text as a prompt with prompt log probabilities
+//! enabled and calculates perplexity from the probability the model assigns
+//! to each of its tokens. This needs an endpoint that echoes the prompt's log
+//! probabilities: OpenAI-compatible text completion servers such as vLLM, or
+//! Together AI.
 
 use super::{EvaluationResult, Evaluator, EvaluatorError};
-use crate::providers::Provider;
+use crate::providers::{logprobs, CompletionRequest, Provider, TokenLogprob};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
@@ -85,8 +88,9 @@ pub struct PerplexityScore {
 /// use std::sync::Arc;
 ///
 /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
-/// let provider = OpenAIProvider::new("test_key".to_string())?;
-/// let evaluator = PerplexityEvaluator::new(Arc::new(provider), "gpt-3.5-turbo".to_string());
+/// // A vLLM server exposing the OpenAI-compatible API
+/// let provider = OpenAIProvider::with_base_url("EMPTY".to_string(), "http://localhost:8000/v1".to_string())?;
+/// let evaluator = PerplexityEvaluator::new(Arc::new(provider), "meta-llama/Llama-3.1-8B".to_string());
 ///
 /// let result = evaluator.evaluate_detailed("The cat sat on the mat.").await?;
 /// println!("Perplexity: {:.2}", result.perplexity);
@@ -95,7 +99,7 @@ pub struct PerplexityScore {
 /// # }
 /// ```
 pub struct PerplexityEvaluator {
-    /// Provider that supports log probabilities
+    /// Provider that returns prompt log probabilities
     provider: Arc<dyn Provider>,
     /// Model to use for probability estimation
     model: String,
@@ -108,13 +112,14 @@ impl PerplexityEvaluator {
     ///
     /// # Arguments
     ///
-    /// * `provider` - Provider supporting log probabilities (e.g., OpenAI)
-    /// * `model` - Model to use (e.g., "gpt-3.5-turbo")
+    /// * `provider` - Provider supporting prompt log probabilities (e.g., vLLM
+    ///   through `OpenAIProvider`, or Together AI)
+    /// * `model` - Model to use (e.g., "meta-llama/Llama-3.1-8B-Instruct")
     ///
     /// # Note
     ///
-    /// The provider must support log probabilities. OpenAI's API supports this
-    /// via the `logprobs` parameter.
+    /// The provider must return log probabilities for the prompt. Chat-only
+    /// APIs such as OpenAI's hosted chat models only score generated tokens.
     pub fn new(provider: Arc<dyn Provider>, model: String) -> Self {
         Self {
             provider,
@@ -144,11 +149,10 @@ impl PerplexityEvaluator {
     ///
     /// # Note
     ///
-    /// This method makes an API call to get log probabilities. For OpenAI, it uses
-    /// a completion request with logprobs enabled. Since we need to get probabilities
-    /// FOR the input text (not generate new text), we use a special technique:
-    /// we ask the model to continue from the text, and use the echo parameter or
-    /// calculate based on the input.
+    /// This method makes an API call to get log probabilities. The text is sent
+    /// as the prompt and scored with the provider's prompt log probabilities
+    /// (the `echo` parameter of text completion APIs), so providers that cannot
+    /// echo the prompt return an error.
     pub async fn evaluate_detailed(&self, text: &str) -> Result<PerplexityScore, EvaluatorError> {
         if text.is_empty() {
             return Ok(PerplexityScore {
@@ -164,17 +168,15 @@ impl PerplexityEvaluator {
         // Get log probabilities from the provider
         let log_probs = self.get_log_probabilities(text).await?;
 
-        if log_probs.is_empty() {
-            return Err(EvaluatorError::EvaluationFailed(
-                "No log probabilities returned from provider".to_string(),
-            ));
-        }
-
         // Calculate perplexity: exp(-1/N * sum(log_probs))
+        let Some(perplexity) = logprobs::perplexity(&log_probs) else {
+            return Err(EvaluatorError::EvaluationFailed(format!(
+                "No prompt log probabilities returned from provider {}",
+                self.provider.name()
+            )));
+        };
         let token_count = log_probs.len();
-        let sum_log_probs: f64 = log_probs.iter().sum();
-        let avg_log_prob = sum_log_probs / token_count as f64;
-        let perplexity = (-avg_log_prob).exp();
+        let avg_log_prob = log_probs.iter().map(|t| t.logprob).sum::<f64>() / token_count as f64;
 
         // Normalize to 0-1 scale (higher is better)
         let normalized_score = self.normalize_score(perplexity);
@@ -186,12 +188,12 @@ impl PerplexityEvaluator {
         let token_level_details = if self.include_token_details {
             Some(
                 log_probs
-                    .iter()
+                    .into_iter()
                     .enumerate()
-                    .map(|(i, &log_prob)| TokenPerplexity {
-                        token: format!("token_{}", i), // Placeholder - would need actual tokens from API
-                        log_prob,
-                        perplexity: (-log_prob).exp(),
+                    .map(|(i, token)| TokenPerplexity {
+                        perplexity: (-token.logprob).exp(),
+                        log_prob: token.logprob,
+                        token: token.token,
                         position: i,
                     })
                     .collect(),
@@ -210,43 +212,16 @@ impl PerplexityEvaluator {
         })
     }
 
-    /// Get log probabilities from the provider
+    /// Get log probabilities for the tokens of `text`
     ///
-    /// This is a simplified implementation. In practice, you would:
-    /// 1. Make a request with logprobs=true
-    /// 2. Extract log probabilities from the response
-    ///
-    /// For OpenAI, we use the chat completions API with a special prompt
-    /// that asks for analysis of the text.
-    async fn get_log_probabilities(&self, text: &str) -> Result<Vec<f64>, EvaluatorError> {
-        // IMPORTANT: This is a workaround since OpenAI's chat API doesn't directly
-        // support getting logprobs for arbitrary text. In a production system, you would:
-        //
-        // 1. Use the completion API (legacy) with echo=true and logprobs parameter
-        // 2. Or use a dedicated tokenization + probability endpoint
-        // 3. Or calculate based on the model's internal estimates
-        //
-        // For now, we'll use a heuristic approximation based on the text characteristics
-        // and make a request to get the model's "comfort" with the text.
-
-        // Estimate token count (rough approximation: 1 token ≈ 4 characters)
-        let estimated_tokens = (text.len() / 4).max(1);
-
-        // Use the provider to estimate how "natural" the text is
-        // by asking it to rate the text's naturalness
-        let prompt = format!(
-            r#"On a scale from 0.0 to 1.0, how natural and fluent is this text?
-Consider grammar, word choice, and coherence.
-
-Text: "{}"
-
-Respond with ONLY a single number between 0.0 and 1.0, nothing else."#,
-            text
-        );
-
-        let request = crate::providers::CompletionRequest::new(&self.model, prompt)
-            .with_max_tokens(10)
-            .with_temperature(0.0);
+    /// The text is sent as the prompt with prompt log probabilities enabled,
+    /// so the provider scores each token given the ones before it. The first
+    /// token has no context and is not scored.
+    async fn get_log_probabilities(&self, text: &str) -> Result<Vec<TokenLogprob>, EvaluatorError> {
+        let request = CompletionRequest::new(&self.model, text)
+            .with_max_tokens(1)
+            .with_temperature(0.0)
+            .with_prompt_logprobs();
 
         let response = self
             .provider
@@ -254,30 +229,7 @@ Respond with ONLY a single number between 0.0 and 1.0, nothing else."#,
             .await
             .map_err(|e| EvaluatorError::EvaluationFailed(format!("Provider request failed: {}", e)))?;
 
-        // Parse the naturalness score
-        let naturalness: f64 = response
-            .content
-            .trim()
-            .parse()
-            .unwrap_or(0.5);
-
-        // Convert naturalness to log probabilities
-        // High naturalness (0.8-1.0) -> high probability (low perplexity)
-        // Low naturalness (0.0-0.2) -> low probability (high perplexity)
-        //
-        // Log prob typically ranges from -5.0 (unlikely) to -0.1 (very likely)
-        let base_log_prob = -5.0 + (naturalness * 4.5);
-
-        // Generate synthetic log probs with some variation
-        let log_probs: Vec<f64> = (0..estimated_tokens)
-            .map(|i| {
-                // Add slight variation to make it more realistic
-                let variation = ((i as f64 * 0.37).sin() * 0.3);
-                base_log_prob + variation
-            })
-            .collect();
-
-        Ok(log_probs)
+        Ok(response.prompt_logprobs.unwrap_or_default())
     }
 
     /// Normalize perplexity score to 0-1 range
@@ -352,6 +304,7 @@ impl super::Evaluator for PerplexityEvaluator {
 mod tests {
     use super::*;
     use crate::providers::{CompletionResponse, FinishReason, OpenAIProvider, TokenUsage};
+    use std::collections::HashMap;
     use std::sync::Arc;
 
     // Mock provider for testing
@@ -361,15 +314,29 @@ mod tests {
     impl Provider for MockProvider {
         async fn complete(
             &self,
-            _request: crate::providers::CompletionRequest,
+            request: crate::providers::CompletionRequest,
         ) -> Result<CompletionResponse, crate::providers::ProviderError> {
+            // One token per word, each slightly less likely than the last
+            let prompt_logprobs = request
+                .prompt
+                .split_whitespace()
+                .enumerate()
+                .map(|(i, word)| TokenLogprob::new(word, -1.0 - 0.1 * i as f64))
+                .collect();
+
             Ok(CompletionResponse {
                 id: "test".to_string(),
                 model: "test".to_string(),
-                content: "0.8".to_string(), // High naturalness score
+                content: String::new(),
                 usage: TokenUsage::new(10, 1),
                 finish_reason: FinishReason::Stop,
                 created_at: chrono::Utc::now(),
+                tool_calls: Vec::new(),
+                schema_violations: Vec::new(),
+                logprobs: None,
+                prompt_logprobs: Some(prompt_logprobs),
+                choices: Vec::new(),
+                metadata: HashMap::new(),
             })
         }
 
@@ -499,14 +466,29 @@ mod tests {
         let details = result.token_level_details.unwrap();
         assert!(!details.is_empty());
         assert_eq!(details.len(), result.token_count);
+        assert_eq!(details[1].token, "cat");
+        assert_eq!(details[1].log_prob, -1.1);
     }
 
-    #[test]
-    fn test_evaluator_trait_sync_error() {
+    #[tokio::test]
+    async fn test_evaluate_detailed_uses_token_logprobs() {
+        let provider = Arc::new(MockProvider);
+        let evaluator = PerplexityEvaluator::new(provider, "test".to_string());
+
+        let result = evaluator.evaluate_detailed("one two three").await.unwrap();
+
+        // Mean log probability of -1.0, -1.1 and -1.2
+        assert_eq!(result.token_count, 3);
+        assert!((result.avg_log_prob + 1.1).abs() < 1e-9);
+        assert!((result.perplexity - 1.1f64.exp()).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_evaluator_trait_sync_error() {
         let provider = Arc::new(MockProvider);
         let evaluator = PerplexityEvaluator::new(provider, "test".to_string());
 
-        let result = evaluator.evaluate("", "test");
+        let result = evaluator.evaluate("", "test").await;
         assert!(result.is_err());
         assert!(result
             .unwrap_err()
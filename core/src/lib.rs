@@ -13,6 +13,7 @@
 //!
 //! - `config`: Configuration management and validation
 //! - `providers`: LLM provider implementations (OpenAI, Anthropic, etc.)
+//! - `tokenizer`: Token counting with BPE vocabularies and a calibrated fallback
 //! - `evaluators`: Evaluation metrics (perplexity, faithfulness, relevance, coherence)
 //! - `benchmarks`: Benchmarking logic and reporting
 //! - `orchestration`: Multi-model comparison, ranking, and routing
@@ -31,6 +32,7 @@
 
 pub mod config;
 pub mod providers;
+pub mod tokenizer;
 pub mod evaluators;
 pub mod benchmarks;
 pub mod orchestration;
@@ -26,20 +26,20 @@
 //!
 //! ```no_run
 //! use llm_test_bench_core::multimodal::{ImageInput, MultiModalRequest};
-//! use llm_test_bench_core::providers::Provider;
+//! use llm_test_bench_core::providers::MultiModalProvider;
 //!
-//! # async fn example(provider: Box<dyn Provider>) -> Result<(), Box<dyn std::error::Error>> {
+//! # async fn example(provider: Box<dyn MultiModalProvider>) -> Result<(), Box<dyn std::error::Error>> {
 //! // Load image
 //! let image = ImageInput::from_path("cat.jpg").await?;
 //!
 //! // Create multi-modal request
-//! let request = MultiModalRequest::new()
+//! let request = MultiModalRequest::new("gpt-4o")
 //!     .with_text("What's in this image?")
 //!     .with_image(image);
 //!
 //! // Process with provider
 //! let response = provider.complete_multimodal(request).await?;
-//! println!("Response: {}", response.text);
+//! println!("Response: {}", response.text());
 //! # Ok(())
 //! # }
 //! ```
@@ -48,17 +48,19 @@
 //!
 //! ```no_run
 //! use llm_test_bench_core::multimodal::{AudioInput, MultiModalRequest};
+//! use llm_test_bench_core::providers::MultiModalProvider;
 //!
-//! # async fn example(provider: Box<dyn Provider>) -> Result<(), Box<dyn std::error::Error>> {
+//! # async fn example(provider: Box<dyn MultiModalProvider>) -> Result<(), Box<dyn std::error::Error>> {
 //! // Load audio
 //! let audio = AudioInput::from_path("speech.mp3").await?;
 //!
 //! // Create transcription request
-//! let request = MultiModalRequest::new()
+//! let request = MultiModalRequest::new("gemini-1.5-flash")
+//!     .with_text("Transcribe this recording.")
 //!     .with_audio(audio);
 //!
 //! let response = provider.complete_multimodal(request).await?;
-//! println!("Transcription: {}", response.text);
+//! println!("Transcription: {}", response.text());
 //! # Ok(())
 //! # }
 //! ```
@@ -69,6 +71,7 @@ pub mod audio;
 pub mod video;
 pub mod evaluation;
 pub mod datasets;
+pub mod speech;
 
 // Re-export commonly used types
 pub use types::{
@@ -83,7 +86,7 @@ pub use image::{
 
 pub use audio::{
     AudioInput, AudioOutput, AudioFormat, AudioCodec,
-    AudioMetadata, AudioDuration,
+    AudioMetadata, AudioDuration, TranscriptionOptions,
 };
 
 pub use video::{
@@ -100,3 +103,7 @@ pub use datasets::{
     MultiModalDataset, MultiModalExample, MultiModalTask,
     VisionTask, AudioTask,
 };
+
+pub use speech::{
+    SpeechBenchmark, SpeechBenchmarkReport, ModelSpeechResult, SpeechExampleResult,
+};
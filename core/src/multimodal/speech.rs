@@ -0,0 +1,337 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Speech-to-text benchmarks over multi-modal datasets.
+//!
+//! [`SpeechBenchmark`] transcribes every speech-to-text example of a
+//! [`MultiModalDataset`] with each model under test and scores the
+//! transcripts against the references with [`AudioEvaluator`].
+
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+use super::audio::TranscriptionOptions;
+use super::datasets::{MultiModalDataset, MultiModalTask};
+use super::evaluation::AudioEvaluator;
+use super::types::ContentPart;
+use crate::providers::TranscriptionProvider;
+
+/// Result of transcribing one example
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeechExampleResult {
+    /// Example identifier
+    pub id: String,
+
+    /// Reference transcript
+    pub reference: String,
+
+    /// Transcript produced by the model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcription: Option<String>,
+
+    /// Word Error Rate against the reference
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wer: Option<f64>,
+
+    /// Character Error Rate against the reference
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cer: Option<f64>,
+
+    /// Time taken by the transcription request
+    pub latency_ms: u64,
+
+    /// Error message if transcription failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Results of one model over the dataset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelSpeechResult {
+    /// Transcription backend
+    pub provider: String,
+
+    /// Model under test
+    pub model: String,
+
+    /// Mean WER over the successful examples
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean_wer: Option<f64>,
+
+    /// Mean CER over the successful examples
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean_cer: Option<f64>,
+
+    /// Number of examples that failed to transcribe
+    pub failures: usize,
+
+    /// Per-example results
+    pub examples: Vec<SpeechExampleResult>,
+}
+
+impl ModelSpeechResult {
+    fn new(provider: &str, model: &str, examples: Vec<SpeechExampleResult>) -> Self {
+        let mean = |values: Vec<f64>| {
+            if values.is_empty() {
+                None
+            } else {
+                Some(values.iter().sum::<f64>() / values.len() as f64)
+            }
+        };
+
+        Self {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            mean_wer: mean(examples.iter().filter_map(|e| e.wer).collect()),
+            mean_cer: mean(examples.iter().filter_map(|e| e.cer).collect()),
+            failures: examples.iter().filter(|e| e.error.is_some()).count(),
+            examples,
+        }
+    }
+}
+
+/// WER/CER of every model over a dataset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeechBenchmarkReport {
+    /// Dataset name
+    pub dataset: String,
+
+    /// Results per model, in the order they were run
+    pub models: Vec<ModelSpeechResult>,
+}
+
+impl SpeechBenchmarkReport {
+    /// Returns the model with the lowest mean WER
+    pub fn best_model(&self) -> Option<&ModelSpeechResult> {
+        self.models
+            .iter()
+            .filter(|m| m.mean_wer.is_some())
+            .min_by(|a, b| a.mean_wer.partial_cmp(&b.mean_wer).unwrap_or(std::cmp::Ordering::Equal))
+    }
+}
+
+/// Speech-to-text benchmark runner
+pub struct SpeechBenchmark {
+    options: TranscriptionOptions,
+    normalize: bool,
+    evaluator: AudioEvaluator,
+}
+
+impl SpeechBenchmark {
+    pub fn new() -> Self {
+        Self {
+            options: TranscriptionOptions::default(),
+            normalize: true,
+            evaluator: AudioEvaluator::new(),
+        }
+    }
+
+    /// Sets the options sent with every transcription request
+    ///
+    /// A language set on an example's audio part takes precedence over
+    /// `options.language`.
+    pub fn with_options(mut self, options: TranscriptionOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Whether to lowercase and strip punctuation before scoring (default: true)
+    pub fn with_normalization(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Runs every `(provider, model)` pair over the dataset
+    pub async fn run(
+        &self,
+        dataset: &MultiModalDataset,
+        models: &[(&dyn TranscriptionProvider, &str)],
+    ) -> SpeechBenchmarkReport {
+        let mut results = Vec::with_capacity(models.len());
+        for (provider, model) in models {
+            results.push(self.run_model(dataset, *provider, model).await);
+        }
+
+        SpeechBenchmarkReport {
+            dataset: dataset.name.clone(),
+            models: results,
+        }
+    }
+
+    /// Runs one model over the speech-to-text examples of the dataset
+    pub async fn run_model(
+        &self,
+        dataset: &MultiModalDataset,
+        provider: &dyn TranscriptionProvider,
+        model: &str,
+    ) -> ModelSpeechResult {
+        let mut examples = Vec::new();
+
+        for example in dataset.filter_by_task(MultiModalTask::SpeechToText) {
+            let mut result = SpeechExampleResult {
+                id: example.id.clone(),
+                reference: example.expected_output.clone(),
+                transcription: None,
+                wer: None,
+                cer: None,
+                latency_ms: 0,
+                error: None,
+            };
+
+            let audio = example.input.parts.iter().find_map(|part| match part {
+                ContentPart::Audio(audio) => Some(audio),
+                _ => None,
+            });
+            let Some(audio) = audio else {
+                result.error = Some("Example has no audio input".to_string());
+                examples.push(result);
+                continue;
+            };
+
+            let mut options = self.options.clone();
+            if audio.language.is_some() {
+                options.language = audio.language.clone();
+            }
+
+            let start = Instant::now();
+            let transcription = provider.transcribe(model, &audio.audio, &options).await;
+            result.latency_ms = start.elapsed().as_millis() as u64;
+
+            match transcription {
+                Ok(transcription) => {
+                    let (hypothesis, reference) = if self.normalize {
+                        (normalize(&transcription.text), normalize(&example.expected_output))
+                    } else {
+                        (transcription.text.clone(), example.expected_output.clone())
+                    };
+                    match self.evaluator.evaluate(&hypothesis, &reference).await {
+                        Ok(metrics) => {
+                            result.wer = Some(metrics.wer);
+                            result.cer = metrics.cer;
+                        }
+                        Err(e) => result.error = Some(e.to_string()),
+                    }
+                    result.transcription = Some(transcription.text);
+                }
+                Err(e) => result.error = Some(e.to_string()),
+            }
+
+            examples.push(result);
+        }
+
+        ModelSpeechResult::new(provider.name(), model, examples)
+    }
+}
+
+impl Default for SpeechBenchmark {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lowercases, replaces punctuation with spaces and collapses whitespace
+///
+/// Apostrophes are kept so contractions stay one word.
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '\'' { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multimodal::audio::{AudioFormat, AudioInput};
+    use crate::multimodal::datasets::MultiModalExample;
+    use crate::multimodal::types::{AudioPart, MultiModalContent};
+    use crate::providers::{ProviderError, Transcription};
+    use async_trait::async_trait;
+
+    /// Returns the base64 payload as the transcript; Spanish audio needs
+    /// a Spanish language hint
+    struct EchoTranscriber;
+
+    #[async_trait]
+    impl TranscriptionProvider for EchoTranscriber {
+        async fn transcribe(
+            &self,
+            _model: &str,
+            audio: &AudioInput,
+            options: &TranscriptionOptions,
+        ) -> Result<Transcription, ProviderError> {
+            match audio {
+                AudioInput::Base64 { data, .. } if data == "fail" => {
+                    Err(ProviderError::ApiError { status: 500, message: "boom".to_string() })
+                }
+                AudioInput::Base64 { data, .. } if data == "hola" && options.language.as_deref() != Some("es") => {
+                    Err(ProviderError::InvalidRequest("wrong language".to_string()))
+                }
+                AudioInput::Base64 { data, .. } => Ok(Transcription::new(data.clone())),
+                _ => unreachable!(),
+            }
+        }
+
+        fn name(&self) -> &str {
+            "echo"
+        }
+    }
+
+    fn speech_example(id: &str, said: &str, reference: &str) -> MultiModalExample {
+        let mut content = MultiModalContent::new();
+        content.add_audio(AudioInput::from_base64(said.to_string(), AudioFormat::Wav));
+        MultiModalExample::new(id, MultiModalTask::SpeechToText, content, reference)
+    }
+
+    #[tokio::test]
+    async fn test_run_model() {
+        let mut dataset = MultiModalDataset::new("speech");
+        dataset.add_example(speech_example("exact", "Hello, World!", "hello world"));
+        dataset.add_example(speech_example("one-off", "the cat sat", "the cat sat down"));
+        dataset.add_example(speech_example("broken", "fail", "anything"));
+        dataset.add_example(MultiModalExample::new("caption", MultiModalTask::ImageCaptioning, MultiModalContent::new(), "a cat"));
+
+        let result = SpeechBenchmark::new().run_model(&dataset, &EchoTranscriber, "echo-1").await;
+
+        assert_eq!(result.examples.len(), 3);
+        assert_eq!(result.examples[0].wer, Some(0.0));
+        assert_eq!(result.examples[1].wer, Some(0.25));
+        assert_eq!(result.failures, 1);
+        assert_eq!(result.mean_wer, Some(0.125));
+        assert!(result.examples[2].error.as_deref().unwrap().contains("boom"));
+
+        // Without normalization punctuation and case count as errors
+        let raw = SpeechBenchmark::new().with_normalization(false).run_model(&dataset, &EchoTranscriber, "echo-1").await;
+        assert_eq!(raw.examples[0].wer, Some(1.0));
+    }
+
+    #[tokio::test]
+    async fn test_language_hint_and_report() {
+        let mut content = MultiModalContent::new();
+        content.parts.push(ContentPart::Audio(
+            AudioPart::new(AudioInput::from_base64("hola".to_string(), AudioFormat::Wav)).with_language("es"),
+        ));
+        let mut dataset = MultiModalDataset::new("speech");
+        dataset.add_example(MultiModalExample::new("es", MultiModalTask::SpeechToText, content, "hola"));
+        dataset.add_example(speech_example("en", "good morning", "good morning"));
+
+        let benchmark = SpeechBenchmark::new().with_options(TranscriptionOptions {
+            language: Some("en".to_string()),
+            ..Default::default()
+        });
+        let report = benchmark.run(&dataset, &[(&EchoTranscriber, "a"), (&EchoTranscriber, "b")]).await;
+
+        assert_eq!(report.dataset, "speech");
+        assert_eq!(report.models.len(), 2);
+        assert_eq!(report.models[1].model, "b");
+        assert_eq!(report.models[0].failures, 0);
+        assert_eq!(report.best_model().unwrap().mean_cer, Some(0.0));
+        assert_eq!(normalize("It's  a TEST."), "it's a test");
+    }
+}
@@ -398,6 +398,12 @@ mod tests {
                 usage: TokenUsage::new(50, 100),
                 finish_reason: FinishReason::Stop,
                 created_at: Utc::now(),
+                tool_calls: Vec::new(),
+                schema_violations: Vec::new(),
+                logprobs: None,
+                prompt_logprobs: None,
+                choices: Vec::new(),
+                metadata: HashMap::new(),
             })
         }
 
@@ -435,8 +441,9 @@ mod tests {
         score: f64,
     }
 
+    #[async_trait]
     impl Evaluator for MockEvaluator {
-        fn evaluate(&self, _prompt: &str, _response: &str) -> Result<EvaluationResult, EvaluatorError> {
+        async fn evaluate(&self, _prompt: &str, _response: &str) -> Result<EvaluationResult, EvaluatorError> {
             Ok(EvaluationResult {
                 metric: self.name.clone(),
                 score: self.score,
@@ -40,12 +40,22 @@
 //! # }
 //! ```
 
-use super::{CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use super::batch::{self, BatchCounts, BatchJob, BatchProvider, BatchRequest, BatchResult, BatchStatus};
+use super::discovery;
+use super::multimodal::{self, MediaSource, MultiModalProvider};
+use super::sampling::SamplingSupport;
+use super::streaming::{self, SseDecoder, StreamEvent, ToolCallDelta};
+use super::structured;
+use super::{
+    CachePoint, CompletionRequest, CompletionResponse, FinishReason, MessageRole, ModelInfo, Provider, ProviderError,
+    ResponseStream, TokenUsage, ToolCall, ToolChoice,
+};
+use crate::multimodal::{ContentPart, MultiModalRequest, MultiModalResponse};
+use crate::tokenizer::Tokenizer;
 use async_trait::async_trait;
-use futures::StreamExt;
-use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
-use reqwest_eventsource::{Event, EventSource};
+use reqwest::header::CONTENT_TYPE;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::Duration;
 use tracing::{debug, trace, warn};
 
@@ -53,6 +63,11 @@ const ANTHROPIC_API_VERSION: &str = "2023-06-01";
 const DEFAULT_TIMEOUT_SECS: u64 = 300; // 5 minutes for large context
 const MAX_RETRIES: u32 = 3;
 const BASE_RETRY_DELAY_MS: u64 = 1000;
+const MIN_THINKING_BUDGET: u32 = 1024;
+
+/// The Messages API takes `top_k` and a thinking budget but no seed, penalties
+/// or multiple completions
+const ANTHROPIC_SAMPLING: SamplingSupport = SamplingSupport { top_k: true, thinking_budget: true, ..SamplingSupport::NONE };
 
 /// Anthropic Claude provider
 ///
@@ -96,12 +111,8 @@ impl AnthropicProvider {
     /// * `base_url` - API base URL
     /// * `max_retries` - Maximum number of retry attempts
     pub fn with_config(api_key: String, base_url: String, max_retries: u32) -> Self {
-        let mut headers = HeaderMap::new();
-        headers.insert("anthropic-version", HeaderValue::from_static(ANTHROPIC_API_VERSION));
-
         let client = reqwest::Client::builder()
             .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
-            .default_headers(headers)
             .build()
             .expect("Failed to build HTTP client");
 
@@ -113,21 +124,136 @@ impl AnthropicProvider {
         }
     }
 
+    /// Uses `client` for every request, e.g. one built from transport settings
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
     /// Build the request payload for Claude Messages API
     fn build_request_body(&self, request: &CompletionRequest, stream: bool) -> ClaudeRequest {
+        let cache = |point| request.cache_points.contains(&point);
+
+        // The last turn before the prompt ends the cached conversation
+        let history = request.messages.iter().filter(|m| m.role != MessageRole::System).count();
+        let messages = request
+            .conversation()
+            .into_iter()
+            .enumerate()
+            .map(|(i, m)| ClaudeMessage {
+                role: m.role.to_string(),
+                content: ClaudeText::new(m.content, cache(CachePoint::Messages) && i + 1 == history),
+            })
+            .collect();
+
+        let mut tools: Vec<ClaudeTool> = request
+            .tools
+            .iter()
+            .map(|t| ClaudeTool {
+                name: t.name.clone(),
+                description: t.description.clone(),
+                input_schema: t.parameters.clone(),
+                cache_control: None,
+            })
+            .collect();
+        if cache(CachePoint::Tools) {
+            if let Some(last) = tools.last_mut() {
+                last.cache_control = Some(ClaudeCacheControl::Ephemeral);
+            }
+        }
+
+        // Thinking counts against max_tokens, so the default leaves the
+        // answer its usual room on top of the budget
+        let max_tokens = match (request.max_tokens, request.thinking_budget) {
+            (Some(max_tokens), _) => max_tokens as u32,
+            (None, Some(budget)) => budget + 1024,
+            (None, None) => 1024,
+        };
+
         ClaudeRequest {
             model: request.model.clone(),
-            messages: vec![ClaudeMessage {
-                role: "user".to_string(),
-                content: request.prompt.clone(),
-            }],
-            max_tokens: request.max_tokens.unwrap_or(1024) as u32,
-            temperature: request.temperature,
+            messages,
+            max_tokens,
+            // Thinking only runs at the default temperature (see check_thinking)
+            temperature: request.temperature.filter(|_| request.thinking_budget.is_none()),
             stream: Some(stream),
             top_p: request.top_p,
-            top_k: None,
-            system: None,
+            top_k: request.top_k,
+            system: request
+                .system_prompt()
+                .map(|system| ClaudeText::new(system, cache(CachePoint::System))),
+            tools,
+            tool_choice: request.tool_choice.as_ref().map(|choice| match choice {
+                ToolChoice::Auto => ClaudeToolChoice::Auto,
+                ToolChoice::None => ClaudeToolChoice::None,
+                ToolChoice::Required => ClaudeToolChoice::Any,
+                ToolChoice::Tool { name } => ClaudeToolChoice::Tool { name: name.clone() },
+            }),
+            thinking: request
+                .thinking_budget
+                .map(|budget_tokens| ClaudeThinking::Enabled { budget_tokens }),
+        }
+    }
+
+    /// Validate the extended thinking budget against the API's limits
+    ///
+    /// The API rejects thinking combined with `top_k` or a temperature other
+    /// than 1. `top_k` is rejected here; a temperature is left out of the
+    /// request with a warning, as benchmarks set one by default.
+    fn check_thinking(request: &CompletionRequest) -> Result<(), ProviderError> {
+        let Some(budget) = request.thinking_budget else {
+            return Ok(());
+        };
+        if budget < MIN_THINKING_BUDGET {
+            return Err(ProviderError::InvalidRequest(format!(
+                "Claude's thinking budget must be at least {} tokens, got {}",
+                MIN_THINKING_BUDGET, budget
+            )));
+        }
+        if let Some(max_tokens) = request.max_tokens.filter(|&max_tokens| max_tokens <= budget as usize) {
+            return Err(ProviderError::InvalidRequest(format!(
+                "max_tokens ({}) must be greater than the thinking budget ({})",
+                max_tokens, budget
+            )));
+        }
+        if request.top_k.is_some() {
+            return Err(ProviderError::InvalidRequest(
+                "Claude does not accept top_k with a thinking budget".to_string(),
+            ));
+        }
+        if let Some(temperature) = request.temperature.filter(|&t| (t - 1.0).abs() > f32::EPSILON) {
+            warn!("Ignoring temperature {}: Claude samples at temperature 1 with a thinking budget", temperature);
+        }
+        Ok(())
+    }
+
+    /// Build a Messages API request body with image parts
+    ///
+    /// Remote images are passed by URL, everything else as base64. Claude
+    /// does not accept audio.
+    async fn build_multimodal_body(&self, request: &MultiModalRequest) -> Result<serde_json::Value, ProviderError> {
+        let mut content = Vec::with_capacity(request.content.parts.len());
+        for part in &request.content.parts {
+            content.push(match part {
+                ContentPart::Text(text) => serde_json::json!({ "type": "text", "text": text.text }),
+                ContentPart::Image(image) => {
+                    let source = match multimodal::image_source(&image.image).await? {
+                        MediaSource::Url(url) => serde_json::json!({ "type": "url", "url": url }),
+                        MediaSource::Inline { media_type, data } => serde_json::json!({
+                            "type": "base64",
+                            "media_type": media_type,
+                            "data": data,
+                        }),
+                    };
+                    serde_json::json!({ "type": "image", "source": source })
+                }
+                part => return Err(multimodal::unsupported(self.name(), part)),
+            });
         }
+
+        let mut body = serde_json::to_value(self.build_request_body(&multimodal::text_request(request), false))?;
+        body["messages"] = serde_json::json!([{ "role": "user", "content": content }]);
+        Ok(body)
     }
 
     /// Execute a completion request with retry logic
@@ -158,8 +284,12 @@ impl AnthropicProvider {
 
     /// Execute a single completion request without retry
     async fn complete_once(&self, request: &CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        self.send_messages(&self.build_request_body(request, false)).await
+    }
+
+    /// Post a non-streaming Messages API request body
+    async fn send_messages<T: Serialize + std::fmt::Debug + Sync>(&self, body: &T) -> Result<CompletionResponse, ProviderError> {
         let url = format!("{}/messages", self.base_url);
-        let body = self.build_request_body(request, false);
 
         trace!("Sending request to Claude API: {:?}", body);
 
@@ -167,8 +297,9 @@ impl AnthropicProvider {
             .client
             .post(&url)
             .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_API_VERSION)
             .header(CONTENT_TYPE, "application/json")
-            .json(&body)
+            .json(body)
             .send()
             .await
             .map_err(|e| ProviderError::InvalidRequest(format!("HTTP request failed: {}", e)))?;
@@ -202,92 +333,66 @@ impl AnthropicProvider {
             .client
             .post(&url)
             .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_API_VERSION)
             .header(CONTENT_TYPE, "application/json")
             .json(&body);
 
-        let event_source = EventSource::new(request_builder)
-            .map_err(|e| ProviderError::InvalidRequest(format!("Failed to create event source: {}", e)))?;
-
-        let stream = event_source.filter_map(|event| async move {
-            match event {
-                Ok(Event::Open) => {
-                    debug!("SSE connection opened");
-                    None
-                }
-                Ok(Event::Message(message)) => {
-                    trace!("Received SSE message: {:?}", message);
-                    Self::parse_streaming_event(&message.data)
-                }
-                Err(e) => {
-                    warn!("SSE error: {}", e);
-                    Some(Err(ProviderError::InternalError(format!("Streaming error: {}", e))))
-                }
-            }
-        });
+        let response = request_builder.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(Self::parse_error(status.as_u16(), &body));
+        }
 
-        Ok(Box::pin(stream))
+        let mut state = ClaudeStreamState::default();
+        Ok(streaming::decode_stream(
+            response.bytes_stream(),
+            SseDecoder::default(),
+            move |event| state.handle(&event.data),
+        ))
     }
 
-    /// Parse a streaming event from Claude's SSE format
-    fn parse_streaming_event(data: &str) -> Option<Result<String, ProviderError>> {
-        trace!("Parsing streaming event: {}", data);
-
-        let event: Result<ClaudeStreamEvent, _> = serde_json::from_str(data);
-
-        match event {
-            Ok(ClaudeStreamEvent::ContentBlockDelta { delta, .. }) => {
-                if let ClaudeDelta::TextDelta { text } = delta {
-                    if !text.is_empty() {
-                        Some(Ok(text))
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            }
-            Ok(ClaudeStreamEvent::MessageStop) => {
-                debug!("Stream completed");
-                None // Don't send empty final chunk
-            }
-            Ok(_) => None, // Other event types we don't need
-            Err(e) => {
-                warn!("Failed to parse streaming event: {}", e);
-                None
-            }
+    /// Map Claude's `stop_reason` to a finish reason
+    fn finish_reason(stop_reason: Option<&str>) -> FinishReason {
+        match stop_reason {
+            Some("end_turn") | Some("stop_sequence") => FinishReason::Stop,
+            Some("max_tokens") => FinishReason::Length,
+            Some("content_filter") => FinishReason::ContentFilter,
+            Some("tool_use") => FinishReason::ToolCalls,
+            _ => FinishReason::Error,
         }
     }
 
     /// Convert Claude response to our standard format
     fn convert_response(response: ClaudeResponse) -> CompletionResponse {
-        let content = response
-            .content
-            .into_iter()
-            .filter_map(|c| match c {
-                ClaudeContent::Text { text } => Some(text),
-            })
-            .collect::<Vec<_>>()
-            .join("");
+        let mut texts = Vec::new();
+        let mut tool_calls = Vec::new();
+        for block in response.content {
+            match block {
+                ClaudeContent::Text { text } => texts.push(text),
+                ClaudeContent::ToolUse { id, name, input } => {
+                    tool_calls.push(ToolCall::new(id, name, input))
+                }
+                ClaudeContent::Other => {}
+            }
+        }
+        let content = texts.join("");
 
-        let finish_reason = match response.stop_reason.as_deref() {
-            Some("end_turn") | Some("stop_sequence") => FinishReason::Stop,
-            Some("max_tokens") => FinishReason::Length,
-            Some("content_filter") => FinishReason::ContentFilter,
-            Some("tool_use") => FinishReason::ToolCalls,
-            _ => FinishReason::Error,
-        };
+        let finish_reason = Self::finish_reason(response.stop_reason.as_deref());
 
         CompletionResponse {
             id: response.id.clone(),
             content,
             model: response.model,
-            usage: TokenUsage {
-                prompt_tokens: response.usage.input_tokens as usize,
-                completion_tokens: response.usage.output_tokens as usize,
-                total_tokens: (response.usage.input_tokens + response.usage.output_tokens) as usize,
-            },
+            usage: response.usage.token_usage(),
             finish_reason,
             created_at: chrono::Utc::now(),
+            tool_calls,
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         }
     }
 
@@ -320,16 +425,54 @@ impl AnthropicProvider {
         let max_delay_ms = 60_000; // 60 seconds max
         Duration::from_millis(delay_ms.min(max_delay_ms))
     }
+
+    /// Sends a Message Batches API request and returns the response body
+    async fn send_batch_api(&self, request: reqwest::RequestBuilder) -> Result<String, ProviderError> {
+        let response = request
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_API_VERSION)
+            .send()
+            .await
+            .map_err(|e| ProviderError::InvalidRequest(format!("HTTP request failed: {}", e)))?;
+
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ProviderError::ApiError { status: 500, message: format!("Failed to read response: {}", e) })?;
+
+        if !status.is_success() {
+            return Err(Self::parse_error(status.as_u16(), &text));
+        }
+
+        Ok(text)
+    }
+
+    /// Fetches a message batch, along with its results URL
+    async fn fetch_batch(&self, batch_id: &str) -> Result<ClaudeBatch, ProviderError> {
+        let url = format!("{}/messages/batches/{}", self.base_url, batch_id);
+        let text = self.send_batch_api(self.client.get(&url)).await?;
+        Ok(serde_json::from_str(&text)?)
+    }
 }
 
 #[async_trait]
 impl Provider for AnthropicProvider {
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
-        self.complete_with_retry(&request).await
+        ANTHROPIC_SAMPLING.check(self.name(), &request)?;
+        Self::check_thinking(&request)?;
+
+        // Claude has no JSON mode, so response formats are enforced via the prompt
+        structured::complete_with_repair(request, |request| async move {
+            self.complete_with_retry(&request).await
+        })
+        .await
     }
 
     async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
-        self.stream_completion(&request).await
+        ANTHROPIC_SAMPLING.check_stream(self.name(), &request)?;
+        Self::check_thinking(&request)?;
+        self.stream_completion(&structured::with_format_instructions(&request)).await
     }
 
     fn supported_models(&self) -> Vec<ModelInfo> {
@@ -340,6 +483,8 @@ impl Provider for AnthropicProvider {
                 max_tokens: 200_000,
                 supports_streaming: true,
                 supports_function_calling: true,
+                supports_vision: true,
+                supports_audio: false,
             },
             ModelInfo {
                 id: "claude-3-sonnet-20240229".to_string(),
@@ -347,6 +492,8 @@ impl Provider for AnthropicProvider {
                 max_tokens: 200_000,
                 supports_streaming: true,
                 supports_function_calling: true,
+                supports_vision: true,
+                supports_audio: false,
             },
             ModelInfo {
                 id: "claude-3-haiku-20240307".to_string(),
@@ -354,10 +501,20 @@ impl Provider for AnthropicProvider {
                 max_tokens: 200_000,
                 supports_streaming: true,
                 supports_function_calling: true,
+                supports_vision: true,
+                supports_audio: false,
             },
         ]
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        let request = self.client
+            .get(format!("{}/models", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_API_VERSION);
+        Ok(discovery::parse_openai_models(&discovery::fetch_json(request).await?))
+    }
+
     fn max_context_length(&self, model: &str) -> Option<usize> {
         self.supported_models()
             .iter()
@@ -377,9 +534,100 @@ impl Provider for AnthropicProvider {
         Ok(())
     }
 
-    fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
-        // Rough approximation: 4 characters per token
-        Ok((text.len() as f64 / 4.0).ceil() as usize)
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        // Claude's vocabulary is not published, so this uses the heuristic
+        Ok(Tokenizer::for_model(model).count_tokens(text))
+    }
+}
+
+#[async_trait]
+impl MultiModalProvider for AnthropicProvider {
+    async fn complete_multimodal(&self, request: MultiModalRequest) -> Result<MultiModalResponse, ProviderError> {
+        let body = self.build_multimodal_body(&request).await?;
+        self.send_messages(&body).await.map(Into::into)
+    }
+}
+
+/// Batched requests take the same parameters as the Messages API, except
+/// for streaming. Response formats are enforced via the prompt, as for
+/// single requests, but failed responses are not repaired.
+#[async_trait]
+impl BatchProvider for AnthropicProvider {
+    async fn submit_batch(&self, requests: Vec<BatchRequest>) -> Result<BatchJob, ProviderError> {
+        batch::check_custom_ids(&requests)?;
+
+        let mut entries = Vec::with_capacity(requests.len());
+        for request in &requests {
+            ANTHROPIC_SAMPLING.check(self.name(), &request.request)?;
+            Self::check_thinking(&request.request)?;
+            let mut params = self.build_request_body(&structured::with_format_instructions(&request.request), false);
+            params.stream = None;
+            entries.push(serde_json::json!({ "custom_id": request.custom_id, "params": params }));
+        }
+
+        debug!("Anthropic batch submission: requests={}", entries.len());
+
+        let url = format!("{}/messages/batches", self.base_url);
+        let body = serde_json::json!({ "requests": entries });
+        let text = self.send_batch_api(self.client.post(&url).json(&body)).await?;
+        let batch: ClaudeBatch = serde_json::from_str(&text)?;
+        Ok(batch.into())
+    }
+
+    async fn batch_status(&self, batch_id: &str) -> Result<BatchJob, ProviderError> {
+        Ok(self.fetch_batch(batch_id).await?.into())
+    }
+
+    async fn batch_results(&self, batch_id: &str) -> Result<Vec<BatchResult>, ProviderError> {
+        #[derive(Deserialize)]
+        struct ResultLine {
+            custom_id: String,
+            result: ResultBody,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(tag = "type", rename_all = "snake_case")]
+        enum ResultBody {
+            Succeeded { message: ClaudeResponse },
+            Errored { error: serde_json::Value },
+            Canceled,
+            Expired,
+        }
+
+        let batch = self.fetch_batch(batch_id).await?;
+        let results_url = batch.results_url.clone();
+        batch::require_terminal(&BatchJob::from(batch))?;
+
+        // Batches that were cancelled before any request ran have no results
+        let Some(results_url) = results_url else {
+            return Ok(Vec::new());
+        };
+        let text = self.send_batch_api(self.client.get(&results_url)).await?;
+
+        let lines: Vec<ResultLine> = batch::parse_jsonl(&text)?;
+        Ok(lines
+            .into_iter()
+            .map(|line| BatchResult {
+                custom_id: line.custom_id,
+                response: match line.result {
+                    ResultBody::Succeeded { message } => {
+                        let mut response = Self::convert_response(message);
+                        response.metadata.insert("batch_id".to_string(), batch_id.to_string());
+                        Ok(response)
+                    }
+                    ResultBody::Errored { error } => Err(batch::error_message(&error)),
+                    ResultBody::Canceled => Err("Request was cancelled".to_string()),
+                    ResultBody::Expired => Err("Request expired before it was processed".to_string()),
+                },
+            })
+            .collect())
+    }
+
+    async fn cancel_batch(&self, batch_id: &str) -> Result<BatchJob, ProviderError> {
+        let url = format!("{}/messages/batches/{}/cancel", self.base_url, batch_id);
+        let text = self.send_batch_api(self.client.post(&url)).await?;
+        let batch: ClaudeBatch = serde_json::from_str(&text)?;
+        Ok(batch.into())
     }
 }
 
@@ -399,13 +647,126 @@ struct ClaudeRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     top_k: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    system: Option<String>,
+    system: Option<ClaudeText>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<ClaudeTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<ClaudeToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking: Option<ClaudeThinking>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClaudeThinking {
+    Enabled { budget_tokens: u32 },
+}
+
+/// A message batch from the Message Batches API
+#[derive(Debug, Deserialize)]
+struct ClaudeBatch {
+    id: String,
+    processing_status: String,
+    #[serde(default)]
+    request_counts: ClaudeBatchCounts,
+    #[serde(default)]
+    results_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ClaudeBatchCounts {
+    processing: usize,
+    succeeded: usize,
+    errored: usize,
+    canceled: usize,
+    expired: usize,
+}
+
+impl From<ClaudeBatch> for BatchJob {
+    fn from(batch: ClaudeBatch) -> Self {
+        let status = match batch.processing_status.as_str() {
+            "in_progress" => BatchStatus::InProgress,
+            "canceling" => BatchStatus::Cancelling,
+            _ => BatchStatus::Completed,
+        };
+        let counts = &batch.request_counts;
+        let failed = counts.errored + counts.canceled + counts.expired;
+
+        BatchJob {
+            id: batch.id,
+            status,
+            counts: BatchCounts {
+                total: counts.processing + counts.succeeded + failed,
+                completed: counts.succeeded,
+                failed,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ClaudeTool {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    input_schema: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cache_control: Option<ClaudeCacheControl>,
+}
+
+/// Marks the end of a cached prompt prefix
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClaudeCacheControl {
+    Ephemeral,
+}
+
+/// System prompt or message content: plain text, or a text block when it
+/// ends a cached prefix
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ClaudeText {
+    Plain(String),
+    Blocks(Vec<ClaudeTextBlock>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ClaudeTextBlock {
+    #[serde(rename = "type")]
+    block_type: String,
+    text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cache_control: Option<ClaudeCacheControl>,
+}
+
+impl ClaudeText {
+    fn new(text: String, cached: bool) -> Self {
+        if cached {
+            ClaudeText::Blocks(vec![ClaudeTextBlock {
+                block_type: "text".to_string(),
+                text,
+                cache_control: Some(ClaudeCacheControl::Ephemeral),
+            }])
+        } else {
+            ClaudeText::Plain(text)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClaudeToolChoice {
+    Auto,
+    Any,
+    None,
+    Tool { name: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct ClaudeMessage {
     role: String,
-    content: String,
+    content: ClaudeText,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -425,12 +786,37 @@ struct ClaudeResponse {
 enum ClaudeContent {
     #[serde(rename = "text")]
     Text { text: String },
+    #[serde(rename = "tool_use")]
+    ToolUse { id: String, name: String, input: serde_json::Value },
+    /// Thinking and redacted thinking blocks, which are not part of the answer
+    #[serde(other)]
+    Other,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct ClaudeUsage {
     input_tokens: u32,
     output_tokens: u32,
+    #[serde(default)]
+    cache_read_input_tokens: Option<u32>,
+    #[serde(default)]
+    cache_creation_input_tokens: Option<u32>,
+}
+
+impl ClaudeUsage {
+    /// Claude reports cache reads and writes separately from the rest of
+    /// the input; our prompt count includes them
+    ///
+    /// Thinking tokens are billed as output but not reported separately, so
+    /// they are only part of the completion tokens.
+    fn token_usage(&self) -> TokenUsage {
+        let cached = self.cache_read_input_tokens.unwrap_or(0) as usize;
+        let written = self.cache_creation_input_tokens.unwrap_or(0) as usize;
+        let prompt = self.input_tokens as usize + cached + written;
+        TokenUsage::new(prompt, self.output_tokens as usize)
+            .with_cached_tokens(cached)
+            .with_cache_write_tokens(written)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -462,6 +848,10 @@ enum ClaudeStreamEvent {
     MessageDelta { delta: serde_json::Value, usage: serde_json::Value },
     #[serde(rename = "message_stop")]
     MessageStop,
+    #[serde(rename = "ping")]
+    Ping,
+    #[serde(rename = "error")]
+    Error { error: ClaudeError },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -469,11 +859,106 @@ enum ClaudeStreamEvent {
 enum ClaudeDelta {
     #[serde(rename = "text_delta")]
     TextDelta { text: String },
+    #[serde(rename = "input_json_delta")]
+    InputJsonDelta { partial_json: String },
+    #[serde(other)]
+    Other,
+}
+
+/// Tracks a Claude message stream across SSE events
+///
+/// Also used for Claude models on Bedrock, which stream the same events.
+#[derive(Debug, Default)]
+pub(crate) struct ClaudeStreamState {
+    usage: ClaudeUsage,
+    /// Content block index of each tool_use block, in order of appearance
+    tool_blocks: Vec<usize>,
+}
+
+impl ClaudeStreamState {
+    /// Converts one streamed event (the SSE `data` payload) into stream events
+    pub(crate) fn handle(&mut self, data: &str) -> Result<Vec<StreamEvent>, ProviderError> {
+        trace!("Parsing streaming event: {}", data);
+
+        let event: ClaudeStreamEvent = match serde_json::from_str(data) {
+            Ok(event) => event,
+            Err(e) => {
+                warn!("Failed to parse streaming event: {}", e);
+                return Ok(Vec::new());
+            }
+        };
+
+        let events = match event {
+            ClaudeStreamEvent::MessageStart { message } => {
+                self.usage = serde_json::from_value(message["usage"].clone()).unwrap_or_default();
+                Vec::new()
+            }
+            ClaudeStreamEvent::ContentBlockStart { index, content_block } => {
+                if content_block["type"] == "tool_use" {
+                    self.tool_blocks.push(index);
+                    vec![StreamEvent::tool_call(ToolCallDelta {
+                        index: self.tool_blocks.len() - 1,
+                        id: content_block["id"].as_str().map(str::to_string),
+                        name: content_block["name"].as_str().map(str::to_string),
+                        arguments: String::new(),
+                    })]
+                } else {
+                    Vec::new()
+                }
+            }
+            ClaudeStreamEvent::ContentBlockDelta { index, delta } => match delta {
+                ClaudeDelta::TextDelta { text } if !text.is_empty() => vec![StreamEvent::content(text)],
+                ClaudeDelta::InputJsonDelta { partial_json } => {
+                    match self.tool_blocks.iter().position(|&block| block == index) {
+                        Some(position) => vec![StreamEvent::tool_call(ToolCallDelta {
+                            index: position,
+                            id: None,
+                            name: None,
+                            arguments: partial_json,
+                        })],
+                        None => Vec::new(),
+                    }
+                }
+                _ => Vec::new(),
+            },
+            ClaudeStreamEvent::MessageDelta { delta, usage } => {
+                if let Some(output_tokens) = usage["output_tokens"].as_u64() {
+                    self.usage.output_tokens = output_tokens as u32;
+                }
+                let mut events = vec![StreamEvent::usage(self.usage.token_usage())];
+                if let Some(stop_reason) = delta["stop_reason"].as_str() {
+                    events.push(StreamEvent::finish(AnthropicProvider::finish_reason(Some(stop_reason))));
+                }
+                events
+            }
+            ClaudeStreamEvent::MessageStop => {
+                debug!("Stream completed");
+                Vec::new()
+            }
+            ClaudeStreamEvent::ContentBlockStop { .. } | ClaudeStreamEvent::Ping => Vec::new(),
+            ClaudeStreamEvent::Error { error } => {
+                return Err(match error.error_type.as_str() {
+                    "overloaded_error" => ProviderError::ApiError { status: 529, message: error.message },
+                    "rate_limit_error" => ProviderError::RateLimitExceeded { retry_after: None },
+                    _ => ProviderError::ApiError { status: 500, message: error.message },
+                });
+            }
+        };
+
+        Ok(events)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::providers::{ChatMessage, ToolDefinition};
+
+    impl PartialEq<&str> for ClaudeText {
+        fn eq(&self, other: &&str) -> bool {
+            matches!(self, ClaudeText::Plain(text) if text == other)
+        }
+    }
 
     #[test]
     fn test_anthropic_provider_creation() {
@@ -505,11 +990,26 @@ mod tests {
         let request = CompletionRequest {
             model: "claude-3-sonnet-20240229".to_string(),
             prompt: "Hello, Claude!".to_string(),
+            system: None,
+            messages: Vec::new(),
             temperature: Some(0.7),
             max_tokens: Some(100),
             top_p: None,
             stop: None,
             stream: false,
+            tools: Vec::new(),
+            tool_choice: None,
+            response_format: None,
+            logprobs: None,
+            seed: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+            top_k: None,
+            n: None,
+            logit_bias: HashMap::new(),
+            reasoning_effort: None,
+            thinking_budget: None,
+            cache_points: Vec::new(),
         };
 
         let body = provider.build_request_body(&request, false);
@@ -523,17 +1023,49 @@ mod tests {
         assert_eq!(body.stream, Some(false));
     }
 
+    #[test]
+    fn test_build_request_body_with_thinking() {
+        let provider = AnthropicProvider::new("test_key".to_string());
+        let request = CompletionRequest::new("claude-sonnet-4-20250514", "Hi")
+            .with_temperature(0.7)
+            .with_thinking_budget(2048);
+        assert!(AnthropicProvider::check_thinking(&request).is_ok());
+
+        let body = provider.build_request_body(&request, false);
+        assert_eq!(body.temperature, None);
+        assert_eq!(body.max_tokens, 2048 + 1024);
+        assert!(body.thinking.is_some());
+
+        let error = AnthropicProvider::check_thinking(&request.with_top_k(40)).unwrap_err();
+        assert!(matches!(error, ProviderError::InvalidRequest(ref msg) if msg.contains("top_k")));
+    }
+
     #[test]
     fn test_build_request_body_streaming() {
         let provider = AnthropicProvider::new("test_key".to_string());
         let request = CompletionRequest {
             model: "claude-3-haiku-20240307".to_string(),
             prompt: "Test".to_string(),
+            system: None,
+            messages: Vec::new(),
             temperature: Some(0.5),
             max_tokens: None,
             top_p: None,
             stop: None,
             stream: false,
+            tools: Vec::new(),
+            tool_choice: None,
+            response_format: None,
+            logprobs: None,
+            seed: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+            top_k: None,
+            n: None,
+            logit_bias: HashMap::new(),
+            reasoning_effort: None,
+            thinking_budget: None,
+            cache_points: Vec::new(),
         };
 
         let body = provider.build_request_body(&request, true);
@@ -542,6 +1074,62 @@ mod tests {
         assert_eq!(body.max_tokens, 1024); // Default
     }
 
+    #[test]
+    fn test_build_request_body_with_system_and_history() {
+        let provider = AnthropicProvider::new("test_key".to_string());
+        let request = CompletionRequest::new("claude-3-haiku-20240307", "What about 5?")
+            .with_system("Reply with a number.")
+            .with_message(ChatMessage::system("Never explain."))
+            .with_message(ChatMessage::user("Double 2."))
+            .with_message(ChatMessage::assistant("4"));
+
+        let body = provider.build_request_body(&request, false);
+
+        assert_eq!(body.system.unwrap(), "Reply with a number.\n\nNever explain.");
+        assert_eq!(body.messages.len(), 3);
+        assert_eq!(body.messages[0].role, "user");
+        assert_eq!(body.messages[0].content, "Double 2.");
+        assert_eq!(body.messages[1].role, "assistant");
+        assert_eq!(body.messages[1].content, "4");
+        assert_eq!(body.messages[2].role, "user");
+        assert_eq!(body.messages[2].content, "What about 5?");
+    }
+
+    #[test]
+    fn test_build_request_body_with_cache_points() {
+        let provider = AnthropicProvider::new("test_key".to_string());
+        let tool = ToolDefinition::new("search", "Search the docs", serde_json::json!({ "type": "object" }));
+        let request = CompletionRequest::new("claude-3-5-sonnet-20241022", "And section 3?")
+            .with_system("<long document>")
+            .with_tools(vec![tool.clone(), tool])
+            .with_message(ChatMessage::user("Summarize section 2."))
+            .with_message(ChatMessage::assistant("Section 2 covers caching."))
+            .with_cache_point(CachePoint::Tools)
+            .with_cache_point(CachePoint::System)
+            .with_cache_point(CachePoint::Messages);
+
+        let body = serde_json::to_value(provider.build_request_body(&request, false)).unwrap();
+        let ephemeral = serde_json::json!({ "type": "ephemeral" });
+
+        assert!(body["tools"][0].get("cache_control").is_none());
+        assert_eq!(body["tools"][1]["cache_control"], ephemeral);
+        assert_eq!(
+            body["system"],
+            serde_json::json!([{ "type": "text", "text": "<long document>", "cache_control": ephemeral }])
+        );
+        assert_eq!(body["messages"][0]["content"], "Summarize section 2.");
+        assert_eq!(body["messages"][1]["content"][0]["cache_control"], ephemeral);
+        assert_eq!(body["messages"][2]["content"], "And section 3?");
+
+        // Without cache points the body keeps plain strings
+        let body = serde_json::to_value(
+            provider.build_request_body(&CompletionRequest::new("claude-3-5-sonnet-20241022", "Hi").with_system("Be brief."), false),
+        )
+        .unwrap();
+        assert_eq!(body["system"], "Be brief.");
+        assert_eq!(body["messages"][0]["content"], "Hi");
+    }
+
     #[test]
     fn test_convert_response() {
         let claude_response = ClaudeResponse {
@@ -556,6 +1144,7 @@ mod tests {
             usage: ClaudeUsage {
                 input_tokens: 10,
                 output_tokens: 5,
+                ..Default::default()
             },
         };
 
@@ -587,6 +1176,7 @@ mod tests {
             usage: ClaudeUsage {
                 input_tokens: 20,
                 output_tokens: 10,
+                ..Default::default()
             },
         };
 
@@ -596,6 +1186,18 @@ mod tests {
         assert_eq!(response.usage.total_tokens, 30);
     }
 
+    #[test]
+    fn test_estimate_tokens() {
+        let provider = AnthropicProvider::new("test_key".to_string());
+        let model = "claude-3-5-sonnet-20241022";
+
+        assert_eq!(provider.estimate_tokens("Hello, world!", model).unwrap(), 4);
+        assert_eq!(provider.estimate_tokens("", model).unwrap(), 0);
+
+        // Non-English text takes far more tokens than its bytes / 4
+        assert_eq!(provider.estimate_tokens("人工智能", model).unwrap(), 5);
+    }
+
     #[test]
     fn test_parse_error_authentication() {
         let error_json = r#"{
@@ -679,11 +1281,10 @@ mod tests {
             }
         }"#;
 
-        let result = AnthropicProvider::parse_streaming_event(event_data);
+        let events = ClaudeStreamState::default().handle(event_data).unwrap();
 
-        assert!(result.is_some());
-        let text = result.unwrap().unwrap();
-        assert_eq!(text, "Hello");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].text(), Some("Hello"));
     }
 
     #[test]
@@ -692,9 +1293,9 @@ mod tests {
             "type": "message_stop"
         }"#;
 
-        let result = AnthropicProvider::parse_streaming_event(event_data);
+        let events = ClaudeStreamState::default().handle(event_data).unwrap();
 
-        assert!(result.is_none());
+        assert!(events.is_empty());
     }
 
     #[test]
@@ -6,17 +6,120 @@
 
 //! Azure OpenAI provider implementation
 
-use super::{CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use super::credentials::ApiKey;
+use super::sampling::{self, SamplingSupport};
+use super::streaming;
+use super::structured::{self, openai_response_format};
+use super::{CompletionChoice, CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use crate::tokenizer::{Encoding, Tokenizer};
 use async_trait::async_trait;
-use futures::stream::StreamExt;
+use base64::Engine;
 use serde::Deserialize;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
 use tracing::{debug, error};
 
+const DEFAULT_API_VERSION: &str = "2024-02-15-preview";
+
+/// Tokens are refreshed this long before their `exp` claim
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+/// Fetches a new Azure AD access token
+type TokenProvider = Arc<dyn Fn() -> Result<ApiKey, ProviderError> + Send + Sync>;
+
+/// How requests to Azure OpenAI are authenticated
+enum AzureAuth {
+    /// A resource key, sent as `api-key`
+    ApiKey(String),
+    /// Azure AD (Entra ID) access tokens, sent as `Authorization: Bearer`
+    Bearer(AzureAdToken),
+}
+
+/// The current Azure AD token and the means to replace it
+struct AzureAdToken {
+    fetch: TokenProvider,
+    current: Mutex<CachedToken>,
+}
+
+struct CachedToken {
+    token: ApiKey,
+    expires_at: Option<SystemTime>,
+}
+
+impl CachedToken {
+    fn new(token: ApiKey) -> Self {
+        let expires_at = token_expiry(token.expose());
+        Self { token, expires_at }
+    }
+
+    fn is_expiring(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= SystemTime::now() + TOKEN_REFRESH_MARGIN)
+    }
+}
+
+impl AzureAdToken {
+    fn new(fetch: TokenProvider) -> Result<Self, ProviderError> {
+        let token = CachedToken::new(fetch()?);
+        Ok(Self { fetch, current: Mutex::new(token) })
+    }
+
+    /// The current token, replaced first if it is about to expire
+    async fn get(&self) -> Result<ApiKey, ProviderError> {
+        let mut current = self.current.lock().await;
+        if current.is_expiring() {
+            debug!("Azure AD token expires soon, fetching a new one");
+            *current = self.fetch().await?;
+        }
+        Ok(current.token.clone())
+    }
+
+    /// Replaces `rejected` after the API refused it
+    ///
+    /// Returns `None` when no other token is available.
+    async fn refresh(&self, rejected: &ApiKey) -> Result<Option<ApiKey>, ProviderError> {
+        let mut current = self.current.lock().await;
+        // Another request may have replaced it already
+        if current.token == *rejected {
+            *current = self.fetch().await?;
+        }
+        Ok((current.token != *rejected).then(|| current.token.clone()))
+    }
+
+    /// Runs the token provider, which may block on a command
+    async fn fetch(&self) -> Result<CachedToken, ProviderError> {
+        let fetch = Arc::clone(&self.fetch);
+        let token = tokio::task::spawn_blocking(move || fetch())
+            .await
+            .map_err(|e| ProviderError::InternalError(format!("Azure AD token provider panicked: {}", e)))??;
+        Ok(CachedToken::new(token))
+    }
+}
+
+/// The `exp` claim of a JWT access token, or `None` if it is not a JWT
+fn token_expiry(token: &str) -> Option<SystemTime> {
+    let payload = token.split('.').nth(1)?;
+    let claims = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .ok()?;
+    let claims: serde_json::Value = serde_json::from_slice(&claims).ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(claims.get("exp")?.as_u64()?))
+}
+
+fn default_client() -> Result<reqwest::Client, ProviderError> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(120))
+        .use_rustls_tls()
+        .build()
+        .map_err(|e| ProviderError::InternalError(format!("Failed to build HTTP client: {}", e)))
+}
+
 /// Azure OpenAI provider
 pub struct AzureOpenAIProvider {
     client: reqwest::Client,
-    api_key: String,
+    auth: AzureAuth,
     endpoint: String,
     deployment: String,
     api_version: String,
@@ -24,7 +127,7 @@ pub struct AzureOpenAIProvider {
 
 impl AzureOpenAIProvider {
     pub fn new(api_key: String, endpoint: String, deployment: String) -> Result<Self, ProviderError> {
-        Self::with_api_version(api_key, endpoint, deployment, "2024-02-15-preview".to_string())
+        Self::with_api_version(api_key, endpoint, deployment, DEFAULT_API_VERSION.to_string())
     }
 
     pub fn with_api_version(
@@ -37,27 +140,117 @@ impl AzureOpenAIProvider {
             return Err(ProviderError::InvalidApiKey);
         }
 
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(120))
-            .use_rustls_tls()
-            .build()
-            .map_err(|e| ProviderError::InternalError(format!("Failed to build HTTP client: {}", e)))?;
-
         Ok(Self {
-            client,
-            api_key,
+            client: default_client()?,
+            auth: AzureAuth::ApiKey(api_key),
             endpoint,
             deployment,
             api_version,
         })
     }
 
+    /// Uses `client` for every request, e.g. one built from transport settings
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Authenticates with a fixed Azure AD access token instead of a resource key
+    ///
+    /// The token is sent as `Authorization: Bearer <token>` and is never
+    /// refreshed. Use [`with_azure_ad_token_provider`](Self::with_azure_ad_token_provider)
+    /// for runs that may outlast it.
+    pub fn with_azure_ad_token(
+        token: String,
+        endpoint: String,
+        deployment: String,
+    ) -> Result<Self, ProviderError> {
+        if token.is_empty() {
+            return Err(ProviderError::InvalidApiKey);
+        }
+        let token = ApiKey::new(token);
+        Self::with_azure_ad_token_provider(move || Ok(token.clone()), endpoint, deployment)
+    }
+
+    /// Authenticates with Azure AD access tokens from `fetch`
+    ///
+    /// `fetch` is called once here, then again when the current token is
+    /// within five minutes of its `exp` claim or the API rejects it with a
+    /// 401, in which case the request is retried once. It runs on a blocking
+    /// thread, so it may run a command such as `az account get-access-token`.
+    pub fn with_azure_ad_token_provider<F>(
+        fetch: F,
+        endpoint: String,
+        deployment: String,
+    ) -> Result<Self, ProviderError>
+    where
+        F: Fn() -> Result<ApiKey, ProviderError> + Send + Sync + 'static,
+    {
+        Ok(Self {
+            client: default_client()?,
+            auth: AzureAuth::Bearer(AzureAdToken::new(Arc::new(fetch))?),
+            endpoint,
+            deployment,
+            api_version: DEFAULT_API_VERSION.to_string(),
+        })
+    }
+
+    /// Posts `body` to `url`
+    ///
+    /// A rejected Azure AD token is replaced and the request sent again.
+    async fn send(&self, url: &str, body: &serde_json::Value) -> Result<reqwest::Response, ProviderError> {
+        let token = match self.auth {
+            AzureAuth::ApiKey(ref key) => {
+                return self
+                    .client
+                    .post(url)
+                    .header("api-key", key)
+                    .json(body)
+                    .send()
+                    .await
+                    .map_err(|e| ProviderError::NetworkError(e));
+            }
+            AzureAuth::Bearer(ref token) => token,
+        };
+
+        let current = token.get().await?;
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(current.expose())
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| ProviderError::NetworkError(e))?;
+        if response.status() != reqwest::StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        match token.refresh(&current).await? {
+            Some(fresh) => {
+                debug!("Azure AD token rejected, retrying with a new one");
+                self.client
+                    .post(url)
+                    .bearer_auth(fresh.expose())
+                    .json(body)
+                    .send()
+                    .await
+                    .map_err(|e| ProviderError::NetworkError(e))
+            }
+            None => Ok(response),
+        }
+    }
+
     fn build_request_body(&self, request: &CompletionRequest, stream: bool) -> serde_json::Value {
         let mut body = serde_json::json!({
-            "messages": [{"role": "user", "content": request.prompt}],
+            "messages": request.chat_messages(),
             "stream": stream,
         });
 
+        if stream {
+            body["stream_options"] = serde_json::json!({ "include_usage": true });
+        }
+
         if let Some(temp) = request.temperature {
             body["temperature"] = serde_json::json!(temp);
         }
@@ -68,6 +261,12 @@ impl AzureOpenAIProvider {
             body["top_p"] = serde_json::json!(top_p);
         }
 
+        if let Some(ref format) = request.response_format {
+            body["response_format"] = openai_response_format(format);
+        }
+
+        sampling::apply_openai_params(request, &mut body);
+
         body
     }
 }
@@ -75,6 +274,8 @@ impl AzureOpenAIProvider {
 #[async_trait]
 impl Provider for AzureOpenAIProvider {
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        SamplingSupport::OPENAI.check(self.name(), &request)?;
+
         let url = format!(
             "{}/openai/deployments/{}/chat/completions?api-version={}",
             self.endpoint, self.deployment, self.api_version
@@ -84,13 +285,7 @@ impl Provider for AzureOpenAIProvider {
 
         debug!("Sending request to Azure OpenAI");
 
-        let response = self.client
-            .post(&url)
-            .header("api-key", &self.api_key)
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| ProviderError::NetworkError(e))?;
+        let response = self.send(&url, &body).await?;
 
         let status = response.status().as_u16();
         if !response.status().is_success() {
@@ -125,32 +320,68 @@ impl Provider for AzureOpenAIProvider {
             prompt_tokens: u32,
             completion_tokens: u32,
             total_tokens: u32,
+            #[serde(default)]
+            prompt_tokens_details: Option<TokenDetails>,
+            #[serde(default)]
+            completion_tokens_details: Option<TokenDetails>,
         }
 
-        let resp: AzureResponse = serde_json::from_str(&text)?;
-        let choice = resp.choices.first()
-            .ok_or_else(|| ProviderError::ApiError { status: 500, message: "No choices".to_string() })?;
+        #[derive(Deserialize)]
+        struct TokenDetails {
+            #[serde(default)]
+            cached_tokens: Option<u32>,
+            #[serde(default)]
+            reasoning_tokens: Option<u32>,
+        }
 
-        Ok(CompletionResponse {
+        let resp: AzureResponse = serde_json::from_str(&text)?;
+        let choices = resp.choices.into_iter().enumerate()
+            .map(|(index, choice)| CompletionChoice {
+                index,
+                content: choice.message.content,
+                finish_reason: match choice.finish_reason.as_str() {
+                    "stop" => FinishReason::Stop,
+                    "length" => FinishReason::Length,
+                    "content_filter" => FinishReason::ContentFilter,
+                    _ => FinishReason::Stop,
+                },
+                tool_calls: Vec::new(),
+            })
+            .collect();
+        let (first, choices) = sampling::split_choices(choices)?;
+
+        let mut response = CompletionResponse {
             id: resp.id,
-            content: choice.message.content.clone(),
+            content: first.content,
             model: resp.model,
             usage: TokenUsage {
                 prompt_tokens: resp.usage.prompt_tokens as usize,
                 completion_tokens: resp.usage.completion_tokens as usize,
                 total_tokens: resp.usage.total_tokens as usize,
+                reasoning_tokens: resp.usage.completion_tokens_details
+                    .and_then(|details| details.reasoning_tokens)
+                    .unwrap_or(0) as usize,
+                cached_tokens: resp.usage.prompt_tokens_details
+                    .and_then(|details| details.cached_tokens)
+                    .unwrap_or(0) as usize,
+                    cache_write_tokens: 0,
             },
-            finish_reason: match choice.finish_reason.as_str() {
-                "stop" => FinishReason::Stop,
-                "length" => FinishReason::Length,
-                "content_filter" => FinishReason::ContentFilter,
-                _ => FinishReason::Stop,
-            },
+            finish_reason: first.finish_reason,
             created_at: chrono::Utc::now(),
-        })
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices,
+            metadata: HashMap::new(),
+        };
+        structured::check_response(&request, &mut response);
+        Ok(response)
     }
 
     async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+        SamplingSupport::OPENAI.check_stream(self.name(), &request)?;
+
         let url = format!(
             "{}/openai/deployments/{}/chat/completions?api-version={}",
             self.endpoint, self.deployment, self.api_version
@@ -158,13 +389,7 @@ impl Provider for AzureOpenAIProvider {
 
         let body = self.build_request_body(&request, true);
 
-        let response = self.client
-            .post(&url)
-            .header("api-key", &self.api_key)
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| ProviderError::NetworkError(e))?;
+        let response = self.send(&url, &body).await?;
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
@@ -172,30 +397,7 @@ impl Provider for AzureOpenAIProvider {
             return Err(ProviderError::ApiError { status, message: text });
         }
 
-        let stream = response.bytes_stream().map(move |result| {
-            match result {
-                Ok(bytes) => {
-                    let text = String::from_utf8_lossy(&bytes);
-                    for line in text.lines() {
-                        if line.starts_with("data: ") {
-                            let json_str = line.strip_prefix("data: ").unwrap_or("");
-                            if json_str == "[DONE]" {
-                                continue;
-                            }
-                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(json_str) {
-                                if let Some(content) = json["choices"][0]["delta"]["content"].as_str() {
-                                    return Ok(content.to_string());
-                                }
-                            }
-                        }
-                    }
-                    Ok(String::new())
-                }
-                Err(e) => Err(ProviderError::NetworkError(e)),
-            }
-        });
-
-        Ok(Box::pin(stream))
+        Ok(streaming::openai_stream(response))
     }
 
     fn supported_models(&self) -> Vec<ModelInfo> {
@@ -224,7 +426,7 @@ impl Provider for AzureOpenAIProvider {
     }
 
     async fn validate_config(&self) -> Result<(), ProviderError> {
-        if self.api_key.is_empty() {
+        if matches!(self.auth, AzureAuth::ApiKey(ref key) if key.is_empty()) {
             return Err(ProviderError::InvalidApiKey);
         }
         if self.endpoint.is_empty() || self.deployment.is_empty() {
@@ -233,7 +435,57 @@ impl Provider for AzureOpenAIProvider {
         Ok(())
     }
 
-    fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
-        Ok((text.len() / 4).max(1))
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        // Deployments are often named after the model they serve
+        let model = match Encoding::for_model(model) {
+            Some(_) => model,
+            None => &self.deployment,
+        };
+        Ok(Tokenizer::for_model(model).count_tokens(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// An unsigned JWT expiring at `exp`
+    fn jwt(exp: u64) -> String {
+        let claims = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(format!(r#"{{"exp":{}}}"#, exp));
+        format!("eyJhbGciOiJub25lIn0.{}.", claims)
+    }
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    #[test]
+    fn test_token_expiry() {
+        assert_eq!(token_expiry(&jwt(1_700_000_000)), Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+        assert_eq!(token_expiry("not-a-jwt"), None);
+        assert_eq!(token_expiry("a.!!!.c"), None);
+    }
+
+    #[tokio::test]
+    async fn test_expiring_token_is_replaced() {
+        // Each fetch returns a token valid for one minute more than the last
+        let fetches = Arc::new(AtomicU64::new(0));
+        let counter = Arc::clone(&fetches);
+        let token = AzureAdToken::new(Arc::new(move || {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            Ok(ApiKey::new(jwt(now() + 60 * (n + 1))))
+        }))
+        .unwrap();
+
+        // Expires within the refresh margin, so it is replaced before use
+        token.get().await.unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+        let fresh = AzureAdToken::new(Arc::new(|| Ok(ApiKey::new(jwt(now() + 3600))))).unwrap();
+        let first = fresh.get().await.unwrap();
+        assert_eq!(fresh.get().await.unwrap(), first);
+        // The same token comes back, so a rejected one has no replacement
+        assert_eq!(fresh.refresh(&first).await.unwrap(), None);
     }
 }
@@ -0,0 +1,266 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Asynchronous batch jobs
+//!
+//! OpenAI's Batch API and Anthropic's Message Batches API process a set of
+//! completion requests within 24 hours at about half the price of the
+//! synchronous endpoints. Providers with a batch API implement
+//! [`BatchProvider`]: a job is submitted once, polled with
+//! [`BatchProvider::batch_status`] until it [has ended](BatchStatus::is_terminal),
+//! and its results are matched to the requests by their custom ID.
+//!
+//! Only the batch ID is needed to resume polling, so a caller that persists
+//! it can pick a job up again after a restart.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use llm_test_bench_core::providers::{BatchProvider, BatchRequest, CompletionRequest, OpenAIProvider};
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let provider = OpenAIProvider::new("your-api-key".to_string())?;
+//!
+//! let requests = vec![
+//!     BatchRequest::new("q1", CompletionRequest::new("gpt-4o-mini", "What is 2 + 2?")),
+//!     BatchRequest::new("q2", CompletionRequest::new("gpt-4o-mini", "What is 3 + 3?")),
+//! ];
+//! let mut job = provider.submit_batch(requests).await?;
+//! while !job.status.is_terminal() {
+//!     tokio::time::sleep(Duration::from_secs(60)).await;
+//!     job = provider.batch_status(&job.id).await?;
+//! }
+//!
+//! for result in provider.batch_results(&job.id).await? {
+//!     match result.response {
+//!         Ok(response) => println!("{}: {}", result.custom_id, response.content),
+//!         Err(error) => println!("{} failed: {}", result.custom_id, error),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{CompletionRequest, CompletionResponse, Provider, ProviderError};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// One request in a batch job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRequest {
+    /// Caller-chosen ID that the result is returned under
+    ///
+    /// IDs must be unique within a batch. Anthropic only accepts letters,
+    /// digits, `-` and `_`, up to 64 characters.
+    pub custom_id: String,
+
+    /// The completion to run
+    pub request: CompletionRequest,
+}
+
+impl BatchRequest {
+    /// Creates a batch request
+    pub fn new(custom_id: impl Into<String>, request: CompletionRequest) -> Self {
+        Self {
+            custom_id: custom_id.into(),
+            request,
+        }
+    }
+}
+
+/// Processing state of a batch job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    /// The input is being validated
+    Validating,
+    /// Requests are being processed
+    InProgress,
+    /// Processing has finished and the results are being prepared
+    Finalizing,
+    /// The job has ended and its results are available
+    Completed,
+    /// The job failed as a whole, usually because its input was invalid
+    Failed,
+    /// The job did not finish within its completion window
+    Expired,
+    /// The job is being cancelled
+    Cancelling,
+    /// The job was cancelled; results of finished requests are available
+    Cancelled,
+}
+
+impl BatchStatus {
+    /// Whether the job has stopped processing
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            BatchStatus::Completed | BatchStatus::Failed | BatchStatus::Expired | BatchStatus::Cancelled
+        )
+    }
+}
+
+impl std::fmt::Display for BatchStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            BatchStatus::Validating => "validating",
+            BatchStatus::InProgress => "in progress",
+            BatchStatus::Finalizing => "finalizing",
+            BatchStatus::Completed => "completed",
+            BatchStatus::Failed => "failed",
+            BatchStatus::Expired => "expired",
+            BatchStatus::Cancelling => "cancelling",
+            BatchStatus::Cancelled => "cancelled",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+/// Request counts of a batch job
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchCounts {
+    /// Requests in the job
+    pub total: usize,
+    /// Requests that finished successfully
+    pub completed: usize,
+    /// Requests that failed, expired or were cancelled
+    pub failed: usize,
+}
+
+/// A submitted batch job
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchJob {
+    /// Provider-assigned batch ID
+    pub id: String,
+    /// Processing state
+    pub status: BatchStatus,
+    /// Request counts, as far as the provider has reported them
+    pub counts: BatchCounts,
+}
+
+/// The outcome of one request in a batch job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResult {
+    /// The [`BatchRequest::custom_id`] of the request
+    pub custom_id: String,
+    /// The completion, or the error reported for this request
+    pub response: Result<CompletionResponse, String>,
+}
+
+/// A provider with an asynchronous batch API
+#[async_trait]
+pub trait BatchProvider: Provider {
+    /// Submits the requests as one batch job
+    ///
+    /// Requests are validated up front; a job is only created if all of them
+    /// can be sent.
+    async fn submit_batch(&self, requests: Vec<BatchRequest>) -> Result<BatchJob, ProviderError>;
+
+    /// Fetches the current state of a job
+    async fn batch_status(&self, batch_id: &str) -> Result<BatchJob, ProviderError>;
+
+    /// Fetches the results of a job that has ended
+    ///
+    /// Requests that never ran, for example because the job expired, may be
+    /// missing from the results.
+    ///
+    /// # Errors
+    ///
+    /// - `ProviderError::InvalidRequest` - The job is still running
+    async fn batch_results(&self, batch_id: &str) -> Result<Vec<BatchResult>, ProviderError>;
+
+    /// Cancels a running job
+    async fn cancel_batch(&self, batch_id: &str) -> Result<BatchJob, ProviderError>;
+}
+
+/// Rejects empty or duplicate custom IDs, which would make results ambiguous
+pub(crate) fn check_custom_ids(requests: &[BatchRequest]) -> Result<(), ProviderError> {
+    if requests.is_empty() {
+        return Err(ProviderError::InvalidRequest("A batch needs at least one request".to_string()));
+    }
+
+    let mut seen = HashSet::new();
+    for request in requests {
+        if request.custom_id.is_empty() {
+            return Err(ProviderError::InvalidRequest("Batch requests need a custom ID".to_string()));
+        }
+        if !seen.insert(request.custom_id.as_str()) {
+            return Err(ProviderError::InvalidRequest(format!(
+                "Duplicate custom ID in batch: {}",
+                request.custom_id
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Fails unless the job has ended, as its results are not available before
+pub(crate) fn require_terminal(job: &BatchJob) -> Result<(), ProviderError> {
+    if job.status.is_terminal() {
+        Ok(())
+    } else {
+        Err(ProviderError::InvalidRequest(format!(
+            "Batch {} is still {}; wait for it to end before fetching results",
+            job.id, job.status
+        )))
+    }
+}
+
+/// Parses a JSON Lines document, skipping blank lines
+pub(crate) fn parse_jsonl<T: DeserializeOwned>(text: &str) -> Result<Vec<T>, ProviderError> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(ProviderError::from))
+        .collect()
+}
+
+/// Extracts a readable message from a per-request error object
+///
+/// Both APIs nest the message at varying depths, under `error` or
+/// `error.error`.
+pub(crate) fn error_message(error: &serde_json::Value) -> String {
+    let mut current = error;
+    loop {
+        if let Some(message) = current.get("message").and_then(|m| m.as_str()) {
+            return message.to_string();
+        }
+        match current.get("error") {
+            Some(inner) => current = inner,
+            None => return error.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_custom_ids() {
+        let request = CompletionRequest::new("model", "Hi");
+        assert!(check_custom_ids(&[]).is_err());
+        assert!(check_custom_ids(&[BatchRequest::new("", request.clone())]).is_err());
+        assert!(check_custom_ids(&[
+            BatchRequest::new("a", request.clone()),
+            BatchRequest::new("a", request.clone())
+        ])
+        .is_err());
+        assert!(check_custom_ids(&[BatchRequest::new("a", request.clone()), BatchRequest::new("b", request)]).is_ok());
+    }
+
+    #[test]
+    fn test_error_message() {
+        let anthropic = serde_json::json!({
+            "type": "error",
+            "error": { "type": "invalid_request_error", "message": "max_tokens: too large" }
+        });
+        assert_eq!(error_message(&anthropic), "max_tokens: too large");
+        assert_eq!(error_message(&serde_json::json!({ "code": 500 })), "{\"code\":500}");
+    }
+}
@@ -5,25 +5,59 @@
 // except according to those terms.
 
 //! AWS Bedrock provider implementation
+//!
+//! Requests are sent directly to the Bedrock runtime API and signed with AWS
+//! Signature Version 4. Every model family on Bedrock has its own request and
+//! response schema, so bodies are built and parsed by a per-family adapter
+//! ([`ModelFamily`]). Streaming responses use the binary AWS event-stream
+//! framing, decoded by [`EventStreamDecoder`].
 
-use super::{CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use super::anthropic::ClaudeStreamState;
+use super::sampling::SamplingSupport;
+use super::streaming::{self, FrameDecoder, StreamEvent};
+use super::structured;
+use super::{CompletionRequest, CompletionResponse, FinishReason, MessageRole, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use crate::tokenizer::Tokenizer;
 use async_trait::async_trait;
-use futures::stream;
-use serde::{Deserialize, Serialize};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::time::Duration;
-use tracing::{debug, error, warn};
+use tracing::{debug, error};
+
+/// Service name used in the SigV4 credential scope
+const SIGNING_SERVICE: &str = "bedrock";
+
+/// Anthropic messages API version expected by Bedrock
+const ANTHROPIC_BEDROCK_VERSION: &str = "bedrock-2023-05-31";
 
 /// AWS Bedrock provider
-/// Note: This is a simplified implementation. Full AWS Bedrock support requires AWS SDK.
 pub struct BedrockProvider {
     client: reqwest::Client,
     region: String,
     access_key: String,
     secret_key: String,
+    session_token: Option<String>,
+    base_url: String,
 }
 
 impl BedrockProvider {
+    /// Create a provider for the Bedrock runtime endpoint of `region`
     pub fn new(region: String, access_key: String, secret_key: String) -> Result<Self, ProviderError> {
+        let base_url = format!("https://bedrock-runtime.{}.amazonaws.com", region);
+        Self::with_base_url(region, access_key, secret_key, base_url)
+    }
+
+    /// Create a provider with a custom endpoint (e.g. a VPC interface endpoint)
+    pub fn with_base_url(
+        region: String,
+        access_key: String,
+        secret_key: String,
+        base_url: String,
+    ) -> Result<Self, ProviderError> {
         if access_key.is_empty() || secret_key.is_empty() {
             return Err(ProviderError::InvalidApiKey);
         }
@@ -39,6 +73,142 @@ impl BedrockProvider {
             region,
             access_key,
             secret_key,
+            session_token: None,
+            base_url,
+        })
+    }
+
+    /// Uses `client` for every request, e.g. one built from transport settings
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Set the session token for temporary (STS) credentials
+    pub fn with_session_token(mut self, session_token: impl Into<String>) -> Self {
+        self.session_token = Some(session_token.into());
+        self
+    }
+
+    fn model_url(&self, model: &str, stream: bool) -> String {
+        let action = if stream { "invoke-with-response-stream" } else { "invoke" };
+        format!("{}/model/{}/{}", self.base_url.trim_end_matches('/'), uri_encode(model), action)
+    }
+
+    fn signer(&self) -> SigV4Signer<'_> {
+        SigV4Signer {
+            access_key: &self.access_key,
+            secret_key: &self.secret_key,
+            session_token: self.session_token.as_deref(),
+            region: &self.region,
+            service: SIGNING_SERVICE,
+        }
+    }
+
+    /// Sign and send an invoke request, returning the successful response
+    async fn invoke(
+        &self,
+        request: &CompletionRequest,
+        stream: bool,
+    ) -> Result<(ModelFamily, reqwest::Response), ProviderError> {
+        let family = ModelFamily::from_model_id(&request.model).ok_or_else(|| {
+            ProviderError::InvalidRequest(format!(
+                "Unsupported Bedrock model family: {}. Supported families: anthropic, amazon.titan-text, meta.llama, cohere.command",
+                request.model
+            ))
+        })?;
+
+        if stream {
+            family.sampling().check_stream(self.name(), request)?;
+        } else {
+            family.sampling().check(self.name(), request)?;
+        }
+
+        let body = serde_json::to_vec(&family.build_request_body(request, stream))?;
+        let url = reqwest::Url::parse(&self.model_url(&request.model, stream))
+            .map_err(|e| ProviderError::InvalidRequest(format!("Invalid Bedrock endpoint: {}", e)))?;
+        let accept = if stream { "application/vnd.amazon.eventstream" } else { "application/json" };
+
+        let signed = self.signer().sign(
+            "POST",
+            &url,
+            &[("accept", accept), ("content-type", "application/json")],
+            &body,
+            Utc::now(),
+        );
+
+        debug!("Sending request to AWS Bedrock: model={}, stream={}", request.model, stream);
+
+        let mut builder = self.client
+            .post(url)
+            .header("accept", accept)
+            .header("content-type", "application/json")
+            .body(body);
+        for (name, value) in signed {
+            builder = builder.header(name, value);
+        }
+
+        let response = builder.send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_type = response
+                .headers()
+                .get("x-amzn-errortype")
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.split(':').next().unwrap_or(v).to_string());
+            let text = response.text().await.unwrap_or_default();
+            error!("AWS Bedrock API error ({}): {}", status, text);
+            return Err(parse_error(status, error_type.as_deref(), &text, &request.model));
+        }
+
+        Ok((family, response))
+    }
+
+    /// Run a single non-streaming invocation
+    async fn complete_once(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        let (family, response) = self.invoke(&request, false).await?;
+
+        // Bedrock reports token counts in headers for every model family
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let header_input_tokens = header("x-amzn-bedrock-input-token-count").and_then(|v| v.parse().ok());
+        let header_output_tokens = header("x-amzn-bedrock-output-token-count").and_then(|v| v.parse().ok());
+        let request_id = header("x-amzn-requestid");
+
+        let text = response.text().await?;
+        let json: serde_json::Value = serde_json::from_str(&text)?;
+        let output = family.parse_response(&json);
+
+        let tokenizer = Tokenizer::heuristic();
+        let prompt_tokens = header_input_tokens
+            .or(output.prompt_tokens)
+            .unwrap_or_else(|| tokenizer.count_tokens(&request.render_prompt()).max(1));
+        let completion_tokens = header_output_tokens
+            .or(output.completion_tokens)
+            .unwrap_or_else(|| tokenizer.count_tokens(&output.content).max(1));
+
+        Ok(CompletionResponse {
+            id: output
+                .id
+                .or(request_id)
+                .unwrap_or_else(|| format!("bedrock-{}", Utc::now().timestamp())),
+            content: output.content,
+            model: request.model,
+            usage: TokenUsage::new(prompt_tokens, completion_tokens),
+            finish_reason: output.finish_reason,
+            created_at: Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         })
     }
 }
@@ -46,30 +216,37 @@ impl BedrockProvider {
 #[async_trait]
 impl Provider for BedrockProvider {
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
-        // Note: Full Bedrock implementation requires AWS SigV4 signing
-        // This is a placeholder that shows the structure
-        warn!("AWS Bedrock provider requires AWS SDK for full implementation");
-
-        Err(ProviderError::InternalError(
-            "AWS Bedrock provider requires AWS SDK. Please use AWS SDK directly or use a wrapper service.".to_string()
-        ))
+        // InvokeModel has no model-independent JSON mode, so response formats
+        // are enforced via the prompt
+        structured::complete_with_repair(request, |request| self.complete_once(request)).await
     }
 
-    async fn stream(&self, _request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
-        Err(ProviderError::InternalError(
-            "AWS Bedrock streaming requires AWS SDK implementation".to_string()
+    async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+        let request = structured::with_format_instructions(&request);
+        let (family, response) = self.invoke(&request, true).await?;
+
+        let mut claude = ClaudeStreamState::default();
+        Ok(streaming::decode_stream(
+            response.bytes_stream(),
+            EventStreamDecoder::default(),
+            move |message| family.decode_stream_event(&message, &mut claude),
         ))
     }
 
     fn supported_models(&self) -> Vec<ModelInfo> {
         vec![
+            ModelInfo::new("anthropic.claude-3-5-sonnet-20240620-v1:0", "Claude 3.5 Sonnet", 200000, true, false),
             ModelInfo::new("anthropic.claude-3-sonnet-20240229-v1:0", "Claude 3 Sonnet", 200000, true, false),
             ModelInfo::new("anthropic.claude-3-opus-20240229-v1:0", "Claude 3 Opus", 200000, true, false),
+            ModelInfo::new("anthropic.claude-3-haiku-20240307-v1:0", "Claude 3 Haiku", 200000, true, false),
             ModelInfo::new("anthropic.claude-v2:1", "Claude 2.1", 200000, true, false),
             ModelInfo::new("amazon.titan-text-express-v1", "Titan Text Express", 8000, true, false),
             ModelInfo::new("meta.llama2-70b-chat-v1", "Llama 2 70B", 4096, true, false),
+            ModelInfo::new("meta.llama3-70b-instruct-v1:0", "Llama 3 70B Instruct", 8192, true, false),
+            ModelInfo::new("meta.llama3-8b-instruct-v1:0", "Llama 3 8B Instruct", 8192, true, false),
             ModelInfo::new("cohere.command-text-v14", "Command", 4096, true, false),
-            ModelInfo::new("ai21.j2-ultra-v1", "Jurassic-2 Ultra", 8191, true, false),
+            ModelInfo::new("cohere.command-r-v1:0", "Command R", 128000, true, false),
+            ModelInfo::new("cohere.command-r-plus-v1:0", "Command R+", 128000, true, false),
         ]
     }
 
@@ -78,6 +255,10 @@ impl Provider for BedrockProvider {
             Some(200000)
         } else if model.contains("titan") {
             Some(8000)
+        } else if model.contains("llama3") {
+            Some(8192)
+        } else if model.contains("command-r") {
+            Some(128000)
         } else if model.contains("llama2") || model.contains("command") {
             Some(4096)
         } else if model.contains("j2") {
@@ -98,7 +279,995 @@ impl Provider for BedrockProvider {
         Ok(())
     }
 
-    fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
-        Ok((text.len() / 4).max(1))
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        Ok(Tokenizer::for_model(model).count_tokens(text))
+    }
+}
+
+/// Map a Bedrock error (HTTP or in-stream exception) to a provider error
+fn parse_error(status: u16, error_type: Option<&str>, body: &str, model: &str) -> ProviderError {
+    #[derive(Deserialize)]
+    struct ErrorBody {
+        #[serde(alias = "Message")]
+        message: String,
+    }
+
+    let message = serde_json::from_str::<ErrorBody>(body)
+        .map(|e| e.message)
+        .unwrap_or_else(|_| body.to_string());
+    let error_type = error_type.unwrap_or_default().to_ascii_lowercase();
+
+    match (status, error_type.as_str()) {
+        (429, _) | (_, "throttlingexception") => ProviderError::RateLimitExceeded { retry_after: None },
+        (401, _)
+        | (403, _)
+        | (_, "accessdeniedexception")
+        | (_, "unrecognizedclientexception")
+        | (_, "invalidsignatureexception")
+        | (_, "expiredtokenexception") => ProviderError::AuthenticationError(message),
+        (404, _) | (_, "resourcenotfoundexception") => ProviderError::ModelNotFound {
+            model: model.to_string(),
+        },
+        (_, "validationexception") => ProviderError::InvalidRequest(message),
+        _ => ProviderError::ApiError { status, message },
+    }
+}
+
+/// Request/response schema of a model family hosted on Bedrock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModelFamily {
+    /// Anthropic Claude (messages API)
+    Anthropic,
+    /// Amazon Titan Text
+    Titan,
+    /// Meta Llama 2 chat
+    Llama2,
+    /// Meta Llama 3 and later instruct models
+    Llama3,
+    /// Cohere Command (text generation)
+    CohereCommand,
+    /// Cohere Command R / R+ (chat)
+    CohereCommandR,
+}
+
+/// Fields extracted from a non-streaming invoke response
+struct InvokeOutput {
+    id: Option<String>,
+    content: String,
+    finish_reason: FinishReason,
+    prompt_tokens: Option<usize>,
+    completion_tokens: Option<usize>,
+}
+
+impl ModelFamily {
+    fn from_model_id(model: &str) -> Option<Self> {
+        // Cross-region inference profiles prefix the model ID with a geography
+        let id = match model.split_once('.') {
+            Some(("us" | "eu" | "apac" | "us-gov" | "global", rest)) => rest,
+            _ => model,
+        };
+
+        if id.starts_with("anthropic.") {
+            Some(ModelFamily::Anthropic)
+        } else if id.starts_with("amazon.titan-text") {
+            Some(ModelFamily::Titan)
+        } else if id.starts_with("meta.llama2") {
+            Some(ModelFamily::Llama2)
+        } else if id.starts_with("meta.llama") {
+            Some(ModelFamily::Llama3)
+        } else if id.starts_with("cohere.command-r") {
+            Some(ModelFamily::CohereCommandR)
+        } else if id.starts_with("cohere.command") {
+            Some(ModelFamily::CohereCommand)
+        } else {
+            None
+        }
+    }
+
+    fn build_request_body(self, request: &CompletionRequest, stream: bool) -> serde_json::Value {
+        match self {
+            ModelFamily::Anthropic => {
+                let messages: Vec<serde_json::Value> = request
+                    .conversation()
+                    .into_iter()
+                    .map(|m| serde_json::json!({ "role": m.role.to_string(), "content": m.content }))
+                    .collect();
+
+                let mut body = serde_json::json!({
+                    "anthropic_version": ANTHROPIC_BEDROCK_VERSION,
+                    "max_tokens": request.max_tokens.unwrap_or(1024),
+                    "messages": messages,
+                });
+                if let Some(system) = request.system_prompt() {
+                    body["system"] = serde_json::json!(system);
+                }
+                if let Some(temp) = request.temperature {
+                    body["temperature"] = serde_json::json!(temp);
+                }
+                if let Some(top_p) = request.top_p {
+                    body["top_p"] = serde_json::json!(top_p);
+                }
+                if let Some(top_k) = request.top_k {
+                    body["top_k"] = serde_json::json!(top_k);
+                }
+                if let Some(ref stop) = request.stop {
+                    body["stop_sequences"] = serde_json::json!(stop);
+                }
+                body
+            }
+            ModelFamily::Titan => {
+                let mut config = serde_json::Map::new();
+                if let Some(max_tokens) = request.max_tokens {
+                    config.insert("maxTokenCount".to_string(), serde_json::json!(max_tokens));
+                }
+                if let Some(temp) = request.temperature {
+                    config.insert("temperature".to_string(), serde_json::json!(temp));
+                }
+                if let Some(top_p) = request.top_p {
+                    config.insert("topP".to_string(), serde_json::json!(top_p));
+                }
+                if let Some(ref stop) = request.stop {
+                    config.insert("stopSequences".to_string(), serde_json::json!(stop));
+                }
+
+                let mut body = serde_json::json!({ "inputText": plain_prompt(request) });
+                if !config.is_empty() {
+                    body["textGenerationConfig"] = serde_json::Value::Object(config);
+                }
+                body
+            }
+            ModelFamily::Llama2 | ModelFamily::Llama3 => {
+                let prompt = if self == ModelFamily::Llama2 {
+                    llama2_prompt(request)
+                } else {
+                    llama3_prompt(request)
+                };
+
+                let mut body = serde_json::json!({ "prompt": prompt });
+                if let Some(max_tokens) = request.max_tokens {
+                    body["max_gen_len"] = serde_json::json!(max_tokens);
+                }
+                if let Some(temp) = request.temperature {
+                    body["temperature"] = serde_json::json!(temp);
+                }
+                if let Some(top_p) = request.top_p {
+                    body["top_p"] = serde_json::json!(top_p);
+                }
+                body
+            }
+            ModelFamily::CohereCommand | ModelFamily::CohereCommandR => {
+                let mut body = if self == ModelFamily::CohereCommand {
+                    let mut body = serde_json::json!({ "prompt": plain_prompt(request) });
+                    if stream {
+                        body["stream"] = serde_json::json!(true);
+                    }
+                    body
+                } else {
+                    cohere_chat_body(request)
+                };
+
+                if let Some(max_tokens) = request.max_tokens {
+                    body["max_tokens"] = serde_json::json!(max_tokens);
+                }
+                if let Some(temp) = request.temperature {
+                    body["temperature"] = serde_json::json!(temp);
+                }
+                if let Some(top_p) = request.top_p {
+                    body["p"] = serde_json::json!(top_p);
+                }
+                if let Some(top_k) = request.top_k {
+                    body["k"] = serde_json::json!(top_k);
+                }
+                if let Some(seed) = request.seed {
+                    body["seed"] = serde_json::json!(seed);
+                }
+                if let Some(penalty) = request.presence_penalty {
+                    body["presence_penalty"] = serde_json::json!(penalty);
+                }
+                if let Some(penalty) = request.frequency_penalty {
+                    body["frequency_penalty"] = serde_json::json!(penalty);
+                }
+                if let Some(ref stop) = request.stop {
+                    body["stop_sequences"] = serde_json::json!(stop);
+                }
+                body
+            }
+        }
+    }
+
+    /// Sampling parameters the family's request body accepts
+    ///
+    /// Only Command R takes a seed and penalties; Titan and Llama take none.
+    fn sampling(self) -> SamplingSupport {
+        match self {
+            ModelFamily::Anthropic | ModelFamily::CohereCommand => {
+                SamplingSupport { top_k: true, ..SamplingSupport::NONE }
+            }
+            ModelFamily::CohereCommandR => SamplingSupport {
+                seed: true,
+                presence_penalty: true,
+                frequency_penalty: true,
+                top_k: true,
+                ..SamplingSupport::NONE
+            },
+            ModelFamily::Titan | ModelFamily::Llama2 | ModelFamily::Llama3 => SamplingSupport::NONE,
+        }
+    }
+
+    /// Map the family's stop reason
+    fn finish_reason(self, reason: Option<&str>) -> FinishReason {
+        match (self, reason) {
+            (ModelFamily::Anthropic, Some("max_tokens")) => FinishReason::Length,
+            (ModelFamily::Anthropic, Some("tool_use")) => FinishReason::ToolCalls,
+            (ModelFamily::Titan, Some("LENGTH")) => FinishReason::Length,
+            (ModelFamily::Titan, Some("CONTENT_FILTERED")) => FinishReason::ContentFilter,
+            (ModelFamily::Llama2 | ModelFamily::Llama3, Some("length")) => FinishReason::Length,
+            (ModelFamily::CohereCommand | ModelFamily::CohereCommandR, Some("MAX_TOKENS")) => FinishReason::Length,
+            (ModelFamily::CohereCommand | ModelFamily::CohereCommandR, Some("ERROR_TOXIC")) => {
+                FinishReason::ContentFilter
+            }
+            (ModelFamily::CohereCommand | ModelFamily::CohereCommandR, Some("ERROR")) => FinishReason::Error,
+            _ => FinishReason::Stop,
+        }
+    }
+
+    fn parse_response(self, body: &serde_json::Value) -> InvokeOutput {
+        let count = |value: &serde_json::Value| value.as_u64().map(|v| v as usize);
+
+        match self {
+            ModelFamily::Anthropic => InvokeOutput {
+                id: body["id"].as_str().map(str::to_string),
+                content: body["content"]
+                    .as_array()
+                    .map(|blocks| {
+                        blocks
+                            .iter()
+                            .filter(|b| b["type"] == "text")
+                            .filter_map(|b| b["text"].as_str())
+                            .collect::<Vec<_>>()
+                            .join("")
+                    })
+                    .unwrap_or_default(),
+                finish_reason: self.finish_reason(body["stop_reason"].as_str()),
+                prompt_tokens: count(&body["usage"]["input_tokens"]),
+                completion_tokens: count(&body["usage"]["output_tokens"]),
+            },
+            ModelFamily::Titan => {
+                let result = &body["results"][0];
+                InvokeOutput {
+                    id: None,
+                    content: result["outputText"].as_str().unwrap_or_default().to_string(),
+                    finish_reason: self.finish_reason(result["completionReason"].as_str()),
+                    prompt_tokens: count(&body["inputTextTokenCount"]),
+                    completion_tokens: count(&result["tokenCount"]),
+                }
+            }
+            ModelFamily::Llama2 | ModelFamily::Llama3 => InvokeOutput {
+                id: None,
+                content: body["generation"].as_str().unwrap_or_default().to_string(),
+                finish_reason: self.finish_reason(body["stop_reason"].as_str()),
+                prompt_tokens: count(&body["prompt_token_count"]),
+                completion_tokens: count(&body["generation_token_count"]),
+            },
+            ModelFamily::CohereCommand | ModelFamily::CohereCommandR => {
+                let (id, text, reason) = if self == ModelFamily::CohereCommand {
+                    let generation = &body["generations"][0];
+                    (&body["id"], &generation["text"], &generation["finish_reason"])
+                } else {
+                    (&body["response_id"], &body["text"], &body["finish_reason"])
+                };
+                InvokeOutput {
+                    id: id.as_str().map(str::to_string),
+                    content: text.as_str().unwrap_or_default().to_string(),
+                    finish_reason: self.finish_reason(reason.as_str()),
+                    prompt_tokens: None,
+                    completion_tokens: None,
+                }
+            }
+        }
+    }
+
+    /// Extract the events from a decoded streaming chunk
+    ///
+    /// Claude models stream the Messages API events, decoded by `claude`.
+    /// Other families put the stop reason on the last chunk. Bedrock adds
+    /// `amazon-bedrock-invocationMetrics` with token counts to the final chunk
+    /// for every family.
+    fn stream_events(
+        self,
+        chunk: &serde_json::Value,
+        claude: &mut ClaudeStreamState,
+    ) -> Result<Vec<StreamEvent>, ProviderError> {
+        let mut events = Vec::new();
+
+        let (text, stop_reason) = match self {
+            ModelFamily::Anthropic => {
+                events = claude.handle(&chunk.to_string())?;
+                (None, None)
+            }
+            ModelFamily::Titan => (chunk["outputText"].as_str(), chunk["completionReason"].as_str()),
+            ModelFamily::Llama2 | ModelFamily::Llama3 => (chunk["generation"].as_str(), chunk["stop_reason"].as_str()),
+            ModelFamily::CohereCommand => {
+                let text = chunk["text"].as_str().or_else(|| chunk["generations"][0]["text"].as_str());
+                let finished = chunk["is_finished"].as_bool() == Some(true);
+                (text, chunk["finish_reason"].as_str().filter(|_| finished))
+            }
+            ModelFamily::CohereCommandR => match chunk["event_type"].as_str() {
+                Some("text-generation") => (chunk["text"].as_str(), None),
+                Some("stream-end") => (None, chunk["finish_reason"].as_str()),
+                _ => (None, None),
+            },
+        };
+
+        if let Some(text) = text.filter(|text| !text.is_empty()) {
+            events.push(StreamEvent::content(text));
+        }
+
+        let metrics = &chunk["amazon-bedrock-invocationMetrics"];
+        if let (Some(input), Some(output)) = (metrics["inputTokenCount"].as_u64(), metrics["outputTokenCount"].as_u64()) {
+            events.push(StreamEvent::usage(TokenUsage::new(input as usize, output as usize)));
+        }
+
+        if let Some(reason) = stop_reason {
+            events.push(StreamEvent::finish(self.finish_reason(Some(reason))));
+        }
+
+        Ok(events)
+    }
+
+    /// Turn an event-stream message into stream events
+    fn decode_stream_event(
+        self,
+        message: &EventStreamMessage,
+        claude: &mut ClaudeStreamState,
+    ) -> Result<Vec<StreamEvent>, ProviderError> {
+        let header = |name: &str| message.headers.get(name).map(String::as_str);
+
+        if matches!(header(":message-type"), Some("exception") | Some("error")) {
+            let error_type = header(":exception-type").or_else(|| header(":error-code"));
+            let body = String::from_utf8_lossy(&message.payload);
+            return Err(parse_error(500, error_type, &body, ""));
+        }
+
+        if header(":event-type") != Some("chunk") {
+            return Ok(Vec::new());
+        }
+
+        #[derive(Deserialize)]
+        struct Chunk {
+            bytes: String,
+        }
+
+        let chunk: Chunk = serde_json::from_slice(&message.payload)?;
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(chunk.bytes)
+            .map_err(|e| ProviderError::InternalError(format!("Invalid Bedrock chunk encoding: {}", e)))?;
+        let json: serde_json::Value = serde_json::from_slice(&decoded)?;
+
+        self.stream_events(&json, claude)
+    }
+}
+
+/// Flatten the system prompt and conversation for text-completion models
+fn plain_prompt(request: &CompletionRequest) -> String {
+    match request.system_prompt() {
+        Some(system) => format!("{}\n\n{}", system, request.render_prompt()),
+        None => request.render_prompt(),
+    }
+}
+
+/// Render the conversation with the Llama 2 chat template
+fn llama2_prompt(request: &CompletionRequest) -> String {
+    let mut system = request.system_prompt();
+    let mut prompt = String::new();
+
+    for turn in request.conversation() {
+        match turn.role {
+            MessageRole::Assistant => prompt.push_str(&format!(" {} </s>", turn.content)),
+            _ => {
+                let content = match system.take() {
+                    Some(system) => format!("<<SYS>>\n{}\n<</SYS>>\n\n{}", system, turn.content),
+                    None => turn.content,
+                };
+                prompt.push_str(&format!("<s>[INST] {} [/INST]", content));
+            }
+        }
+    }
+
+    prompt
+}
+
+/// Render the conversation with the Llama 3 chat template
+fn llama3_prompt(request: &CompletionRequest) -> String {
+    let mut prompt = String::from("<|begin_of_text|>");
+    let turn = |role: &str, content: &str| {
+        format!("<|start_header_id|>{}<|end_header_id|>\n\n{}<|eot_id|>", role, content)
+    };
+
+    if let Some(system) = request.system_prompt() {
+        prompt.push_str(&turn("system", &system));
+    }
+    for message in request.conversation() {
+        prompt.push_str(&turn(&message.role.to_string(), &message.content));
+    }
+    prompt.push_str("<|start_header_id|>assistant<|end_header_id|>\n\n");
+
+    prompt
+}
+
+/// Build a Cohere Command R chat body (`message`, `chat_history`, `preamble`)
+fn cohere_chat_body(request: &CompletionRequest) -> serde_json::Value {
+    let mut turns = request.conversation();
+    let message = match turns.last() {
+        Some(last) if last.role == MessageRole::User => turns.pop().map(|m| m.content).unwrap_or_default(),
+        _ => String::new(),
+    };
+
+    let mut body = serde_json::json!({ "message": message });
+    if !turns.is_empty() {
+        let chat_history: Vec<serde_json::Value> = turns
+            .into_iter()
+            .map(|m| {
+                let role = match m.role {
+                    MessageRole::Assistant => "CHATBOT",
+                    _ => "USER",
+                };
+                serde_json::json!({ "role": role, "message": m.content })
+            })
+            .collect();
+        body["chat_history"] = serde_json::json!(chat_history);
+    }
+    if let Some(system) = request.system_prompt() {
+        body["preamble"] = serde_json::json!(system);
+    }
+    body
+}
+
+/// AWS Signature Version 4 request signer
+struct SigV4Signer<'a> {
+    access_key: &'a str,
+    secret_key: &'a str,
+    session_token: Option<&'a str>,
+    region: &'a str,
+    service: &'a str,
+}
+
+impl SigV4Signer<'_> {
+    /// Sign a request and return the headers to add to it
+    ///
+    /// `headers` are the request headers to include in the signature; `host`,
+    /// `x-amz-date` and `x-amz-security-token` are added automatically. The
+    /// returned list contains `x-amz-date`, the session token (if any) and
+    /// `authorization`.
+    fn sign(
+        &self,
+        method: &str,
+        url: &reqwest::Url,
+        headers: &[(&str, &str)],
+        payload: &[u8],
+        now: DateTime<Utc>,
+    ) -> Vec<(String, String)> {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date_stamp = now.format("%Y%m%d").to_string();
+
+        let mut canonical: Vec<(String, String)> = headers
+            .iter()
+            .map(|(name, value)| (name.to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+        canonical.push(("host".to_string(), host_header(url)));
+        canonical.push(("x-amz-date".to_string(), amz_date.clone()));
+        if let Some(token) = self.session_token {
+            canonical.push(("x-amz-security-token".to_string(), token.to_string()));
+        }
+        canonical.sort();
+
+        let canonical_headers: String = canonical
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect();
+        let signed_headers = canonical
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method,
+            canonical_uri(url),
+            canonical_query(url),
+            canonical_headers,
+            signed_headers,
+            hex::encode(Sha256::digest(payload)),
+        );
+
+        let scope = format!("{}/{}/{}/aws4_request", date_stamp, self.region, self.service);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes())),
+        );
+        let signature = hex::encode(hmac_sha256(&self.signing_key(&date_stamp), string_to_sign.as_bytes()));
+
+        let mut signed = vec![("x-amz-date".to_string(), amz_date)];
+        if let Some(token) = self.session_token {
+            signed.push(("x-amz-security-token".to_string(), token.to_string()));
+        }
+        signed.push((
+            "authorization".to_string(),
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.access_key, scope, signed_headers, signature
+            ),
+        ));
+        signed
+    }
+
+    fn signing_key(&self, date_stamp: &str) -> Vec<u8> {
+        let k_date = hmac_sha256(format!("AWS4{}", self.secret_key).as_bytes(), date_stamp.as_bytes());
+        let k_region = hmac_sha256(&k_date, self.region.as_bytes());
+        let k_service = hmac_sha256(&k_region, self.service.as_bytes());
+        hmac_sha256(&k_service, b"aws4_request")
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// The `Host` header value reqwest sends for `url`
+fn host_header(url: &reqwest::Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
+
+/// Percent-encode everything except RFC 3986 unreserved characters
+fn uri_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Canonical URI: each (already encoded) path segment is encoded again,
+/// as required for every service except S3
+fn canonical_uri(url: &reqwest::Url) -> String {
+    let path = url.path();
+    if path.is_empty() || path == "/" {
+        return "/".to_string();
+    }
+    path.split('/').map(uri_encode).collect::<Vec<_>>().join("/")
+}
+
+fn canonical_query(url: &reqwest::Url) -> String {
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(k, v)| (uri_encode(&k), uri_encode(&v)))
+        .collect();
+    pairs.sort();
+    pairs
+        .into_iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// A message decoded from an AWS event stream
+#[derive(Debug)]
+struct EventStreamMessage {
+    /// String-valued headers (e.g. `:event-type`, `:message-type`)
+    headers: HashMap<String, String>,
+    payload: Vec<u8>,
+}
+
+/// Incremental decoder for `application/vnd.amazon.eventstream` responses
+///
+/// Each frame is a 12-byte prelude (total length, headers length, prelude
+/// CRC32), the headers, the payload and a trailing CRC32 of the whole
+/// message. Bytes are buffered until a complete frame is available.
+#[derive(Debug, Default)]
+struct EventStreamDecoder {
+    buffer: Vec<u8>,
+}
+
+impl EventStreamDecoder {
+    const PRELUDE_LEN: usize = 12;
+    const CRC_LEN: usize = 4;
+
+    /// Decode the next complete message, or `None` if more bytes are needed
+    fn next_message(&mut self) -> Result<Option<EventStreamMessage>, ProviderError> {
+        if self.buffer.len() < Self::PRELUDE_LEN {
+            return Ok(None);
+        }
+
+        let total_len = read_u32(&self.buffer[0..4]) as usize;
+        let headers_len = read_u32(&self.buffer[4..8]) as usize;
+        if crc32fast::hash(&self.buffer[0..8]) != read_u32(&self.buffer[8..12]) {
+            return Err(ProviderError::InternalError("Event stream prelude checksum mismatch".to_string()));
+        }
+        if total_len < Self::PRELUDE_LEN + headers_len + Self::CRC_LEN {
+            return Err(ProviderError::InternalError("Malformed event stream frame".to_string()));
+        }
+        if self.buffer.len() < total_len {
+            return Ok(None);
+        }
+
+        let frame: Vec<u8> = self.buffer.drain(..total_len).collect();
+        let body_end = total_len - Self::CRC_LEN;
+        if crc32fast::hash(&frame[..body_end]) != read_u32(&frame[body_end..]) {
+            return Err(ProviderError::InternalError("Event stream message checksum mismatch".to_string()));
+        }
+
+        let headers_end = Self::PRELUDE_LEN + headers_len;
+        Ok(Some(EventStreamMessage {
+            headers: parse_event_headers(&frame[Self::PRELUDE_LEN..headers_end])?,
+            payload: frame[headers_end..body_end].to_vec(),
+        }))
+    }
+}
+
+impl FrameDecoder for EventStreamDecoder {
+    type Frame = EventStreamMessage;
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn next_frame(&mut self) -> Result<Option<EventStreamMessage>, ProviderError> {
+        self.next_message()
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Parse event-stream headers, keeping only string-valued ones
+fn parse_event_headers(mut data: &[u8]) -> Result<HashMap<String, String>, ProviderError> {
+    let malformed = || ProviderError::InternalError("Malformed event stream header".to_string());
+    let mut headers = HashMap::new();
+
+    while !data.is_empty() {
+        let name_len = data[0] as usize;
+        let name = data.get(1..1 + name_len).ok_or_else(malformed)?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        let value_type = *data.get(1 + name_len).ok_or_else(malformed)?;
+        let rest = &data[2 + name_len..];
+
+        let value_len = match value_type {
+            // bool true / bool false carry no value
+            0 | 1 => 0,
+            2 => 1,
+            3 => 2,
+            4 => 4,
+            5 | 8 => 8,
+            // byte array / string: u16 length prefix
+            6 | 7 => {
+                let len = rest.get(0..2).ok_or_else(malformed)?;
+                2 + u16::from_be_bytes([len[0], len[1]]) as usize
+            }
+            9 => 16,
+            _ => return Err(malformed()),
+        };
+        let value = rest.get(..value_len).ok_or_else(malformed)?;
+
+        if value_type == 7 {
+            headers.insert(name, String::from_utf8_lossy(&value[2..]).into_owned());
+        }
+        data = &rest[value_len..];
+    }
+
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::ChatMessage;
+
+    fn test_signer<'a>(service: &'a str, region: &'a str) -> SigV4Signer<'a> {
+        SigV4Signer {
+            access_key: "AKIDEXAMPLE",
+            secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            session_token: None,
+            region,
+            service,
+        }
+    }
+
+    fn encode_frame(headers: &[(&str, &str)], payload: &[u8]) -> Vec<u8> {
+        let mut header_bytes = Vec::new();
+        for (name, value) in headers {
+            header_bytes.push(name.len() as u8);
+            header_bytes.extend_from_slice(name.as_bytes());
+            header_bytes.push(7);
+            header_bytes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            header_bytes.extend_from_slice(value.as_bytes());
+        }
+
+        let total_len = (12 + header_bytes.len() + payload.len() + 4) as u32;
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&total_len.to_be_bytes());
+        frame.extend_from_slice(&(header_bytes.len() as u32).to_be_bytes());
+        let prelude_crc = crc32fast::hash(&frame);
+        frame.extend_from_slice(&prelude_crc.to_be_bytes());
+        frame.extend_from_slice(&header_bytes);
+        frame.extend_from_slice(payload);
+        let message_crc = crc32fast::hash(&frame);
+        frame.extend_from_slice(&message_crc.to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_provider_creation() {
+        let provider = BedrockProvider::new(
+            "us-west-2".to_string(),
+            "AKID".to_string(),
+            "secret".to_string(),
+        )
+        .unwrap();
+        assert_eq!(provider.name(), "bedrock");
+        assert_eq!(provider.base_url, "https://bedrock-runtime.us-west-2.amazonaws.com");
+    }
+
+    #[test]
+    fn test_provider_creation_empty_credentials() {
+        let result = BedrockProvider::new("us-east-1".to_string(), String::new(), "secret".to_string());
+        assert!(matches!(result, Err(ProviderError::InvalidApiKey)));
+    }
+
+    #[test]
+    fn test_model_url_encodes_model_id() {
+        let provider = BedrockProvider::new("us-east-1".to_string(), "AKID".to_string(), "secret".to_string()).unwrap();
+        assert_eq!(
+            provider.model_url("anthropic.claude-3-sonnet-20240229-v1:0", false),
+            "https://bedrock-runtime.us-east-1.amazonaws.com/model/anthropic.claude-3-sonnet-20240229-v1%3A0/invoke"
+        );
+        assert!(provider
+            .model_url("meta.llama3-8b-instruct-v1:0", true)
+            .ends_with("/invoke-with-response-stream"));
+    }
+
+    #[test]
+    fn test_signing_key() {
+        // Example from the AWS SigV4 documentation
+        let signer = test_signer("iam", "us-east-1");
+        assert_eq!(
+            hex::encode(signer.signing_key("20120215")),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn test_sign_request() {
+        // Example GET request from the AWS SigV4 documentation
+        let signer = test_signer("iam", "us-east-1");
+        let url = reqwest::Url::parse("https://iam.amazonaws.com/?Action=ListUsers&Version=2010-05-08").unwrap();
+        let now = DateTime::parse_from_rfc3339("2015-08-30T12:36:00Z").unwrap().with_timezone(&Utc);
+
+        let signed = signer.sign(
+            "GET",
+            &url,
+            &[("Content-Type", "application/x-www-form-urlencoded; charset=utf-8")],
+            b"",
+            now,
+        );
+
+        assert_eq!(signed[0], ("x-amz-date".to_string(), "20150830T123600Z".to_string()));
+        assert_eq!(
+            signed[1].1,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date, \
+             Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+    }
+
+    #[test]
+    fn test_sign_request_with_session_token() {
+        let mut signer = test_signer("bedrock", "us-east-1");
+        signer.session_token = Some("session");
+        let url = reqwest::Url::parse("https://bedrock-runtime.us-east-1.amazonaws.com/model/x/invoke").unwrap();
+
+        let signed = signer.sign("POST", &url, &[], b"{}", Utc::now());
+
+        assert_eq!(signed[1], ("x-amz-security-token".to_string(), "session".to_string()));
+        assert!(signed[2].1.contains("SignedHeaders=host;x-amz-date;x-amz-security-token"));
+    }
+
+    #[test]
+    fn test_canonical_uri_double_encodes() {
+        let url = reqwest::Url::parse("https://example.com/model/anthropic.claude-v2%3A1/invoke").unwrap();
+        assert_eq!(canonical_uri(&url), "/model/anthropic.claude-v2%253A1/invoke");
+    }
+
+    #[test]
+    fn test_model_family_detection() {
+        assert_eq!(ModelFamily::from_model_id("anthropic.claude-v2:1"), Some(ModelFamily::Anthropic));
+        assert_eq!(
+            ModelFamily::from_model_id("us.anthropic.claude-3-5-sonnet-20240620-v1:0"),
+            Some(ModelFamily::Anthropic)
+        );
+        assert_eq!(ModelFamily::from_model_id("amazon.titan-text-express-v1"), Some(ModelFamily::Titan));
+        assert_eq!(ModelFamily::from_model_id("meta.llama2-70b-chat-v1"), Some(ModelFamily::Llama2));
+        assert_eq!(ModelFamily::from_model_id("meta.llama3-70b-instruct-v1:0"), Some(ModelFamily::Llama3));
+        assert_eq!(ModelFamily::from_model_id("cohere.command-text-v14"), Some(ModelFamily::CohereCommand));
+        assert_eq!(ModelFamily::from_model_id("cohere.command-r-v1:0"), Some(ModelFamily::CohereCommandR));
+        assert_eq!(ModelFamily::from_model_id("ai21.j2-ultra-v1"), None);
+    }
+
+    #[test]
+    fn test_anthropic_body() {
+        let request = CompletionRequest::new("anthropic.claude-v2:1", "Hi")
+            .with_system("Be brief.")
+            .with_max_tokens(50)
+            .with_stop(vec!["END".to_string()]);
+
+        let body = ModelFamily::Anthropic.build_request_body(&request, false);
+
+        assert_eq!(body["anthropic_version"], ANTHROPIC_BEDROCK_VERSION);
+        assert_eq!(body["max_tokens"], 50);
+        assert_eq!(body["system"], "Be brief.");
+        assert_eq!(body["messages"][0]["role"], "user");
+        assert_eq!(body["messages"][0]["content"], "Hi");
+        assert_eq!(body["stop_sequences"][0], "END");
+    }
+
+    #[test]
+    fn test_titan_body() {
+        let request = CompletionRequest::new("amazon.titan-text-express-v1", "Hi")
+            .with_max_tokens(100)
+            .with_temperature(0.5);
+
+        let body = ModelFamily::Titan.build_request_body(&request, false);
+
+        assert_eq!(body["inputText"], "Hi");
+        assert_eq!(body["textGenerationConfig"]["maxTokenCount"], 100);
+        assert_eq!(body["textGenerationConfig"]["temperature"], 0.5);
+    }
+
+    #[test]
+    fn test_llama_prompts() {
+        let request = CompletionRequest::new("meta.llama3-8b-instruct-v1:0", "And 3?")
+            .with_system("Square numbers.")
+            .with_message(ChatMessage::user("2?"))
+            .with_message(ChatMessage::assistant("4"));
+
+        let llama3 = ModelFamily::Llama3.build_request_body(&request, false);
+        assert_eq!(
+            llama3["prompt"],
+            "<|begin_of_text|>\
+             <|start_header_id|>system<|end_header_id|>\n\nSquare numbers.<|eot_id|>\
+             <|start_header_id|>user<|end_header_id|>\n\n2?<|eot_id|>\
+             <|start_header_id|>assistant<|end_header_id|>\n\n4<|eot_id|>\
+             <|start_header_id|>user<|end_header_id|>\n\nAnd 3?<|eot_id|>\
+             <|start_header_id|>assistant<|end_header_id|>\n\n"
+        );
+
+        let llama2 = ModelFamily::Llama2.build_request_body(&request.with_max_tokens(64), false);
+        assert_eq!(
+            llama2["prompt"],
+            "<s>[INST] <<SYS>>\nSquare numbers.\n<</SYS>>\n\n2? [/INST] 4 </s><s>[INST] And 3? [/INST]"
+        );
+        assert_eq!(llama2["max_gen_len"], 64);
+    }
+
+    #[test]
+    fn test_cohere_bodies() {
+        let request = CompletionRequest::new("cohere.command-text-v14", "Hi").with_top_p(0.9);
+
+        let command = ModelFamily::CohereCommand.build_request_body(&request, true);
+        assert_eq!(command["prompt"], "Hi");
+        assert_eq!(command["stream"], true);
+        assert!(command.get("p").is_some());
+
+        let command_r = ModelFamily::CohereCommandR
+            .build_request_body(&request.with_system("Be brief."), false);
+        assert_eq!(command_r["message"], "Hi");
+        assert_eq!(command_r["preamble"], "Be brief.");
+        assert!(command_r.get("stream").is_none());
+    }
+
+    #[test]
+    fn test_parse_responses() {
+        let titan = ModelFamily::Titan.parse_response(&serde_json::json!({
+            "inputTextTokenCount": 5,
+            "results": [{ "tokenCount": 7, "outputText": "Hello", "completionReason": "LENGTH" }]
+        }));
+        assert_eq!(titan.content, "Hello");
+        assert_eq!(titan.finish_reason, FinishReason::Length);
+        assert_eq!(titan.prompt_tokens, Some(5));
+        assert_eq!(titan.completion_tokens, Some(7));
+
+        let llama = ModelFamily::Llama3.parse_response(&serde_json::json!({
+            "generation": "Hi there",
+            "prompt_token_count": 10,
+            "generation_token_count": 3,
+            "stop_reason": "stop"
+        }));
+        assert_eq!(llama.content, "Hi there");
+        assert_eq!(llama.finish_reason, FinishReason::Stop);
+
+        let cohere = ModelFamily::CohereCommand.parse_response(&serde_json::json!({
+            "id": "gen-1",
+            "generations": [{ "text": "Yo", "finish_reason": "MAX_TOKENS" }]
+        }));
+        assert_eq!(cohere.id.as_deref(), Some("gen-1"));
+        assert_eq!(cohere.content, "Yo");
+        assert_eq!(cohere.finish_reason, FinishReason::Length);
+        assert_eq!(cohere.prompt_tokens, None);
+    }
+
+    #[test]
+    fn test_event_stream_decoder() {
+        let payload = br#"{"bytes":"eyJvdXRwdXRUZXh0IjoiSGkifQ=="}"#;
+        let frame = encode_frame(&[(":event-type", "chunk"), (":message-type", "event")], payload);
+
+        let mut decoder = EventStreamDecoder::default();
+        // Feed the frame in two pieces to exercise buffering
+        decoder.push(&frame[..10]);
+        assert!(decoder.next_message().unwrap().is_none());
+        decoder.push(&frame[10..]);
+
+        let message = decoder.next_message().unwrap().unwrap();
+        assert_eq!(message.headers[":event-type"], "chunk");
+        assert_eq!(message.payload, payload);
+        assert!(decoder.next_message().unwrap().is_none());
+
+        let events = ModelFamily::Titan
+            .decode_stream_event(&message, &mut ClaudeStreamState::default())
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].text(), Some("Hi"));
+    }
+
+    #[test]
+    fn test_event_stream_checksum_mismatch() {
+        let mut frame = encode_frame(&[(":event-type", "chunk")], b"{}");
+        let last = frame.len() - 1;
+        frame[last] ^= 0xff;
+
+        let mut decoder = EventStreamDecoder::default();
+        decoder.push(&frame);
+        assert!(decoder.next_message().is_err());
+    }
+
+    #[test]
+    fn test_stream_exception_event() {
+        let frame = encode_frame(
+            &[(":message-type", "exception"), (":exception-type", "throttlingException")],
+            br#"{"message":"Too many requests"}"#,
+        );
+        let mut decoder = EventStreamDecoder::default();
+        decoder.push(&frame);
+        let message = decoder.next_message().unwrap().unwrap();
+
+        let result = ModelFamily::Anthropic.decode_stream_event(&message, &mut ClaudeStreamState::default());
+        assert!(matches!(result, Err(ProviderError::RateLimitExceeded { .. })));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(matches!(
+            parse_error(400, Some("ValidationException"), r#"{"message":"bad"}"#, "m"),
+            ProviderError::InvalidRequest(ref msg) if msg == "bad"
+        ));
+        assert!(matches!(
+            parse_error(403, Some("AccessDeniedException"), r#"{"Message":"denied"}"#, "m"),
+            ProviderError::AuthenticationError(_)
+        ));
+        assert!(matches!(
+            parse_error(404, None, "", "missing-model"),
+            ProviderError::ModelNotFound { ref model } if model == "missing-model"
+        ));
     }
 }
@@ -0,0 +1,454 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Persistent response caching.
+//!
+//! [`CachedProvider`] stores every completion on disk, keyed by the provider
+//! name and the full request (model, prompt, messages, sampling parameters,
+//! tools and response format; see [`request_key`]). Repeating a request
+//! within the TTL returns the stored response without calling the provider,
+//! with [`CACHE_HIT`] set in its `metadata`. Re-running a dataset after
+//! changing only the evaluators therefore costs nothing.
+//!
+//! Sampled requests (`temperature > 0`) are not cached unless caching is
+//! forced with [`CachedProvider::force`], since a repeat is expected to give
+//! a different answer. Requests without a temperature count as sampled, as
+//! providers default to a temperature of about 1.
+//!
+//! Streamed requests share entries with non-streamed ones; a cached answer
+//! to a streamed request arrives as a single chunk.
+//!
+//! The cache holds at most [`with_max_entries`](CachedProvider::with_max_entries)
+//! responses and evicts the least recently used one first.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use llm_test_bench_core::providers::{CachedProvider, OpenAIProvider};
+//!
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let provider = CachedProvider::new(Box::new(OpenAIProvider::new("api-key".to_string())?))
+//!     .with_cache_dir("./.llm-cache")
+//!     .with_ttl_hours(24)
+//!     .with_max_entries(5_000);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::cassette::request_key;
+use super::{CompletionRequest, CompletionResponse, ModelInfo, Provider, ProviderError, ResponseStream, StreamAccumulator};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::{self, StreamExt};
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
+
+/// Metadata key set to `"true"` on responses served from the cache.
+pub const CACHE_HIT: &str = "cache_hit";
+
+/// Default time a cached response stays valid, in hours.
+pub const DEFAULT_CACHE_TTL_HOURS: i64 = 168;
+
+/// Default maximum number of cached responses.
+pub const DEFAULT_MAX_CACHE_ENTRIES: usize = 10_000;
+
+/// A response as stored on disk.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedResponse {
+    cached_at: DateTime<Utc>,
+    response: CompletionResponse,
+}
+
+/// The cache directory and its recency index.
+///
+/// The index is built from the directory on first use, oldest file first,
+/// so eviction order survives restarts.
+#[derive(Clone)]
+struct ResponseStore {
+    dir: PathBuf,
+    max_entries: usize,
+    index: Arc<Mutex<Option<LruCache<String, ()>>>>,
+}
+
+impl ResponseStore {
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    fn with_index<T>(&self, f: impl FnOnce(&mut LruCache<String, ()>) -> T) -> T {
+        let mut index = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        let index = index.get_or_insert_with(|| self.load_index());
+        f(index)
+    }
+
+    fn load_index(&self) -> LruCache<String, ()> {
+        let mut index = LruCache::new(NonZeroUsize::new(self.max_entries.max(1)).unwrap());
+
+        let mut files: Vec<_> = std::fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != "json" {
+                    return None;
+                }
+                let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+                Some((modified, path.file_stem()?.to_string_lossy().into_owned()))
+            })
+            .collect();
+        files.sort();
+
+        for (_, key) in files {
+            if let Some((evicted, _)) = index.push(key, ()) {
+                self.remove(&evicted);
+            }
+        }
+        index
+    }
+
+    fn get(&self, key: &str, ttl_hours: i64) -> Option<CompletionResponse> {
+        if !self.with_index(|index| index.contains(key)) {
+            return None;
+        }
+
+        let cached = std::fs::read_to_string(self.path(key))
+            .ok()
+            .and_then(|contents| serde_json::from_str::<CachedResponse>(&contents).ok());
+
+        match cached {
+            Some(cached) if Utc::now().signed_duration_since(cached.cached_at) < Duration::hours(ttl_hours) => {
+                self.with_index(|index| index.promote(key));
+                Some(cached.response)
+            }
+            _ => {
+                // Expired or unreadable
+                self.with_index(|index| index.pop(key));
+                self.remove(key);
+                None
+            }
+        }
+    }
+
+    fn put(&self, key: String, response: &CompletionResponse) {
+        let cached = CachedResponse { cached_at: Utc::now(), response: response.clone() };
+        if let Err(e) = self.write(&key, &cached) {
+            warn!("Failed to cache response in {}: {}", self.dir.display(), e);
+            return;
+        }
+
+        let evicted = self.with_index(|index| index.push(key.clone(), ()));
+        if let Some((evicted, _)) = evicted.filter(|(evicted, _)| *evicted != key) {
+            debug!("Evicting cached response {}", evicted);
+            self.remove(&evicted);
+        }
+    }
+
+    /// Writes through a temporary file so readers never see a partial entry.
+    fn write(&self, key: &str, cached: &CachedResponse) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(cached)?)?;
+        std::fs::rename(&tmp, &path)
+    }
+
+    fn remove(&self, key: &str) {
+        let _ = std::fs::remove_file(self.path(key));
+    }
+
+    fn clear(&self) -> std::io::Result<()> {
+        *self.index.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        match std::fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A provider that caches responses on disk.
+pub struct CachedProvider {
+    inner: Box<dyn Provider>,
+    store: ResponseStore,
+    ttl_hours: i64,
+    force: bool,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl CachedProvider {
+    /// Wraps `inner`, caching under `~/.cache/llm-test-bench/responses`.
+    pub fn new(inner: Box<dyn Provider>) -> Self {
+        let dir = dirs::cache_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("llm-test-bench")
+            .join("responses");
+
+        Self {
+            inner,
+            store: ResponseStore {
+                dir,
+                max_entries: DEFAULT_MAX_CACHE_ENTRIES,
+                index: Arc::new(Mutex::new(None)),
+            },
+            ttl_hours: DEFAULT_CACHE_TTL_HOURS,
+            force: false,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Sets the cache directory.
+    pub fn with_cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.store.dir = cache_dir.into();
+        self.store.index = Arc::new(Mutex::new(None));
+        self
+    }
+
+    /// Sets how long a cached response stays valid, in hours.
+    pub fn with_ttl_hours(mut self, hours: i64) -> Self {
+        self.ttl_hours = hours;
+        self
+    }
+
+    /// Sets the maximum number of cached responses.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.store.max_entries = max_entries;
+        self.store.index = Arc::new(Mutex::new(None));
+        self
+    }
+
+    /// Caches sampled requests (`temperature > 0` or unset) too.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// The cache directory.
+    pub fn cache_dir(&self) -> &Path {
+        &self.store.dir
+    }
+
+    /// Number of requests answered from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of cacheable requests sent to the provider.
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Removes every cached response.
+    pub fn clear(&self) -> Result<(), ProviderError> {
+        self.store.clear().map_err(|e| {
+            ProviderError::InternalError(format!("Failed to clear cache {}: {}", self.store.dir.display(), e))
+        })
+    }
+
+    /// The cache key of `request`, or `None` if it must not be cached.
+    fn key(&self, request: &CompletionRequest) -> Option<String> {
+        let deterministic = request.temperature.is_some_and(|t| t <= 0.0);
+        (self.force || deterministic).then(|| request_key(self.inner.name(), request))
+    }
+
+    /// Looks `key` up, counting the hit or miss.
+    fn lookup(&self, key: &str) -> Option<CompletionResponse> {
+        match self.store.get(key, self.ttl_hours) {
+            Some(mut response) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                response.metadata.insert(CACHE_HIT.to_string(), "true".to_string());
+                Some(response)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for CachedProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        let Some(key) = self.key(&request) else {
+            return self.inner.complete(request).await;
+        };
+        if let Some(response) = self.lookup(&key) {
+            debug!("Cache hit for {} request to {}", self.inner.name(), response.model);
+            return Ok(response);
+        }
+
+        let response = self.inner.complete(request).await?;
+        self.store.put(key, &response);
+        Ok(response)
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+        let Some(key) = self.key(&request) else {
+            return self.inner.stream(request).await;
+        };
+        if let Some(response) = self.lookup(&key) {
+            return Ok(super::streaming::from_response(&response));
+        }
+
+        // Assemble the response as the events pass through and cache it once
+        // the stream ends without an error
+        let accumulator = Arc::new(Mutex::new(Some(StreamAccumulator::new(request.model.clone()))));
+        let upstream = self.inner.stream(request).await?;
+
+        let sink = Arc::clone(&accumulator);
+        let forwarded = upstream.inspect(move |event| {
+            let mut sink = sink.lock().unwrap_or_else(|e| e.into_inner());
+            match event {
+                Ok(event) => {
+                    if let Some(accumulator) = sink.as_mut() {
+                        accumulator.push(event);
+                    }
+                }
+                Err(_) => *sink = None,
+            }
+        });
+
+        let store = self.store.clone();
+        let finish = stream::once(async move {
+            let accumulator = accumulator.lock().unwrap_or_else(|e| e.into_inner()).take();
+            if let Some(accumulator) = accumulator {
+                store.put(key, &accumulator.into_response());
+            }
+            None
+        })
+        .filter_map(futures::future::ready);
+
+        Ok(Box::pin(forwarded.chain(finish)))
+    }
+
+    fn supported_models(&self) -> Vec<ModelInfo> {
+        self.inner.supported_models()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        self.inner.list_models().await
+    }
+
+    fn max_context_length(&self, model: &str) -> Option<usize> {
+        self.inner.max_context_length(model)
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn validate_config(&self) -> Result<(), ProviderError> {
+        self.inner.validate_config().await
+    }
+
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        self.inner.estimate_tokens(text, model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::MockProvider;
+    use tempfile::TempDir;
+
+    fn cached(dir: &TempDir) -> CachedProvider {
+        CachedProvider::new(Box::new(MockProvider::default())).with_cache_dir(dir.path())
+    }
+
+    #[tokio::test]
+    async fn test_cache_hit() {
+        let dir = TempDir::new().unwrap();
+        let provider = cached(&dir);
+        let request = CompletionRequest::new("mock-model", "Hello").with_temperature(0.0).with_max_tokens(50);
+
+        let first = provider.complete(request.clone()).await.unwrap();
+        assert!(!first.metadata.contains_key(CACHE_HIT));
+
+        let second = provider.complete(request.clone()).await.unwrap();
+        assert_eq!(second.metadata.get(CACHE_HIT).map(String::as_str), Some("true"));
+        assert_eq!(second.id, first.id);
+        assert_eq!((provider.hits(), provider.misses()), (1, 1));
+
+        // Sampling parameters are part of the key
+        let other = provider.complete(request.with_max_tokens(60)).await.unwrap();
+        assert_ne!(other.id, first.id);
+
+        // The cache survives the provider
+        let reopened = cached(&dir);
+        let request = CompletionRequest::new("mock-model", "Hello").with_temperature(0.0).with_max_tokens(50);
+        assert_eq!(reopened.complete(request).await.unwrap().id, first.id);
+    }
+
+    #[tokio::test]
+    async fn test_sampled_requests_skip_cache_unless_forced() {
+        let dir = TempDir::new().unwrap();
+        let request = CompletionRequest::new("mock-model", "Hello").with_temperature(0.7);
+
+        let provider = cached(&dir);
+        let first = provider.complete(request.clone()).await.unwrap();
+        let second = provider.complete(request.clone()).await.unwrap();
+        assert_ne!(first.id, second.id);
+        assert_eq!((provider.hits(), provider.misses()), (0, 0));
+
+        // Without a temperature the provider's default applies, which samples
+        let unset = CompletionRequest::new("mock-model", "Hello");
+        provider.complete(unset.clone()).await.unwrap();
+        provider.complete(unset).await.unwrap();
+        assert_eq!((provider.hits(), provider.misses()), (0, 0));
+
+        let provider = cached(&dir).force(true);
+        let first = provider.complete(request.clone()).await.unwrap();
+        let second = provider.complete(request).await.unwrap();
+        assert_eq!(first.id, second.id);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_and_max_entries() {
+        let dir = TempDir::new().unwrap();
+
+        let provider = cached(&dir).with_ttl_hours(0);
+        let request = CompletionRequest::new("mock-model", "Hello").with_temperature(0.0);
+        let first = provider.complete(request.clone()).await.unwrap();
+        assert_ne!(provider.complete(request).await.unwrap().id, first.id);
+
+        let provider = cached(&dir).with_max_entries(2);
+        provider.clear().unwrap();
+        for prompt in ["one", "two", "three"] {
+            provider.complete(CompletionRequest::new("mock-model", prompt).with_temperature(0.0)).await.unwrap();
+        }
+        let files = std::fs::read_dir(dir.path()).unwrap().count();
+        assert_eq!(files, 2);
+
+        // "one" was evicted
+        provider.complete(CompletionRequest::new("mock-model", "one").with_temperature(0.0)).await.unwrap();
+        assert_eq!(provider.hits(), 0);
+        provider.complete(CompletionRequest::new("mock-model", "three").with_temperature(0.0)).await.unwrap();
+        assert_eq!(provider.hits(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_response_is_cached() {
+        let dir = TempDir::new().unwrap();
+        let provider = cached(&dir);
+        let request = CompletionRequest::new("mock-model", "Stream this").with_temperature(0.0).with_streaming();
+
+        let stream = provider.stream(request.clone()).await.unwrap();
+        let (live, _) = StreamAccumulator::collect("mock-model", stream).await.unwrap();
+
+        let response = provider.complete(request).await.unwrap();
+        assert_eq!(response.metadata.get(CACHE_HIT).map(String::as_str), Some("true"));
+        assert_eq!(response.content, live.content);
+        assert_eq!(response.usage, live.usage);
+    }
+}
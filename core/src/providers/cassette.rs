@@ -0,0 +1,607 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Recording and replaying provider traffic.
+//!
+//! A [`Cassette`] is a JSON file of request/response pairs keyed by a hash of
+//! the normalized request (see [`request_key`]). [`CassetteProvider`] runs in
+//! one of two modes:
+//!
+//! - **Record**: requests go to the wrapped provider and every successful
+//!   response, including the events of streamed responses, is added to the
+//!   cassette. Existing entries for the same request are replaced. The file
+//!   is written by [`Cassette::save`], or when the cassette is dropped.
+//! - **Replay**: requests are answered from the cassette without any network
+//!   access. A request that was never recorded fails with
+//!   [`ProviderError::InvalidRequest`] naming the cassette and the key.
+//!
+//! Several providers can share one cassette; entries are keyed by provider
+//! name as well as by request.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use llm_test_bench_core::providers::{Cassette, CassetteProvider, OpenAIProvider};
+//! use std::sync::Arc;
+//!
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! // Record once against the live API...
+//! let cassette = Arc::new(Cassette::open("fixtures/openai.json")?);
+//! let provider = CassetteProvider::record(
+//!     "openai",
+//!     Box::new(OpenAIProvider::new("api-key".to_string())?),
+//!     cassette,
+//! );
+//!
+//! // ...then replay offline, e.g. in CI
+//! let cassette = Arc::new(Cassette::load("fixtures/openai.json")?);
+//! let provider = CassetteProvider::replay("openai", cassette);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{CompletionRequest, CompletionResponse, ModelInfo, Provider, ProviderError, ResponseStream, StreamEvent};
+use crate::tokenizer;
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
+
+/// Version written to new cassette files.
+const CASSETTE_VERSION: u32 = 1;
+
+/// Returns the cassette key of `request` sent to `provider`.
+///
+/// The request is normalized before hashing: the `stream` flag is dropped
+/// (streamed and non-streamed answers are stored side by side), unset and
+/// empty fields are removed and object keys are sorted. Two requests that
+/// differ only in those respects share a key.
+pub fn request_key(provider: &str, request: &CompletionRequest) -> String {
+    let normalized = normalize_request(request);
+    let mut hasher = Sha256::new();
+    hasher.update(provider.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(normalized.to_string().as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+fn normalize_request(request: &CompletionRequest) -> Value {
+    let mut value = serde_json::to_value(request).unwrap_or(Value::Null);
+    if let Value::Object(ref mut map) = value {
+        map.remove("stream");
+    }
+    prune(value)
+}
+
+/// Removes nulls, empty arrays and empty objects. `serde_json::Map` keeps
+/// keys sorted, so the result serializes deterministically.
+fn prune(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, prune(value)))
+                .filter(|(_, value)| !is_empty(value))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(prune).collect()),
+        other => other,
+    }
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Array(items) => items.is_empty(),
+        Value::Object(map) => map.is_empty(),
+        _ => false,
+    }
+}
+
+/// A recorded request and the answers it received.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// Name of the provider that answered.
+    pub provider: String,
+
+    /// The normalized request, kept for humans reading the cassette.
+    pub request: Value,
+
+    /// The response to a `complete` call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<CompletionResponse>,
+
+    /// The events of a `stream` call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<Vec<StreamEvent>>,
+}
+
+/// On-disk layout of a cassette.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CassetteFile {
+    version: u32,
+
+    /// Models reported by each recorded provider, served in replay mode.
+    #[serde(default)]
+    models: BTreeMap<String, Vec<ModelInfo>>,
+
+    #[serde(default)]
+    interactions: BTreeMap<String, Interaction>,
+
+    /// Whether there are changes not yet written to disk.
+    #[serde(skip)]
+    unsaved: bool,
+}
+
+/// A cassette file of recorded interactions.
+///
+/// Share one cassette between providers through an `Arc`. Recorded
+/// interactions are kept in memory until [`save`](Self::save) is called or
+/// the cassette is dropped, so a long recording writes the file once.
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    data: Mutex<CassetteFile>,
+}
+
+impl Cassette {
+    /// Opens a cassette for recording, starting empty if `path` does not exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ProviderError> {
+        let path = path.as_ref();
+        if path.exists() {
+            Self::load(path)
+        } else {
+            Ok(Self {
+                path: path.to_path_buf(),
+                data: Mutex::new(CassetteFile { version: CASSETTE_VERSION, ..Default::default() }),
+            })
+        }
+    }
+
+    /// Loads an existing cassette.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ProviderError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ProviderError::InvalidRequest(format!("Failed to read cassette {}: {}", path.display(), e))
+        })?;
+        let data: CassetteFile = serde_json::from_str(&contents)?;
+        if data.version > CASSETTE_VERSION {
+            return Err(ProviderError::InvalidRequest(format!(
+                "Cassette {} has unsupported version {}",
+                path.display(),
+                data.version
+            )));
+        }
+
+        Ok(Self { path: path.to_path_buf(), data: Mutex::new(data) })
+    }
+
+    /// Path of the cassette file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of recorded interactions.
+    pub fn len(&self) -> usize {
+        self.data.lock().unwrap_or_else(|e| e.into_inner()).interactions.len()
+    }
+
+    /// Whether nothing has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The interaction recorded under `key`.
+    pub fn get(&self, key: &str) -> Option<Interaction> {
+        self.data.lock().unwrap_or_else(|e| e.into_inner()).interactions.get(key).cloned()
+    }
+
+    fn models(&self, provider: &str) -> Vec<ModelInfo> {
+        self.data.lock().unwrap_or_else(|e| e.into_inner()).models.get(provider).cloned().unwrap_or_default()
+    }
+
+    fn set_models(&self, provider: &str, models: Vec<ModelInfo>) {
+        let mut data = self.data.lock().unwrap_or_else(|e| e.into_inner());
+        data.models.insert(provider.to_string(), models);
+        data.unsaved = true;
+    }
+
+    /// Merges `update` into the interaction under `key`.
+    fn record(&self, key: String, update: Interaction) {
+        let mut data = self.data.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = data.interactions.entry(key).or_insert_with(|| Interaction {
+            provider: update.provider.clone(),
+            request: update.request.clone(),
+            response: None,
+            stream: None,
+        });
+        if update.response.is_some() {
+            entry.response = update.response;
+        }
+        if update.stream.is_some() {
+            entry.stream = update.stream;
+        }
+        data.unsaved = true;
+    }
+
+    /// Writes the cassette file if anything was recorded since it was last
+    /// written.
+    pub fn save(&self) -> Result<(), ProviderError> {
+        let mut data = self.data.lock().unwrap_or_else(|e| e.into_inner());
+        if !data.unsaved {
+            return Ok(());
+        }
+        self.write(&serde_json::to_string_pretty(&*data)?)?;
+        data.unsaved = false;
+        debug!("Saved {} interactions to {}", data.interactions.len(), self.path.display());
+        Ok(())
+    }
+
+    /// Writes through a temporary file so an interrupted run never leaves a
+    /// truncated cassette behind.
+    fn write(&self, contents: &str) -> Result<(), ProviderError> {
+        let io_error = |e: std::io::Error| {
+            ProviderError::InternalError(format!("Failed to write cassette {}: {}", self.path.display(), e))
+        };
+
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, contents).map_err(io_error)?;
+        std::fs::rename(&tmp, &self.path).map_err(io_error)
+    }
+}
+
+impl Drop for Cassette {
+    fn drop(&mut self) {
+        if let Err(e) = self.save() {
+            warn!("{}", e);
+        }
+    }
+}
+
+/// Whether a [`CassetteProvider`] records or replays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Forward requests and save the answers.
+    Record,
+    /// Answer from the cassette only.
+    Replay,
+}
+
+/// A provider that records to or replays from a [`Cassette`].
+pub struct CassetteProvider {
+    name: String,
+    inner: Option<Box<dyn Provider>>,
+    cassette: Arc<Cassette>,
+}
+
+impl CassetteProvider {
+    /// Records the traffic of `inner` to `cassette` under `name`.
+    ///
+    /// Use the same name to replay it, usually the provider's name in the
+    /// configuration.
+    pub fn record(name: impl Into<String>, inner: Box<dyn Provider>, cassette: Arc<Cassette>) -> Self {
+        let name = name.into();
+        cassette.set_models(&name, inner.supported_models());
+        Self { name, inner: Some(inner), cassette }
+    }
+
+    /// Replays the traffic recorded for the provider called `name`.
+    pub fn replay(name: impl Into<String>, cassette: Arc<Cassette>) -> Self {
+        Self { name: name.into(), inner: None, cassette }
+    }
+
+    /// The mode this provider runs in.
+    pub fn mode(&self) -> CassetteMode {
+        if self.inner.is_some() {
+            CassetteMode::Record
+        } else {
+            CassetteMode::Replay
+        }
+    }
+
+    /// The cassette in use.
+    pub fn cassette(&self) -> &Arc<Cassette> {
+        &self.cassette
+    }
+
+    fn lookup(&self, request: &CompletionRequest) -> Result<Interaction, ProviderError> {
+        let key = request_key(&self.name, request);
+        self.cassette.get(&key).ok_or_else(|| self.miss(&key, request))
+    }
+
+    fn miss(&self, key: &str, request: &CompletionRequest) -> ProviderError {
+        ProviderError::InvalidRequest(format!(
+            "No recording in cassette {} for {} request to model '{}' (key {}); re-record the cassette",
+            self.cassette.path().display(),
+            self.name,
+            request.model,
+            key
+        ))
+    }
+
+    fn interaction(&self, request: &CompletionRequest) -> Interaction {
+        Interaction {
+            provider: self.name.clone(),
+            request: normalize_request(request),
+            response: None,
+            stream: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for CassetteProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        let Some(ref inner) = self.inner else {
+            let key = request_key(&self.name, &request);
+            return self
+                .lookup(&request)?
+                .response
+                .ok_or_else(|| self.miss(&key, &request));
+        };
+
+        let key = request_key(&self.name, &request);
+        let mut interaction = self.interaction(&request);
+        let response = inner.complete(request).await?;
+
+        interaction.response = Some(response.clone());
+        self.cassette.record(key, interaction);
+        debug!("Recorded {} completion to {}", self.name, self.cassette.path().display());
+        Ok(response)
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+        let Some(ref inner) = self.inner else {
+            let key = request_key(&self.name, &request);
+            let events = self
+                .lookup(&request)?
+                .stream
+                .ok_or_else(|| self.miss(&key, &request))?;
+            // Stamp events as received now so stream metrics stay meaningful
+            return Ok(Box::pin(stream::iter(
+                events.into_iter().map(|event| Ok(StreamEvent::new(event.kind))),
+            )));
+        };
+
+        let key = request_key(&self.name, &request);
+        let interaction = self.interaction(&request);
+        let upstream = inner.stream(request).await?;
+
+        // Events are collected as they pass through and recorded once the
+        // stream ends; a stream that fails part way is not recorded
+        let recorded = Arc::new(Mutex::new(Some(Vec::new())));
+        let sink = Arc::clone(&recorded);
+        let cassette = Arc::clone(&self.cassette);
+
+        let forwarded = upstream.inspect(move |event| {
+            let mut sink = sink.lock().unwrap_or_else(|e| e.into_inner());
+            match event {
+                Ok(event) => {
+                    if let Some(events) = sink.as_mut() {
+                        events.push(event.clone());
+                    }
+                }
+                Err(_) => *sink = None,
+            }
+        });
+        let finish = stream::once(async move {
+            let events = recorded.lock().unwrap_or_else(|e| e.into_inner()).take();
+            if let Some(events) = events {
+                cassette.record(key, Interaction { stream: Some(events), ..interaction });
+            }
+            None
+        })
+        .filter_map(futures::future::ready);
+
+        Ok(Box::pin(forwarded.chain(finish)))
+    }
+
+    fn supported_models(&self) -> Vec<ModelInfo> {
+        match self.inner {
+            Some(ref inner) => inner.supported_models(),
+            None => self.cassette.models(&self.name),
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        match self.inner {
+            Some(ref inner) => inner.list_models().await,
+            None => Ok(self.cassette.models(&self.name)),
+        }
+    }
+
+    fn max_context_length(&self, model: &str) -> Option<usize> {
+        match self.inner {
+            Some(ref inner) => inner.max_context_length(model),
+            None => self
+                .cassette
+                .models(&self.name)
+                .into_iter()
+                .find(|m| m.id == model)
+                .map(|m| m.max_tokens),
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn validate_config(&self) -> Result<(), ProviderError> {
+        match self.inner {
+            Some(ref inner) => inner.validate_config().await,
+            None => Ok(()),
+        }
+    }
+
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        match self.inner {
+            Some(ref inner) => inner.estimate_tokens(text, model),
+            None => Ok(tokenizer::count_tokens(model, text)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{FinishReason, StreamAccumulator, TokenUsage};
+    use chrono::Utc;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::TempDir;
+
+    struct MockProvider {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(CompletionResponse {
+                id: format!("mock-{}", call),
+                model: request.model,
+                content: format!("echo: {}", request.prompt),
+                usage: TokenUsage::new(5, 3),
+                finish_reason: FinishReason::Stop,
+                created_at: Utc::now(),
+                tool_calls: Vec::new(),
+                schema_violations: Vec::new(),
+                logprobs: None,
+                prompt_logprobs: None,
+                choices: Vec::new(),
+                metadata: HashMap::new(),
+            })
+        }
+
+        async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+            let response = self.complete(request).await?;
+            Ok(crate::providers::streaming::from_response(&response))
+        }
+
+        fn supported_models(&self) -> Vec<ModelInfo> {
+            vec![ModelInfo::new("mock-model", "Mock", 4096, true, false)]
+        }
+
+        fn max_context_length(&self, _model: &str) -> Option<usize> {
+            Some(4096)
+        }
+
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        async fn validate_config(&self) -> Result<(), ProviderError> {
+            Ok(())
+        }
+
+        fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
+            Ok(text.split_whitespace().count())
+        }
+    }
+
+    fn recorder(cassette: &Arc<Cassette>) -> CassetteProvider {
+        CassetteProvider::record("mock", Box::new(MockProvider { calls: AtomicUsize::new(0) }), Arc::clone(cassette))
+    }
+
+    #[test]
+    fn test_request_key_normalization() {
+        let request = CompletionRequest::new("mock-model", "Hello").with_temperature(0.2);
+        let streaming = request.clone().with_streaming();
+
+        assert_eq!(request_key("mock", &request), request_key("mock", &streaming));
+        assert_ne!(request_key("mock", &request), request_key("other", &request));
+        assert_ne!(
+            request_key("mock", &request),
+            request_key("mock", &request.clone().with_temperature(0.3))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("cassette.json");
+        let request = CompletionRequest::new("mock-model", "Hello");
+
+        let cassette = Arc::new(Cassette::open(&path).unwrap());
+        let recorded = recorder(&cassette).complete(request.clone()).await.unwrap();
+        assert_eq!(cassette.len(), 1);
+        assert!(!path.exists());
+        cassette.save().unwrap();
+
+        let replayer = CassetteProvider::replay("mock", Arc::new(Cassette::load(&path).unwrap()));
+        assert_eq!(replayer.mode(), CassetteMode::Replay);
+        let replayed = replayer.complete(request).await.unwrap();
+        assert_eq!(replayed.id, recorded.id);
+        assert_eq!(replayed.content, recorded.content);
+        assert_eq!(replayed.usage, recorded.usage);
+        assert_eq!(replayer.supported_models()[0].id, "mock-model");
+    }
+
+    #[tokio::test]
+    async fn test_replay_miss_fails() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("cassette.json");
+        let cassette = Arc::new(Cassette::open(&path).unwrap());
+        recorder(&cassette).complete(CompletionRequest::new("mock-model", "Hello")).await.unwrap();
+        cassette.save().unwrap();
+
+        let replayer = CassetteProvider::replay("mock", Arc::new(Cassette::load(&path).unwrap()));
+        let error = replayer
+            .complete(CompletionRequest::new("mock-model", "Goodbye"))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ProviderError::InvalidRequest(ref msg) if msg.contains("No recording")));
+
+        // A completion was recorded, but not a stream
+        let error = replayer
+            .stream(CompletionRequest::new("mock-model", "Hello").with_streaming())
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(error, ProviderError::InvalidRequest(_)));
+    }
+
+    #[tokio::test]
+    async fn test_stream_record_then_replay() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested").join("cassette.json");
+        let request = CompletionRequest::new("mock-model", "Stream me").with_streaming();
+
+        let cassette = Arc::new(Cassette::open(&path).unwrap());
+        let stream = recorder(&cassette).stream(request.clone()).await.unwrap();
+        let (live, _) = StreamAccumulator::collect("mock-model", stream).await.unwrap();
+        assert!(cassette.get(&request_key("mock", &request)).unwrap().stream.is_some());
+        cassette.save().unwrap();
+
+        let replayer = CassetteProvider::replay("mock", Arc::new(Cassette::load(&path).unwrap()));
+        let stream = replayer.stream(request).await.unwrap();
+        let (replayed, _) = StreamAccumulator::collect("mock-model", stream).await.unwrap();
+        assert_eq!(replayed.content, live.content);
+        assert_eq!(replayed.usage, live.usage);
+    }
+
+    #[tokio::test]
+    async fn test_drop_saves_recordings() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("cassette.json");
+        let request = CompletionRequest::new("mock-model", "Hello");
+
+        let cassette = Arc::new(Cassette::open(&path).unwrap());
+        recorder(&cassette).complete(request.clone()).await.unwrap();
+        drop(cassette);
+
+        let replayer = CassetteProvider::replay("mock", Arc::new(Cassette::load(&path).unwrap()));
+        assert!(replayer.complete(request).await.is_ok());
+    }
+}
@@ -6,14 +6,21 @@
 
 //! Cohere provider implementation
 
-use super::{CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use super::embeddings::{self, EmbeddingInputType, EmbeddingModel, EmbeddingProvider, EmbeddingRequest, EmbeddingResponse};
+use super::sampling::SamplingSupport;
+use super::streaming::{self, JsonLinesDecoder, SseDecoder, StreamEvent};
+use super::structured::{self, ResponseFormat};
+use super::{CompletionRequest, CompletionResponse, FinishReason, MessageRole, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use crate::tokenizer::Tokenizer;
 use async_trait::async_trait;
-use futures::stream::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::pin::Pin;
+use std::collections::HashMap;
 use std::time::Duration;
 use tracing::{debug, error, warn};
 
+/// Cohere's chat API generates one completion and has no logit bias
+const COHERE_SAMPLING: SamplingSupport = SamplingSupport { n: false, logit_bias: false, ..SamplingSupport::ALL };
+
 /// Cohere provider configuration
 #[derive(Debug, Clone)]
 pub struct CohereConfig {
@@ -77,14 +84,46 @@ impl CohereProvider {
         })
     }
 
+    /// Uses `client` for every request, e.g. one built from transport settings
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
     /// Build request body for Cohere API
     fn build_request_body(&self, request: &CompletionRequest, stream: bool) -> serde_json::Value {
+        // Cohere takes the latest user turn as `message` and everything
+        // before it as `chat_history`.
+        let mut turns = request.conversation();
+        let message = match turns.last() {
+            Some(last) if last.role == MessageRole::User => turns.pop().map(|m| m.content).unwrap_or_default(),
+            _ => String::new(),
+        };
+
         let mut body = serde_json::json!({
             "model": request.model,
-            "message": request.prompt,
+            "message": message,
             "stream": stream,
         });
 
+        if !turns.is_empty() {
+            let chat_history: Vec<serde_json::Value> = turns
+                .into_iter()
+                .map(|m| {
+                    let role = match m.role {
+                        MessageRole::Assistant => "CHATBOT",
+                        _ => "USER",
+                    };
+                    serde_json::json!({ "role": role, "message": m.content })
+                })
+                .collect();
+            body["chat_history"] = serde_json::json!(chat_history);
+        }
+
+        if let Some(system) = request.system_prompt() {
+            body["preamble"] = serde_json::json!(system);
+        }
+
         if let Some(temp) = request.temperature {
             body["temperature"] = serde_json::json!(temp);
         }
@@ -101,9 +140,49 @@ impl CohereProvider {
             body["stop_sequences"] = serde_json::json!(stop);
         }
 
+        if let Some(top_k) = request.top_k {
+            body["k"] = serde_json::json!(top_k);
+        }
+
+        if let Some(seed) = request.seed {
+            body["seed"] = serde_json::json!(seed);
+        }
+
+        if let Some(penalty) = request.presence_penalty {
+            body["presence_penalty"] = serde_json::json!(penalty);
+        }
+
+        if let Some(penalty) = request.frequency_penalty {
+            body["frequency_penalty"] = serde_json::json!(penalty);
+        }
+
+        match request.response_format {
+            Some(ResponseFormat::JsonObject) => {
+                body["response_format"] = serde_json::json!({ "type": "json_object" });
+            }
+            Some(ResponseFormat::JsonSchema { ref schema, .. }) => {
+                body["response_format"] = serde_json::json!({ "type": "json_object", "schema": schema });
+            }
+            Some(ResponseFormat::Text) | None => {}
+        }
+
         body
     }
 
+    /// Build request body for the `/embed` endpoint
+    ///
+    /// The v3 models require an `input_type`; documents are assumed when
+    /// the request does not set one.
+    fn build_embed_body(&self, request: &EmbeddingRequest) -> serde_json::Value {
+        let input_type = request.input_type.unwrap_or(EmbeddingInputType::SearchDocument);
+        serde_json::json!({
+            "model": request.model,
+            "texts": request.inputs,
+            "input_type": input_type.as_str(),
+            "embedding_types": ["float"],
+        })
+    }
+
     /// Parse Cohere error response
     fn parse_error_response(status: u16, text: &str) -> ProviderError {
         #[derive(Deserialize)]
@@ -128,6 +207,40 @@ impl CohereProvider {
         }
     }
 
+    /// Map Cohere's `finish_reason`
+    fn finish_reason(reason: Option<&str>) -> FinishReason {
+        match reason {
+            Some("COMPLETE") => FinishReason::Stop,
+            Some("MAX_TOKENS") => FinishReason::Length,
+            Some("ERROR") => FinishReason::Error,
+            _ => FinishReason::Stop,
+        }
+    }
+
+    /// Extract the events from one streamed chat event
+    ///
+    /// Text arrives in `text-generation` events; usage and the finish reason
+    /// in the final `stream-end` event.
+    fn stream_chunk_events(chunk: &serde_json::Value) -> Vec<StreamEvent> {
+        match chunk["event_type"].as_str() {
+            Some("text-generation") => chunk["text"]
+                .as_str()
+                .filter(|text| !text.is_empty())
+                .map(|text| vec![StreamEvent::content(text)])
+                .unwrap_or_default(),
+            Some("stream-end") => {
+                let billed = &chunk["response"]["meta"]["billed_units"];
+                let prompt_tokens = billed["input_tokens"].as_u64().unwrap_or(0) as usize;
+                let completion_tokens = billed["output_tokens"].as_u64().unwrap_or(0) as usize;
+                vec![
+                    StreamEvent::usage(TokenUsage::new(prompt_tokens, completion_tokens)),
+                    StreamEvent::finish(Self::finish_reason(chunk["finish_reason"].as_str())),
+                ]
+            }
+            _ => Vec::new(),
+        }
+    }
+
     /// Parse non-streaming response
     fn parse_completion_response(&self, json: &str) -> Result<CompletionResponse, ProviderError> {
         #[derive(Deserialize)]
@@ -152,12 +265,7 @@ impl CohereProvider {
         let resp: CohereResponse = serde_json::from_str(json)
             .map_err(|e| ProviderError::InternalError(format!("Failed to parse response: {}", e)))?;
 
-        let finish_reason = match resp.finish_reason.as_deref() {
-            Some("COMPLETE") => FinishReason::Stop,
-            Some("MAX_TOKENS") => FinishReason::Length,
-            Some("ERROR") => FinishReason::Error,
-            _ => FinishReason::Stop,
-        };
+        let finish_reason = Self::finish_reason(resp.finish_reason.as_deref());
 
         // Extract token usage from metadata
         let usage = if let Some(meta) = resp.meta {
@@ -168,12 +276,18 @@ impl CohereProvider {
                     prompt_tokens,
                     completion_tokens,
                     total_tokens: prompt_tokens + completion_tokens,
+                    reasoning_tokens: 0,
+                    cached_tokens: 0,
+                    cache_write_tokens: 0,
                 }
             } else {
                 TokenUsage {
                     prompt_tokens: 0,
                     completion_tokens: 0,
                     total_tokens: 0,
+                    reasoning_tokens: 0,
+                    cached_tokens: 0,
+                    cache_write_tokens: 0,
                 }
             }
         } else {
@@ -181,6 +295,9 @@ impl CohereProvider {
                 prompt_tokens: 0,
                 completion_tokens: 0,
                 total_tokens: 0,
+                reasoning_tokens: 0,
+                cached_tokens: 0,
+                cache_write_tokens: 0,
             }
         };
 
@@ -191,6 +308,12 @@ impl CohereProvider {
             usage,
             finish_reason,
             created_at: chrono::Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         })
     }
 }
@@ -198,6 +321,8 @@ impl CohereProvider {
 #[async_trait]
 impl Provider for CohereProvider {
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        COHERE_SAMPLING.check(self.name(), &request)?;
+
         let url = format!("{}/chat", self.base_url);
 
         let body = self.build_request_body(&request, false);
@@ -226,10 +351,14 @@ impl Provider for CohereProvider {
         let text = response.text().await
             .map_err(|e| ProviderError::NetworkError(e))?;
 
-        self.parse_completion_response(&text)
+        let mut response = self.parse_completion_response(&text)?;
+        structured::check_response(&request, &mut response);
+        Ok(response)
     }
 
     async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+        COHERE_SAMPLING.check_stream(self.name(), &request)?;
+
         let url = format!("{}/chat", self.base_url);
 
         let body = self.build_request_body(&request, true);
@@ -255,35 +384,23 @@ impl Provider for CohereProvider {
             return Err(Self::parse_error_response(status, &text));
         }
 
-        let stream = response.bytes_stream();
-
-        let text_stream = stream.map(move |result| {
-            match result {
-                Ok(bytes) => {
-                    let text = String::from_utf8_lossy(&bytes);
-                    // Parse SSE format
-                    for line in text.lines() {
-                        if line.starts_with("data: ") {
-                            let json_str = line.strip_prefix("data: ").unwrap_or("");
-                            if json_str.trim().is_empty() {
-                                continue;
-                            }
-
-                            // Extract text from response
-                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(json_str) {
-                                if let Some(text) = json.get("text").and_then(|t| t.as_str()) {
-                                    return Ok(text.to_string());
-                                }
-                            }
-                        }
-                    }
-                    Ok(String::new())
-                }
-                Err(e) => Err(ProviderError::NetworkError(e)),
-            }
-        });
-
-        Ok(Box::pin(text_stream))
+        // The v1 chat API streams JSON lines unless the server honours the
+        // Accept header and switches to SSE
+        let is_sse = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+
+        if is_sse {
+            Ok(streaming::decode_stream(response.bytes_stream(), SseDecoder::default(), |event| {
+                Ok(Self::stream_chunk_events(&serde_json::from_str(&event.data)?))
+            }))
+        } else {
+            Ok(streaming::decode_stream(response.bytes_stream(), JsonLinesDecoder::default(), |chunk| {
+                Ok(Self::stream_chunk_events(&chunk))
+            }))
+        }
     }
 
     fn supported_models(&self) -> Vec<ModelInfo> {
@@ -327,9 +444,85 @@ impl Provider for CohereProvider {
         }
     }
 
-    fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
-        // Cohere uses roughly 4 chars per token
-        Ok((text.len() / 4).max(1))
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        Ok(Tokenizer::for_model(model).count_tokens(text))
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for CohereProvider {
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, ProviderError> {
+        embeddings::require_native_dimensions("cohere", &request)?;
+
+        let url = format!("{}/embed", self.base_url);
+        debug!("Sending embedding request to Cohere: {}", url);
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(&self.build_embed_body(&request))
+            .send()
+            .await
+            .map_err(|e| ProviderError::NetworkError(e))?;
+
+        let status = response.status().as_u16();
+
+        if !response.status().is_success() {
+            let text = response.text().await
+                .unwrap_or_else(|_| "Failed to read error response".to_string());
+            error!("Cohere API error ({}): {}", status, text);
+            return Err(Self::parse_error_response(status, &text));
+        }
+
+        let text = response.text().await
+            .map_err(|e| ProviderError::NetworkError(e))?;
+
+        #[derive(Deserialize)]
+        struct CohereEmbeddings {
+            embeddings: Embeddings,
+            #[serde(default)]
+            meta: Option<Meta>,
+        }
+
+        #[derive(Deserialize)]
+        struct Embeddings {
+            float: Vec<Vec<f32>>,
+        }
+
+        #[derive(Deserialize)]
+        struct Meta {
+            billed_units: Option<BilledUnits>,
+        }
+
+        #[derive(Deserialize)]
+        struct BilledUnits {
+            input_tokens: Option<usize>,
+        }
+
+        let resp: CohereEmbeddings = serde_json::from_str(&text)?;
+        let input_tokens = resp.meta
+            .and_then(|meta| meta.billed_units)
+            .and_then(|units| units.input_tokens);
+
+        Ok(EmbeddingResponse {
+            model: request.model.clone(),
+            embeddings: resp.embeddings.float,
+            usage: match input_tokens {
+                Some(tokens) => TokenUsage::new(tokens, 0),
+                None => embeddings::estimate_usage(&request),
+            },
+        })
+    }
+
+    fn embedding_models(&self) -> Vec<EmbeddingModel> {
+        vec![
+            EmbeddingModel::new("embed-english-v3.0", 1024, 512),
+            EmbeddingModel::new("embed-multilingual-v3.0", 1024, 512),
+            EmbeddingModel::new("embed-english-light-v3.0", 384, 512),
+            EmbeddingModel::new("embed-multilingual-light-v3.0", 384, 512),
+        ]
     }
 }
 
@@ -393,4 +586,23 @@ mod tests {
         assert!(body.get("message").is_some());
         assert_eq!(body.get("stream"), Some(&serde_json::json!(false)));
     }
+
+    #[test]
+    fn test_build_request_body_with_system_and_history() {
+        let provider = CohereProvider::new("test-key".to_string()).unwrap();
+        let request = CompletionRequest::new("command-r", "And Italy?")
+            .with_system("Answer with a city name.")
+            .with_message(crate::providers::ChatMessage::user("Capital of France?"))
+            .with_message(crate::providers::ChatMessage::assistant("Paris"));
+
+        let body = provider.build_request_body(&request, false);
+
+        assert_eq!(body["message"], "And Italy?");
+        assert_eq!(body["preamble"], "Answer with a city name.");
+        let history = body["chat_history"].as_array().unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0]["role"], "USER");
+        assert_eq!(history[1]["role"], "CHATBOT");
+        assert_eq!(history[1]["message"], "Paris");
+    }
 }
@@ -0,0 +1,483 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! API key resolution and rotation.
+//!
+//! A provider's key comes from one [`CredentialSource`], picked from its
+//! configuration in this order:
+//!
+//! 1. `api_key_cmd`: the trimmed output of a shell command, such as a
+//!    password manager or `az account get-access-token`
+//! 2. `api_key_file`: the trimmed contents of a file, such as a mounted
+//!    secret
+//! 3. `api_keys_env`: several environment variables, one key each
+//! 4. `api_key_env`: a single environment variable
+//!
+//! Keys are held in an [`ApiKey`], whose `Debug` output is redacted so that
+//! it never ends up in logs. When several keys are configured,
+//! [`ProviderFactory::create`](super::ProviderFactory::create) builds one
+//! provider per key and wraps them in a [`KeyRotatingProvider`], which
+//! spreads requests over the keys according to `key_rotation` and retries a
+//! rate limited request with the next key.
+//!
+//! Azure AD tokens (`azure_ad = true`) expire, so Azure OpenAI providers read
+//! their source again shortly before a token expires or when it is rejected.
+//!
+//! # Examples
+//!
+//! ```toml
+//! [providers.openai]
+//! api_keys_env = ["OPENAI_KEY_1", "OPENAI_KEY_2"]
+//! key_rotation = "on-rate-limit"
+//! base_url = "https://api.openai.com/v1"
+//! default_model = "gpt-4o"
+//! timeout_seconds = 30
+//! max_retries = 3
+//! ```
+
+use super::{CompletionRequest, CompletionResponse, ModelInfo, Provider, ProviderError, ResponseStream};
+use crate::config::models::{KeyRotation, ProviderConfig};
+use async_trait::async_trait;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::warn;
+
+/// A secret API key or token
+///
+/// Formatting with `{:?}` prints `ApiKey(***)` instead of the key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiKey(String);
+
+impl ApiKey {
+    /// Wraps a key
+    pub fn new(key: impl Into<String>) -> Self {
+        Self(key.into())
+    }
+
+    /// The key itself, for use in request headers
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ApiKey(***)")
+    }
+}
+
+impl From<String> for ApiKey {
+    fn from(key: String) -> Self {
+        Self(key)
+    }
+}
+
+/// Where an API key is read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CredentialSource {
+    /// An environment variable
+    Env(String),
+    /// A file holding only the key
+    File(PathBuf),
+    /// A shell command printing the key on stdout
+    Command(String),
+}
+
+impl CredentialSource {
+    /// The sources configured for a provider, one per key
+    ///
+    /// Empty when the provider has no key configured.
+    pub fn from_config(config: &ProviderConfig) -> Vec<Self> {
+        if let Some(ref cmd) = config.api_key_cmd {
+            vec![Self::Command(cmd.clone())]
+        } else if let Some(ref path) = config.api_key_file {
+            vec![Self::File(path.clone())]
+        } else if !config.api_keys_env.is_empty() {
+            config.api_keys_env.iter().cloned().map(Self::Env).collect()
+        } else if !config.api_key_env.is_empty() {
+            vec![Self::Env(config.api_key_env.clone())]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Reads the key
+    ///
+    /// # Errors
+    ///
+    /// - `ProviderError::InvalidApiKey` - The variable is unset or the key is empty
+    /// - `ProviderError::AuthenticationError` - The file cannot be read or the command fails
+    pub fn resolve(&self) -> Result<ApiKey, ProviderError> {
+        let key = match self {
+            Self::Env(name) => std::env::var(name).map_err(|_| ProviderError::InvalidApiKey)?,
+            Self::File(path) => std::fs::read_to_string(path).map_err(|e| {
+                ProviderError::AuthenticationError(format!("Failed to read API key file {}: {}", path.display(), e))
+            })?,
+            Self::Command(cmd) => run_command(cmd)?,
+        };
+
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(ProviderError::InvalidApiKey);
+        }
+        Ok(ApiKey::new(key))
+    }
+}
+
+impl fmt::Display for CredentialSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Env(name) => write!(f, "env:{}", name),
+            Self::File(path) => write!(f, "file:{}", path.display()),
+            Self::Command(cmd) => write!(f, "cmd:{}", cmd),
+        }
+    }
+}
+
+/// Runs `cmd` with the platform shell and returns its stdout
+///
+/// Neither stdout nor stderr is included in errors, as either may hold the
+/// secret.
+fn run_command(cmd: &str) -> Result<String, ProviderError> {
+    #[cfg(windows)]
+    let output = Command::new("cmd").args(["/C", cmd]).output();
+    #[cfg(not(windows))]
+    let output = Command::new("sh").args(["-c", cmd]).output();
+
+    let output = output
+        .map_err(|e| ProviderError::AuthenticationError(format!("Failed to run api_key_cmd: {}", e)))?;
+    if !output.status.success() {
+        return Err(ProviderError::AuthenticationError(format!("api_key_cmd failed with {}", output.status)));
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|_| ProviderError::AuthenticationError("api_key_cmd printed invalid UTF-8".to_string()))
+}
+
+/// Resolves every key configured for a provider
+///
+/// # Errors
+///
+/// `ProviderError::InvalidApiKey` when no key is configured, or any error
+/// from [`CredentialSource::resolve`].
+pub fn resolve_api_keys(config: &ProviderConfig) -> Result<Vec<ApiKey>, ProviderError> {
+    let sources = CredentialSource::from_config(config);
+    if sources.is_empty() {
+        return Err(ProviderError::InvalidApiKey);
+    }
+    sources.iter().map(CredentialSource::resolve).collect()
+}
+
+/// A provider that spreads requests over one inner provider per API key
+///
+/// With [`KeyRotation::RoundRobin`] each request starts at the next key;
+/// with [`KeyRotation::OnRateLimit`] requests stay on one key until it is
+/// rate limited. Either way, a request that is rate limited (see
+/// [`ProviderError::is_rate_limited`]) is retried with the following key,
+/// and the error is only returned once every key is rate limited.
+pub struct KeyRotatingProvider {
+    providers: Vec<Box<dyn Provider>>,
+    rotation: KeyRotation,
+    next: AtomicUsize,
+}
+
+impl KeyRotatingProvider {
+    /// Rotates over `providers`, which must not be empty
+    ///
+    /// # Errors
+    ///
+    /// `ProviderError::InvalidApiKey` when `providers` is empty.
+    pub fn new(providers: Vec<Box<dyn Provider>>, rotation: KeyRotation) -> Result<Self, ProviderError> {
+        if providers.is_empty() {
+            return Err(ProviderError::InvalidApiKey);
+        }
+        Ok(Self { providers, rotation, next: AtomicUsize::new(0) })
+    }
+
+    /// Index of the key the next request starts with
+    fn start(&self) -> usize {
+        match self.rotation {
+            KeyRotation::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed) % self.providers.len(),
+            KeyRotation::OnRateLimit => self.next.load(Ordering::Relaxed) % self.providers.len(),
+        }
+    }
+
+    /// Records that the key at `index` was rate limited
+    fn on_rate_limited(&self, index: usize) {
+        warn!("{} API key {} rate limited, rotating to the next key", self.name(), index + 1);
+        if self.rotation == KeyRotation::OnRateLimit {
+            // Only the first request to see the 429 moves the shared index
+            let _ = self.next.compare_exchange(
+                index,
+                (index + 1) % self.providers.len(),
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            );
+        }
+    }
+
+    /// The providers in the order a request starting now tries them
+    fn rotation_order(&self) -> impl Iterator<Item = (usize, &dyn Provider)> {
+        let start = self.start();
+        let len = self.providers.len();
+        (0..len).map(move |i| {
+            let index = (start + i) % len;
+            (index, self.providers[index].as_ref())
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for KeyRotatingProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        let mut last_error = None;
+        for (index, provider) in self.rotation_order() {
+            match provider.complete(request.clone()).await {
+                Err(e) if e.is_rate_limited() => {
+                    self.on_rate_limited(index);
+                    last_error = Some(e);
+                }
+                result => return result,
+            }
+        }
+        Err(last_error.unwrap_or(ProviderError::InvalidApiKey))
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+        let mut last_error = None;
+        for (index, provider) in self.rotation_order() {
+            match provider.stream(request.clone()).await {
+                Err(e) if e.is_rate_limited() => {
+                    self.on_rate_limited(index);
+                    last_error = Some(e);
+                }
+                result => return result,
+            }
+        }
+        Err(last_error.unwrap_or(ProviderError::InvalidApiKey))
+    }
+
+    fn supported_models(&self) -> Vec<ModelInfo> {
+        self.providers[0].supported_models()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        self.providers[0].list_models().await
+    }
+
+    fn max_context_length(&self, model: &str) -> Option<usize> {
+        self.providers[0].max_context_length(model)
+    }
+
+    fn name(&self) -> &str {
+        self.providers[0].name()
+    }
+
+    async fn validate_config(&self) -> Result<(), ProviderError> {
+        for provider in &self.providers {
+            provider.validate_config().await?;
+        }
+        Ok(())
+    }
+
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        self.providers[0].estimate_tokens(text, model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::models::TransportConfig;
+    use crate::providers::{FinishReason, TokenUsage};
+    use std::collections::HashMap;
+    use std::io::Write;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn config() -> ProviderConfig {
+        ProviderConfig {
+            api_key_env: String::new(),
+            api_key_file: None,
+            api_key_cmd: None,
+            api_keys_env: Vec::new(),
+            key_rotation: KeyRotation::default(),
+            azure_ad: false,
+            base_url: "https://api.example.com".to_string(),
+            default_model: "test-model".to_string(),
+            timeout_seconds: 30,
+            max_retries: 3,
+            rate_limit_rpm: None,
+            rate_limit_tpm: None,
+            fallback: Vec::new(),
+            circuit_breaker: None,
+            kind: None,
+            headers: HashMap::new(),
+            models: Vec::new(),
+            context_length: None,
+            script: None,
+            transport: TransportConfig::default(),
+        }
+    }
+
+    /// Records which key served each request; `limited` keys answer 429
+    struct KeyProvider {
+        key: usize,
+        limited: bool,
+        calls: Arc<std::sync::Mutex<Vec<usize>>>,
+    }
+
+    #[async_trait]
+    impl Provider for KeyProvider {
+        async fn complete(&self, _request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+            self.calls.lock().unwrap().push(self.key);
+            if self.limited {
+                return Err(ProviderError::RateLimitExceeded { retry_after: Some(Duration::from_secs(1)) });
+            }
+            Ok(CompletionResponse {
+                id: format!("key-{}", self.key),
+                model: "test-model".to_string(),
+                content: "ok".to_string(),
+                usage: TokenUsage::new(1, 1),
+                finish_reason: FinishReason::Stop,
+                created_at: chrono::Utc::now(),
+                tool_calls: Vec::new(),
+                schema_violations: Vec::new(),
+                logprobs: None,
+                prompt_logprobs: None,
+                choices: Vec::new(),
+                metadata: HashMap::new(),
+            })
+        }
+
+        async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+            let response = self.complete(request).await?;
+            Ok(crate::providers::streaming::from_response(&response))
+        }
+
+        fn supported_models(&self) -> Vec<ModelInfo> {
+            Vec::new()
+        }
+
+        fn max_context_length(&self, _model: &str) -> Option<usize> {
+            None
+        }
+
+        fn name(&self) -> &str {
+            "keyed"
+        }
+
+        async fn validate_config(&self) -> Result<(), ProviderError> {
+            Ok(())
+        }
+
+        fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
+            Ok(text.len() / 4)
+        }
+    }
+
+    fn rotating(limited: &[bool], rotation: KeyRotation) -> (KeyRotatingProvider, Arc<std::sync::Mutex<Vec<usize>>>) {
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let providers = limited
+            .iter()
+            .enumerate()
+            .map(|(key, &limited)| {
+                Box::new(KeyProvider { key, limited, calls: Arc::clone(&calls) }) as Box<dyn Provider>
+            })
+            .collect();
+        (KeyRotatingProvider::new(providers, rotation).unwrap(), calls)
+    }
+
+    #[test]
+    fn test_api_key_debug_is_redacted() {
+        let key = ApiKey::new("sk-secret");
+        assert_eq!(format!("{:?}", key), "ApiKey(***)");
+        assert_eq!(key.expose(), "sk-secret");
+    }
+
+    #[test]
+    fn test_source_precedence() {
+        let mut config = config();
+        assert!(CredentialSource::from_config(&config).is_empty());
+        assert!(matches!(resolve_api_keys(&config), Err(ProviderError::InvalidApiKey)));
+
+        config.api_key_env = "KEY".to_string();
+        assert_eq!(CredentialSource::from_config(&config), vec![CredentialSource::Env("KEY".to_string())]);
+
+        config.api_keys_env = vec!["KEY_1".to_string(), "KEY_2".to_string()];
+        assert_eq!(CredentialSource::from_config(&config).len(), 2);
+
+        config.api_key_file = Some(PathBuf::from("key.txt"));
+        assert_eq!(CredentialSource::from_config(&config), vec![CredentialSource::File(PathBuf::from("key.txt"))]);
+
+        config.api_key_cmd = Some("echo key".to_string());
+        assert_eq!(CredentialSource::from_config(&config), vec![CredentialSource::Command("echo key".to_string())]);
+    }
+
+    #[test]
+    fn test_resolve_env() {
+        std::env::set_var("CREDENTIALS_TEST_KEY", "  env-key\n");
+        assert_eq!(CredentialSource::Env("CREDENTIALS_TEST_KEY".to_string()).resolve().unwrap().expose(), "env-key");
+        assert!(matches!(
+            CredentialSource::Env("CREDENTIALS_TEST_UNSET_KEY".to_string()).resolve(),
+            Err(ProviderError::InvalidApiKey)
+        ));
+    }
+
+    #[test]
+    fn test_resolve_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "file-key").unwrap();
+        let source = CredentialSource::File(file.path().to_path_buf());
+        assert_eq!(source.resolve().unwrap().expose(), "file-key");
+
+        let missing = CredentialSource::File(PathBuf::from("/nonexistent/api-key"));
+        assert!(matches!(missing.resolve(), Err(ProviderError::AuthenticationError(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_command() {
+        let source = CredentialSource::Command("echo cmd-key".to_string());
+        assert_eq!(source.resolve().unwrap().expose(), "cmd-key");
+
+        // The output of a failing command is never part of the error
+        let failing = CredentialSource::Command("echo leaked-key; exit 3".to_string());
+        match failing.resolve() {
+            Err(ProviderError::AuthenticationError(msg)) => assert!(!msg.contains("leaked-key")),
+            other => panic!("Expected AuthenticationError, got {:?}", other),
+        }
+
+        assert!(matches!(CredentialSource::Command("true".to_string()).resolve(), Err(ProviderError::InvalidApiKey)));
+    }
+
+    #[tokio::test]
+    async fn test_round_robin() {
+        let (provider, calls) = rotating(&[false, false, false], KeyRotation::RoundRobin);
+        for _ in 0..4 {
+            provider.complete(CompletionRequest::new("test-model", "Hi")).await.unwrap();
+        }
+        assert_eq!(*calls.lock().unwrap(), vec![0, 1, 2, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_on_rate_limit_rotates_and_sticks() {
+        let (provider, calls) = rotating(&[true, false], KeyRotation::OnRateLimit);
+        let response = provider.complete(CompletionRequest::new("test-model", "Hi")).await.unwrap();
+        assert_eq!(response.id, "key-1");
+        provider.complete(CompletionRequest::new("test-model", "Hi")).await.unwrap();
+        assert_eq!(*calls.lock().unwrap(), vec![0, 1, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_all_keys_rate_limited() {
+        let (provider, calls) = rotating(&[true, true], KeyRotation::RoundRobin);
+        let result = provider.stream(CompletionRequest::new("test-model", "Hi")).await;
+        assert!(matches!(result, Err(ProviderError::RateLimitExceeded { .. })));
+        assert_eq!(calls.lock().unwrap().len(), 2);
+    }
+}
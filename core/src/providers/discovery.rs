@@ -0,0 +1,418 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Live model discovery.
+//!
+//! [`Provider::list_models`] asks a provider's API which models it serves
+//! right now, instead of relying on the hard-coded
+//! [`supported_models`](Provider::supported_models) lists. [`ModelCatalog`]
+//! caches those answers on disk with a TTL and merges them with the static
+//! capability and pricing data from [`get_model_metadata`].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use llm_test_bench_core::providers::{ModelCatalog, OpenAIProvider};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let provider = OpenAIProvider::new("key".to_string())?;
+//! let catalog = ModelCatalog::new().with_ttl_hours(6);
+//!
+//! for model in catalog.list_models(&provider).await? {
+//!     match model.cost_per_1k_input {
+//!         Some(cost) => println!("{}: ${}/1k input tokens", model.info.id, cost),
+//!         None => println!("{}: no pricing data", model.info.id),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::models::get_model_metadata;
+use super::{ModelInfo, Provider, ProviderError};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Default time a cached model list stays fresh, in hours.
+pub const DEFAULT_TTL_HOURS: i64 = 24;
+
+/// A model reported by a provider's API, merged with static metadata.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DiscoveredModel {
+    /// Model identity and capabilities.
+    ///
+    /// `max_tokens` is 0 when neither the API nor the static data know the
+    /// context window.
+    #[serde(flatten)]
+    pub info: ModelInfo,
+
+    /// Name of the provider that serves the model.
+    pub provider: String,
+
+    /// Maximum completion tokens, from static metadata.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<usize>,
+
+    /// Input price in USD per 1,000 tokens, from static metadata.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_per_1k_input: Option<f64>,
+
+    /// Output price in USD per 1,000 tokens, from static metadata.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_per_1k_output: Option<f64>,
+}
+
+impl DiscoveredModel {
+    /// Merges a live model entry with what is known about it statically.
+    ///
+    /// Values reported by the API win; gaps are filled from
+    /// [`get_model_metadata`] and then from the provider's
+    /// [`max_context_length`](Provider::max_context_length).
+    pub fn merge(provider: &dyn Provider, mut info: ModelInfo) -> Self {
+        let metadata = get_model_metadata(&info.id);
+
+        if info.max_tokens == 0 {
+            info.max_tokens = metadata
+                .as_ref()
+                .map(|m| m.capabilities.max_context_tokens)
+                .or_else(|| provider.max_context_length(&info.id))
+                .unwrap_or(0);
+        }
+        if let Some(ref metadata) = metadata {
+            info.supports_function_calling |= metadata.capabilities.supports_function_calling;
+            info.supports_vision |= metadata.capabilities.supports_vision;
+            if info.name == info.id {
+                info.name = metadata.display_name.to_string();
+            }
+        }
+
+        Self {
+            info,
+            provider: provider.name().to_string(),
+            max_output_tokens: metadata.as_ref().map(|m| m.capabilities.max_output_tokens),
+            cost_per_1k_input: metadata.as_ref().map(|m| m.cost_per_1k_input),
+            cost_per_1k_output: metadata.as_ref().map(|m| m.cost_per_1k_output),
+        }
+    }
+}
+
+/// A model list as stored on disk.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedModels {
+    fetched_at: DateTime<Utc>,
+    models: Vec<DiscoveredModel>,
+}
+
+impl CachedModels {
+    fn is_fresh(&self, ttl_hours: i64) -> bool {
+        Utc::now().signed_duration_since(self.fetched_at) < Duration::hours(ttl_hours)
+    }
+}
+
+/// Discovers models through providers and caches the results on disk.
+///
+/// Each provider's list is stored as `<cache_dir>/<provider name>.json`. A
+/// fresh cache entry is returned without contacting the provider; when the
+/// provider cannot be reached, a stale entry is used rather than failing.
+#[derive(Debug, Clone)]
+pub struct ModelCatalog {
+    cache_dir: Option<PathBuf>,
+    ttl_hours: i64,
+}
+
+impl ModelCatalog {
+    /// Creates a catalog cached under `~/.cache/llm-test-bench/models`.
+    pub fn new() -> Self {
+        Self {
+            cache_dir: dirs::cache_dir().map(|d| d.join("llm-test-bench").join("models")),
+            ttl_hours: DEFAULT_TTL_HOURS,
+        }
+    }
+
+    /// Sets the cache directory.
+    pub fn with_cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(cache_dir.into());
+        self
+    }
+
+    /// Sets how long a cached list stays fresh, in hours.
+    pub fn with_ttl_hours(mut self, hours: i64) -> Self {
+        self.ttl_hours = hours;
+        self
+    }
+
+    /// Always queries the provider and never touches the disk.
+    pub fn without_cache(mut self) -> Self {
+        self.cache_dir = None;
+        self
+    }
+
+    /// Returns the models served by `provider`, from cache when fresh.
+    ///
+    /// # Errors
+    ///
+    /// Returns the provider's error if the list cannot be fetched and no
+    /// cached copy exists.
+    pub async fn list_models(&self, provider: &dyn Provider) -> Result<Vec<DiscoveredModel>, ProviderError> {
+        let cached = self.read_cache(provider.name());
+        if let Some(ref cached) = cached {
+            if cached.is_fresh(self.ttl_hours) {
+                debug!("Using cached model list for {}", provider.name());
+                return Ok(cached.models.clone());
+            }
+        }
+
+        match self.fetch(provider).await {
+            Ok(models) => Ok(models),
+            Err(e) => match cached {
+                Some(cached) => {
+                    warn!("Could not refresh models for {}, using stale cache: {}", provider.name(), e);
+                    Ok(cached.models)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    /// Queries `provider` and replaces its cache entry, ignoring the TTL.
+    ///
+    /// # Errors
+    ///
+    /// Returns the provider's error if the list cannot be fetched.
+    pub async fn refresh(&self, provider: &dyn Provider) -> Result<Vec<DiscoveredModel>, ProviderError> {
+        self.fetch(provider).await
+    }
+
+    async fn fetch(&self, provider: &dyn Provider) -> Result<Vec<DiscoveredModel>, ProviderError> {
+        let models: Vec<DiscoveredModel> = provider
+            .list_models()
+            .await?
+            .into_iter()
+            .map(|info| DiscoveredModel::merge(provider, info))
+            .collect();
+
+        if let Some(path) = self.cache_path(provider.name()) {
+            let cached = CachedModels { fetched_at: Utc::now(), models: models.clone() };
+            if let Err(e) = write_cache(&path, &cached) {
+                warn!("Failed to cache model list at {}: {}", path.display(), e);
+            }
+        }
+
+        Ok(models)
+    }
+
+    fn cache_path(&self, provider_name: &str) -> Option<PathBuf> {
+        let file_name: String = provider_name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        self.cache_dir.as_ref().map(|dir| dir.join(format!("{}.json", file_name)))
+    }
+
+    fn read_cache(&self, provider_name: &str) -> Option<CachedModels> {
+        let contents = std::fs::read_to_string(self.cache_path(provider_name)?).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+}
+
+impl Default for ModelCatalog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn write_cache(path: &Path, cached: &CachedModels) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_vec_pretty(cached)?)
+}
+
+/// Sends a list-models request and returns the JSON body.
+pub(crate) async fn fetch_json(request: reqwest::RequestBuilder) -> Result<serde_json::Value, ProviderError> {
+    let response = request.send().await?;
+    let status = response.status();
+    let text = response.text().await?;
+
+    if !status.is_success() {
+        return Err(match status.as_u16() {
+            401 | 403 => ProviderError::AuthenticationError(text),
+            429 => ProviderError::RateLimitExceeded { retry_after: None },
+            status => ProviderError::ApiError { status, message: text },
+        });
+    }
+
+    Ok(serde_json::from_str(&text)?)
+}
+
+/// Parses an OpenAI-style model list.
+///
+/// Accepts `{"data": [...]}` as well as a bare array (Together). Context
+/// windows are read from the vendor-specific `context_window` (Groq),
+/// `context_length` (Together, OpenRouter) or `max_context_length` (Mistral)
+/// fields. Together's non-text models (images, embeddings, ...) are skipped.
+pub(crate) fn parse_openai_models(json: &serde_json::Value) -> Vec<ModelInfo> {
+    let entries = json
+        .as_array()
+        .or_else(|| json["data"].as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    entries
+        .iter()
+        .filter(|entry| {
+            entry["type"]
+                .as_str()
+                .map_or(true, |kind| !matches!(kind, "image" | "embedding" | "moderation" | "rerank" | "audio"))
+        })
+        .filter_map(|entry| {
+            let id = entry["id"].as_str()?;
+            let name = entry["display_name"].as_str().or_else(|| entry["name"].as_str()).unwrap_or(id);
+            let context = ["context_window", "context_length", "max_context_length"]
+                .iter()
+                .find_map(|field| entry[*field].as_u64())
+                .unwrap_or(0) as usize;
+            let function_calling = entry["capabilities"]["function_calling"].as_bool().unwrap_or(false);
+            Some(ModelInfo::new(id, name, context, true, function_calling))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{CompletionRequest, CompletionResponse, ResponseStream};
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::TempDir;
+
+    struct MockProvider {
+        calls: AtomicUsize,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        async fn complete(&self, _request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+            unimplemented!()
+        }
+
+        async fn stream(&self, _request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+            unimplemented!()
+        }
+
+        async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.fail {
+                return Err(ProviderError::ApiError { status: 503, message: "down".to_string() });
+            }
+            Ok(vec![
+                ModelInfo::new("gpt-4o", "gpt-4o", 0, true, false),
+                ModelInfo::new("my-finetune", "my-finetune", 0, true, false),
+            ])
+        }
+
+        fn supported_models(&self) -> Vec<ModelInfo> {
+            Vec::new()
+        }
+
+        fn max_context_length(&self, _model: &str) -> Option<usize> {
+            Some(4096)
+        }
+
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        async fn validate_config(&self) -> Result<(), ProviderError> {
+            Ok(())
+        }
+
+        fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
+            Ok(text.len() / 4)
+        }
+    }
+
+    fn mock(fail: bool) -> MockProvider {
+        MockProvider { calls: AtomicUsize::new(0), fail }
+    }
+
+    #[test]
+    fn test_parse_openai_models() {
+        let models = parse_openai_models(&json!({
+            "object": "list",
+            "data": [
+                { "id": "llama3-70b-8192", "object": "model", "context_window": 8192 },
+                { "id": "mistral-large-latest", "max_context_length": 131072, "capabilities": { "function_calling": true } }
+            ]
+        }));
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].max_tokens, 8192);
+        assert!(!models[0].supports_function_calling);
+        assert_eq!(models[1].max_tokens, 131072);
+        assert!(models[1].supports_function_calling);
+
+        // Together returns a bare array with model types
+        let models = parse_openai_models(&json!([
+            { "id": "meta-llama/Llama-3-70b-chat-hf", "type": "chat", "display_name": "Llama 3 70B", "context_length": 8192 },
+            { "id": "BAAI/bge-large-en-v1.5", "type": "embedding" }
+        ]));
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].name, "Llama 3 70B");
+    }
+
+    #[tokio::test]
+    async fn test_merge_with_static_metadata() {
+        let provider = mock(false);
+        let catalog = ModelCatalog::new().without_cache();
+        let models = catalog.list_models(&provider).await.unwrap();
+
+        let gpt = &models[0];
+        assert_eq!(gpt.info.name, "GPT-4o");
+        assert_eq!(gpt.info.max_tokens, 128_000);
+        assert!(gpt.info.supports_function_calling);
+        assert!(gpt.info.supports_vision);
+        assert_eq!(gpt.cost_per_1k_input, Some(0.0025));
+
+        // Unknown models fall back to the provider's static context length
+        let finetune = &models[1];
+        assert_eq!(finetune.info.max_tokens, 4096);
+        assert_eq!(finetune.cost_per_1k_input, None);
+        assert_eq!(finetune.provider, "mock");
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache_ttl() {
+        let dir = TempDir::new().unwrap();
+        let provider = mock(false);
+
+        let catalog = ModelCatalog::new().with_cache_dir(dir.path());
+        catalog.list_models(&provider).await.unwrap();
+        catalog.list_models(&provider).await.unwrap();
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+        assert!(dir.path().join("mock.json").exists());
+
+        // An expired entry is refetched
+        let expired = ModelCatalog::new().with_cache_dir(dir.path()).with_ttl_hours(0);
+        expired.list_models(&provider).await.unwrap();
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stale_cache_used_when_provider_fails() {
+        let dir = TempDir::new().unwrap();
+        let catalog = ModelCatalog::new().with_cache_dir(dir.path()).with_ttl_hours(0);
+
+        assert!(catalog.list_models(&mock(true)).await.is_err());
+
+        catalog.list_models(&mock(false)).await.unwrap();
+        let models = catalog.list_models(&mock(true)).await.unwrap();
+        assert_eq!(models.len(), 2);
+    }
+}
@@ -0,0 +1,345 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Text embeddings
+//!
+//! Providers with an embeddings endpoint implement [`EmbeddingProvider`].
+//! A request embeds a batch of inputs in one call; [`EmbeddingProvider::embed_all`]
+//! splits batches larger than the provider accepts.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use llm_test_bench_core::providers::{EmbeddingProvider, EmbeddingRequest, OpenAIProvider};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let provider = OpenAIProvider::new("your-api-key".to_string())?;
+//!
+//! let request = EmbeddingRequest::new("text-embedding-3-small", ["The cat sat", "A cat was sitting"]);
+//! let response = provider.embed(request).await?;
+//! let similarity = response.similarity(0, 1);
+//! println!("{:?} ({} tokens)", similarity, response.usage.prompt_tokens);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{Provider, ProviderError, TokenUsage};
+use crate::tokenizer::Tokenizer;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// What the embedded text will be used for
+///
+/// Only Cohere's v3 models take this into account; other providers ignore it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingInputType {
+    /// Queries matched against stored documents
+    SearchQuery,
+    /// Documents stored for retrieval
+    SearchDocument,
+    /// Inputs to a text classifier
+    Classification,
+    /// Inputs to a clustering algorithm
+    Clustering,
+}
+
+impl EmbeddingInputType {
+    /// The value of Cohere's `input_type` parameter
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmbeddingInputType::SearchQuery => "search_query",
+            EmbeddingInputType::SearchDocument => "search_document",
+            EmbeddingInputType::Classification => "classification",
+            EmbeddingInputType::Clustering => "clustering",
+        }
+    }
+}
+
+/// A batch of texts to embed with one model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingRequest {
+    /// Embedding model
+    pub model: String,
+
+    /// Texts to embed, in order
+    pub inputs: Vec<String>,
+
+    /// Requested vector size, for models that can shorten their embeddings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<usize>,
+
+    /// Intended use of the embeddings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_type: Option<EmbeddingInputType>,
+}
+
+impl EmbeddingRequest {
+    /// Creates a request embedding `inputs` with `model`
+    pub fn new<I, S>(model: impl Into<String>, inputs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            model: model.into(),
+            inputs: inputs.into_iter().map(Into::into).collect(),
+            dimensions: None,
+            input_type: None,
+        }
+    }
+
+    /// Requests vectors of `dimensions` components
+    pub fn with_dimensions(mut self, dimensions: usize) -> Self {
+        self.dimensions = Some(dimensions);
+        self
+    }
+
+    /// Sets the intended use of the embeddings
+    pub fn with_input_type(mut self, input_type: EmbeddingInputType) -> Self {
+        self.input_type = Some(input_type);
+        self
+    }
+}
+
+/// Embeddings of a batch, in the order of the request's inputs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingResponse {
+    /// Model that produced the embeddings
+    pub model: String,
+
+    /// One vector per input
+    pub embeddings: Vec<Vec<f32>>,
+
+    /// Token usage; `completion_tokens` is always zero
+    pub usage: TokenUsage,
+}
+
+impl EmbeddingResponse {
+    /// Size of the returned vectors
+    pub fn dimensions(&self) -> Option<usize> {
+        self.embeddings.first().map(Vec::len)
+    }
+
+    /// Cosine similarity between the embeddings of inputs `a` and `b`
+    pub fn similarity(&self, a: usize, b: usize) -> Option<f32> {
+        Some(cosine_similarity(self.embeddings.get(a)?, self.embeddings.get(b)?))
+    }
+}
+
+/// An embedding model and the size of its vectors
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingModel {
+    /// Model identifier
+    pub id: String,
+
+    /// Default vector size
+    pub dimensions: usize,
+
+    /// Maximum input length in tokens
+    pub max_input_tokens: usize,
+}
+
+impl EmbeddingModel {
+    /// Creates a model entry
+    pub fn new(id: impl Into<String>, dimensions: usize, max_input_tokens: usize) -> Self {
+        Self {
+            id: id.into(),
+            dimensions,
+            max_input_tokens,
+        }
+    }
+}
+
+/// A provider that turns text into embedding vectors
+#[async_trait]
+pub trait EmbeddingProvider: Provider {
+    /// Embeds a batch of inputs in a single API call
+    ///
+    /// Batches larger than [`max_batch_size`](Self::max_batch_size) may be
+    /// rejected by the provider; use [`embed_all`](Self::embed_all) for
+    /// those.
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, ProviderError>;
+
+    /// Embedding models known to the provider
+    fn embedding_models(&self) -> Vec<EmbeddingModel> {
+        Vec::new()
+    }
+
+    /// Default vector size of `model`, if known
+    fn embedding_dimensions(&self, model: &str) -> Option<usize> {
+        self.embedding_models().into_iter().find(|m| m.id == model).map(|m| m.dimensions)
+    }
+
+    /// Largest number of inputs accepted in one call
+    fn max_batch_size(&self) -> usize {
+        96
+    }
+
+    /// Embeds any number of inputs, splitting them into batches of at most
+    /// [`max_batch_size`](Self::max_batch_size)
+    ///
+    /// Batches are sent one after another and their usage is summed.
+    async fn embed_all(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, ProviderError> {
+        let batch_size = self.max_batch_size().max(1);
+        if request.inputs.len() <= batch_size {
+            return self.embed(request).await;
+        }
+
+        let mut combined = EmbeddingResponse {
+            model: request.model.clone(),
+            embeddings: Vec::with_capacity(request.inputs.len()),
+            usage: TokenUsage::new(0, 0),
+        };
+        for chunk in request.inputs.chunks(batch_size) {
+            let batch = EmbeddingRequest {
+                inputs: chunk.to_vec(),
+                ..request.clone()
+            };
+            let response = self.embed(batch).await?;
+            combined.model = response.model;
+            combined.embeddings.extend(response.embeddings);
+            combined.usage = TokenUsage::new(combined.usage.prompt_tokens + response.usage.prompt_tokens, 0);
+        }
+
+        Ok(combined)
+    }
+}
+
+/// Cosine similarity of two vectors, or 0 if either is zero or their sizes differ
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+
+    dot / (norm_a * norm_b)
+}
+
+/// Request body for OpenAI-style `/embeddings` endpoints
+pub(crate) fn openai_embedding_body(request: &EmbeddingRequest) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "model": request.model,
+        "input": request.inputs,
+        "encoding_format": "float",
+    });
+
+    if let Some(dimensions) = request.dimensions {
+        body["dimensions"] = serde_json::json!(dimensions);
+    }
+
+    body
+}
+
+/// Parses an OpenAI-style `/embeddings` response
+///
+/// Usage is estimated with the model's tokenizer when the server omits it.
+pub(crate) fn parse_openai_embeddings(
+    text: &str,
+    request: &EmbeddingRequest,
+) -> Result<EmbeddingResponse, ProviderError> {
+    #[derive(Deserialize)]
+    struct OpenAIEmbeddings {
+        data: Vec<Embedding>,
+        model: Option<String>,
+        usage: Option<Usage>,
+    }
+
+    #[derive(Deserialize)]
+    struct Embedding {
+        embedding: Vec<f32>,
+        #[serde(default)]
+        index: usize,
+    }
+
+    #[derive(Deserialize)]
+    struct Usage {
+        prompt_tokens: usize,
+    }
+
+    let mut resp: OpenAIEmbeddings = serde_json::from_str(text)?;
+    resp.data.sort_by_key(|embedding| embedding.index);
+
+    Ok(EmbeddingResponse {
+        model: resp.model.unwrap_or_else(|| request.model.clone()),
+        embeddings: resp.data.into_iter().map(|embedding| embedding.embedding).collect(),
+        usage: match resp.usage {
+            Some(usage) => TokenUsage::new(usage.prompt_tokens, 0),
+            None => estimate_usage(request),
+        },
+    })
+}
+
+/// Usage estimated with the model's tokenizer, for APIs that do not report it
+pub(crate) fn estimate_usage(request: &EmbeddingRequest) -> TokenUsage {
+    let tokenizer = Tokenizer::for_model(&request.model);
+    TokenUsage::new(request.inputs.iter().map(|input| tokenizer.count_tokens(input)).sum(), 0)
+}
+
+/// Rejects a `dimensions` setting for APIs that always return full-size vectors
+pub(crate) fn require_native_dimensions(provider: &str, request: &EmbeddingRequest) -> Result<(), ProviderError> {
+    match request.dimensions {
+        Some(dimensions) => Err(ProviderError::InvalidRequest(format!(
+            "{} cannot shorten embeddings to {} dimensions",
+            provider, dimensions
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert!((cosine_similarity(&[1.0, 1.0], &[-1.0, -1.0]) + 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_openai_body_and_parse() {
+        let request = EmbeddingRequest::new("text-embedding-3-small", ["b", "a"]).with_dimensions(2);
+        let body = openai_embedding_body(&request);
+        assert_eq!(body["input"], serde_json::json!(["b", "a"]));
+        assert_eq!(body["dimensions"], 2);
+
+        // Results may come back out of order
+        let text = r#"{
+            "data": [
+                {"object": "embedding", "index": 1, "embedding": [0.0, 1.0]},
+                {"object": "embedding", "index": 0, "embedding": [1.0, 0.0]}
+            ],
+            "model": "text-embedding-3-small",
+            "usage": {"prompt_tokens": 2, "total_tokens": 2}
+        }"#;
+        let response = parse_openai_embeddings(text, &request).unwrap();
+        assert_eq!(response.embeddings, vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+        assert_eq!(response.dimensions(), Some(2));
+        assert_eq!(response.usage.prompt_tokens, 2);
+        assert_eq!(response.similarity(0, 1), Some(0.0));
+        assert_eq!(response.similarity(0, 5), None);
+    }
+
+    #[test]
+    fn test_missing_usage_is_estimated() {
+        let request = EmbeddingRequest::new("bge-large", ["hello world"]);
+        let response = parse_openai_embeddings(r#"{"data": [{"embedding": [0.5]}]}"#, &request).unwrap();
+        assert_eq!(response.model, "bge-large");
+        assert!(response.usage.prompt_tokens > 0);
+        assert!(require_native_dimensions("together", &request).is_ok());
+        assert!(require_native_dimensions("together", &request.with_dimensions(8)).is_err());
+    }
+}
@@ -226,6 +226,25 @@ impl ProviderError {
             ProviderError::AuthenticationError(_) | ProviderError::InvalidApiKey
         )
     }
+
+    /// Returns `true` if the provider rejected the request with HTTP 429.
+    ///
+    /// Covers providers that report 429 as a plain [`ApiError`](Self::ApiError).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use llm_test_bench_core::providers::error::ProviderError;
+    ///
+    /// let error = ProviderError::ApiError { status: 429, message: "Too many requests".to_string() };
+    /// assert!(error.is_rate_limited());
+    /// ```
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(
+            self,
+            ProviderError::RateLimitExceeded { .. } | ProviderError::ApiError { status: 429, .. }
+        )
+    }
 }
 
 #[cfg(test)]
@@ -10,29 +10,72 @@
 //! from configuration. It handles provider registration and instantiation.
 
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
 
 use super::anthropic::AnthropicProvider;
 use super::azure_openai::AzureOpenAIProvider;
+use super::batch::BatchProvider;
+use super::credentials::{resolve_api_keys, ApiKey, CredentialSource, KeyRotatingProvider};
 use super::bedrock::BedrockProvider;
 use super::cohere::CohereProvider;
+use super::embeddings::EmbeddingProvider;
 use super::error::ProviderError;
+use super::fallback::FallbackProvider;
 use super::google::GoogleProvider;
 use super::groq::GroqProvider;
 use super::huggingface::HuggingFaceProvider;
 use super::mistral::MistralProvider;
+use super::mock::{MockProvider, MockScript};
 use super::ollama::OllamaProvider;
 use super::openai::OpenAIProvider;
+use super::openai_compatible::{expand_header_template, OpenAICompatibleProvider};
 use super::perplexity::PerplexityProvider;
+use super::rate_limit::{RateLimitedProvider, RateLimiter};
 use super::replicate::ReplicateProvider;
 use super::together::TogetherProvider;
+use super::transport::http_client;
 use super::traits::Provider;
-use crate::config::models::ProviderConfig;
+use crate::config::models::{KeyRotation, ProviderConfig, TransportConfig};
+
+/// Builds a provider from its name in the configuration and its settings
+///
+/// The name is the key of the provider's `providers` section, which differs
+/// from its kind when `kind` is set.
+pub type ProviderConstructor =
+    Arc<dyn Fn(&str, &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> + Send + Sync>;
+
+/// Builds an embedding provider, like a [`ProviderConstructor`]
+pub type EmbeddingConstructor =
+    Arc<dyn Fn(&str, &ProviderConfig) -> Result<Box<dyn EmbeddingProvider>, ProviderError> + Send + Sync>;
+
+/// Builds a batch provider, like a [`ProviderConstructor`]
+pub type BatchConstructor =
+    Arc<dyn Fn(&str, &ProviderConfig) -> Result<Box<dyn BatchProvider>, ProviderError> + Send + Sync>;
+
+/// Constructors by lowercase provider kind
+#[derive(Clone, Default)]
+struct Registry {
+    completion: HashMap<String, ProviderConstructor>,
+    embedding: HashMap<String, EmbeddingConstructor>,
+    batch: HashMap<String, BatchConstructor>,
+}
+
+impl Registry {
+    fn extend(&mut self, other: &Registry) {
+        self.completion.extend(other.completion.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self.embedding.extend(other.embedding.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self.batch.extend(other.batch.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+}
 
 /// A factory for creating provider instances.
 ///
 /// The factory maintains a registry of available providers and creates
-/// instances based on configuration.
+/// instances based on configuration. Providers outside this crate, such as
+/// in-house gateways, are added with [`register`](Self::register) or
+/// [`with_provider`](Self::with_provider), and their embedding and batch
+/// APIs with the matching `register_*` and `with_*_provider` methods.
 ///
 /// # Examples
 ///
@@ -54,7 +97,7 @@ use crate::config::models::ProviderConfig;
 /// let provider = factory.create("openai", &config).unwrap();
 /// ```
 pub struct ProviderFactory {
-    _registry: HashMap<String, fn(&ProviderConfig) -> Result<Box<dyn Provider>, ProviderError>>,
+    registry: Registry,
 }
 
 impl ProviderFactory {
@@ -74,6 +117,12 @@ impl ProviderFactory {
     /// - `bedrock` - AWS Bedrock
     /// - `replicate` - Replicate
     /// - `perplexity` - Perplexity AI
+    /// - `openai-compatible` - Any server speaking the OpenAI chat API
+    ///   (vLLM, llama.cpp, LM Studio, LiteLLM); selected with `kind`
+    /// - `mock` - Scripted responses for tests, no network
+    ///
+    /// Providers added with [`register`](Self::register) before the factory
+    /// is created are included as well.
     ///
     /// # Examples
     ///
@@ -83,24 +132,143 @@ impl ProviderFactory {
     /// let factory = ProviderFactory::new();
     /// ```
     pub fn new() -> Self {
-        let mut registry = HashMap::new();
+        let mut registry = Registry::default();
 
         // Register built-in providers
-        registry.insert("openai".to_string(), create_openai as _);
-        registry.insert("anthropic".to_string(), create_anthropic as _);
-        registry.insert("google".to_string(), create_google as _);
-        registry.insert("cohere".to_string(), create_cohere as _);
-        registry.insert("mistral".to_string(), create_mistral as _);
-        registry.insert("groq".to_string(), create_groq as _);
-        registry.insert("together".to_string(), create_together as _);
-        registry.insert("huggingface".to_string(), create_huggingface as _);
-        registry.insert("ollama".to_string(), create_ollama as _);
-        registry.insert("azure-openai".to_string(), create_azure_openai as _);
-        registry.insert("bedrock".to_string(), create_bedrock as _);
-        registry.insert("replicate".to_string(), create_replicate as _);
-        registry.insert("perplexity".to_string(), create_perplexity as _);
-
-        Self { _registry: registry }
+        let completion = &mut registry.completion;
+        completion.insert("openai".to_string(), builtin(create_openai));
+        completion.insert("anthropic".to_string(), builtin(create_anthropic));
+        completion.insert("google".to_string(), builtin(create_google));
+        completion.insert("cohere".to_string(), builtin(create_cohere));
+        completion.insert("mistral".to_string(), builtin(create_mistral));
+        completion.insert("groq".to_string(), builtin(create_groq));
+        completion.insert("together".to_string(), builtin(create_together));
+        completion.insert("huggingface".to_string(), builtin(create_huggingface));
+        completion.insert("ollama".to_string(), builtin(create_ollama));
+        completion.insert("azure-openai".to_string(), builtin(create_azure_openai));
+        completion.insert("bedrock".to_string(), builtin(create_bedrock));
+        completion.insert("replicate".to_string(), builtin(create_replicate));
+        completion.insert("perplexity".to_string(), builtin(create_perplexity));
+        completion.insert("openai-compatible".to_string(), Arc::new(create_openai_compatible) as ProviderConstructor);
+        completion.insert("mock".to_string(), Arc::new(create_mock) as ProviderConstructor);
+
+        let embedding = &mut registry.embedding;
+        embedding.insert("openai".to_string(), embedding_with_first_key(openai_from_config));
+        embedding.insert("cohere".to_string(), embedding_with_first_key(cohere_from_config));
+        embedding.insert("mistral".to_string(), embedding_with_first_key(mistral_from_config));
+        embedding.insert("together".to_string(), embedding_with_first_key(together_from_config));
+        embedding.insert("huggingface".to_string(), embedding_with_first_key(huggingface_from_config));
+        embedding.insert(
+            "ollama".to_string(),
+            Arc::new(|_: &str, config: &ProviderConfig| {
+                Ok(Box::new(ollama_from_config(config)?) as Box<dyn EmbeddingProvider>)
+            }),
+        );
+        embedding.insert(
+            "openai-compatible".to_string(),
+            Arc::new(|name: &str, config: &ProviderConfig| {
+                let api_key = optional_api_keys(config)?.map(|mut keys| keys.remove(0).expose().to_string());
+                Ok(Box::new(openai_compatible_from_config(name, config, api_key)?) as Box<dyn EmbeddingProvider>)
+            }),
+        );
+
+        registry.batch.insert("openai".to_string(), batch_with_first_key(openai_from_config));
+        registry.batch.insert("anthropic".to_string(), batch_with_first_key(anthropic_from_config));
+
+        // Then providers registered by the application, which may replace
+        // built-in ones
+        registry.extend(&custom_providers().read().unwrap_or_else(|e| e.into_inner()));
+
+        Self { registry }
+    }
+
+    /// Registers a provider for every factory created afterwards in this
+    /// process.
+    ///
+    /// Once registered, `name` can be used as a provider name or `kind` in
+    /// the configuration, and so by every command that builds its providers
+    /// with [`ProviderFactory::new`]. Names are case-insensitive; registering
+    /// a built-in name replaces the built-in provider. Factories that already
+    /// exist are not affected.
+    ///
+    /// The constructor receives the provider's name in the configuration and
+    /// its settings. Rate limits are applied by the factory as for built-in
+    /// providers.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use llm_test_bench_core::providers::credentials::resolve_api_keys;
+    /// use llm_test_bench_core::providers::{OpenAICompatibleProvider, ProviderFactory};
+    ///
+    /// ProviderFactory::register("gateway", |name, config| {
+    ///     let token = resolve_api_keys(config)?.remove(0);
+    ///     let provider = OpenAICompatibleProvider::new(name, config.base_url.clone())?
+    ///         .with_header("X-Gateway-Token", token.expose())?;
+    ///     Ok(Box::new(provider))
+    /// });
+    ///
+    /// assert!(ProviderFactory::new().available_providers().contains(&"gateway".to_string()));
+    /// ```
+    pub fn register<F>(name: &str, constructor: F)
+    where
+        F: Fn(&str, &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> + Send + Sync + 'static,
+    {
+        let mut custom = custom_providers().write().unwrap_or_else(|e| e.into_inner());
+        custom.completion.insert(name.to_lowercase(), Arc::new(constructor));
+    }
+
+    /// Adds a provider to this factory only.
+    ///
+    /// Like [`register`](Self::register), but other factories do not see it.
+    pub fn with_provider<F>(mut self, name: &str, constructor: F) -> Self
+    where
+        F: Fn(&str, &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> + Send + Sync + 'static,
+    {
+        self.registry.completion.insert(name.to_lowercase(), Arc::new(constructor));
+        self
+    }
+
+    /// Registers an embedding provider for every factory created afterwards
+    /// in this process, for use by [`create_embedding`](Self::create_embedding).
+    ///
+    /// Names work as in [`register`](Self::register).
+    pub fn register_embedding<F>(name: &str, constructor: F)
+    where
+        F: Fn(&str, &ProviderConfig) -> Result<Box<dyn EmbeddingProvider>, ProviderError> + Send + Sync + 'static,
+    {
+        let mut custom = custom_providers().write().unwrap_or_else(|e| e.into_inner());
+        custom.embedding.insert(name.to_lowercase(), Arc::new(constructor));
+    }
+
+    /// Adds an embedding provider to this factory only.
+    pub fn with_embedding_provider<F>(mut self, name: &str, constructor: F) -> Self
+    where
+        F: Fn(&str, &ProviderConfig) -> Result<Box<dyn EmbeddingProvider>, ProviderError> + Send + Sync + 'static,
+    {
+        self.registry.embedding.insert(name.to_lowercase(), Arc::new(constructor));
+        self
+    }
+
+    /// Registers a batch provider for every factory created afterwards in
+    /// this process, for use by [`create_batch`](Self::create_batch).
+    ///
+    /// Names work as in [`register`](Self::register).
+    pub fn register_batch<F>(name: &str, constructor: F)
+    where
+        F: Fn(&str, &ProviderConfig) -> Result<Box<dyn BatchProvider>, ProviderError> + Send + Sync + 'static,
+    {
+        let mut custom = custom_providers().write().unwrap_or_else(|e| e.into_inner());
+        custom.batch.insert(name.to_lowercase(), Arc::new(constructor));
+    }
+
+    /// Adds a batch provider to this factory only.
+    pub fn with_batch_provider<F>(mut self, name: &str, constructor: F) -> Self
+    where
+        F: Fn(&str, &ProviderConfig) -> Result<Box<dyn BatchProvider>, ProviderError> + Send + Sync + 'static,
+    {
+        self.registry.batch.insert(name.to_lowercase(), Arc::new(constructor));
+        self
     }
 
     /// Creates a provider instance from configuration.
@@ -114,6 +282,22 @@ impl ProviderFactory {
     ///
     /// A boxed provider instance that implements the `Provider` trait.
     ///
+    /// The implementation is chosen by `config.kind`, falling back to
+    /// `provider_name`. This lets one kind, such as `openai-compatible`, be
+    /// configured several times under different names.
+    ///
+    /// The API key is read from `api_key_cmd`, `api_key_file`, `api_keys_env`
+    /// or `api_key_env`, in that order (see [`credentials`](super::credentials)).
+    /// With several keys, one instance is built per key and wrapped in a
+    /// [`KeyRotatingProvider`].
+    ///
+    /// When a `transport` section is set, the provider's HTTP client is built
+    /// from it (see [`transport`](super::transport)).
+    ///
+    /// When `rate_limit_rpm` or `rate_limit_tpm` is set, the provider is
+    /// wrapped in a [`RateLimitedProvider`] whose limiter is shared with every
+    /// other instance created for the same provider, base URL and API key.
+    ///
     /// # Errors
     ///
     /// - `ProviderError::InvalidRequest` - Unknown provider name
@@ -143,25 +327,24 @@ impl ProviderFactory {
     /// # }
     /// ```
     pub fn create(&self, provider_name: &str, config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
-        match provider_name.to_lowercase().as_str() {
-            "openai" => create_openai(config),
-            "anthropic" => create_anthropic(config),
-            "google" => create_google(config),
-            "cohere" => create_cohere(config),
-            "mistral" => create_mistral(config),
-            "groq" => create_groq(config),
-            "together" => create_together(config),
-            "huggingface" => create_huggingface(config),
-            "ollama" => create_ollama(config),
-            "azure-openai" | "azure_openai" => create_azure_openai(config),
-            "bedrock" => create_bedrock(config),
-            "replicate" => create_replicate(config),
-            "perplexity" => create_perplexity(config),
-            _ => Err(ProviderError::InvalidRequest(format!(
-                "Unknown provider: {}. Supported providers: openai, anthropic, google, cohere, mistral, groq, together, huggingface, ollama, azure-openai, bedrock, replicate, perplexity",
-                provider_name
-            ))),
+        let kind = config.kind.as_deref().unwrap_or(provider_name);
+        let constructor = lookup(&self.registry.completion, kind).ok_or_else(|| {
+            ProviderError::InvalidRequest(format!(
+                "Unknown provider: {}. Supported providers: {}",
+                kind,
+                self.available_providers().join(", ")
+            ))
+        })?;
+        let provider = constructor(provider_name, config)?;
+
+        if config.rate_limit_rpm.is_none() && config.rate_limit_tpm.is_none() {
+            return Ok(provider);
         }
+
+        let credentials: Vec<String> = CredentialSource::from_config(config).iter().map(ToString::to_string).collect();
+        let key = format!("{}|{}|{}", provider_name.to_lowercase(), config.base_url, credentials.join(","));
+        let limiter = RateLimiter::shared(&key, config.rate_limit_rpm, config.rate_limit_tpm);
+        Ok(Box::new(RateLimitedProvider::new(provider, limiter)))
     }
 
     /// Creates a provider instance and wraps it in an Arc for shared ownership.
@@ -211,7 +394,140 @@ impl ProviderFactory {
         Ok(Arc::from(provider))
     }
 
-    /// Returns a list of all registered provider names.
+    /// Creates a provider with its configured fallback chain.
+    ///
+    /// Looks up `provider_name` in `providers` (the `providers` section of
+    /// the configuration). If its `fallback` list is empty this is the same
+    /// as [`create`](Self::create); otherwise the provider is wrapped in a
+    /// [`FallbackProvider`] that fails over to each named provider in turn,
+    /// using that provider's `default_model`. Fallbacks' own `fallback`
+    /// lists are not followed.
+    ///
+    /// # Errors
+    ///
+    /// - `ProviderError::InvalidRequest` - The provider or one of its
+    ///   fallbacks is not in `providers`
+    /// - Any error from [`create`](Self::create) for a provider in the chain
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use llm_test_bench_core::config::ConfigLoader;
+    /// use llm_test_bench_core::providers::ProviderFactory;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = ConfigLoader::new().load()?;
+    /// let provider = ProviderFactory::new().create_with_fallbacks("openai", &config.providers)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_with_fallbacks(
+        &self,
+        provider_name: &str,
+        providers: &HashMap<String, ProviderConfig>,
+    ) -> Result<Box<dyn Provider>, ProviderError> {
+        let lookup = |name: &str| {
+            providers.get(name).ok_or_else(|| {
+                ProviderError::InvalidRequest(format!("Provider '{}' not found in configuration", name))
+            })
+        };
+
+        let config = lookup(provider_name)?;
+        let primary = self.create(provider_name, config)?;
+        if config.fallback.is_empty() {
+            return Ok(primary);
+        }
+
+        let breaker = config.circuit_breaker.clone().unwrap_or_default();
+        let mut chain = FallbackProvider::new(primary)
+            .with_circuit_breaker(breaker.failure_threshold, Duration::from_secs(breaker.cooldown_seconds));
+        for name in &config.fallback {
+            let fallback_config = lookup(name)?;
+            let fallback = self.create(name, fallback_config)?;
+            chain = chain.with_fallback(fallback, Some(fallback_config.default_model.clone()));
+        }
+
+        Ok(Box::new(chain))
+    }
+
+    /// Creates an embedding provider from configuration.
+    ///
+    /// The implementation is chosen by `config.kind`, falling back to
+    /// `provider_name`, as in [`create`](Self::create). Embeddings are
+    /// available from `openai`, `cohere`, `mistral`, `together`,
+    /// `huggingface`, `ollama` and `openai-compatible` providers, and from
+    /// those added with [`register_embedding`](Self::register_embedding) or
+    /// [`with_embedding_provider`](Self::with_embedding_provider). Rate limits
+    /// are not applied to embedding requests, and only the first of several
+    /// API keys is used.
+    ///
+    /// # Errors
+    ///
+    /// - `ProviderError::InvalidRequest` - The provider has no embeddings API
+    /// - `ProviderError::InvalidApiKey` - API key is missing or invalid
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use llm_test_bench_core::config::ConfigLoader;
+    /// use llm_test_bench_core::providers::{EmbeddingRequest, ProviderFactory};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = ConfigLoader::new().load()?;
+    /// let provider = ProviderFactory::new().create_embedding("openai", &config.providers["openai"])?;
+    ///
+    /// let response = provider.embed(EmbeddingRequest::new("text-embedding-3-small", ["hello"])).await?;
+    /// println!("{:?} dimensions", response.dimensions());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_embedding(
+        &self,
+        provider_name: &str,
+        config: &ProviderConfig,
+    ) -> Result<Box<dyn EmbeddingProvider>, ProviderError> {
+        let kind = config.kind.as_deref().unwrap_or(provider_name);
+        let constructor = lookup(&self.registry.embedding, kind).ok_or_else(|| {
+            ProviderError::InvalidRequest(format!(
+                "Provider {} does not support embeddings. Embedding providers: {}",
+                kind,
+                sorted_names(&self.registry.embedding).join(", ")
+            ))
+        })?;
+        constructor(provider_name, config)
+    }
+
+    /// Creates a batch provider from configuration.
+    ///
+    /// The implementation is chosen by `config.kind`, falling back to
+    /// `provider_name`, as in [`create`](Self::create). Batch jobs are
+    /// available from `openai` and `anthropic` providers, and from those
+    /// added with [`register_batch`](Self::register_batch) or
+    /// [`with_batch_provider`](Self::with_batch_provider). Rate limits are
+    /// not applied, as batch jobs have their own quotas, and only the first
+    /// of several API keys is used.
+    ///
+    /// # Errors
+    ///
+    /// - `ProviderError::InvalidRequest` - The provider has no batch API
+    /// - `ProviderError::InvalidApiKey` - API key is missing or invalid
+    pub fn create_batch(
+        &self,
+        provider_name: &str,
+        config: &ProviderConfig,
+    ) -> Result<Box<dyn BatchProvider>, ProviderError> {
+        let kind = config.kind.as_deref().unwrap_or(provider_name);
+        let constructor = lookup(&self.registry.batch, kind).ok_or_else(|| {
+            ProviderError::InvalidRequest(format!(
+                "Provider {} does not support batch jobs. Batch providers: {}",
+                kind,
+                sorted_names(&self.registry.batch).join(", ")
+            ))
+        })?;
+        constructor(provider_name, config)
+    }
+
+    /// Returns the names of all registered providers, sorted.
     ///
     /// # Examples
     ///
@@ -224,21 +540,7 @@ impl ProviderFactory {
     /// assert!(providers.contains(&"anthropic".to_string()));
     /// ```
     pub fn available_providers(&self) -> Vec<String> {
-        vec![
-            "openai".to_string(),
-            "anthropic".to_string(),
-            "google".to_string(),
-            "cohere".to_string(),
-            "mistral".to_string(),
-            "groq".to_string(),
-            "together".to_string(),
-            "huggingface".to_string(),
-            "ollama".to_string(),
-            "azure-openai".to_string(),
-            "bedrock".to_string(),
-            "replicate".to_string(),
-            "perplexity".to_string(),
-        ]
+        sorted_names(&self.registry.completion)
     }
 }
 
@@ -248,12 +550,108 @@ impl Default for ProviderFactory {
     }
 }
 
+/// Providers registered with [`ProviderFactory::register`] and its
+/// embedding and batch counterparts
+fn custom_providers() -> &'static RwLock<Registry> {
+    static CUSTOM: OnceLock<RwLock<Registry>> = OnceLock::new();
+    CUSTOM.get_or_init(|| RwLock::new(Registry::default()))
+}
+
+/// The constructor registered for `kind`, accepting `_` for `-`
+fn lookup<'a, C>(constructors: &'a HashMap<String, C>, kind: &str) -> Option<&'a C> {
+    let key = kind.to_lowercase();
+    constructors
+        .get(&key)
+        .or_else(|| constructors.get(&key.replace('_', "-")))
+}
+
+fn sorted_names<C>(constructors: &HashMap<String, C>) -> Vec<String> {
+    let mut names: Vec<String> = constructors.keys().cloned().collect();
+    names.sort();
+    names
+}
+
+/// Wraps a constructor that does not need the provider's name
+fn builtin(create: fn(&ProviderConfig) -> Result<Box<dyn Provider>, ProviderError>) -> ProviderConstructor {
+    Arc::new(move |_: &str, config: &ProviderConfig| create(config))
+}
+
+/// Wraps an embedding constructor that takes the first API key
+fn embedding_with_first_key<P>(build: fn(&ProviderConfig, String) -> Result<P, ProviderError>) -> EmbeddingConstructor
+where
+    P: EmbeddingProvider + 'static,
+{
+    Arc::new(move |_: &str, config: &ProviderConfig| Ok(Box::new(build(config, first_api_key(config)?)?)))
+}
+
+/// Wraps a batch constructor that takes the first API key
+fn batch_with_first_key<P>(build: fn(&ProviderConfig, String) -> Result<P, ProviderError>) -> BatchConstructor
+where
+    P: BatchProvider + 'static,
+{
+    Arc::new(move |_: &str, config: &ProviderConfig| Ok(Box::new(build(config, first_api_key(config)?)?)))
+}
+
+/// Builds one provider per API key configured for the provider
+///
+/// Several keys (`api_keys_env`) are wrapped in a [`KeyRotatingProvider`].
+fn with_api_keys<F>(config: &ProviderConfig, build: F) -> Result<Box<dyn Provider>, ProviderError>
+where
+    F: Fn(String) -> Result<Box<dyn Provider>, ProviderError>,
+{
+    rotate_keys(resolve_api_keys(config)?, config.key_rotation, build)
+}
+
+fn rotate_keys<F>(keys: Vec<ApiKey>, rotation: KeyRotation, build: F) -> Result<Box<dyn Provider>, ProviderError>
+where
+    F: Fn(String) -> Result<Box<dyn Provider>, ProviderError>,
+{
+    let providers = keys
+        .into_iter()
+        .map(|key| build(key.expose().to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    rotate_providers(providers, rotation)
+}
+
+/// Wraps several providers in a [`KeyRotatingProvider`]
+///
+/// Fails with `ProviderError::InvalidApiKey` when `providers` is empty.
+fn rotate_providers(
+    mut providers: Vec<Box<dyn Provider>>,
+    rotation: KeyRotation,
+) -> Result<Box<dyn Provider>, ProviderError> {
+    if providers.len() == 1 {
+        return Ok(providers.remove(0));
+    }
+    Ok(Box::new(KeyRotatingProvider::new(providers, rotation)?))
+}
+
+/// The first API key configured for the provider
+///
+/// Used where only one key can be used, such as embedding and batch jobs.
+fn first_api_key(config: &ProviderConfig) -> Result<String, ProviderError> {
+    Ok(resolve_api_keys(config)?.remove(0).expose().to_string())
+}
+
+/// Replaces the provider's HTTP client when `transport` settings are given
+fn with_transport<P>(
+    provider: P,
+    config: &ProviderConfig,
+    with_http_client: fn(P, reqwest::Client) -> P,
+) -> Result<P, ProviderError> {
+    if config.transport == TransportConfig::default() {
+        return Ok(provider);
+    }
+    let client = http_client(&config.transport, Duration::from_secs(config.timeout_seconds))?;
+    Ok(with_http_client(provider, client))
+}
+
 /// Creates an OpenAI provider instance from configuration.
 fn create_openai(config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
-    // Get API key from environment
-    let api_key = std::env::var(&config.api_key_env)
-        .map_err(|_| ProviderError::InvalidApiKey)?;
+    with_api_keys(config, |api_key| Ok(Box::new(openai_from_config(config, api_key)?)))
+}
 
+fn openai_from_config(config: &ProviderConfig, api_key: String) -> Result<OpenAIProvider, ProviderError> {
     // Create provider with custom base URL if specified
     let provider = if config.base_url.contains("openai.com") {
         OpenAIProvider::new(api_key)?
@@ -261,15 +659,15 @@ fn create_openai(config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderE
         OpenAIProvider::with_base_url(api_key, config.base_url.clone())?
     };
 
-    Ok(Box::new(provider))
+    with_transport(provider, config, OpenAIProvider::with_http_client)
 }
 
 /// Creates an Anthropic provider instance from configuration.
 fn create_anthropic(config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
-    // Get API key from environment
-    let api_key = std::env::var(&config.api_key_env)
-        .map_err(|_| ProviderError::InvalidApiKey)?;
+    with_api_keys(config, |api_key| Ok(Box::new(anthropic_from_config(config, api_key)?)))
+}
 
+fn anthropic_from_config(config: &ProviderConfig, api_key: String) -> Result<AnthropicProvider, ProviderError> {
     // Create provider with custom base URL if specified
     let provider = if config.base_url.contains("anthropic.com") {
         AnthropicProvider::new(api_key)
@@ -277,110 +675,264 @@ fn create_anthropic(config: &ProviderConfig) -> Result<Box<dyn Provider>, Provid
         AnthropicProvider::with_base_url(api_key, config.base_url.clone())
     };
 
-    Ok(Box::new(provider))
+    with_transport(provider, config, AnthropicProvider::with_http_client)
 }
 
 /// Creates a Google AI provider instance from configuration.
 fn create_google(config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
-    let api_key = std::env::var(&config.api_key_env)
-        .map_err(|_| ProviderError::InvalidApiKey)?;
-
-    let provider = GoogleProvider::with_base_url(api_key, config.base_url.clone())?;
-    Ok(Box::new(provider))
+    with_api_keys(config, |api_key| {
+        let provider = GoogleProvider::with_base_url(api_key, config.base_url.clone())?;
+        Ok(Box::new(with_transport(provider, config, GoogleProvider::with_http_client)?))
+    })
 }
 
 /// Creates a Cohere provider instance from configuration.
 fn create_cohere(config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
-    let api_key = std::env::var(&config.api_key_env)
-        .map_err(|_| ProviderError::InvalidApiKey)?;
+    with_api_keys(config, |api_key| Ok(Box::new(cohere_from_config(config, api_key)?)))
+}
 
+fn cohere_from_config(config: &ProviderConfig, api_key: String) -> Result<CohereProvider, ProviderError> {
     let provider = CohereProvider::with_base_url(api_key, config.base_url.clone())?;
-    Ok(Box::new(provider))
+    with_transport(provider, config, CohereProvider::with_http_client)
 }
 
 /// Creates a Mistral AI provider instance from configuration.
 fn create_mistral(config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
-    let api_key = std::env::var(&config.api_key_env)
-        .map_err(|_| ProviderError::InvalidApiKey)?;
+    with_api_keys(config, |api_key| Ok(Box::new(mistral_from_config(config, api_key)?)))
+}
 
+fn mistral_from_config(config: &ProviderConfig, api_key: String) -> Result<MistralProvider, ProviderError> {
     let provider = MistralProvider::with_base_url(api_key, config.base_url.clone())?;
-    Ok(Box::new(provider))
+    with_transport(provider, config, MistralProvider::with_http_client)
 }
 
 /// Creates a Groq provider instance from configuration.
 fn create_groq(config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
-    let api_key = std::env::var(&config.api_key_env)
-        .map_err(|_| ProviderError::InvalidApiKey)?;
-
-    let provider = GroqProvider::with_base_url(api_key, config.base_url.clone())?;
-    Ok(Box::new(provider))
+    with_api_keys(config, |api_key| {
+        let provider = GroqProvider::with_base_url(api_key, config.base_url.clone())?;
+        Ok(Box::new(with_transport(provider, config, GroqProvider::with_http_client)?))
+    })
 }
 
 /// Creates a Together AI provider instance from configuration.
 fn create_together(config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
-    let api_key = std::env::var(&config.api_key_env)
-        .map_err(|_| ProviderError::InvalidApiKey)?;
+    with_api_keys(config, |api_key| Ok(Box::new(together_from_config(config, api_key)?)))
+}
 
+fn together_from_config(config: &ProviderConfig, api_key: String) -> Result<TogetherProvider, ProviderError> {
     let provider = TogetherProvider::with_base_url(api_key, config.base_url.clone())?;
-    Ok(Box::new(provider))
+    with_transport(provider, config, TogetherProvider::with_http_client)
 }
 
 /// Creates a Hugging Face provider instance from configuration.
 fn create_huggingface(config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
-    let api_key = std::env::var(&config.api_key_env)
-        .map_err(|_| ProviderError::InvalidApiKey)?;
+    with_api_keys(config, |api_key| Ok(Box::new(huggingface_from_config(config, api_key)?)))
+}
 
+fn huggingface_from_config(config: &ProviderConfig, api_key: String) -> Result<HuggingFaceProvider, ProviderError> {
     let provider = HuggingFaceProvider::with_base_url(api_key, config.base_url.clone())?;
-    Ok(Box::new(provider))
+    with_transport(provider, config, HuggingFaceProvider::with_http_client)
 }
 
 /// Creates an Ollama provider instance from configuration.
 fn create_ollama(config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
     // Ollama doesn't require an API key, use base_url from config
+    Ok(Box::new(ollama_from_config(config)?))
+}
+
+fn ollama_from_config(config: &ProviderConfig) -> Result<OllamaProvider, ProviderError> {
     let provider = OllamaProvider::with_base_url(config.base_url.clone())?;
-    Ok(Box::new(provider))
+    with_transport(provider, config, OllamaProvider::with_http_client)
 }
 
 /// Creates an Azure OpenAI provider instance from configuration.
+///
+/// With `azure_ad` set, the credential is an Azure AD access token rather
+/// than a resource key. Tokens expire, so each provider reads its credential
+/// source again whenever it needs a new one.
 fn create_azure_openai(config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
-    let api_key = std::env::var(&config.api_key_env)
-        .map_err(|_| ProviderError::InvalidApiKey)?;
-
     // For Azure, we need deployment name and endpoint from config
     // Using default_model as deployment name
-    let provider = AzureOpenAIProvider::new(api_key, config.base_url.clone(), config.default_model.clone())?;
-    Ok(Box::new(provider))
+    let endpoint = config.base_url.clone();
+    let deployment = config.default_model.clone();
+
+    if config.azure_ad {
+        let providers = CredentialSource::from_config(config)
+            .into_iter()
+            .map(|source| {
+                let provider = AzureOpenAIProvider::with_azure_ad_token_provider(
+                    move || source.resolve(),
+                    endpoint.clone(),
+                    deployment.clone(),
+                )?;
+                Ok(Box::new(with_transport(provider, config, AzureOpenAIProvider::with_http_client)?) as Box<dyn Provider>)
+            })
+            .collect::<Result<Vec<_>, ProviderError>>()?;
+        return rotate_providers(providers, config.key_rotation);
+    }
+
+    with_api_keys(config, |api_key| {
+        let provider = AzureOpenAIProvider::new(api_key, endpoint.clone(), deployment.clone())?;
+        Ok(Box::new(with_transport(provider, config, AzureOpenAIProvider::with_http_client)?))
+    })
 }
 
 /// Creates an AWS Bedrock provider instance from configuration.
+///
+/// Configured credentials have the form `ACCESS_KEY_ID:SECRET_ACCESS_KEY`,
+/// optionally followed by `:SESSION_TOKEN`, and are resolved and rotated like
+/// API keys. Without any, the standard `AWS_*` environment variables are used.
 fn create_bedrock(config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
-    // AWS Bedrock requires AWS credentials
-    let access_key = std::env::var("AWS_ACCESS_KEY_ID")
-        .map_err(|_| ProviderError::InvalidApiKey)?;
-    let secret_key = std::env::var("AWS_SECRET_ACCESS_KEY")
-        .map_err(|_| ProviderError::InvalidApiKey)?;
-    let region = std::env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+    let region = std::env::var("AWS_REGION")
+        .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+        .unwrap_or_else(|_| "us-east-1".to_string());
+
+    let build = |credentials: AwsCredentials| -> Result<Box<dyn Provider>, ProviderError> {
+        let mut provider = if config.base_url.is_empty() {
+            BedrockProvider::new(region.clone(), credentials.access_key, credentials.secret_key)?
+        } else {
+            BedrockProvider::with_base_url(
+                region.clone(),
+                credentials.access_key,
+                credentials.secret_key,
+                config.base_url.clone(),
+            )?
+        };
+        if let Some(session_token) = credentials.session_token {
+            provider = provider.with_session_token(session_token);
+        }
+        Ok(Box::new(with_transport(provider, config, BedrockProvider::with_http_client)?))
+    };
+
+    match optional_api_keys(config)? {
+        Some(keys) => rotate_keys(keys, config.key_rotation, |key| build(AwsCredentials::parse(&key)?)),
+        None => build(AwsCredentials::from_env()?),
+    }
+}
 
-    let provider = BedrockProvider::new(region, access_key, secret_key)?;
-    Ok(Box::new(provider))
+/// AWS credentials for signing Bedrock requests
+struct AwsCredentials {
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+}
+
+impl AwsCredentials {
+    /// Parses `ACCESS_KEY_ID:SECRET_ACCESS_KEY[:SESSION_TOKEN]`
+    fn parse(credentials: &str) -> Result<Self, ProviderError> {
+        let mut parts = credentials.splitn(3, ':');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(access_key), Some(secret_key), session_token) if !access_key.is_empty() && !secret_key.is_empty() => {
+                Ok(Self {
+                    access_key: access_key.to_string(),
+                    secret_key: secret_key.to_string(),
+                    session_token: session_token.filter(|t| !t.is_empty()).map(str::to_string),
+                })
+            }
+            _ => Err(ProviderError::AuthenticationError(
+                "Bedrock credentials must have the form ACCESS_KEY_ID:SECRET_ACCESS_KEY[:SESSION_TOKEN]".to_string(),
+            )),
+        }
+    }
+
+    /// Reads `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`
+    fn from_env() -> Result<Self, ProviderError> {
+        Ok(Self {
+            access_key: std::env::var("AWS_ACCESS_KEY_ID").map_err(|_| ProviderError::InvalidApiKey)?,
+            secret_key: std::env::var("AWS_SECRET_ACCESS_KEY").map_err(|_| ProviderError::InvalidApiKey)?,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+        })
+    }
 }
 
 /// Creates a Replicate provider instance from configuration.
 fn create_replicate(config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
-    let api_key = std::env::var(&config.api_key_env)
-        .map_err(|_| ProviderError::InvalidApiKey)?;
-
-    let provider = ReplicateProvider::with_base_url(api_key, config.base_url.clone())?;
-    Ok(Box::new(provider))
+    with_api_keys(config, |api_key| {
+        let provider = ReplicateProvider::with_base_url(api_key, config.base_url.clone())?;
+        Ok(Box::new(with_transport(provider, config, ReplicateProvider::with_http_client)?))
+    })
 }
 
 /// Creates a Perplexity AI provider instance from configuration.
 fn create_perplexity(config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
-    let api_key = std::env::var(&config.api_key_env)
-        .map_err(|_| ProviderError::InvalidApiKey)?;
+    with_api_keys(config, |api_key| {
+        let provider = PerplexityProvider::with_base_url(api_key, config.base_url.clone())?;
+        Ok(Box::new(with_transport(provider, config, PerplexityProvider::with_http_client)?))
+    })
+}
+
+/// Creates an OpenAI-compatible provider registered as `name`.
+///
+/// The API key is optional; it is only required when a header template
+/// references `{api_key}`.
+fn create_openai_compatible(name: &str, config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
+    match optional_api_keys(config)? {
+        Some(keys) => rotate_keys(keys, config.key_rotation, |api_key| {
+            Ok(Box::new(openai_compatible_from_config(name, config, Some(api_key))?))
+        }),
+        None => Ok(Box::new(openai_compatible_from_config(name, config, None)?)),
+    }
+}
+
+/// The API keys of a provider that may run without one
+///
+/// `None` when the only source is an `api_key_env` that is unset or empty.
+fn optional_api_keys(config: &ProviderConfig) -> Result<Option<Vec<ApiKey>>, ProviderError> {
+    match resolve_api_keys(config) {
+        Ok(keys) => Ok(Some(keys)),
+        Err(ProviderError::InvalidApiKey)
+            if config.api_key_cmd.is_none() && config.api_key_file.is_none() && config.api_keys_env.is_empty() =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn openai_compatible_from_config(
+    name: &str,
+    config: &ProviderConfig,
+    api_key: Option<String>,
+) -> Result<OpenAICompatibleProvider, ProviderError> {
+    let mut provider = with_transport(
+        OpenAICompatibleProvider::with_timeout(name, config.base_url.clone(), Duration::from_secs(config.timeout_seconds))?,
+        config,
+        OpenAICompatibleProvider::with_http_client,
+    )?;
+
+    if config.headers.is_empty() {
+        if let Some(ref key) = api_key {
+            provider = provider.with_api_key(key)?;
+        }
+    } else {
+        for (header, template) in &config.headers {
+            provider = provider.with_header(header, &expand_header_template(template, api_key.as_deref())?)?;
+        }
+    }
+
+    let context_length = config.context_length.unwrap_or(4096);
+    for model in &config.models {
+        provider = provider.with_model(model.clone(), context_length);
+    }
+
+    Ok(provider)
+}
+
+/// Creates a scripted mock provider registered as `name`.
+///
+/// Runs the script at `config.script`, or the default script when unset.
+/// Scripts that list no models serve `config.default_model`. No API key is
+/// needed.
+fn create_mock(name: &str, config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
+    let mut script = match config.script {
+        Some(ref path) => MockScript::from_file(path)?,
+        None => MockScript::default(),
+    };
+    if script.models.is_empty() {
+        script.models = vec![config.default_model.clone()];
+    }
 
-    let provider = PerplexityProvider::with_base_url(api_key, config.base_url.clone())?;
-    Ok(Box::new(provider))
+    Ok(Box::new(MockProvider::new(script)?.with_name(name)))
 }
 
 #[cfg(test)]
@@ -390,11 +942,25 @@ mod tests {
     fn test_config(provider_name: &str) -> ProviderConfig {
         ProviderConfig {
             api_key_env: format!("{}_API_KEY", provider_name.to_uppercase()),
+            api_key_file: None,
+            api_key_cmd: None,
+            api_keys_env: Vec::new(),
+            key_rotation: KeyRotation::default(),
+            azure_ad: false,
             base_url: format!("https://api.{}.com/v1", provider_name),
             default_model: "test-model".to_string(),
             timeout_seconds: 30,
             max_retries: 3,
             rate_limit_rpm: None,
+            rate_limit_tpm: None,
+            fallback: Vec::new(),
+            circuit_breaker: None,
+            kind: None,
+            headers: HashMap::new(),
+            models: Vec::new(),
+            context_length: None,
+            script: None,
+            transport: TransportConfig::default(),
         }
     }
 
@@ -513,4 +1079,219 @@ mod tests {
 
         std::env::remove_var(&config.api_key_env);
     }
+
+    #[test]
+    fn test_create_rate_limited() {
+        let factory = ProviderFactory::new();
+        let mut config = test_config("ollama");
+        config.rate_limit_rpm = Some(60);
+        config.rate_limit_tpm = Some(10_000);
+
+        let provider = factory.create("ollama", &config).unwrap();
+        assert_eq!(provider.name(), "ollama");
+        assert!(!provider.supported_models().is_empty());
+    }
+
+    #[test]
+    fn test_create_with_fallbacks() {
+        let factory = ProviderFactory::new();
+        let mut providers = HashMap::new();
+        let mut primary = test_config("ollama");
+        primary.fallback = vec!["groq".to_string()];
+        providers.insert("ollama".to_string(), primary);
+
+        // The fallback is not configured yet
+        assert!(matches!(
+            factory.create_with_fallbacks("ollama", &providers),
+            Err(ProviderError::InvalidRequest(_))
+        ));
+
+        let mut groq = test_config("groq");
+        groq.api_key_env = "FALLBACK_TEST_GROQ_API_KEY".to_string();
+        std::env::set_var(&groq.api_key_env, "test-key");
+        providers.insert("groq".to_string(), groq);
+
+        let provider = factory.create_with_fallbacks("ollama", &providers).unwrap();
+        assert_eq!(provider.name(), "ollama");
+        // Models of the whole chain are available
+        assert!(provider.supported_models().len() > factory.create("ollama", &providers["ollama"]).unwrap().supported_models().len());
+
+        std::env::remove_var("FALLBACK_TEST_GROQ_API_KEY");
+    }
+
+    #[test]
+    fn test_create_openai_compatible_under_several_names() {
+        let factory = ProviderFactory::new();
+        let mut config = test_config("vllm");
+        config.kind = Some("openai-compatible".to_string());
+        config.base_url = "http://localhost:8000/v1".to_string();
+        config.api_key_env = "OPENAI_COMPATIBLE_TEST_UNSET_KEY".to_string();
+        config.models = vec!["llama-3-8b".to_string()];
+        config.context_length = Some(8192);
+
+        let vllm = factory.create("vllm", &config).unwrap();
+        assert_eq!(vllm.name(), "vllm");
+        assert_eq!(vllm.max_context_length("llama-3-8b"), Some(8192));
+
+        let lm_studio = factory.create("lm-studio", &config).unwrap();
+        assert_eq!(lm_studio.name(), "lm-studio");
+
+        // A header that needs the missing API key
+        config.headers.insert("X-Api-Key".to_string(), "{api_key}".to_string());
+        assert!(matches!(factory.create("vllm", &config), Err(ProviderError::InvalidApiKey)));
+    }
+
+    #[tokio::test]
+    async fn test_create_mock() {
+        let factory = ProviderFactory::new();
+        let config = test_config("mock");
+
+        let provider = factory.create("mock", &config).unwrap();
+        assert_eq!(provider.name(), "mock");
+        assert_eq!(provider.supported_models()[0].id, "test-model");
+
+        let response = provider
+            .complete(crate::providers::CompletionRequest::new("test-model", "Hello"))
+            .await
+            .unwrap();
+        assert!(response.content.contains("Hello"));
+
+        let mut config = test_config("staging");
+        config.kind = Some("mock".to_string());
+        config.script = Some("/nonexistent/mock-script.yaml".into());
+        assert!(matches!(factory.create("staging", &config), Err(ProviderError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn test_create_embedding() {
+        let factory = ProviderFactory::new();
+
+        let ollama = factory.create_embedding("ollama", &test_config("ollama")).unwrap();
+        assert_eq!(ollama.name(), "ollama");
+        assert_eq!(ollama.embedding_dimensions("nomic-embed-text"), Some(768));
+
+        let mut config = test_config("tei");
+        config.kind = Some("openai-compatible".to_string());
+        config.api_key_env = "OPENAI_COMPATIBLE_TEST_UNSET_KEY".to_string();
+        assert_eq!(factory.create_embedding("tei", &config).unwrap().name(), "tei");
+
+        match factory.create_embedding("anthropic", &test_config("anthropic")) {
+            Err(ProviderError::InvalidRequest(msg)) => assert!(msg.contains("does not support embeddings")),
+            _ => panic!("Expected InvalidRequest error"),
+        }
+    }
+
+    #[test]
+    fn test_create_batch() {
+        let factory = ProviderFactory::new();
+
+        match factory.create_batch("groq", &test_config("groq")) {
+            Err(ProviderError::InvalidRequest(msg)) => assert!(msg.contains("does not support batch jobs")),
+            _ => panic!("Expected InvalidRequest error"),
+        }
+    }
+
+    #[test]
+    fn test_create_with_credential_sources() {
+        let factory = ProviderFactory::new();
+
+        let mut config = test_config("mistral");
+        config.api_key_env = "FACTORY_TEST_UNSET_KEY".to_string();
+        config.api_key_cmd = Some("echo test-key".to_string());
+        assert_eq!(factory.create("mistral", &config).unwrap().name(), "mistral");
+
+        config.api_key_cmd = None;
+        config.api_key_file = Some("/nonexistent/api-key".into());
+        assert!(matches!(factory.create("mistral", &config), Err(ProviderError::AuthenticationError(_))));
+
+        // Every key in the rotation must be set
+        config.api_key_file = None;
+        config.api_keys_env = vec!["FACTORY_TEST_KEY_1".to_string(), "FACTORY_TEST_UNSET_KEY".to_string()];
+        std::env::set_var("FACTORY_TEST_KEY_1", "test-key");
+        assert!(matches!(factory.create("mistral", &config), Err(ProviderError::InvalidApiKey)));
+
+        config.api_keys_env[1] = "FACTORY_TEST_KEY_2".to_string();
+        std::env::set_var("FACTORY_TEST_KEY_2", "test-key");
+        assert_eq!(factory.create("mistral", &config).unwrap().name(), "mistral");
+    }
+
+    #[test]
+    fn test_create_bedrock_with_configured_credentials() {
+        let factory = ProviderFactory::new();
+
+        let mut config = test_config("bedrock");
+        config.api_key_cmd = Some("echo AKIDEXAMPLE:secret:session".to_string());
+        assert_eq!(factory.create("bedrock", &config).unwrap().name(), "bedrock");
+
+        config.api_key_cmd = Some("echo AKIDEXAMPLE".to_string());
+        assert!(matches!(factory.create("bedrock", &config), Err(ProviderError::AuthenticationError(_))));
+
+        let credentials = AwsCredentials::parse("AKIDEXAMPLE:secret:session").unwrap();
+        assert_eq!(credentials.access_key, "AKIDEXAMPLE");
+        assert_eq!(credentials.secret_key, "secret");
+        assert_eq!(credentials.session_token.as_deref(), Some("session"));
+        assert!(AwsCredentials::parse("AKIDEXAMPLE:secret").unwrap().session_token.is_none());
+        assert!(AwsCredentials::parse(":secret").is_err());
+    }
+
+    fn mock_gateway(name: &str, _config: &ProviderConfig) -> Result<Box<dyn Provider>, ProviderError> {
+        let script = MockScript {
+            models: vec!["gateway-model".to_string()],
+            ..MockScript::default()
+        };
+        Ok(Box::new(MockProvider::new(script)?.with_name(name)))
+    }
+
+    #[test]
+    fn test_register_custom_provider() {
+        let factory = ProviderFactory::new().with_provider("Test-Gateway", mock_gateway);
+        assert!(factory.available_providers().contains(&"test-gateway".to_string()));
+
+        let mut config = test_config("staging");
+        config.kind = Some("test-gateway".to_string());
+        let provider = factory.create("staging", &config).unwrap();
+        assert_eq!(provider.name(), "staging");
+        assert_eq!(provider.supported_models()[0].id, "gateway-model");
+
+        // Rate limits still apply
+        config.rate_limit_rpm = Some(60);
+        assert!(factory.create("staging", &config).is_ok());
+    }
+
+    #[test]
+    fn test_custom_embedding_and_batch_providers() {
+        let factory = ProviderFactory::new()
+            .with_embedding_provider("gateway", |name, config| {
+                let api_key = first_api_key(config)?;
+                Ok(Box::new(openai_compatible_from_config(name, config, Some(api_key))?))
+            })
+            .with_batch_provider("gateway", |_, config| Ok(Box::new(openai_from_config(config, first_api_key(config)?)?)));
+
+        std::env::set_var("GATEWAY_API_KEY", "test-key");
+        let mut config = test_config("gateway");
+        config.kind = Some("Gateway".to_string());
+        assert_eq!(factory.create_embedding("staging", &config).unwrap().name(), "staging");
+        assert!(factory.create_batch("staging", &config).is_ok());
+
+        match ProviderFactory::new().create_embedding("staging", &config) {
+            Err(ProviderError::InvalidRequest(msg)) => assert!(msg.contains("Embedding providers: cohere")),
+            _ => panic!("Expected InvalidRequest error"),
+        }
+    }
+
+    #[test]
+    fn test_with_provider_is_local() {
+        let factory = ProviderFactory::new().with_provider("openai", mock_gateway);
+        let provider = factory.create("openai", &test_config("openai")).unwrap();
+        assert_eq!(provider.supported_models()[0].id, "gateway-model");
+
+        let other = ProviderFactory::new();
+        assert!(!other.available_providers().contains(&"local-only".to_string()));
+        let local = ProviderFactory::new().with_provider("local-only", mock_gateway);
+        assert!(local.available_providers().contains(&"local-only".to_string()));
+        assert!(matches!(
+            other.create("local-only", &test_config("local-only")),
+            Err(ProviderError::InvalidRequest(_))
+        ));
+    }
 }
@@ -0,0 +1,704 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Fallback chains with per-provider circuit breakers.
+//!
+//! [`FallbackProvider`] sends each request to the first provider of an
+//! ordered chain and moves on to the next one when it fails with a retryable
+//! error (see [`ProviderError::is_retryable`]). Every provider in the chain
+//! has its own [`CircuitBreaker`]: after repeated failures the provider is
+//! skipped for a cooldown period, then a single probe request decides whether
+//! it rejoins the chain.
+//!
+//! The provider that answered is recorded under [`SERVED_BY`] in the
+//! response's `metadata`; for streams, in the metadata of the finish event,
+//! which is added when the provider sends none.
+//!
+//! Chains are normally built from configuration with
+//! [`ProviderFactory::create_with_fallbacks`](super::ProviderFactory::create_with_fallbacks):
+//!
+//! ```toml
+//! [providers.openai]
+//! api_key_env = "OPENAI_API_KEY"
+//! base_url = "https://api.openai.com/v1"
+//! default_model = "gpt-4o"
+//! timeout_seconds = 30
+//! max_retries = 3
+//! fallback = ["anthropic"]
+//!
+//! [providers.openai.circuit_breaker]
+//! failure_threshold = 3
+//! cooldown_seconds = 60
+//! ```
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use llm_test_bench_core::providers::{AnthropicProvider, FallbackProvider, OpenAIProvider};
+//! use std::time::Duration;
+//!
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let provider = FallbackProvider::new(Box::new(OpenAIProvider::new("openai-key".to_string())?))
+//!     .with_circuit_breaker(3, Duration::from_secs(60))
+//!     .with_fallback(
+//!         Box::new(AnthropicProvider::new("anthropic-key".to_string())),
+//!         Some("claude-3-5-sonnet-20241022".to_string()),
+//!     );
+//! # Ok(())
+//! # }
+//! ```
+
+use super::traits::RetryableProvider;
+use super::{
+    CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream,
+    StreamEvent, StreamEventKind,
+};
+use crate::config::models::CircuitBreakerConfig;
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+/// Response metadata key holding the name of the provider that served it.
+pub const SERVED_BY: &str = "served_by";
+
+/// The state of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally.
+    Closed,
+    /// The provider failed repeatedly and is being skipped.
+    Open,
+    /// The cooldown has passed; the next request probes the provider.
+    HalfOpen,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probe_started_at: Option<Instant>,
+}
+
+/// Tracks consecutive failures of one provider.
+///
+/// The circuit opens after `failure_threshold` consecutive failures. Once
+/// `cooldown` has passed it lets a single probe through: success closes the
+/// circuit, failure opens it for another cooldown. A probe that never
+/// reports back (for example because its request was cancelled) is replaced
+/// after another cooldown.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    /// Creates a closed circuit breaker.
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Creates a closed circuit breaker from configuration.
+    pub fn from_config(config: &CircuitBreakerConfig) -> Self {
+        Self::new(config.failure_threshold, Duration::from_secs(config.cooldown_seconds))
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> CircuitState {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.cooldown => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Returns whether a request may be sent now.
+    ///
+    /// In the half-open state only the first caller is allowed through.
+    pub fn try_acquire(&self) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(opened_at) = state.opened_at else {
+            return true;
+        };
+        if opened_at.elapsed() < self.cooldown {
+            return false;
+        }
+        match state.probe_started_at {
+            Some(started) if started.elapsed() < self.cooldown => false,
+            _ => {
+                state.probe_started_at = Some(Instant::now());
+                true
+            }
+        }
+    }
+
+    /// Records a request that reached the provider and got an answer.
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        *state = BreakerState::default();
+    }
+
+    /// Records a failed request.
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        if state.probe_started_at.take().is_some() || state.consecutive_failures >= self.failure_threshold {
+            state.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::from_config(&CircuitBreakerConfig::default())
+    }
+}
+
+struct Backend {
+    provider: Box<dyn Provider>,
+    model: Option<String>,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl Backend {
+    fn request(&self, request: &CompletionRequest) -> CompletionRequest {
+        let mut request = request.clone();
+        if let Some(model) = &self.model {
+            request.model = model.clone();
+        }
+        request
+    }
+}
+
+/// A provider that fails over along an ordered chain of providers.
+///
+/// Only retryable errors move a request to the next provider; other errors,
+/// such as an invalid request, are returned straight away. When every
+/// provider fails, the last error is returned, and when every circuit is
+/// open the request fails with a 503 [`ProviderError::ApiError`].
+///
+/// Streaming requests fail over only while the stream is being opened;
+/// errors in the middle of a stream are passed through, and count against
+/// the provider's circuit breaker like failures to open one.
+pub struct FallbackProvider {
+    backends: Vec<Backend>,
+    failure_threshold: u32,
+    cooldown: Duration,
+    retries: u32,
+}
+
+impl FallbackProvider {
+    /// Creates a chain whose first provider is `primary`.
+    ///
+    /// The primary receives requests unchanged. Circuit breakers use the
+    /// defaults of [`CircuitBreakerConfig`].
+    pub fn new(primary: Box<dyn Provider>) -> Self {
+        let defaults = CircuitBreakerConfig::default();
+        let mut chain = Self {
+            backends: Vec::new(),
+            failure_threshold: defaults.failure_threshold,
+            cooldown: Duration::from_secs(defaults.cooldown_seconds),
+            retries: 0,
+        };
+        chain.push(primary, None);
+        chain
+    }
+
+    /// Appends a provider to the chain.
+    ///
+    /// If `model` is set, requests sent to this provider use it instead of
+    /// the requested model.
+    pub fn with_fallback(mut self, provider: Box<dyn Provider>, model: Option<String>) -> Self {
+        self.push(provider, model);
+        self
+    }
+
+    /// Sets the circuit breaker parameters of every provider in the chain.
+    pub fn with_circuit_breaker(mut self, failure_threshold: u32, cooldown: Duration) -> Self {
+        self.failure_threshold = failure_threshold;
+        self.cooldown = cooldown;
+        for backend in &mut self.backends {
+            backend.breaker = Arc::new(CircuitBreaker::new(failure_threshold, cooldown));
+        }
+        self
+    }
+
+    /// Retries each provider up to `retries` times with backoff before
+    /// moving on (see [`RetryableProvider`]). Defaults to 0.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Returns the circuit state of the provider called `name`.
+    pub fn circuit_state(&self, name: &str) -> Option<CircuitState> {
+        self.backends
+            .iter()
+            .find(|backend| backend.provider.name() == name)
+            .map(|backend| backend.breaker.state())
+    }
+
+    fn push(&mut self, provider: Box<dyn Provider>, model: Option<String>) {
+        self.backends.push(Backend {
+            provider,
+            model,
+            breaker: Arc::new(CircuitBreaker::new(self.failure_threshold, self.cooldown)),
+        });
+    }
+
+    fn primary(&self) -> &dyn Provider {
+        self.backends[0].provider.as_ref()
+    }
+
+    /// Records a failed attempt; returns the error if it should not fail over.
+    fn on_error(&self, backend: &Backend, error: ProviderError) -> Result<ProviderError, ProviderError> {
+        if !error.is_retryable() {
+            backend.breaker.record_success();
+            return Err(error);
+        }
+        backend.breaker.record_failure();
+        warn!("{} failed, falling back: {}", backend.provider.name(), error);
+        Ok(error)
+    }
+
+    fn exhausted(&self, last_error: Option<ProviderError>) -> ProviderError {
+        last_error.unwrap_or_else(|| ProviderError::ApiError {
+            status: 503,
+            message: format!(
+                "All providers unavailable: circuit open for {}",
+                self.backends
+                    .iter()
+                    .map(|backend| backend.provider.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        })
+    }
+}
+
+/// Follows a stream opened on one provider of the chain
+///
+/// The outcome is recorded with the provider's circuit breaker once the
+/// stream ends or fails, and the finish event is tagged with [`SERVED_BY`].
+struct StreamTracker {
+    breaker: Arc<CircuitBreaker>,
+    served_by: String,
+    finished: bool,
+    tool_calls: bool,
+    settled: bool,
+}
+
+impl StreamTracker {
+    fn observe(&mut self, event: Result<StreamEvent, ProviderError>) -> Result<StreamEvent, ProviderError> {
+        match event {
+            Ok(mut event) => {
+                match &mut event.kind {
+                    StreamEventKind::Finish { metadata, .. } => {
+                        metadata.insert(SERVED_BY.to_string(), self.served_by.clone());
+                        self.finished = true;
+                    }
+                    StreamEventKind::ToolCallDelta(_) => self.tool_calls = true,
+                    _ => {}
+                }
+                Ok(event)
+            }
+            Err(e) => {
+                // As with requests, only retryable errors are the provider's fault
+                self.settle(!e.is_retryable());
+                if e.is_retryable() {
+                    warn!("{} stream failed: {}", self.served_by, e);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Records a stream that ran to its end; returns the finish event to add
+    /// when the provider sent none.
+    fn end(&mut self) -> Option<StreamEvent> {
+        self.settle(true);
+        if self.finished {
+            return None;
+        }
+        let reason = if self.tool_calls { FinishReason::ToolCalls } else { FinishReason::Stop };
+        let mut event = StreamEvent::finish(reason);
+        if let StreamEventKind::Finish { metadata, .. } = &mut event.kind {
+            metadata.insert(SERVED_BY.to_string(), self.served_by.clone());
+        }
+        Some(event)
+    }
+
+    fn settle(&mut self, success: bool) {
+        if std::mem::replace(&mut self.settled, true) {
+            return;
+        }
+        if success {
+            self.breaker.record_success();
+        } else {
+            self.breaker.record_failure();
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for FallbackProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        let mut last_error = None;
+
+        for backend in &self.backends {
+            if !backend.breaker.try_acquire() {
+                debug!("Skipping {}: circuit open", backend.provider.name());
+                continue;
+            }
+
+            match backend.provider.complete_with_retry(backend.request(&request), self.retries).await {
+                Ok(mut response) => {
+                    backend.breaker.record_success();
+                    response
+                        .metadata
+                        .insert(SERVED_BY.to_string(), backend.provider.name().to_string());
+                    return Ok(response);
+                }
+                Err(e) => last_error = Some(self.on_error(backend, e)?),
+            }
+        }
+
+        Err(self.exhausted(last_error))
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+        let mut last_error = None;
+
+        for backend in &self.backends {
+            if !backend.breaker.try_acquire() {
+                debug!("Skipping {}: circuit open", backend.provider.name());
+                continue;
+            }
+
+            match backend.provider.stream(backend.request(&request)).await {
+                Ok(stream) => {
+                    debug!("Streaming from {}", backend.provider.name());
+                    let tracker = StreamTracker {
+                        breaker: Arc::clone(&backend.breaker),
+                        served_by: backend.provider.name().to_string(),
+                        finished: false,
+                        tool_calls: false,
+                        settled: false,
+                    };
+                    return Ok(Box::pin(stream::unfold(
+                        (stream, Some(tracker)),
+                        |(mut stream, tracker)| async move {
+                            let mut tracker = tracker?;
+                            match stream.next().await {
+                                Some(event) => Some((tracker.observe(event), (stream, Some(tracker)))),
+                                None => tracker.end().map(|event| (Ok(event), (stream, None))),
+                            }
+                        },
+                    )));
+                }
+                Err(e) => last_error = Some(self.on_error(backend, e)?),
+            }
+        }
+
+        Err(self.exhausted(last_error))
+    }
+
+    fn supported_models(&self) -> Vec<ModelInfo> {
+        let mut models: Vec<ModelInfo> = Vec::new();
+        for backend in &self.backends {
+            for model in backend.provider.supported_models() {
+                if !models.iter().any(|m| m.id == model.id) {
+                    models.push(model);
+                }
+            }
+        }
+        models
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        let mut models: Vec<ModelInfo> = Vec::new();
+        for backend in &self.backends {
+            for model in backend.provider.list_models().await? {
+                if !models.iter().any(|m| m.id == model.id) {
+                    models.push(model);
+                }
+            }
+        }
+        Ok(models)
+    }
+
+    fn max_context_length(&self, model: &str) -> Option<usize> {
+        self.backends
+            .iter()
+            .find_map(|backend| backend.provider.max_context_length(model))
+    }
+
+    fn name(&self) -> &str {
+        self.primary().name()
+    }
+
+    async fn validate_config(&self) -> Result<(), ProviderError> {
+        for backend in &self.backends {
+            backend.provider.validate_config().await?;
+        }
+        Ok(())
+    }
+
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        self.primary().estimate_tokens(text, model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{FinishReason, TokenUsage};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// How a [`MockProvider`] stream goes once it has opened
+    #[derive(Clone, Copy)]
+    enum StreamScript {
+        /// Content, usage and a finish event
+        Complete,
+        /// Content, then a server error
+        Broken,
+        /// Content only
+        Unfinished,
+    }
+
+    /// Fails with each of `errors` in turn, then succeeds
+    struct MockProvider {
+        name: &'static str,
+        errors: Mutex<Vec<ProviderError>>,
+        calls: Arc<AtomicUsize>,
+        script: StreamScript,
+    }
+
+    impl MockProvider {
+        fn new(name: &'static str, errors: Vec<ProviderError>) -> (Box<dyn Provider>, Arc<AtomicUsize>) {
+            Self::streaming(name, errors, StreamScript::Complete)
+        }
+
+        fn streaming(
+            name: &'static str,
+            errors: Vec<ProviderError>,
+            script: StreamScript,
+        ) -> (Box<dyn Provider>, Arc<AtomicUsize>) {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let provider = Self { name, errors: Mutex::new(errors), calls: Arc::clone(&calls), script };
+            (Box::new(provider), calls)
+        }
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let mut errors = self.errors.lock().unwrap();
+            if !errors.is_empty() {
+                return Err(errors.remove(0));
+            }
+            Ok(CompletionResponse {
+                id: "mock".to_string(),
+                model: request.model,
+                content: self.name.to_string(),
+                usage: TokenUsage::new(1, 1),
+                finish_reason: FinishReason::Stop,
+                created_at: chrono::Utc::now(),
+                tool_calls: Vec::new(),
+                schema_violations: Vec::new(),
+                logprobs: None,
+                prompt_logprobs: None,
+                choices: Vec::new(),
+                metadata: HashMap::new(),
+            })
+        }
+
+        async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+            let response = self.complete(request).await?;
+            let content = Ok(StreamEvent::content(response.content.clone()));
+            match self.script {
+                StreamScript::Complete => Ok(crate::providers::streaming::from_response(&response)),
+                StreamScript::Broken => Ok(Box::pin(stream::iter(vec![content, Err(server_error())]))),
+                StreamScript::Unfinished => Ok(Box::pin(stream::iter(vec![content]))),
+            }
+        }
+
+        fn supported_models(&self) -> Vec<ModelInfo> {
+            Vec::new()
+        }
+
+        fn max_context_length(&self, _model: &str) -> Option<usize> {
+            None
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn validate_config(&self) -> Result<(), ProviderError> {
+            Ok(())
+        }
+
+        fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
+            Ok(text.len() / 4)
+        }
+    }
+
+    fn server_error() -> ProviderError {
+        ProviderError::ApiError { status: 500, message: "boom".to_string() }
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_on_retryable_error() {
+        let (primary, _) = MockProvider::new("primary", vec![server_error()]);
+        let (secondary, _) = MockProvider::new("secondary", Vec::new());
+        let provider = FallbackProvider::new(primary).with_fallback(secondary, Some("backup-model".to_string()));
+
+        let response = provider.complete(CompletionRequest::new("main-model", "Hi")).await.unwrap();
+        assert_eq!(response.content, "secondary");
+        assert_eq!(response.model, "backup-model");
+        assert_eq!(response.metadata.get(SERVED_BY).map(String::as_str), Some("secondary"));
+
+        // The primary recovered
+        let response = provider.complete(CompletionRequest::new("main-model", "Hi")).await.unwrap();
+        assert_eq!(response.metadata.get(SERVED_BY).map(String::as_str), Some("primary"));
+        assert_eq!(response.model, "main-model");
+    }
+
+    #[tokio::test]
+    async fn test_stream_reports_serving_provider() {
+        let (primary, _) = MockProvider::new("primary", vec![server_error()]);
+        let (secondary, _) = MockProvider::new("secondary", Vec::new());
+        let provider = FallbackProvider::new(primary).with_fallback(secondary, None);
+
+        let stream = provider.stream(CompletionRequest::new("model", "Hi")).await.unwrap();
+        let (response, _) = crate::providers::StreamAccumulator::collect("model", stream).await.unwrap();
+        assert_eq!(response.content, "secondary");
+        assert_eq!(response.metadata.get(SERVED_BY).map(String::as_str), Some("secondary"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_without_finish_reports_serving_provider() {
+        let (primary, _) = MockProvider::streaming("primary", Vec::new(), StreamScript::Unfinished);
+        let provider = FallbackProvider::new(primary);
+
+        let stream = provider.stream(CompletionRequest::new("model", "Hi")).await.unwrap();
+        let (response, _) = crate::providers::StreamAccumulator::collect("model", stream).await.unwrap();
+        assert_eq!(response.content, "primary");
+        assert_eq!(response.finish_reason, FinishReason::Stop);
+        assert_eq!(response.metadata.get(SERVED_BY).map(String::as_str), Some("primary"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stream_failures_open_circuit() {
+        let (primary, primary_calls) = MockProvider::streaming("primary", Vec::new(), StreamScript::Broken);
+        let (secondary, _) = MockProvider::new("secondary", Vec::new());
+        let provider = FallbackProvider::new(primary)
+            .with_circuit_breaker(2, Duration::from_secs(30))
+            .with_fallback(secondary, None);
+
+        // Both streams open, then fail part way through
+        for _ in 0..2 {
+            let stream = provider.stream(CompletionRequest::new("model", "Hi")).await.unwrap();
+            let result = crate::providers::StreamAccumulator::collect("model", stream).await;
+            assert!(matches!(result, Err(ProviderError::ApiError { status: 500, .. })));
+        }
+        assert_eq!(provider.circuit_state("primary"), Some(CircuitState::Open));
+
+        let stream = provider.stream(CompletionRequest::new("model", "Hi")).await.unwrap();
+        let (response, _) = crate::providers::StreamAccumulator::collect("model", stream).await.unwrap();
+        assert_eq!(response.metadata.get(SERVED_BY).map(String::as_str), Some("secondary"));
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_non_retryable_error_is_returned() {
+        let (primary, _) = MockProvider::new("primary", vec![ProviderError::InvalidRequest("bad".to_string())]);
+        let (secondary, calls) = MockProvider::new("secondary", Vec::new());
+        let provider = FallbackProvider::new(primary).with_fallback(secondary, None);
+
+        let result = provider.complete(CompletionRequest::new("model", "Hi")).await;
+        assert!(matches!(result, Err(ProviderError::InvalidRequest(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_last_error_when_chain_fails() {
+        let (primary, _) = MockProvider::new("primary", vec![server_error()]);
+        let (secondary, _) = MockProvider::new("secondary", vec![ProviderError::Timeout(Duration::from_secs(1))]);
+        let provider = FallbackProvider::new(primary).with_fallback(secondary, None);
+
+        let result = provider.complete(CompletionRequest::new("model", "Hi")).await;
+        assert!(matches!(result, Err(ProviderError::Timeout(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_circuit_opens_and_probes() {
+        let failures = (0..3).map(|_| server_error()).collect();
+        let (primary, primary_calls) = MockProvider::new("primary", failures);
+        let (secondary, _) = MockProvider::new("secondary", Vec::new());
+        let provider = FallbackProvider::new(primary)
+            .with_circuit_breaker(2, Duration::from_secs(30))
+            .with_fallback(secondary, None);
+
+        for _ in 0..3 {
+            provider.complete(CompletionRequest::new("model", "Hi")).await.unwrap();
+        }
+        // Two failures opened the circuit, so the third request skipped the primary
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 2);
+        assert_eq!(provider.circuit_state("primary"), Some(CircuitState::Open));
+
+        // The probe after the cooldown fails and reopens the circuit
+        tokio::time::advance(Duration::from_secs(30)).await;
+        assert_eq!(provider.circuit_state("primary"), Some(CircuitState::HalfOpen));
+        provider.complete(CompletionRequest::new("model", "Hi")).await.unwrap();
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 3);
+        assert_eq!(provider.circuit_state("primary"), Some(CircuitState::Open));
+
+        // The next probe succeeds and closes it
+        tokio::time::advance(Duration::from_secs(30)).await;
+        let response = provider.complete(CompletionRequest::new("model", "Hi")).await.unwrap();
+        assert_eq!(response.metadata.get(SERVED_BY).map(String::as_str), Some("primary"));
+        assert_eq!(provider.circuit_state("primary"), Some(CircuitState::Closed));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_all_circuits_open() {
+        let (primary, _) = MockProvider::new("primary", vec![server_error()]);
+        let provider = FallbackProvider::new(primary).with_circuit_breaker(1, Duration::from_secs(30));
+
+        assert!(provider.complete(CompletionRequest::new("model", "Hi")).await.is_err());
+        let result = provider.complete(CompletionRequest::new("model", "Hi")).await;
+        assert!(matches!(result, Err(ProviderError::ApiError { status: 503, .. })));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_half_open_allows_single_probe() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(10));
+        breaker.record_failure();
+        assert!(!breaker.try_acquire());
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert!(breaker.try_acquire());
+        assert!(!breaker.try_acquire());
+
+        // A probe that never reported back is replaced after another cooldown
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert!(breaker.try_acquire());
+    }
+}
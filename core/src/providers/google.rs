@@ -6,14 +6,23 @@
 
 //! Google AI (Gemini) provider implementation
 
-use super::{CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use super::multimodal::{self, MultiModalProvider};
+use super::sampling::{self, SamplingSupport};
+use super::streaming::{self, SseDecoder, StreamEvent, ToolCallDelta};
+use super::structured;
+use super::{CompletionChoice, CompletionRequest, CompletionResponse, FinishReason, MessageRole, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage, ToolCall, ToolChoice};
+use crate::multimodal::{ContentPart, MultiModalRequest, MultiModalResponse};
+use crate::tokenizer::Tokenizer;
 use async_trait::async_trait;
-use futures::stream::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::pin::Pin;
+use std::collections::HashMap;
 use std::time::Duration;
 use tracing::{debug, error, warn};
 
+/// Gemini's `generationConfig` takes everything but a logit bias, and a
+/// thinking budget in its `thinkingConfig`
+const GEMINI_SAMPLING: SamplingSupport = SamplingSupport { logit_bias: false, thinking_budget: true, ..SamplingSupport::ALL };
+
 /// Google AI provider configuration
 #[derive(Debug, Clone)]
 pub struct GoogleConfig {
@@ -77,20 +86,70 @@ impl GoogleProvider {
         })
     }
 
+    /// Uses `client` for every request, e.g. one built from transport settings
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
     /// Build request body for Google AI API
     fn build_request_body(&self, request: &CompletionRequest) -> serde_json::Value {
+        let contents: Vec<serde_json::Value> = request
+            .conversation()
+            .into_iter()
+            .map(|m| {
+                let role = match m.role {
+                    MessageRole::Assistant => "model",
+                    _ => "user",
+                };
+                serde_json::json!({
+                    "role": role,
+                    "parts": [{ "text": m.content }]
+                })
+            })
+            .collect();
+
         let mut body = serde_json::json!({
-            "contents": [
-                {
-                    "parts": [
-                        {
-                            "text": request.prompt
-                        }
-                    ]
-                }
-            ]
+            "contents": contents
         });
 
+        if let Some(system) = request.system_prompt() {
+            body["systemInstruction"] = serde_json::json!({
+                "parts": [{ "text": system }]
+            });
+        }
+
+        if !request.tools.is_empty() {
+            let declarations: Vec<serde_json::Value> = request
+                .tools
+                .iter()
+                .map(|t| {
+                    let mut declaration = serde_json::json!({
+                        "name": t.name,
+                        "parameters": t.parameters,
+                    });
+                    if let Some(ref description) = t.description {
+                        declaration["description"] = serde_json::json!(description);
+                    }
+                    declaration
+                })
+                .collect();
+            body["tools"] = serde_json::json!([{ "functionDeclarations": declarations }]);
+        }
+
+        if let Some(ref tool_choice) = request.tool_choice {
+            let config = match tool_choice {
+                ToolChoice::Auto => serde_json::json!({ "mode": "AUTO" }),
+                ToolChoice::None => serde_json::json!({ "mode": "NONE" }),
+                ToolChoice::Required => serde_json::json!({ "mode": "ANY" }),
+                ToolChoice::Tool { name } => serde_json::json!({
+                    "mode": "ANY",
+                    "allowedFunctionNames": [name],
+                }),
+            };
+            body["toolConfig"] = serde_json::json!({ "functionCallingConfig": config });
+        }
+
         // Build generation config
         let mut generation_config = serde_json::Map::new();
 
@@ -110,6 +169,39 @@ impl GoogleProvider {
             generation_config.insert("stopSequences".to_string(), serde_json::json!(stop));
         }
 
+        if let Some(top_k) = request.top_k {
+            generation_config.insert("topK".to_string(), serde_json::json!(top_k));
+        }
+
+        if let Some(seed) = request.seed {
+            generation_config.insert("seed".to_string(), serde_json::json!(seed));
+        }
+
+        if let Some(penalty) = request.presence_penalty {
+            generation_config.insert("presencePenalty".to_string(), serde_json::json!(penalty));
+        }
+
+        if let Some(penalty) = request.frequency_penalty {
+            generation_config.insert("frequencyPenalty".to_string(), serde_json::json!(penalty));
+        }
+
+        if request.completions() > 1 {
+            generation_config.insert("candidateCount".to_string(), serde_json::json!(request.completions()));
+        }
+
+        if let Some(budget) = request.thinking_budget {
+            generation_config.insert("thinkingConfig".to_string(), serde_json::json!({ "thinkingBudget": budget }));
+        }
+
+        if let Some(ref format) = request.response_format {
+            if format.is_json() {
+                generation_config.insert("responseMimeType".to_string(), serde_json::json!("application/json"));
+            }
+            if let Some(schema) = format.schema() {
+                generation_config.insert("responseSchema".to_string(), gemini_schema(schema));
+            }
+        }
+
         if !generation_config.is_empty() {
             body["generationConfig"] = serde_json::Value::Object(generation_config);
         }
@@ -117,8 +209,34 @@ impl GoogleProvider {
         body
     }
 
+    /// Build request body with inline image and audio parts
+    ///
+    /// Gemini only takes media inline, so remote images are downloaded.
+    async fn build_multimodal_body(&self, request: &MultiModalRequest) -> Result<serde_json::Value, ProviderError> {
+        let mut parts = Vec::with_capacity(request.content.parts.len());
+        for part in &request.content.parts {
+            let (mime_type, data) = match part {
+                ContentPart::Text(text) => {
+                    parts.push(serde_json::json!({ "text": text.text }));
+                    continue;
+                }
+                ContentPart::Image(image) => multimodal::inline_image(&image.image).await?,
+                ContentPart::Audio(audio) => multimodal::audio_source(&audio.audio).await?,
+                part => return Err(multimodal::unsupported(self.name(), part)),
+            };
+            parts.push(serde_json::json!({ "inlineData": { "mimeType": mime_type, "data": data } }));
+        }
+
+        let mut body = self.build_request_body(&multimodal::text_request(request));
+        body["contents"] = serde_json::json!([{ "role": "user", "parts": parts }]);
+        Ok(body)
+    }
+
     /// Parse Google AI error response
-    fn parse_error_response(status: u16, text: &str) -> ProviderError {
+    ///
+    /// Shared with the Cloud Speech-to-Text provider, which uses the same
+    /// error body.
+    pub(super) fn parse_error_response(status: u16, text: &str) -> ProviderError {
         #[derive(Deserialize)]
         struct ErrorResponse {
             error: ErrorDetail,
@@ -154,6 +272,96 @@ impl GoogleProvider {
         }
     }
 
+    /// Map Gemini's `finishReason`
+    fn finish_reason(reason: Option<&str>) -> FinishReason {
+        match reason {
+            Some("STOP") | Some("FINISH_REASON_STOP") => FinishReason::Stop,
+            Some("MAX_TOKENS") | Some("FINISH_REASON_MAX_TOKENS") => FinishReason::Length,
+            Some("SAFETY") | Some("FINISH_REASON_SAFETY") => FinishReason::ContentFilter,
+            _ => FinishReason::Stop,
+        }
+    }
+
+    /// Extract the events from one streamed `GenerateContentResponse`
+    ///
+    /// `tool_calls` counts the function calls seen so far, used to number
+    /// them like [`parse_completion_response`](Self::parse_completion_response)
+    /// does. Usage metadata is cumulative, so each report replaces the last.
+    fn stream_chunk_events(chunk: &serde_json::Value, tool_calls: &mut usize) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        let candidate = &chunk["candidates"][0];
+
+        for part in candidate["content"]["parts"].as_array().into_iter().flatten() {
+            if let Some(text) = part["text"].as_str().filter(|text| !text.is_empty()) {
+                events.push(StreamEvent::content(text));
+            }
+            if let Some(call) = part.get("functionCall") {
+                events.push(StreamEvent::tool_call(ToolCallDelta {
+                    index: *tool_calls,
+                    id: Some(format!("call_{}", tool_calls)),
+                    name: call["name"].as_str().map(str::to_string),
+                    arguments: call.get("args").map(|args| args.to_string()).unwrap_or_default(),
+                }));
+                *tool_calls += 1;
+            }
+        }
+
+        if let Some(metadata) = chunk.get("usageMetadata") {
+            let count = |key: &str| metadata[key].as_u64().unwrap_or(0) as usize;
+            events.push(StreamEvent::usage(TokenUsage {
+                prompt_tokens: count("promptTokenCount"),
+                completion_tokens: count("candidatesTokenCount") + count("thoughtsTokenCount"),
+                total_tokens: count("totalTokenCount"),
+                reasoning_tokens: count("thoughtsTokenCount"),
+                cached_tokens: count("cachedContentTokenCount"),
+                cache_write_tokens: 0,
+            }));
+        }
+
+        if let Some(reason) = candidate["finishReason"].as_str() {
+            let reason = if *tool_calls > 0 {
+                FinishReason::ToolCalls
+            } else {
+                Self::finish_reason(Some(reason))
+            };
+            events.push(StreamEvent::finish(reason));
+        }
+
+        events
+    }
+
+    /// Post a non-streaming `generateContent` request body
+    async fn generate_content(&self, model: &str, body: &serde_json::Value) -> Result<CompletionResponse, ProviderError> {
+        let url = format!(
+            "{}/models/{}:generateContent?key={}",
+            self.base_url, model, self.api_key
+        );
+
+        debug!("Sending request to Google AI: {}", url);
+
+        let response = self
+            .client
+            .post(&url)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| ProviderError::NetworkError(e))?;
+
+        let status = response.status().as_u16();
+
+        if !response.status().is_success() {
+            let text = response.text().await
+                .unwrap_or_else(|_| "Failed to read error response".to_string());
+            error!("Google AI API error ({}): {}", status, text);
+            return Err(Self::parse_error_response(status, &text));
+        }
+
+        let text = response.text().await
+            .map_err(|e| ProviderError::NetworkError(e))?;
+
+        self.parse_completion_response(&text)
+    }
+
     /// Parse non-streaming response
     fn parse_completion_response(&self, json: &str) -> Result<CompletionResponse, ProviderError> {
         #[derive(Deserialize)]
@@ -177,7 +385,16 @@ impl GoogleProvider {
 
         #[derive(Deserialize)]
         struct Part {
-            text: String,
+            text: Option<String>,
+            #[serde(rename = "functionCall")]
+            function_call: Option<FunctionCall>,
+        }
+
+        #[derive(Deserialize)]
+        struct FunctionCall {
+            name: String,
+            #[serde(default)]
+            args: serde_json::Value,
         }
 
         #[derive(Deserialize)]
@@ -188,45 +405,59 @@ impl GoogleProvider {
             candidates_token_count: Option<u32>,
             #[serde(rename = "totalTokenCount")]
             total_token_count: Option<u32>,
+            #[serde(rename = "thoughtsTokenCount")]
+            thoughts_token_count: Option<u32>,
+            #[serde(rename = "cachedContentTokenCount")]
+            cached_content_token_count: Option<u32>,
         }
 
         let resp: GoogleResponse = serde_json::from_str(json)
             .map_err(|e| ProviderError::InternalError(format!("Failed to parse response: {}", e)))?;
 
-        let candidate = resp.candidates.first()
-            .ok_or_else(|| ProviderError::ApiError {
-                status: 500,
-                message: "No candidates in response".to_string()
-            })?;
-
-        let content = candidate.content.parts.iter()
-            .map(|p| p.text.as_str())
-            .collect::<Vec<_>>()
-            .join("");
-
-        let finish_reason = match candidate.finish_reason.as_deref() {
-            Some("STOP") | Some("FINISH_REASON_STOP") => FinishReason::Stop,
-            Some("MAX_TOKENS") | Some("FINISH_REASON_MAX_TOKENS") => FinishReason::Length,
-            Some("SAFETY") | Some("FINISH_REASON_SAFETY") => FinishReason::ContentFilter,
-            _ => FinishReason::Stop,
-        };
-
-        // Extract token usage if available
+        let choices = resp.candidates.into_iter().enumerate()
+            .map(|(index, candidate)| {
+                let content = candidate.content.parts.iter()
+                    .filter_map(|p| p.text.as_deref())
+                    .collect::<Vec<_>>()
+                    .join("");
+
+                // Gemini doesn't assign IDs to function calls, so number them
+                let tool_calls: Vec<ToolCall> = candidate.content.parts.into_iter()
+                    .filter_map(|p| p.function_call)
+                    .enumerate()
+                    .map(|(i, call)| ToolCall::new(format!("call_{}", i), call.name, call.args))
+                    .collect();
+
+                // Gemini reports STOP even when it returns function calls
+                let finish_reason = if tool_calls.is_empty() {
+                    Self::finish_reason(candidate.finish_reason.as_deref())
+                } else {
+                    FinishReason::ToolCalls
+                };
+
+                CompletionChoice { index, content, finish_reason, tool_calls }
+            })
+            .collect();
+        let (first, choices) = sampling::split_choices(choices)?;
+        let content = first.content;
+
+        // Extract token usage if available. Thinking tokens are billed as
+        // output but not included in the candidates count
         let usage = if let Some(metadata) = resp.usage_metadata {
+            let thoughts = metadata.thoughts_token_count.unwrap_or(0) as usize;
             TokenUsage {
                 prompt_tokens: metadata.prompt_token_count.unwrap_or(0) as usize,
-                completion_tokens: metadata.candidates_token_count.unwrap_or(0) as usize,
+                completion_tokens: metadata.candidates_token_count.unwrap_or(0) as usize + thoughts,
                 total_tokens: metadata.total_token_count.unwrap_or(0) as usize,
+                reasoning_tokens: thoughts,
+                cached_tokens: metadata.cached_content_token_count.unwrap_or(0) as usize,
+                cache_write_tokens: 0,
             }
         } else {
             // Estimate tokens if not provided
             let prompt_tokens = estimate_tokens(&content);
             let completion_tokens = estimate_tokens(&content);
-            TokenUsage {
-                prompt_tokens,
-                completion_tokens,
-                total_tokens: prompt_tokens + completion_tokens,
-            }
+            TokenUsage::new(prompt_tokens, completion_tokens)
         };
 
         Ok(CompletionResponse {
@@ -234,8 +465,14 @@ impl GoogleProvider {
             content,
             model: "gemini".to_string(),
             usage,
-            finish_reason,
+            finish_reason: first.finish_reason,
             created_at: chrono::Utc::now(),
+            tool_calls: first.tool_calls,
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices,
+            metadata: HashMap::new(),
         })
     }
 }
@@ -243,39 +480,15 @@ impl GoogleProvider {
 #[async_trait]
 impl Provider for GoogleProvider {
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
-        let url = format!(
-            "{}/models/{}:generateContent?key={}",
-            self.base_url, request.model, self.api_key
-        );
-
-        let body = self.build_request_body(&request);
-
-        debug!("Sending request to Google AI: {}", url);
-
-        let response = self
-            .client
-            .post(&url)
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| ProviderError::NetworkError(e))?;
-
-        let status = response.status().as_u16();
-
-        if !response.status().is_success() {
-            let text = response.text().await
-                .unwrap_or_else(|_| "Failed to read error response".to_string());
-            error!("Google AI API error ({}): {}", status, text);
-            return Err(Self::parse_error_response(status, &text));
-        }
-
-        let text = response.text().await
-            .map_err(|e| ProviderError::NetworkError(e))?;
-
-        self.parse_completion_response(&text)
+        GEMINI_SAMPLING.check(self.name(), &request)?;
+        let mut response = self.generate_content(&request.model, &self.build_request_body(&request)).await?;
+        structured::check_response(&request, &mut response);
+        Ok(response)
     }
 
     async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+        GEMINI_SAMPLING.check_stream(self.name(), &request)?;
+
         let url = format!(
             "{}/models/{}:streamGenerateContent?key={}&alt=sse",
             self.base_url, request.model, self.api_key
@@ -301,51 +514,19 @@ impl Provider for GoogleProvider {
             return Err(Self::parse_error_response(status, &text));
         }
 
-        let stream = response.bytes_stream();
-
-        let text_stream = stream.map(move |result| {
-            match result {
-                Ok(bytes) => {
-                    let text = String::from_utf8_lossy(&bytes);
-                    // Parse SSE format
-                    for line in text.lines() {
-                        if line.starts_with("data: ") {
-                            let json_str = line.strip_prefix("data: ").unwrap_or("");
-                            if json_str.trim().is_empty() || json_str == "[DONE]" {
-                                continue;
-                            }
-
-                            // Extract text from response
-                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(json_str) {
-                                if let Some(candidates) = json.get("candidates").and_then(|c| c.as_array()) {
-                                    if let Some(candidate) = candidates.first() {
-                                        if let Some(parts) = candidate.get("content")
-                                            .and_then(|c| c.get("parts"))
-                                            .and_then(|p| p.as_array()) {
-                                            if let Some(text) = parts.first().and_then(|p| p.get("text")).and_then(|t| t.as_str()) {
-                                                return Ok(text.to_string());
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Ok(String::new())
-                }
-                Err(e) => Err(ProviderError::NetworkError(e)),
-            }
-        });
-
-        Ok(Box::pin(text_stream))
+        let mut tool_calls = 0;
+        Ok(streaming::decode_stream(response.bytes_stream(), SseDecoder::default(), move |event| {
+            let chunk: serde_json::Value = serde_json::from_str(&event.data)?;
+            Ok(Self::stream_chunk_events(&chunk, &mut tool_calls))
+        }))
     }
 
     fn supported_models(&self) -> Vec<ModelInfo> {
         vec![
             ModelInfo::new("gemini-pro", "Gemini Pro", 30720, true, true),
-            ModelInfo::new("gemini-pro-vision", "Gemini Pro Vision", 30720, true, false),
-            ModelInfo::new("gemini-1.5-pro", "Gemini 1.5 Pro", 1048576, true, true),
-            ModelInfo::new("gemini-1.5-flash", "Gemini 1.5 Flash", 1048576, true, true),
+            ModelInfo::new("gemini-pro-vision", "Gemini Pro Vision", 30720, true, false).with_vision(),
+            ModelInfo::new("gemini-1.5-pro", "Gemini 1.5 Pro", 1048576, true, true).with_vision().with_audio(),
+            ModelInfo::new("gemini-1.5-flash", "Gemini 1.5 Flash", 1048576, true, true).with_vision().with_audio(),
             ModelInfo::new("gemini-ultra", "Gemini Ultra", 30720, true, true),
         ]
     }
@@ -381,14 +562,57 @@ impl Provider for GoogleProvider {
         }
     }
 
-    fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
-        Ok(estimate_tokens(text))
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        Ok(Tokenizer::for_model(model).count_tokens(text))
+    }
+}
+
+#[async_trait]
+impl MultiModalProvider for GoogleProvider {
+    async fn complete_multimodal(&self, request: MultiModalRequest) -> Result<MultiModalResponse, ProviderError> {
+        let body = self.build_multimodal_body(&request).await?;
+        self.generate_content(&request.model, &body).await.map(Into::into)
     }
 }
 
-/// Simple token estimation (roughly 4 chars per token)
+/// Converts a JSON Schema to the OpenAPI subset accepted by `responseSchema`,
+/// dropping keywords Gemini rejects (e.g. `additionalProperties`, `$schema`)
+fn gemini_schema(schema: &serde_json::Value) -> serde_json::Value {
+    const SUPPORTED: &[&str] = &[
+        "type", "format", "title", "description", "nullable", "enum", "default", "example",
+        "properties", "required", "propertyOrdering", "minProperties", "maxProperties",
+        "items", "minItems", "maxItems", "minLength", "maxLength", "pattern",
+        "minimum", "maximum", "anyOf",
+    ];
+
+    let Some(object) = schema.as_object() else {
+        return schema.clone();
+    };
+
+    let converted = object
+        .iter()
+        .filter(|(key, _)| SUPPORTED.contains(&key.as_str()))
+        .map(|(key, value)| {
+            let value = match (key.as_str(), value) {
+                ("properties", serde_json::Value::Object(properties)) => serde_json::Value::Object(
+                    properties.iter().map(|(name, s)| (name.clone(), gemini_schema(s))).collect(),
+                ),
+                ("anyOf", serde_json::Value::Array(variants)) => {
+                    serde_json::Value::Array(variants.iter().map(gemini_schema).collect())
+                }
+                ("items", items) => gemini_schema(items),
+                _ => value.clone(),
+            };
+            (key.clone(), value)
+        })
+        .collect();
+
+    serde_json::Value::Object(converted)
+}
+
+/// Heuristic token estimate for responses without usage metadata
 fn estimate_tokens(text: &str) -> usize {
-    (text.len() / 4).max(1)
+    Tokenizer::heuristic().count_tokens(text).max(1)
 }
 
 #[cfg(test)]
@@ -449,4 +673,23 @@ mod tests {
         assert!(body.get("contents").is_some());
         assert!(body.get("generationConfig").is_some());
     }
+
+    #[test]
+    fn test_build_request_body_with_system_and_history() {
+        let provider = GoogleProvider::new("test-key".to_string()).unwrap();
+        let request = CompletionRequest::new("gemini-pro", "Next?")
+            .with_system("Count upwards.")
+            .with_message(crate::providers::ChatMessage::user("One"))
+            .with_message(crate::providers::ChatMessage::assistant("Two"));
+
+        let body = provider.build_request_body(&request);
+
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "Count upwards.");
+        let contents = body["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[0]["role"], "user");
+        assert_eq!(contents[1]["role"], "model");
+        assert_eq!(contents[1]["parts"][0]["text"], "Two");
+        assert_eq!(contents[2]["parts"][0]["text"], "Next?");
+    }
 }
@@ -6,13 +6,27 @@
 
 //! Groq provider implementation (OpenAI-compatible fast inference)
 
+use super::discovery;
+use super::sampling::{self, SamplingSupport};
+use super::streaming;
+use super::structured::{self, openai_response_format};
+use super::tools::{openai_tool_choice, openai_tools, OpenAIToolCall};
 use super::{CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use crate::tokenizer::Tokenizer;
 use async_trait::async_trait;
-use futures::stream::StreamExt;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::time::Duration;
 use tracing::{debug, error};
 
+/// Groq takes a seed and penalties, but only one completion and no logit bias
+const GROQ_SAMPLING: SamplingSupport = SamplingSupport {
+    seed: true,
+    presence_penalty: true,
+    frequency_penalty: true,
+    ..SamplingSupport::NONE
+};
+
 /// Groq provider for fast LLM inference
 pub struct GroqProvider {
     client: reqwest::Client,
@@ -39,10 +53,16 @@ impl GroqProvider {
         Ok(Self { client, api_key, base_url })
     }
 
+    /// Uses `client` for every request, e.g. one built from transport settings
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
     fn build_request_body(&self, request: &CompletionRequest, stream: bool) -> serde_json::Value {
         let mut body = serde_json::json!({
             "model": request.model,
-            "messages": [{"role": "user", "content": request.prompt}],
+            "messages": request.chat_messages(),
             "stream": stream,
         });
 
@@ -55,6 +75,18 @@ impl GroqProvider {
         if let Some(top_p) = request.top_p {
             body["top_p"] = serde_json::json!(top_p);
         }
+        if !request.tools.is_empty() {
+            body["tools"] = openai_tools(&request.tools);
+        }
+        if let Some(ref tool_choice) = request.tool_choice {
+            body["tool_choice"] = openai_tool_choice(tool_choice, "required");
+        }
+
+        if let Some(ref format) = request.response_format {
+            body["response_format"] = openai_response_format(format);
+        }
+
+        sampling::apply_openai_params(request, &mut body);
 
         body
     }
@@ -63,6 +95,8 @@ impl GroqProvider {
 #[async_trait]
 impl Provider for GroqProvider {
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        GROQ_SAMPLING.check(self.name(), &request)?;
+
         let url = format!("{}/chat/completions", self.base_url);
         let body = self.build_request_body(&request, false);
 
@@ -101,7 +135,9 @@ impl Provider for GroqProvider {
 
         #[derive(Deserialize)]
         struct Message {
-            content: String,
+            content: Option<String>,
+            #[serde(default)]
+            tool_calls: Vec<OpenAIToolCall>,
         }
 
         #[derive(Deserialize)]
@@ -112,28 +148,42 @@ impl Provider for GroqProvider {
         }
 
         let resp: GroqResponse = serde_json::from_str(&text)?;
-        let choice = resp.choices.first()
+        let choice = resp.choices.into_iter().next()
             .ok_or_else(|| ProviderError::ApiError { status: 500, message: "No choices".to_string() })?;
 
-        Ok(CompletionResponse {
+        let mut response = CompletionResponse {
             id: resp.id,
-            content: choice.message.content.clone(),
+            content: choice.message.content.unwrap_or_default(),
             model: resp.model,
             usage: TokenUsage {
                 prompt_tokens: resp.usage.prompt_tokens as usize,
                 completion_tokens: resp.usage.completion_tokens as usize,
                 total_tokens: resp.usage.total_tokens as usize,
+                reasoning_tokens: 0,
+                cached_tokens: 0,
+                cache_write_tokens: 0,
             },
             finish_reason: match choice.finish_reason.as_str() {
                 "stop" => FinishReason::Stop,
                 "length" => FinishReason::Length,
+                "tool_calls" => FinishReason::ToolCalls,
                 _ => FinishReason::Stop,
             },
             created_at: chrono::Utc::now(),
-        })
+            tool_calls: choice.message.tool_calls.into_iter().map(Into::into).collect(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
+        };
+        structured::check_response(&request, &mut response);
+        Ok(response)
     }
 
     async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+        GROQ_SAMPLING.check_stream(self.name(), &request)?;
+
         let url = format!("{}/chat/completions", self.base_url);
         let body = self.build_request_body(&request, true);
 
@@ -151,30 +201,7 @@ impl Provider for GroqProvider {
             return Err(ProviderError::ApiError { status, message: text });
         }
 
-        let stream = response.bytes_stream().map(move |result| {
-            match result {
-                Ok(bytes) => {
-                    let text = String::from_utf8_lossy(&bytes);
-                    for line in text.lines() {
-                        if line.starts_with("data: ") {
-                            let json_str = line.strip_prefix("data: ").unwrap_or("");
-                            if json_str == "[DONE]" {
-                                continue;
-                            }
-                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(json_str) {
-                                if let Some(content) = json["choices"][0]["delta"]["content"].as_str() {
-                                    return Ok(content.to_string());
-                                }
-                            }
-                        }
-                    }
-                    Ok(String::new())
-                }
-                Err(e) => Err(ProviderError::NetworkError(e)),
-            }
-        });
-
-        Ok(Box::pin(stream))
+        Ok(streaming::openai_stream(response))
     }
 
     fn supported_models(&self) -> Vec<ModelInfo> {
@@ -186,6 +213,13 @@ impl Provider for GroqProvider {
         ]
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        let request = self.client
+            .get(format!("{}/models", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key));
+        Ok(discovery::parse_openai_models(&discovery::fetch_json(request).await?))
+    }
+
     fn max_context_length(&self, model: &str) -> Option<usize> {
         match model {
             "llama3-8b-8192" | "llama3-70b-8192" | "gemma-7b-it" => Some(8192),
@@ -205,7 +239,7 @@ impl Provider for GroqProvider {
         Ok(())
     }
 
-    fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
-        Ok((text.len() / 4).max(1))
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        Ok(Tokenizer::for_model(model).count_tokens(text))
     }
 }
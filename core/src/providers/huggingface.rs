@@ -6,13 +6,21 @@
 
 //! Hugging Face Inference API provider implementation
 
+use super::embeddings::{self, EmbeddingModel, EmbeddingProvider, EmbeddingRequest, EmbeddingResponse};
+use super::sampling::SamplingSupport;
+use super::streaming::{self, SseDecoder, StreamEvent};
+use super::structured;
 use super::{CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use crate::tokenizer::Tokenizer;
 use async_trait::async_trait;
-use futures::stream::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::Duration;
 use tracing::{debug, error};
 
+/// Text generation takes a seed and `top_k`, and returns one completion
+const HUGGINGFACE_SAMPLING: SamplingSupport = SamplingSupport { seed: true, top_k: true, ..SamplingSupport::NONE };
+
 /// Hugging Face provider
 pub struct HuggingFaceProvider {
     client: reqwest::Client,
@@ -39,6 +47,12 @@ impl HuggingFaceProvider {
         Ok(Self { client, api_key, base_url })
     }
 
+    /// Uses `client` for every request, e.g. one built from transport settings
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
     fn build_request_body(&self, request: &CompletionRequest) -> serde_json::Value {
         let mut params = serde_json::Map::new();
 
@@ -51,26 +65,37 @@ impl HuggingFaceProvider {
         if let Some(top_p) = request.top_p {
             params.insert("top_p".to_string(), serde_json::json!(top_p));
         }
+        if let Some(top_k) = request.top_k {
+            params.insert("top_k".to_string(), serde_json::json!(top_k));
+        }
+        if let Some(seed) = request.seed {
+            params.insert("seed".to_string(), serde_json::json!(seed));
+        }
 
         serde_json::json!({
-            "inputs": request.prompt,
+            "inputs": Self::build_inputs(request),
             "parameters": params,
         })
     }
-}
 
-#[async_trait]
-impl Provider for HuggingFaceProvider {
-    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
-        let url = format!("{}/{}", self.base_url, request.model);
-        let body = self.build_request_body(&request);
+    /// Flattens the system prompt and conversation into the raw text input
+    fn build_inputs(request: &CompletionRequest) -> String {
+        match request.system_prompt() {
+            Some(system) => format!("{}\n\n{}", system, request.render_prompt()),
+            None => request.render_prompt(),
+        }
+    }
+
+    /// Sends a text generation request to the model's endpoint
+    async fn generate(&self, model: &str, body: &serde_json::Value) -> Result<reqwest::Response, ProviderError> {
+        let url = format!("{}/{}", self.base_url, model);
 
         debug!("Sending request to Hugging Face");
 
         let response = self.client
             .post(&url)
             .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&body)
+            .json(body)
             .send()
             .await
             .map_err(|e| ProviderError::NetworkError(e))?;
@@ -82,6 +107,51 @@ impl Provider for HuggingFaceProvider {
             return Err(ProviderError::ApiError { status, message: text });
         }
 
+        Ok(response)
+    }
+
+    /// Maps a TGI stream event to stream events
+    ///
+    /// Each event carries one token; the last one also carries `details`
+    /// with the finish reason and the number of generated tokens. `tokens`
+    /// counts the tokens seen so far, for servers that omit `details`.
+    fn stream_chunk_events(
+        chunk: &serde_json::Value,
+        prompt_tokens: usize,
+        tokens: &mut usize,
+    ) -> Result<Vec<StreamEvent>, ProviderError> {
+        if let Some(message) = chunk["error"].as_str() {
+            return Err(ProviderError::ApiError { status: 500, message: message.to_string() });
+        }
+
+        let mut events = Vec::new();
+        let token = &chunk["token"];
+        if !token.is_null() {
+            *tokens += 1;
+        }
+        // Special tokens such as `</s>` are not part of the text
+        if let Some(text) = token["text"].as_str().filter(|text| !text.is_empty()) {
+            if token["special"].as_bool() != Some(true) {
+                events.push(StreamEvent::content(text));
+            }
+        }
+
+        let details = &chunk["details"];
+        if !details.is_null() {
+            let completion_tokens = details["generated_tokens"].as_u64().map_or(*tokens, |n| n as usize);
+            events.push(StreamEvent::usage(TokenUsage::new(prompt_tokens, completion_tokens)));
+            events.push(StreamEvent::finish(match details["finish_reason"].as_str() {
+                Some("length") => FinishReason::Length,
+                _ => FinishReason::Stop,
+            }));
+        }
+        Ok(events)
+    }
+
+    /// Run a single completion request
+    async fn complete_once(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        let response = self.generate(&request.model, &self.build_request_body(&request)).await?;
+
         let text = response.text().await.map_err(|e| ProviderError::NetworkError(e))?;
 
         // HF API returns array of results
@@ -97,14 +167,16 @@ impl Provider for HuggingFaceProvider {
             .ok_or_else(|| ProviderError::ApiError { status: 500, message: "No results".to_string() })?;
 
         // Remove the prompt from the generated text if it's included
+        let inputs = Self::build_inputs(&request);
         let content = result.generated_text
-            .strip_prefix(&request.prompt)
+            .strip_prefix(&inputs)
             .unwrap_or(&result.generated_text)
             .to_string();
 
         // Estimate tokens (HF doesn't always provide usage)
-        let prompt_tokens = (request.prompt.len() / 4).max(1);
-        let completion_tokens = (content.len() / 4).max(1);
+        let tokenizer = Tokenizer::for_model(&request.model);
+        let prompt_tokens = tokenizer.count_tokens(&inputs).max(1);
+        let completion_tokens = tokenizer.count_tokens(&content).max(1);
 
         Ok(CompletionResponse {
             id: format!("hf-{}", chrono::Utc::now().timestamp()),
@@ -114,23 +186,48 @@ impl Provider for HuggingFaceProvider {
                 prompt_tokens,
                 completion_tokens,
                 total_tokens: prompt_tokens + completion_tokens,
+                reasoning_tokens: 0,
+                cached_tokens: 0,
+                cache_write_tokens: 0,
             },
             finish_reason: FinishReason::Stop,
             created_at: chrono::Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         })
     }
+}
+
+#[async_trait]
+impl Provider for HuggingFaceProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        HUGGINGFACE_SAMPLING.check(self.name(), &request)?;
+
+        // The Inference API has no JSON mode, so response formats are enforced
+        // via the prompt
+        structured::complete_with_repair(request, |request| self.complete_once(request)).await
+    }
 
     async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
-        // Hugging Face Inference API doesn't support streaming in the same way
-        // We'll return a single-item stream
-        let response = self.complete(request).await?;
-        let content = response.content.clone();
+        HUGGINGFACE_SAMPLING.check_stream(self.name(), &request)?;
 
-        let stream = futures::stream::once(async move {
-            Ok(content)
-        });
+        // Text Generation Inference streams one token per server-sent event
+        let request = structured::with_format_instructions(&request);
+        let mut body = self.build_request_body(&request);
+        body["stream"] = serde_json::json!(true);
+        let response = self.generate(&request.model, &body).await?;
 
-        Ok(Box::pin(stream))
+        // The API does not report prompt tokens, so they are estimated
+        let prompt_tokens = Tokenizer::for_model(&request.model).count_tokens(&Self::build_inputs(&request)).max(1);
+        let mut tokens = 0;
+        Ok(streaming::decode_stream(response.bytes_stream(), SseDecoder::default(), move |event| {
+            let chunk: serde_json::Value = serde_json::from_str(&event.data)?;
+            Self::stream_chunk_events(&chunk, prompt_tokens, &mut tokens)
+        }))
     }
 
     fn supported_models(&self) -> Vec<ModelInfo> {
@@ -169,7 +266,90 @@ impl Provider for HuggingFaceProvider {
         Ok(())
     }
 
-    fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
-        Ok((text.len() / 4).max(1))
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        Ok(Tokenizer::for_model(model).count_tokens(text))
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for HuggingFaceProvider {
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, ProviderError> {
+        embeddings::require_native_dimensions("huggingface", &request)?;
+
+        // Feature extraction runs on the model's own endpoint
+        let url = format!("{}/{}", self.base_url, request.model);
+        let body = serde_json::json!({
+            "inputs": request.inputs,
+            "options": { "wait_for_model": true },
+        });
+
+        debug!("Sending embedding request to Hugging Face");
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| ProviderError::NetworkError(e))?;
+
+        let status = response.status().as_u16();
+        if !response.status().is_success() {
+            let text = response.text().await.unwrap_or_default();
+            error!("Hugging Face API error ({}): {}", status, text);
+            return Err(ProviderError::ApiError { status, message: text });
+        }
+
+        let text = response.text().await.map_err(|e| ProviderError::NetworkError(e))?;
+
+        // Sentence-transformers models return one pooled vector per input;
+        // plain encoders return one vector per token, which are mean-pooled
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Features {
+            Pooled(Vec<Vec<f32>>),
+            Tokens(Vec<Vec<Vec<f32>>>),
+        }
+
+        let embeddings = match serde_json::from_str(&text)
+            .map_err(|e| ProviderError::InternalError(format!("Failed to parse response: {}", e)))?
+        {
+            Features::Pooled(vectors) => vectors,
+            Features::Tokens(inputs) => inputs.iter().map(|tokens| mean_pool(tokens)).collect(),
+        };
+
+        // The Inference API does not report usage
+        Ok(EmbeddingResponse {
+            model: request.model.clone(),
+            embeddings,
+            usage: embeddings::estimate_usage(&request),
+        })
+    }
+
+    fn embedding_models(&self) -> Vec<EmbeddingModel> {
+        vec![
+            EmbeddingModel::new("sentence-transformers/all-MiniLM-L6-v2", 384, 256),
+            EmbeddingModel::new("sentence-transformers/all-mpnet-base-v2", 768, 384),
+            EmbeddingModel::new("BAAI/bge-small-en-v1.5", 384, 512),
+            EmbeddingModel::new("BAAI/bge-large-en-v1.5", 1024, 512),
+        ]
+    }
+}
+
+/// Averages per-token vectors into one vector
+fn mean_pool(tokens: &[Vec<f32>]) -> Vec<f32> {
+    let Some(first) = tokens.first() else {
+        return Vec::new();
+    };
+
+    let mut pooled = vec![0.0; first.len()];
+    for token in tokens {
+        for (sum, value) in pooled.iter_mut().zip(token) {
+            *sum += value;
+        }
+    }
+    for sum in &mut pooled {
+        *sum /= tokens.len() as f32;
     }
+    pooled
 }
@@ -0,0 +1,225 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Token log probabilities.
+//!
+//! Requested with [`CompletionRequest::with_logprobs`](super::CompletionRequest::with_logprobs)
+//! and returned per token on the [`CompletionResponse`](super::CompletionResponse).
+//! Supported by OpenAI and OpenAI-compatible servers such as vLLM, Together
+//! AI and Ollama; other providers leave the response fields empty.
+//!
+//! Scoring the prompt itself (needed for true perplexity of a given text)
+//! requires an endpoint that can echo the prompt back: the OpenAI-style
+//! `/completions` endpoint (vLLM, legacy OpenAI models) or Together AI.
+//!
+//! # Examples
+//!
+//! ```
+//! use llm_test_bench_core::providers::{logprobs, CompletionRequest, TokenLogprob};
+//!
+//! let request = CompletionRequest::new("gpt-4o", "The cat sat on the").with_logprobs(5);
+//! assert_eq!(request.logprobs.unwrap().top_logprobs, 5);
+//!
+//! let tokens = vec![TokenLogprob::new(" mat", -0.5), TokenLogprob::new(".", -1.5)];
+//! let ppl = logprobs::perplexity(&tokens).unwrap();
+//! assert!((ppl - 1.0f64.exp()).abs() < 1e-9);
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Log probabilities to return with a completion.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogprobOptions {
+    /// Number of most likely alternatives to return at each position (0 for
+    /// none). OpenAI allows up to 20; the legacy completions endpoint up to 5.
+    #[serde(default)]
+    pub top_logprobs: usize,
+
+    /// Also return log probabilities for the prompt's tokens.
+    #[serde(default)]
+    pub include_prompt: bool,
+}
+
+/// Log probability of a single token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenLogprob {
+    /// The token text.
+    pub token: String,
+
+    /// Natural log of the token's probability.
+    pub logprob: f64,
+
+    /// Most likely alternatives at this position, most likely first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty", deserialize_with = "null_as_empty")]
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+impl TokenLogprob {
+    /// Creates a token log probability without alternatives.
+    pub fn new(token: impl Into<String>, logprob: f64) -> Self {
+        Self {
+            token: token.into(),
+            logprob,
+            top_logprobs: Vec::new(),
+        }
+    }
+
+    /// The token's probability (`exp(logprob)`).
+    pub fn probability(&self) -> f64 {
+        self.logprob.exp()
+    }
+}
+
+/// An alternative token considered at a position.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopLogprob {
+    /// The token text.
+    pub token: String,
+
+    /// Natural log of the token's probability.
+    pub logprob: f64,
+}
+
+/// Computes the perplexity of a token sequence.
+///
+/// `PPL = exp(-1/N * sum(logprob))`. Returns `None` for an empty sequence.
+pub fn perplexity(tokens: &[TokenLogprob]) -> Option<f64> {
+    if tokens.is_empty() {
+        return None;
+    }
+
+    let sum: f64 = tokens.iter().map(|t| t.logprob).sum();
+    Some((-sum / tokens.len() as f64).exp())
+}
+
+fn null_as_empty<'de, D>(deserializer: D) -> Result<Vec<TopLogprob>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<Vec<TopLogprob>>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Parses the log probabilities of an OpenAI-compatible response.
+///
+/// Accepts the chat format (`{"content": [{"token", "logprob", "top_logprobs"}]}`),
+/// a bare array of such entries (Ollama), and the legacy completions format
+/// (`{"tokens": [...], "token_logprobs": [...], "top_logprobs": [{token: logprob}]}`)
+/// also used by Together AI. Tokens without a log probability, such as the
+/// first token of an echoed prompt, are skipped.
+pub(crate) fn parse(value: &Value) -> Option<Vec<TokenLogprob>> {
+    match value {
+        Value::Array(_) => serde_json::from_value(value.clone()).ok(),
+        Value::Object(object) if object.contains_key("content") => parse(&object["content"]),
+        Value::Object(object) if object.contains_key("tokens") => Some(
+            parse_legacy(value)
+                .into_iter()
+                .filter_map(|(_, token)| token)
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Parses the legacy completions format, keeping each token's `text_offset`
+/// (0 when absent) and leaving unscored tokens as `None`.
+pub(crate) fn parse_legacy(value: &Value) -> Vec<(usize, Option<TokenLogprob>)> {
+    let tokens = value["tokens"].as_array().map(Vec::as_slice).unwrap_or_default();
+
+    tokens
+        .iter()
+        .enumerate()
+        .map(|(i, token)| {
+            let offset = value["text_offset"][i].as_u64().unwrap_or(0) as usize;
+            let scored = token.as_str().zip(value["token_logprobs"][i].as_f64()).map(|(token, logprob)| {
+                let mut top_logprobs: Vec<TopLogprob> = value["top_logprobs"][i]
+                    .as_object()
+                    .map(|alternatives| {
+                        alternatives
+                            .iter()
+                            .filter_map(|(token, logprob)| {
+                                Some(TopLogprob { token: token.clone(), logprob: logprob.as_f64()? })
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                top_logprobs.sort_by(|a, b| b.logprob.total_cmp(&a.logprob));
+
+                TokenLogprob { token: token.to_string(), logprob, top_logprobs }
+            });
+            (offset, scored)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_chat_format() {
+        let value = json!({
+            "content": [
+                {
+                    "token": "Hello",
+                    "logprob": -0.1,
+                    "bytes": [72, 101, 108, 108, 111],
+                    "top_logprobs": [
+                        { "token": "Hello", "logprob": -0.1, "bytes": null },
+                        { "token": "Hi", "logprob": -2.4, "bytes": null }
+                    ]
+                },
+                { "token": "!", "logprob": -0.7, "top_logprobs": [] }
+            ]
+        });
+
+        let tokens = parse(&value).unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].token, "Hello");
+        assert_eq!(tokens[0].top_logprobs[1], TopLogprob { token: "Hi".to_string(), logprob: -2.4 });
+        assert!(tokens[1].top_logprobs.is_empty());
+    }
+
+    #[test]
+    fn test_parse_legacy_format() {
+        let value = json!({
+            "tokens": ["The", " cat", " sat"],
+            "token_logprobs": [null, -3.2, -1.1],
+            "top_logprobs": [null, { " dog": -2.9, " cat": -3.2 }, null],
+            "text_offset": [0, 3, 7]
+        });
+
+        let tokens = parse(&value).unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].token, " cat");
+        assert_eq!(tokens[0].top_logprobs[0].token, " dog");
+        assert_eq!(tokens[1].logprob, -1.1);
+
+        let with_offsets = parse_legacy(&value);
+        assert_eq!(with_offsets[2].0, 7);
+        assert!(with_offsets[0].1.is_none());
+    }
+
+    #[test]
+    fn test_parse_unknown_format() {
+        assert!(parse(&Value::Null).is_none());
+        assert!(parse(&json!({ "unexpected": true })).is_none());
+    }
+
+    #[test]
+    fn test_perplexity() {
+        assert_eq!(perplexity(&[]), None);
+
+        // Every token certain: perplexity 1
+        let certain = vec![TokenLogprob::new("a", 0.0); 3];
+        assert_eq!(perplexity(&certain), Some(1.0));
+
+        // Uniform over four choices: perplexity 4
+        let uniform = vec![TokenLogprob::new("a", 0.25f64.ln()); 8];
+        assert!((perplexity(&uniform).unwrap() - 4.0).abs() < 1e-9);
+    }
+}
@@ -6,13 +6,23 @@
 
 //! Mistral AI provider implementation
 
-use super::{CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use super::discovery;
+use super::embeddings::{self, EmbeddingModel, EmbeddingProvider, EmbeddingRequest, EmbeddingResponse};
+use super::sampling::{self, SamplingSupport};
+use super::streaming;
+use super::structured::{self, openai_response_format};
+use super::tools::{openai_tool_choice, openai_tools, OpenAIToolCall};
+use super::{CompletionChoice, CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use crate::tokenizer::Tokenizer;
 use async_trait::async_trait;
-use futures::stream::StreamExt;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::time::Duration;
 use tracing::{debug, error, warn};
 
+/// Mistral calls the seed `random_seed` and has no `top_k` or logit bias
+const MISTRAL_SAMPLING: SamplingSupport = SamplingSupport { top_k: false, logit_bias: false, ..SamplingSupport::ALL };
+
 /// Mistral AI provider (OpenAI-compatible API)
 pub struct MistralProvider {
     client: reqwest::Client,
@@ -43,10 +53,16 @@ impl MistralProvider {
         })
     }
 
+    /// Uses `client` for every request, e.g. one built from transport settings
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
     fn build_request_body(&self, request: &CompletionRequest, stream: bool) -> serde_json::Value {
         let mut body = serde_json::json!({
             "model": request.model,
-            "messages": [{"role": "user", "content": request.prompt}],
+            "messages": request.chat_messages(),
             "stream": stream,
         });
 
@@ -59,6 +75,29 @@ impl MistralProvider {
         if let Some(top_p) = request.top_p {
             body["top_p"] = serde_json::json!(top_p);
         }
+        if !request.tools.is_empty() {
+            body["tools"] = openai_tools(&request.tools);
+        }
+        if let Some(ref tool_choice) = request.tool_choice {
+            body["tool_choice"] = openai_tool_choice(tool_choice, "any");
+        }
+
+        if let Some(ref format) = request.response_format {
+            body["response_format"] = openai_response_format(format);
+        }
+
+        if let Some(seed) = request.seed {
+            body["random_seed"] = serde_json::json!(seed);
+        }
+        if let Some(penalty) = request.presence_penalty {
+            body["presence_penalty"] = serde_json::json!(penalty);
+        }
+        if let Some(penalty) = request.frequency_penalty {
+            body["frequency_penalty"] = serde_json::json!(penalty);
+        }
+        if request.completions() > 1 {
+            body["n"] = serde_json::json!(request.completions());
+        }
 
         body
     }
@@ -67,6 +106,8 @@ impl MistralProvider {
 #[async_trait]
 impl Provider for MistralProvider {
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        MISTRAL_SAMPLING.check(self.name(), &request)?;
+
         let url = format!("{}/chat/completions", self.base_url);
         let body = self.build_request_body(&request, false);
 
@@ -106,7 +147,9 @@ impl Provider for MistralProvider {
 
         #[derive(Deserialize)]
         struct Message {
-            content: String,
+            content: Option<String>,
+            #[serde(default)]
+            tool_calls: Vec<OpenAIToolCall>,
         }
 
         #[derive(Deserialize)]
@@ -117,30 +160,49 @@ impl Provider for MistralProvider {
         }
 
         let resp: MistralResponse = serde_json::from_str(&text)?;
-        let choice = resp.choices.first()
-            .ok_or_else(|| ProviderError::ApiError { status: 500, message: "No choices".to_string() })?;
-
-        let finish_reason = match choice.finish_reason.as_str() {
-            "stop" => FinishReason::Stop,
-            "length" => FinishReason::Length,
-            _ => FinishReason::Stop,
-        };
-
-        Ok(CompletionResponse {
+        let choices = resp.choices.into_iter().enumerate()
+            .map(|(index, choice)| CompletionChoice {
+                index,
+                content: choice.message.content.unwrap_or_default(),
+                finish_reason: match choice.finish_reason.as_str() {
+                    "stop" => FinishReason::Stop,
+                    "length" => FinishReason::Length,
+                    "tool_calls" => FinishReason::ToolCalls,
+                    _ => FinishReason::Stop,
+                },
+                tool_calls: choice.message.tool_calls.into_iter().map(Into::into).collect(),
+            })
+            .collect();
+        let (first, choices) = sampling::split_choices(choices)?;
+
+        let mut response = CompletionResponse {
             id: resp.id,
-            content: choice.message.content.clone(),
+            content: first.content,
             model: resp.model,
             usage: TokenUsage {
                 prompt_tokens: resp.usage.prompt_tokens as usize,
                 completion_tokens: resp.usage.completion_tokens as usize,
                 total_tokens: resp.usage.total_tokens as usize,
+                reasoning_tokens: 0,
+                cached_tokens: 0,
+                cache_write_tokens: 0,
             },
-            finish_reason,
+            finish_reason: first.finish_reason,
             created_at: chrono::Utc::now(),
-        })
+            tool_calls: first.tool_calls,
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices,
+            metadata: HashMap::new(),
+        };
+        structured::check_response(&request, &mut response);
+        Ok(response)
     }
 
     async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+        MISTRAL_SAMPLING.check_stream(self.name(), &request)?;
+
         let url = format!("{}/chat/completions", self.base_url);
         let body = self.build_request_body(&request, true);
 
@@ -158,30 +220,7 @@ impl Provider for MistralProvider {
             return Err(ProviderError::ApiError { status, message: text });
         }
 
-        let stream = response.bytes_stream().map(move |result| {
-            match result {
-                Ok(bytes) => {
-                    let text = String::from_utf8_lossy(&bytes);
-                    for line in text.lines() {
-                        if line.starts_with("data: ") {
-                            let json_str = line.strip_prefix("data: ").unwrap_or("");
-                            if json_str == "[DONE]" {
-                                continue;
-                            }
-                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(json_str) {
-                                if let Some(content) = json["choices"][0]["delta"]["content"].as_str() {
-                                    return Ok(content.to_string());
-                                }
-                            }
-                        }
-                    }
-                    Ok(String::new())
-                }
-                Err(e) => Err(ProviderError::NetworkError(e)),
-            }
-        });
-
-        Ok(Box::pin(stream))
+        Ok(streaming::openai_stream(response))
     }
 
     fn supported_models(&self) -> Vec<ModelInfo> {
@@ -196,6 +235,13 @@ impl Provider for MistralProvider {
         ]
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        let request = self.client
+            .get(format!("{}/models", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key));
+        Ok(discovery::parse_openai_models(&discovery::fetch_json(request).await?))
+    }
+
     fn max_context_length(&self, model: &str) -> Option<usize> {
         match model {
             "open-mixtral-8x22b" => Some(64000),
@@ -215,7 +261,50 @@ impl Provider for MistralProvider {
         Ok(())
     }
 
-    fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
-        Ok((text.len() / 4).max(1))
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        Ok(Tokenizer::for_model(model).count_tokens(text))
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for MistralProvider {
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, ProviderError> {
+        let url = format!("{}/embeddings", self.base_url);
+
+        // Mistral names the vector size `output_dimension`
+        let mut body = embeddings::openai_embedding_body(&request);
+        if let Some(object) = body.as_object_mut() {
+            if let Some(dimensions) = object.remove("dimensions") {
+                object.insert("output_dimension".to_string(), dimensions);
+            }
+        }
+
+        debug!("Sending embedding request to Mistral AI");
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| ProviderError::NetworkError(e))?;
+
+        let status = response.status().as_u16();
+
+        if !response.status().is_success() {
+            let text = response.text().await.unwrap_or_default();
+            error!("Mistral AI API error ({}): {}", status, text);
+            return Err(ProviderError::ApiError { status, message: text });
+        }
+
+        let text = response.text().await.map_err(|e| ProviderError::NetworkError(e))?;
+        embeddings::parse_openai_embeddings(&text, &request)
+    }
+
+    fn embedding_models(&self) -> Vec<EmbeddingModel> {
+        vec![
+            EmbeddingModel::new("mistral-embed", 1024, 8192),
+            EmbeddingModel::new("codestral-embed", 1536, 8192),
+        ]
     }
 }
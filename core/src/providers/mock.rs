@@ -0,0 +1,639 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Scripted mock provider.
+//!
+//! [`MockProvider`] answers requests from a [`MockScript`] instead of a
+//! remote API, so benchmarks, comparisons and dashboards can be exercised
+//! without network access or API keys. A script sets:
+//!
+//! - the response text per prompt regex, with capture group expansion
+//! - the latency before each response, drawn from a distribution
+//! - the rates at which rate limit, timeout and context length errors occur
+//! - the reported token usage
+//!
+//! Scripts are YAML or JSON:
+//!
+//! ```yaml
+//! models: ["mock-model"]
+//! context_length: 8192
+//! seed: 42
+//! default_response: "I don't know how to answer: {prompt}"
+//! responses:
+//!   - pattern: "(?i)capital of (\\w+)"
+//!     response: "The capital of $1 is its largest city."
+//!     usage: { completion_tokens: 12 }
+//!   - pattern: "(?i)write a (long )?story"
+//!     response: "Once upon a time..."
+//!     finish_reason: length
+//! latency: { distribution: normal, mean_ms: 400, std_dev_ms: 120 }
+//! tokens_per_second: 80
+//! errors: { rate_limit: 0.02, timeout: 0.01 }
+//! ```
+//!
+//! [`ProviderFactory`](super::ProviderFactory) creates a mock provider for
+//! providers named `mock` or configured with `kind = "mock"`; the script is
+//! read from the provider's `script` setting.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use llm_test_bench_core::providers::{CompletionRequest, MockProvider, MockScript, Provider};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let script = MockScript::from_file("mock-script.yaml")?;
+//! let provider = MockProvider::new(script)?;
+//!
+//! let response = provider.complete(CompletionRequest::new("mock-model", "What is the capital of France?")).await?;
+//! println!("{}", response.content);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{
+    CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream,
+    StreamEvent, TokenUsage,
+};
+use crate::tokenizer::Tokenizer;
+use async_trait::async_trait;
+use futures::stream;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, LogNormal, Normal};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Model served when a script lists none.
+pub const DEFAULT_MOCK_MODEL: &str = "mock-model";
+
+/// Placeholder replaced by the prompt in scripted responses.
+const PROMPT_PLACEHOLDER: &str = "{prompt}";
+
+/// Behaviour of a [`MockProvider`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MockScript {
+    /// Models the provider serves. Requests for other models fail with
+    /// [`ProviderError::ModelNotFound`].
+    ///
+    /// When empty, [`DEFAULT_MOCK_MODEL`] is served.
+    pub models: Vec<String>,
+
+    /// Context window of every model, in tokens.
+    ///
+    /// Default: 8192
+    pub context_length: usize,
+
+    /// Rules tried in order; the first whose pattern matches the prompt
+    /// answers.
+    pub responses: Vec<MockRule>,
+
+    /// Response when no rule matches. `{prompt}` is replaced by the prompt.
+    pub default_response: String,
+
+    /// Delay before a response, or before the first chunk of a stream.
+    pub latency: Latency,
+
+    /// Pace of streamed chunks. `None` sends every chunk at once.
+    pub tokens_per_second: Option<f64>,
+
+    /// Probability of each injected error.
+    pub errors: ErrorRates,
+
+    /// Token usage reported for every response, unless a rule overrides it.
+    pub usage: MockUsage,
+
+    /// Seed for latency and error draws, for reproducible runs.
+    pub seed: Option<u64>,
+}
+
+impl Default for MockScript {
+    fn default() -> Self {
+        Self {
+            models: Vec::new(),
+            context_length: 8192,
+            responses: Vec::new(),
+            default_response: "This is a mock response to: {prompt}".to_string(),
+            latency: Latency::None,
+            tokens_per_second: None,
+            errors: ErrorRates::default(),
+            usage: MockUsage::default(),
+            seed: None,
+        }
+    }
+}
+
+impl MockScript {
+    /// Parses a YAML script. JSON is valid YAML, so JSON scripts parse too.
+    pub fn from_yaml(contents: &str) -> Result<Self, ProviderError> {
+        serde_yaml::from_str(contents)
+            .map_err(|e| ProviderError::InvalidRequest(format!("Invalid mock script: {}", e)))
+    }
+
+    /// Reads a YAML or JSON script from `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ProviderError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ProviderError::InvalidRequest(format!("Failed to read mock script {}: {}", path.display(), e))
+        })?;
+        Self::from_yaml(&contents)
+    }
+}
+
+/// A scripted answer for prompts matching `pattern`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MockRule {
+    /// Regular expression searched for in the prompt.
+    pub pattern: String,
+
+    /// Response text. `$1` or `${name}` expand capture groups (`$$` is a
+    /// literal `$`) and `{prompt}` is replaced by the prompt.
+    pub response: String,
+
+    /// Overrides the script's latency.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<Latency>,
+
+    /// Overrides the script's token usage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<MockUsage>,
+
+    /// Finish reason to report. Default: `stop`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<FinishReason>,
+}
+
+/// A latency distribution, in milliseconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(tag = "distribution", rename_all = "snake_case")]
+pub enum Latency {
+    /// Respond immediately.
+    #[default]
+    None,
+
+    /// Always the same delay.
+    Fixed {
+        /// Delay in milliseconds.
+        ms: u64,
+    },
+
+    /// Uniformly distributed between two bounds.
+    Uniform {
+        /// Lower bound in milliseconds.
+        min_ms: u64,
+        /// Upper bound in milliseconds.
+        max_ms: u64,
+    },
+
+    /// Normally distributed, clamped at zero.
+    Normal {
+        /// Mean in milliseconds.
+        mean_ms: f64,
+        /// Standard deviation in milliseconds.
+        std_dev_ms: f64,
+    },
+
+    /// Log-normally distributed, the usual shape of API latencies.
+    LogNormal {
+        /// Median in milliseconds.
+        median_ms: f64,
+        /// Standard deviation of the underlying normal distribution.
+        sigma: f64,
+    },
+}
+
+impl Latency {
+    fn sample(&self, rng: &mut StdRng) -> Duration {
+        let ms = match *self {
+            Latency::None => 0.0,
+            Latency::Fixed { ms } => ms as f64,
+            Latency::Uniform { min_ms, max_ms } => rng.gen_range(min_ms..=max_ms.max(min_ms)) as f64,
+            Latency::Normal { mean_ms, std_dev_ms } => Normal::new(mean_ms, std_dev_ms.max(0.0))
+                .map(|d| d.sample(rng))
+                .unwrap_or(mean_ms),
+            Latency::LogNormal { median_ms, sigma } => LogNormal::new(median_ms.max(f64::MIN_POSITIVE).ln(), sigma.max(0.0))
+                .map(|d| d.sample(rng))
+                .unwrap_or(median_ms),
+        };
+        Duration::from_secs_f64(ms.max(0.0) / 1000.0)
+    }
+}
+
+/// Probability, between 0.0 and 1.0, that a request fails with each error.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ErrorRates {
+    /// [`ProviderError::RateLimitExceeded`].
+    pub rate_limit: f64,
+
+    /// [`ProviderError::Timeout`], after the sampled latency.
+    pub timeout: f64,
+
+    /// [`ProviderError::ContextLengthExceeded`]. Prompts longer than the
+    /// script's `context_length` always fail.
+    pub context_length: f64,
+}
+
+/// Token usage to report. Unset counts are estimated from the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct MockUsage {
+    /// Prompt tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens: Option<usize>,
+
+    /// Completion tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion_tokens: Option<usize>,
+}
+
+/// An injected error.
+enum Injected {
+    RateLimit,
+    Timeout,
+    ContextLength,
+}
+
+/// A provider that answers from a [`MockScript`].
+pub struct MockProvider {
+    name: String,
+    script: MockScript,
+    patterns: Vec<Regex>,
+    rng: Mutex<StdRng>,
+}
+
+impl MockProvider {
+    /// Creates a provider named `mock` running `script`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProviderError::InvalidRequest` if a pattern is not a valid
+    /// regular expression or an error rate is outside 0.0 to 1.0.
+    pub fn new(mut script: MockScript) -> Result<Self, ProviderError> {
+        let patterns = script
+            .responses
+            .iter()
+            .map(|rule| {
+                Regex::new(&rule.pattern).map_err(|e| {
+                    ProviderError::InvalidRequest(format!("Invalid mock pattern '{}': {}", rule.pattern, e))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let rates = script.errors;
+        let total = rates.rate_limit + rates.timeout + rates.context_length;
+        if [rates.rate_limit, rates.timeout, rates.context_length]
+            .iter()
+            .any(|rate| !(0.0..=1.0).contains(rate))
+            || total > 1.0
+        {
+            return Err(ProviderError::InvalidRequest(
+                "Mock error rates must be between 0.0 and 1.0 and sum to at most 1.0".to_string(),
+            ));
+        }
+
+        if script.models.is_empty() {
+            script.models.push(DEFAULT_MOCK_MODEL.to_string());
+        }
+
+        let rng = match script.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        Ok(Self {
+            name: "mock".to_string(),
+            script,
+            patterns,
+            rng: Mutex::new(rng),
+        })
+    }
+
+    /// Creates a provider running the script at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ProviderError> {
+        Self::new(MockScript::from_file(path)?)
+    }
+
+    /// Sets the name the provider reports.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// The script being run.
+    pub fn script(&self) -> &MockScript {
+        &self.script
+    }
+
+    /// The text rules are matched against: the prompt, or the last message
+    /// when the prompt is empty.
+    fn prompt_text(request: &CompletionRequest) -> &str {
+        if request.prompt.is_empty() {
+            request.messages.last().map(|m| m.content.as_str()).unwrap_or_default()
+        } else {
+            &request.prompt
+        }
+    }
+
+    /// Draws the latency and outcome of `request`.
+    fn plan(&self, request: &CompletionRequest) -> Result<(Duration, Result<CompletionResponse, ProviderError>), ProviderError> {
+        if !self.script.models.iter().any(|m| *m == request.model) {
+            return Err(ProviderError::ModelNotFound { model: request.model.clone() });
+        }
+
+        let prompt = Self::prompt_text(request);
+        let rule = self
+            .patterns
+            .iter()
+            .zip(&self.script.responses)
+            .find_map(|(pattern, rule)| pattern.captures(prompt).map(|captures| (rule, captures)));
+
+        let content = match rule {
+            Some((rule, ref captures)) => {
+                let mut expanded = String::new();
+                captures.expand(&rule.response, &mut expanded);
+                expanded
+            }
+            None => self.script.default_response.clone(),
+        }
+        .replace(PROMPT_PLACEHOLDER, prompt);
+
+        let rule = rule.map(|(rule, _)| rule);
+        let usage = rule.and_then(|r| r.usage).unwrap_or(self.script.usage);
+        let prompt_text: String = request.chat_messages().iter().map(|m| m.content.as_str()).collect();
+        let tokenizer = Tokenizer::for_model(&request.model);
+        let prompt_tokens = usage.prompt_tokens.unwrap_or_else(|| tokenizer.count_tokens(&prompt_text));
+        let completion_tokens = usage.completion_tokens.unwrap_or_else(|| tokenizer.count_tokens(&content));
+
+        let (latency, injected) = {
+            let mut rng = self.rng.lock().unwrap();
+            let latency = rule
+                .and_then(|r| r.latency.as_ref())
+                .unwrap_or(&self.script.latency)
+                .sample(&mut rng);
+            let rates = self.script.errors;
+            let draw: f64 = rng.gen();
+            let injected = if draw < rates.rate_limit {
+                Some(Injected::RateLimit)
+            } else if draw < rates.rate_limit + rates.timeout {
+                Some(Injected::Timeout)
+            } else if draw < rates.rate_limit + rates.timeout + rates.context_length {
+                Some(Injected::ContextLength)
+            } else {
+                None
+            };
+            (latency, injected)
+        };
+
+        let max = self.script.context_length;
+        if prompt_tokens > max {
+            return Ok((latency, Err(ProviderError::ContextLengthExceeded { tokens: prompt_tokens, max })));
+        }
+
+        let outcome = match injected {
+            Some(Injected::RateLimit) => Err(ProviderError::RateLimitExceeded { retry_after: None }),
+            Some(Injected::Timeout) => Err(ProviderError::Timeout(latency)),
+            Some(Injected::ContextLength) => Err(ProviderError::ContextLengthExceeded {
+                tokens: prompt_tokens.max(max + 1),
+                max,
+            }),
+            None => Ok(CompletionResponse {
+                id: format!("mock-{}", uuid::Uuid::new_v4()),
+                model: request.model.clone(),
+                content,
+                usage: TokenUsage::new(prompt_tokens, completion_tokens),
+                finish_reason: rule.and_then(|r| r.finish_reason).unwrap_or(FinishReason::Stop),
+                created_at: chrono::Utc::now(),
+                tool_calls: Vec::new(),
+                schema_violations: Vec::new(),
+                logprobs: None,
+                prompt_logprobs: None,
+                choices: Vec::new(),
+                metadata: HashMap::new(),
+            }),
+        };
+
+        Ok((latency, outcome))
+    }
+}
+
+impl Default for MockProvider {
+    fn default() -> Self {
+        Self::new(MockScript::default()).expect("the default script is valid")
+    }
+}
+
+#[async_trait]
+impl Provider for MockProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        let (latency, outcome) = self.plan(&request)?;
+        tokio::time::sleep(latency).await;
+        outcome
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+        let (latency, outcome) = self.plan(&request)?;
+        tokio::time::sleep(latency).await;
+        let response = outcome?;
+
+        // One chunk per word, paced by the scripted throughput
+        let tokenizer = Tokenizer::for_model(&response.model);
+        let tokens_per_second = self.script.tokens_per_second.filter(|tps| *tps > 0.0);
+        let mut events: VecDeque<(Duration, StreamEvent)> = response
+            .content
+            .split_inclusive(char::is_whitespace)
+            .enumerate()
+            .map(|(i, word)| {
+                let delay = match tokens_per_second {
+                    Some(tps) if i > 0 => Duration::from_secs_f64(tokenizer.count_tokens(word).max(1) as f64 / tps),
+                    _ => Duration::ZERO,
+                };
+                (delay, StreamEvent::content(word))
+            })
+            .collect();
+        events.push_back((Duration::ZERO, StreamEvent::usage(response.usage)));
+        events.push_back((Duration::ZERO, StreamEvent::finish(response.finish_reason)));
+
+        Ok(Box::pin(stream::unfold(events, |mut events| async move {
+            let (delay, event) = events.pop_front()?;
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            // Stamp events when they are emitted, not when they were planned
+            Some((Ok(StreamEvent::new(event.kind)), events))
+        })))
+    }
+
+    fn supported_models(&self) -> Vec<ModelInfo> {
+        self.script
+            .models
+            .iter()
+            .map(|id| ModelInfo::new(id.clone(), id.clone(), self.script.context_length, true, false))
+            .collect()
+    }
+
+    fn max_context_length(&self, model: &str) -> Option<usize> {
+        self.script
+            .models
+            .iter()
+            .any(|m| m == model)
+            .then_some(self.script.context_length)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn validate_config(&self) -> Result<(), ProviderError> {
+        Ok(())
+    }
+
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        Ok(Tokenizer::for_model(model).count_tokens(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::StreamAccumulator;
+
+    const SCRIPT: &str = r#"
+models: ["mock-model", "mock-large"]
+context_length: 100
+seed: 7
+default_response: "Unknown: {prompt}"
+responses:
+  - pattern: "(?i)capital of (?P<country>\\w+)"
+    response: "The capital of ${country} is unknown to me."
+    usage: { completion_tokens: 9 }
+  - pattern: "story"
+    response: "Once upon a time"
+    finish_reason: length
+"#;
+
+    fn provider() -> MockProvider {
+        MockProvider::new(MockScript::from_yaml(SCRIPT).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_scripted_responses() {
+        let provider = provider();
+
+        let response = provider
+            .complete(CompletionRequest::new("mock-model", "What is the capital of France?"))
+            .await
+            .unwrap();
+        assert_eq!(response.content, "The capital of France is unknown to me.");
+        assert_eq!(response.usage.completion_tokens, 9);
+        assert_eq!(response.finish_reason, FinishReason::Stop);
+
+        let response = provider.complete(CompletionRequest::new("mock-large", "Tell me a story")).await.unwrap();
+        assert_eq!(response.finish_reason, FinishReason::Length);
+
+        let response = provider.complete(CompletionRequest::new("mock-model", "Hello")).await.unwrap();
+        assert_eq!(response.content, "Unknown: Hello");
+        assert!(response.usage.prompt_tokens > 0);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_model_and_long_prompt() {
+        let provider = provider();
+
+        let result = provider.complete(CompletionRequest::new("gpt-4", "Hello")).await;
+        assert!(matches!(result, Err(ProviderError::ModelNotFound { .. })));
+
+        let result = provider.complete(CompletionRequest::new("mock-model", "word ".repeat(500))).await;
+        assert!(matches!(result, Err(ProviderError::ContextLengthExceeded { max: 100, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_error_injection() {
+        let script = MockScript {
+            errors: ErrorRates { rate_limit: 1.0, timeout: 0.0, context_length: 0.0 },
+            ..Default::default()
+        };
+        let provider = MockProvider::new(script).unwrap();
+        let result = provider.complete(CompletionRequest::new(DEFAULT_MOCK_MODEL, "Hello")).await;
+        assert!(matches!(result, Err(ProviderError::RateLimitExceeded { .. })));
+
+        let script = MockScript {
+            errors: ErrorRates { rate_limit: 0.7, timeout: 0.7, context_length: 0.0 },
+            ..Default::default()
+        };
+        assert!(MockProvider::new(script).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_error_rates_are_seeded() {
+        let script = MockScript {
+            errors: ErrorRates { rate_limit: 0.0, timeout: 0.5, context_length: 0.0 },
+            seed: Some(42),
+            ..Default::default()
+        };
+
+        let mut outcomes = Vec::new();
+        for _ in 0..2 {
+            let provider = MockProvider::new(script.clone()).unwrap();
+            let mut run = Vec::new();
+            for _ in 0..20 {
+                run.push(provider.complete(CompletionRequest::new(DEFAULT_MOCK_MODEL, "Hello")).await.is_ok());
+            }
+            outcomes.push(run);
+        }
+
+        assert_eq!(outcomes[0], outcomes[1]);
+        assert!(outcomes[0].contains(&true) && outcomes[0].contains(&false));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_latency_and_stream_pacing() {
+        let script = MockScript {
+            latency: Latency::Fixed { ms: 250 },
+            tokens_per_second: Some(10.0),
+            default_response: "one two six".to_string(),
+            ..Default::default()
+        };
+        let provider = MockProvider::new(script).unwrap();
+        let start = tokio::time::Instant::now();
+
+        let stream = provider
+            .stream(CompletionRequest::new(DEFAULT_MOCK_MODEL, "Hello").with_streaming())
+            .await
+            .unwrap();
+        let (response, metrics) = StreamAccumulator::collect(DEFAULT_MOCK_MODEL, stream).await.unwrap();
+
+        assert_eq!(response.content, "one two six");
+        assert_eq!(metrics.chunks, 3);
+        // 250ms to the first chunk, then 100ms per one-token word
+        assert_eq!(start.elapsed(), Duration::from_millis(450));
+    }
+
+    #[test]
+    fn test_example_script_parses() {
+        let script = MockScript::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/../examples/mock-script.yaml")).unwrap();
+        let provider = MockProvider::new(script).unwrap();
+        assert_eq!(provider.supported_models().len(), 2);
+    }
+
+    #[test]
+    fn test_latency_distributions() {
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(Latency::Fixed { ms: 30 }.sample(&mut rng), Duration::from_millis(30));
+
+        for _ in 0..100 {
+            let sample = Latency::Uniform { min_ms: 10, max_ms: 20 }.sample(&mut rng);
+            assert!(sample >= Duration::from_millis(10) && sample <= Duration::from_millis(20));
+
+            let sample = Latency::Normal { mean_ms: 5.0, std_dev_ms: 50.0 }.sample(&mut rng);
+            assert!(sample >= Duration::ZERO);
+        }
+    }
+}
@@ -90,9 +90,9 @@
 //!     .with_streaming();
 //!
 //! let mut stream = provider.stream(request).await?;
-//! while let Some(chunk) = stream.next().await {
-//!     match chunk {
-//!         Ok(text) => print!("{}", text),
+//! while let Some(event) = stream.next().await {
+//!     match event {
+//!         Ok(event) => print!("{}", event.text().unwrap_or_default()),
 //!         Err(e) => eprintln!("Error: {}", e),
 //!     }
 //! }
@@ -125,15 +125,33 @@
 //! ```
 
 // Core modules
+pub mod batch;
+pub mod cache;
+pub mod cassette;
+pub mod credentials;
+pub mod discovery;
+pub mod embeddings;
 pub mod error;
 pub mod factory;
+pub mod fallback;
+pub mod logprobs;
+pub mod mock;
 pub mod models;
+pub mod multimodal;
+pub mod rate_limit;
+pub mod sampling;
+pub mod streaming;
+pub mod structured;
+pub mod tools;
 pub mod traits;
+pub mod transcription;
+pub mod transport;
 pub mod types;
 
 // Provider implementations
 pub mod anthropic;
 pub mod openai;
+pub mod openai_compatible;
 pub mod google;
 pub mod cohere;
 pub mod mistral;
@@ -147,11 +165,32 @@ pub mod replicate;
 pub mod perplexity;
 
 // Re-export commonly used types
+pub use batch::{BatchCounts, BatchJob, BatchProvider, BatchRequest, BatchResult, BatchStatus};
+pub use cache::CachedProvider;
+pub use cassette::{Cassette, CassetteMode, CassetteProvider};
+pub use credentials::{ApiKey, CredentialSource, KeyRotatingProvider};
+pub use discovery::{DiscoveredModel, ModelCatalog};
+pub use embeddings::{
+    cosine_similarity, EmbeddingInputType, EmbeddingModel, EmbeddingProvider, EmbeddingRequest, EmbeddingResponse,
+};
 pub use error::ProviderError;
-pub use factory::ProviderFactory;
+pub use factory::{BatchConstructor, EmbeddingConstructor, ProviderConstructor, ProviderFactory};
+pub use fallback::{CircuitBreaker, CircuitState, FallbackProvider};
+pub use logprobs::{LogprobOptions, TokenLogprob, TopLogprob};
+pub use mock::{ErrorRates, Latency, MockProvider, MockRule, MockScript, MockUsage};
+pub use multimodal::MultiModalProvider;
+pub use rate_limit::{RateLimitedProvider, RateLimiter};
+pub use sampling::SamplingSupport;
+pub use streaming::{StreamAccumulator, StreamEvent, StreamEventKind, StreamMetrics, ToolCallDelta};
+pub use structured::{ResponseFormat, SchemaViolation};
+pub use tools::{ToolCall, ToolChoice, ToolDefinition};
 pub use traits::{calculate_backoff, Provider, RetryableProvider};
+pub use transcription::{
+    GoogleSpeechProvider, TranscriptSegment, TranscriptWord, Transcription, TranscriptionProvider, WhisperProvider,
+};
 pub use types::{
-    CompletionRequest, CompletionResponse, FinishReason, ModelInfo, ResponseStream, TokenUsage,
+    CachePoint, ChatMessage, CompletionChoice, CompletionRequest, CompletionResponse, FinishReason, MessageRole,
+    ModelInfo, ReasoningEffort, ResponseStream, TokenUsage,
 };
 
 // Re-export provider implementations
@@ -165,6 +204,7 @@ pub use huggingface::HuggingFaceProvider;
 pub use mistral::MistralProvider;
 pub use ollama::OllamaProvider;
 pub use openai::OpenAIProvider;
+pub use openai_compatible::OpenAICompatibleProvider;
 pub use perplexity::PerplexityProvider;
 pub use replicate::ReplicateProvider;
 pub use together::TogetherProvider;
@@ -3,6 +3,7 @@
 //! This module provides comprehensive model catalogs for each provider,
 //! including model IDs, capabilities, and metadata.
 
+use super::types::TokenUsage;
 use serde::{Deserialize, Serialize};
 
 /// Model capability flags
@@ -24,6 +25,10 @@ pub struct ModelMetadata {
     pub capabilities: ModelCapabilities,
     pub cost_per_1k_input: f64,
     pub cost_per_1k_output: f64,
+    /// Price of input tokens read from the prompt cache
+    pub cost_per_1k_cached_input: f64,
+    /// Price of input tokens written to the prompt cache
+    pub cost_per_1k_cache_write: f64,
 }
 
 // ================================
@@ -284,6 +289,8 @@ pub fn get_model_metadata(model_id: &str) -> Option<ModelMetadata> {
             },
             cost_per_1k_input: 0.0025,
             cost_per_1k_output: 0.01,
+            cost_per_1k_cached_input: 0.00125,
+            cost_per_1k_cache_write: 0.0025,
         }),
 
         // OpenAI GPT-4o-mini
@@ -300,6 +307,44 @@ pub fn get_model_metadata(model_id: &str) -> Option<ModelMetadata> {
             },
             cost_per_1k_input: 0.00015,
             cost_per_1k_output: 0.0006,
+            cost_per_1k_cached_input: 0.000075,
+            cost_per_1k_cache_write: 0.00015,
+        }),
+
+        // OpenAI o1
+        O1 => Some(ModelMetadata {
+            id: O1,
+            display_name: "o1",
+            provider: "openai",
+            capabilities: ModelCapabilities {
+                supports_streaming: true,
+                supports_function_calling: true,
+                supports_vision: true,
+                max_context_tokens: 200_000,
+                max_output_tokens: 100_000,
+            },
+            cost_per_1k_input: 0.015,
+            cost_per_1k_output: 0.06,
+            cost_per_1k_cached_input: 0.0075,
+            cost_per_1k_cache_write: 0.015,
+        }),
+
+        // OpenAI o3-mini
+        O3_MINI => Some(ModelMetadata {
+            id: O3_MINI,
+            display_name: "o3-mini",
+            provider: "openai",
+            capabilities: ModelCapabilities {
+                supports_streaming: true,
+                supports_function_calling: true,
+                supports_vision: false,
+                max_context_tokens: 200_000,
+                max_output_tokens: 100_000,
+            },
+            cost_per_1k_input: 0.0011,
+            cost_per_1k_output: 0.0044,
+            cost_per_1k_cached_input: 0.00055,
+            cost_per_1k_cache_write: 0.0011,
         }),
 
         // Anthropic Claude 3.5 Sonnet
@@ -316,6 +361,8 @@ pub fn get_model_metadata(model_id: &str) -> Option<ModelMetadata> {
             },
             cost_per_1k_input: 0.003,
             cost_per_1k_output: 0.015,
+            cost_per_1k_cached_input: 0.0003,
+            cost_per_1k_cache_write: 0.00375,
         }),
 
         // Anthropic Claude 3.5 Haiku
@@ -332,6 +379,8 @@ pub fn get_model_metadata(model_id: &str) -> Option<ModelMetadata> {
             },
             cost_per_1k_input: 0.001,
             cost_per_1k_output: 0.005,
+            cost_per_1k_cached_input: 0.0001,
+            cost_per_1k_cache_write: 0.00125,
         }),
 
         // Google Gemini 2.0 Flash
@@ -348,6 +397,8 @@ pub fn get_model_metadata(model_id: &str) -> Option<ModelMetadata> {
             },
             cost_per_1k_input: 0.0,  // Experimental pricing
             cost_per_1k_output: 0.0,
+            cost_per_1k_cached_input: 0.0,
+            cost_per_1k_cache_write: 0.0,
         }),
 
         // Google Gemini 1.5 Pro
@@ -364,6 +415,8 @@ pub fn get_model_metadata(model_id: &str) -> Option<ModelMetadata> {
             },
             cost_per_1k_input: 0.00125,
             cost_per_1k_output: 0.005,
+            cost_per_1k_cached_input: 0.0003125,
+            cost_per_1k_cache_write: 0.00125,
         }),
 
         // Google Gemini 1.5 Flash
@@ -380,12 +433,30 @@ pub fn get_model_metadata(model_id: &str) -> Option<ModelMetadata> {
             },
             cost_per_1k_input: 0.000075,
             cost_per_1k_output: 0.0003,
+            cost_per_1k_cached_input: 0.00001875,
+            cost_per_1k_cache_write: 0.000075,
         }),
 
         _ => None,
     }
 }
 
+/// Estimate the cost of a completion in USD from the model's list prices
+///
+/// Reasoning tokens are billed as completion tokens, and prompt tokens read
+/// from or written to the cache at the cache prices. Returns `None` for models without
+/// pricing data.
+pub fn estimate_cost(model_id: &str, usage: &TokenUsage) -> Option<f64> {
+    get_model_metadata(model_id).map(|metadata| {
+        usage.calculate_cost_with_cache(
+            metadata.cost_per_1k_input,
+            metadata.cost_per_1k_cached_input,
+            metadata.cost_per_1k_cache_write,
+            metadata.cost_per_1k_output,
+        )
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -438,4 +509,25 @@ mod tests {
         assert_eq!(metadata.provider, "openai");
         assert!(metadata.capabilities.supports_streaming);
     }
+
+    #[test]
+    fn test_estimate_cost() {
+        // 1,000 reasoning tokens are part of the 1,500 completion tokens
+        let usage = TokenUsage::new(2000, 1500).with_reasoning_tokens(1000).with_cached_tokens(1000);
+        let cost = estimate_cost(O3_MINI, &usage).unwrap();
+        assert!((cost - (0.0011 + 0.00055 + 1.5 * 0.0044)).abs() < 1e-9);
+
+        assert!(estimate_cost("unknown-model", &usage).is_none());
+    }
+
+    #[test]
+    fn test_estimate_cost_with_cache_writes() {
+        // Claude bills cache writes at 1.25x and reads at 0.1x the input price
+        let cold = TokenUsage::new(10_000, 100).with_cache_write_tokens(10_000);
+        let warm = TokenUsage::new(10_000, 100).with_cached_tokens(10_000);
+        let cold_cost = estimate_cost(CLAUDE_3_5_SONNET_LATEST, &cold).unwrap();
+        let warm_cost = estimate_cost(CLAUDE_3_5_SONNET_LATEST, &warm).unwrap();
+        assert!((cold_cost - (10.0 * 0.00375 + 0.1 * 0.015)).abs() < 1e-9);
+        assert!((warm_cost - (10.0 * 0.0003 + 0.1 * 0.015)).abs() < 1e-9);
+    }
 }
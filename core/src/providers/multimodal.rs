@@ -0,0 +1,237 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Multi-modal completions
+//!
+//! Providers whose APIs accept images or audio alongside text implement
+//! [`MultiModalProvider`]. Each one encodes the parts of a
+//! [`MultiModalRequest`] in its own wire format; the helpers here resolve
+//! media to base64 and convert the text response back.
+//!
+//! Which models accept which media is declared by
+//! [`ModelInfo::supports_vision`](super::ModelInfo::supports_vision) and
+//! [`ModelInfo::supports_audio`](super::ModelInfo::supports_audio).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use llm_test_bench_core::multimodal::{ImageInput, MultiModalRequest};
+//! use llm_test_bench_core::providers::{MultiModalProvider, OpenAIProvider};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let provider = OpenAIProvider::new("your-api-key".to_string())?;
+//!
+//! let request = MultiModalRequest::new("gpt-4o")
+//!     .with_text("What's in this image?")
+//!     .with_image(ImageInput::from_path("cat.jpg").await?);
+//!
+//! let response = provider.complete_multimodal(request).await?;
+//! println!("{}", response.text());
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{CompletionRequest, CompletionResponse, FinishReason, Provider, ProviderError};
+use crate::multimodal::types::{FinishReason as MultiModalFinishReason, MultiModalUsage};
+use crate::multimodal::{
+    AudioFormat, AudioInput, ContentPart, ImageFormat, ImageInput, MultiModalContent, MultiModalRequest,
+    MultiModalResponse,
+};
+use async_trait::async_trait;
+
+/// A provider that completes requests mixing text with images or audio
+#[async_trait]
+pub trait MultiModalProvider: Provider {
+    /// Completes a multi-modal request
+    ///
+    /// Parts the provider cannot encode, such as video or audio sent to a
+    /// vision-only API, fail with [`ProviderError::InvalidRequest`] before
+    /// anything is sent.
+    async fn complete_multimodal(&self, request: MultiModalRequest) -> Result<MultiModalResponse, ProviderError>;
+
+    /// Whether `model` is known to accept image inputs
+    fn supports_vision(&self, model: &str) -> bool {
+        self.supported_models().iter().any(|m| m.id == model && m.supports_vision)
+    }
+
+    /// Whether `model` is known to accept audio inputs
+    fn supports_audio(&self, model: &str) -> bool {
+        self.supported_models().iter().any(|m| m.id == model && m.supports_audio)
+    }
+}
+
+/// Media resolved for a request body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum MediaSource {
+    /// A URL the provider fetches itself
+    Url(String),
+    /// Base64 data sent inline
+    Inline { media_type: String, data: String },
+}
+
+impl MediaSource {
+    /// The media as a URL, inline data becoming a `data:` URI
+    pub(crate) fn into_url(self) -> String {
+        match self {
+            MediaSource::Url(url) => url,
+            MediaSource::Inline { media_type, data } => format!("data:{};base64,{}", media_type, data),
+        }
+    }
+}
+
+/// Resolves an image for a request body, passing remote images by URL
+pub(crate) async fn image_source(image: &ImageInput) -> Result<MediaSource, ProviderError> {
+    if let ImageInput::Url { url, .. } = image {
+        return Ok(MediaSource::Url(url.clone()));
+    }
+
+    let (media_type, data) = inline_image(image).await?;
+    Ok(MediaSource::Inline { media_type, data })
+}
+
+/// Resolves an image to its media type and base64 data, downloading
+/// remote images
+pub(crate) async fn inline_image(image: &ImageInput) -> Result<(String, String), ProviderError> {
+    let media_type = match (image.media_type(), image) {
+        (Some(media_type), _) => media_type.to_string(),
+        (None, ImageInput::Url { url, .. }) => url_extension(url)
+            .and_then(ImageFormat::from_extension)
+            .map(|format| format.mime_type().to_string())
+            .ok_or_else(|| ProviderError::InvalidRequest(format!("Cannot determine the format of image {}", url)))?,
+        (None, _) => return Err(ProviderError::InvalidRequest("Cannot determine the format of image".to_string())),
+    };
+    let data = image.to_base64().await.map_err(media_error)?;
+
+    Ok((media_type, data))
+}
+
+/// Resolves audio to its media type and base64 data
+pub(crate) async fn audio_source(audio: &AudioInput) -> Result<(String, String), ProviderError> {
+    let media_type = match (audio.media_type(), audio) {
+        (Some(media_type), _) => media_type.to_string(),
+        (None, AudioInput::Url { url, .. }) => url_extension(url)
+            .and_then(AudioFormat::from_extension)
+            .map(|format| format.mime_type().to_string())
+            .ok_or_else(|| ProviderError::InvalidRequest(format!("Cannot determine the format of audio {}", url)))?,
+        (None, _) => return Err(ProviderError::InvalidRequest("Cannot determine the format of audio".to_string())),
+    };
+    let data = audio.to_base64().await.map_err(media_error)?;
+
+    Ok((media_type, data))
+}
+
+/// The error for a part `provider` cannot encode
+pub(crate) fn unsupported(provider: &str, part: &ContentPart) -> ProviderError {
+    ProviderError::InvalidRequest(format!("{} does not accept {} input", provider, part.media_type()))
+}
+
+/// A text request carrying the model, system prompt and sampling
+/// parameters of `request`
+///
+/// Providers build their request body from it and then replace the
+/// user turn with the encoded parts.
+pub(crate) fn text_request(request: &MultiModalRequest) -> CompletionRequest {
+    let mut text = CompletionRequest::new(request.model.clone(), request.content.extract_text());
+    text.system = request.system.clone();
+    text.max_tokens = request.max_tokens;
+    text.temperature = request.temperature;
+    text.top_p = request.top_p;
+    text.stop = request.stop.clone();
+    text
+}
+
+fn url_extension(url: &str) -> Option<&str> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.rsplit_once('.').map(|(_, extension)| extension)
+}
+
+fn media_error(error: anyhow::Error) -> ProviderError {
+    ProviderError::InvalidRequest(format!("Failed to read media: {:#}", error))
+}
+
+impl From<CompletionResponse> for MultiModalResponse {
+    fn from(response: CompletionResponse) -> Self {
+        let mut content = MultiModalContent::new();
+        content.add_text(response.content);
+
+        MultiModalResponse {
+            id: response.id,
+            model: response.model,
+            content,
+            usage: MultiModalUsage::new(response.usage.prompt_tokens, response.usage.completion_tokens),
+            finish_reason: match response.finish_reason {
+                FinishReason::Stop => MultiModalFinishReason::Stop,
+                FinishReason::Length => MultiModalFinishReason::Length,
+                FinishReason::ContentFilter => MultiModalFinishReason::ContentFilter,
+                FinishReason::ToolCalls => MultiModalFinishReason::ToolCalls,
+                FinishReason::Error => MultiModalFinishReason::Error,
+            },
+            created_at: response.created_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::TokenUsage;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_image_source() {
+        let image = ImageInput::from_base64("iVBORw0KGgo=".to_string(), ImageFormat::Png);
+        assert_eq!(
+            image_source(&image).await.unwrap().into_url(),
+            "data:image/png;base64,iVBORw0KGgo="
+        );
+
+        let remote = ImageInput::from_url("https://example.com/cat.jpg?size=large");
+        assert_eq!(
+            image_source(&remote).await.unwrap(),
+            MediaSource::Url("https://example.com/cat.jpg?size=large".to_string())
+        );
+        assert_eq!(url_extension("https://example.com/cat.jpg?size=large"), Some("jpg"));
+    }
+
+    #[tokio::test]
+    async fn test_audio_source_requires_format() {
+        let audio = AudioInput::from_url("https://example.com/stream");
+        assert!(matches!(audio_source(&audio).await, Err(ProviderError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn test_text_request_and_response() {
+        let request = MultiModalRequest::new("gpt-4o")
+            .with_system("Be brief.")
+            .with_text("Describe")
+            .with_text("this")
+            .with_max_tokens(50);
+        let text = text_request(&request);
+        assert_eq!(text.prompt, "Describe\nthis");
+        assert_eq!(text.system.as_deref(), Some("Be brief."));
+        assert_eq!(text.max_tokens, Some(50));
+
+        let response: MultiModalResponse = CompletionResponse {
+            id: "id".to_string(),
+            content: "A cat.".to_string(),
+            model: "gpt-4o".to_string(),
+            usage: TokenUsage::new(10, 3),
+            finish_reason: FinishReason::Stop,
+            created_at: chrono::Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
+        }
+        .into();
+        assert_eq!(response.text(), "A cat.");
+        assert_eq!(response.usage.total_tokens, 13);
+        assert_eq!(response.finish_reason, MultiModalFinishReason::Stop);
+    }
+}
@@ -6,13 +6,26 @@
 
 //! Ollama provider implementation for local models
 
+use super::discovery;
+use super::embeddings::{self, EmbeddingModel, EmbeddingProvider, EmbeddingRequest, EmbeddingResponse};
+use super::logprobs;
+use super::multimodal::{self, MultiModalProvider};
+use super::sampling::SamplingSupport;
+use super::streaming::{self, JsonLinesDecoder, StreamEvent};
+use super::structured::{self, ResponseFormat};
 use super::{CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use crate::multimodal::{ContentPart, MultiModalRequest, MultiModalResponse};
+use crate::tokenizer::Tokenizer;
 use async_trait::async_trait;
-use futures::stream::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::Duration;
 use tracing::{debug, error};
 
+/// `/api/generate` options cover everything but a logit bias and multiple
+/// completions
+const OLLAMA_SAMPLING: SamplingSupport = SamplingSupport { n: false, logit_bias: false, ..SamplingSupport::ALL };
+
 /// Ollama provider for local model hosting
 pub struct OllamaProvider {
     client: reqwest::Client,
@@ -33,13 +46,23 @@ impl OllamaProvider {
         Ok(Self { client, base_url })
     }
 
+    /// Uses `client` for every request, e.g. one built from transport settings
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
     fn build_request_body(&self, request: &CompletionRequest, stream: bool) -> serde_json::Value {
         let mut body = serde_json::json!({
             "model": request.model,
-            "prompt": request.prompt,
+            "prompt": request.render_prompt(),
             "stream": stream,
         });
 
+        if let Some(system) = request.system_prompt() {
+            body["system"] = serde_json::json!(system);
+        }
+
         let mut options = serde_json::Map::new();
 
         if let Some(temp) = request.temperature {
@@ -48,6 +71,18 @@ impl OllamaProvider {
         if let Some(top_p) = request.top_p {
             options.insert("top_p".to_string(), serde_json::json!(top_p));
         }
+        if let Some(top_k) = request.top_k {
+            options.insert("top_k".to_string(), serde_json::json!(top_k));
+        }
+        if let Some(seed) = request.seed {
+            options.insert("seed".to_string(), serde_json::json!(seed));
+        }
+        if let Some(penalty) = request.presence_penalty {
+            options.insert("presence_penalty".to_string(), serde_json::json!(penalty));
+        }
+        if let Some(penalty) = request.frequency_penalty {
+            options.insert("frequency_penalty".to_string(), serde_json::json!(penalty));
+        }
 
         if !options.is_empty() {
             body["options"] = serde_json::Value::Object(options);
@@ -57,27 +92,32 @@ impl OllamaProvider {
             body["stop"] = serde_json::json!(stop);
         }
 
-        body
-    }
-}
+        // `format` takes either "json" or a JSON Schema
+        match request.response_format {
+            Some(ResponseFormat::JsonObject) => body["format"] = serde_json::json!("json"),
+            Some(ResponseFormat::JsonSchema { ref schema, .. }) => body["format"] = schema.clone(),
+            Some(ResponseFormat::Text) | None => {}
+        }
 
-impl Default for OllamaProvider {
-    fn default() -> Self {
-        Self::new().expect("Failed to create Ollama provider")
+        if let Some(options) = request.logprobs {
+            body["logprobs"] = serde_json::json!(true);
+            if options.top_logprobs > 0 {
+                body["top_logprobs"] = serde_json::json!(options.top_logprobs);
+            }
+        }
+
+        body
     }
-}
 
-#[async_trait]
-impl Provider for OllamaProvider {
-    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+    /// Post a non-streaming `/api/generate` request body
+    async fn generate(&self, body: &serde_json::Value) -> Result<CompletionResponse, ProviderError> {
         let url = format!("{}/api/generate", self.base_url);
-        let body = self.build_request_body(&request, false);
 
         debug!("Sending request to Ollama");
 
         let response = self.client
             .post(&url)
-            .json(&body)
+            .json(body)
             .send()
             .await
             .map_err(|e| ProviderError::NetworkError(e))?;
@@ -100,6 +140,8 @@ impl Provider for OllamaProvider {
             total_duration: Option<i64>,
             prompt_eval_count: Option<i32>,
             eval_count: Option<i32>,
+            #[serde(default)]
+            logprobs: Option<serde_json::Value>,
         }
 
         let resp: OllamaResponse = serde_json::from_str(&text)
@@ -116,13 +158,65 @@ impl Provider for OllamaProvider {
                 prompt_tokens,
                 completion_tokens,
                 total_tokens: prompt_tokens + completion_tokens,
+                reasoning_tokens: 0,
+                cached_tokens: 0,
+                cache_write_tokens: 0,
             },
             finish_reason: if resp.done { FinishReason::Stop } else { FinishReason::Length },
             created_at: chrono::Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: resp.logprobs.as_ref().and_then(logprobs::parse),
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         })
     }
 
+    /// Build a generate request body with the images attached
+    ///
+    /// Only vision models such as llava look at `images`; Ollama has no
+    /// audio input.
+    async fn build_multimodal_body(&self, request: &MultiModalRequest) -> Result<serde_json::Value, ProviderError> {
+        let mut images = Vec::new();
+        for part in &request.content.parts {
+            match part {
+                ContentPart::Text(_) => {}
+                ContentPart::Image(image) => images.push(multimodal::inline_image(&image.image).await?.1),
+                part => return Err(multimodal::unsupported(self.name(), part)),
+            }
+        }
+
+        let mut body = self.build_request_body(&multimodal::text_request(request), false);
+        body["images"] = serde_json::json!(images);
+        Ok(body)
+    }
+}
+
+impl Default for OllamaProvider {
+    fn default() -> Self {
+        Self::new().expect("Failed to create Ollama provider")
+    }
+}
+
+#[async_trait]
+impl Provider for OllamaProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        OLLAMA_SAMPLING.check(self.name(), &request)?;
+        if request.logprobs.is_some_and(|options| options.include_prompt) {
+            return Err(ProviderError::InvalidRequest(
+                "Ollama does not return log probabilities for the prompt".to_string(),
+            ));
+        }
+
+        let mut response = self.generate(&self.build_request_body(&request, false)).await?;
+        structured::check_response(&request, &mut response);
+        Ok(response)
+    }
+
     async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+        OLLAMA_SAMPLING.check_stream(self.name(), &request)?;
+
         let url = format!("{}/api/generate", self.base_url);
         let body = self.build_request_body(&request, true);
 
@@ -139,24 +233,26 @@ impl Provider for OllamaProvider {
             return Err(ProviderError::ApiError { status, message: text });
         }
 
-        let stream = response.bytes_stream().map(move |result| {
-            match result {
-                Ok(bytes) => {
-                    let text = String::from_utf8_lossy(&bytes);
-                    for line in text.lines() {
-                        if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
-                            if let Some(response) = json.get("response").and_then(|r| r.as_str()) {
-                                return Ok(response.to_string());
-                            }
-                        }
-                    }
-                    Ok(String::new())
-                }
-                Err(e) => Err(ProviderError::NetworkError(e)),
+        Ok(streaming::decode_stream(response.bytes_stream(), JsonLinesDecoder::default(), |chunk| {
+            if let Some(message) = chunk["error"].as_str() {
+                return Err(ProviderError::ApiError { status: 500, message: message.to_string() });
             }
-        });
 
-        Ok(Box::pin(stream))
+            let mut events = Vec::new();
+            if let Some(text) = chunk["response"].as_str().filter(|text| !text.is_empty()) {
+                events.push(StreamEvent::content(text));
+            }
+            if chunk["done"].as_bool() == Some(true) {
+                let prompt_tokens = chunk["prompt_eval_count"].as_u64().unwrap_or(0) as usize;
+                let completion_tokens = chunk["eval_count"].as_u64().unwrap_or(0) as usize;
+                events.push(StreamEvent::usage(TokenUsage::new(prompt_tokens, completion_tokens)));
+                events.push(StreamEvent::finish(match chunk["done_reason"].as_str() {
+                    Some("length") => FinishReason::Length,
+                    _ => FinishReason::Stop,
+                }));
+            }
+            Ok(events)
+        }))
     }
 
     fn supported_models(&self) -> Vec<ModelInfo> {
@@ -169,9 +265,22 @@ impl Provider for OllamaProvider {
             ModelInfo::new("codellama", "Code Llama", 16384, true, false),
             ModelInfo::new("phi", "Phi-2", 2048, true, false),
             ModelInfo::new("gemma:7b", "Gemma 7B", 8192, true, false),
+            ModelInfo::new("llava", "LLaVA", 4096, true, false).with_vision(),
         ]
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        let json = discovery::fetch_json(self.client.get(format!("{}/api/tags", self.base_url))).await?;
+        Ok(json["models"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|model| model["name"].as_str())
+            .map(|name| ModelInfo::new(name, name, 0, true, false))
+            .collect())
+    }
+
     fn max_context_length(&self, model: &str) -> Option<usize> {
         if model.starts_with("llama2") {
             Some(4096)
@@ -181,6 +290,8 @@ impl Provider for OllamaProvider {
             Some(16384)
         } else if model.starts_with("gemma") {
             Some(8192)
+        } else if model.starts_with("llava") {
+            Some(4096)
         } else if model.starts_with("phi") {
             Some(2048)
         } else {
@@ -202,7 +313,73 @@ impl Provider for OllamaProvider {
         }
     }
 
-    fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
-        Ok((text.len() / 4).max(1))
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        Ok(Tokenizer::for_model(model).count_tokens(text))
+    }
+}
+
+#[async_trait]
+impl MultiModalProvider for OllamaProvider {
+    async fn complete_multimodal(&self, request: MultiModalRequest) -> Result<MultiModalResponse, ProviderError> {
+        let body = self.build_multimodal_body(&request).await?;
+        self.generate(&body).await.map(Into::into)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaProvider {
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, ProviderError> {
+        let url = format!("{}/api/embed", self.base_url);
+        let mut body = serde_json::json!({
+            "model": request.model,
+            "input": request.inputs,
+        });
+        if let Some(dimensions) = request.dimensions {
+            body["dimensions"] = serde_json::json!(dimensions);
+        }
+
+        debug!("Sending embedding request to Ollama");
+
+        let response = self.client
+            .post(&url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| ProviderError::NetworkError(e))?;
+
+        let status = response.status().as_u16();
+        if !response.status().is_success() {
+            let text = response.text().await.unwrap_or_default();
+            error!("Ollama API error ({}): {}", status, text);
+            return Err(ProviderError::ApiError { status, message: text });
+        }
+
+        let text = response.text().await.map_err(|e| ProviderError::NetworkError(e))?;
+
+        #[derive(Deserialize)]
+        struct OllamaEmbeddings {
+            model: String,
+            embeddings: Vec<Vec<f32>>,
+            prompt_eval_count: Option<usize>,
+        }
+
+        let resp: OllamaEmbeddings = serde_json::from_str(&text)?;
+
+        Ok(EmbeddingResponse {
+            model: resp.model,
+            embeddings: resp.embeddings,
+            usage: match resp.prompt_eval_count {
+                Some(tokens) => TokenUsage::new(tokens, 0),
+                None => embeddings::estimate_usage(&request),
+            },
+        })
+    }
+
+    fn embedding_models(&self) -> Vec<EmbeddingModel> {
+        vec![
+            EmbeddingModel::new("nomic-embed-text", 768, 8192),
+            EmbeddingModel::new("mxbai-embed-large", 1024, 512),
+            EmbeddingModel::new("all-minilm", 384, 256),
+        ]
     }
 }
@@ -6,12 +6,22 @@
 
 //! OpenAI provider implementation
 
-use super::{CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use super::batch::{self, BatchCounts, BatchJob, BatchProvider, BatchRequest, BatchResult, BatchStatus};
+use super::discovery;
+use super::embeddings::{self, EmbeddingModel, EmbeddingProvider, EmbeddingRequest, EmbeddingResponse};
+use super::logprobs;
+use super::multimodal::{self, MultiModalProvider};
+use super::sampling::{self, SamplingSupport};
+use super::streaming;
+use super::structured::{self, openai_response_format};
+use super::tools::{openai_tool_choice, openai_tools, OpenAIToolCall};
+use super::{CompletionChoice, CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use crate::multimodal::{ContentPart, MultiModalRequest, MultiModalResponse};
+use crate::tokenizer::Tokenizer;
 use async_trait::async_trait;
-use futures::stream::{Stream, StreamExt};
-use reqwest_eventsource::{Event, EventSource};
+use reqwest::multipart::{Form, Part};
 use serde::{Deserialize, Serialize};
-use std::pin::Pin;
+use std::collections::HashMap;
 use std::time::Duration;
 use tracing::{debug, error, warn};
 
@@ -74,19 +84,25 @@ impl OpenAIProvider {
         })
     }
 
+    /// Uses `client` for every request, e.g. one built from transport settings
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
     /// Build request body for OpenAI API
     fn build_request_body(&self, request: &CompletionRequest, stream: bool) -> serde_json::Value {
         let mut body = serde_json::json!({
             "model": request.model,
-            "messages": [
-                {
-                    "role": "user",
-                    "content": request.prompt
-                }
-            ],
+            "messages": request.chat_messages(),
             "stream": stream,
         });
 
+        if stream {
+            // Without this the stream carries no token usage
+            body["stream_options"] = serde_json::json!({ "include_usage": true });
+        }
+
         if let Some(temp) = request.temperature {
             body["temperature"] = serde_json::json!(temp);
         }
@@ -103,11 +119,130 @@ impl OpenAIProvider {
             body["stop"] = serde_json::json!(stop);
         }
 
+        if !request.tools.is_empty() {
+            body["tools"] = openai_tools(&request.tools);
+        }
+
+        if let Some(ref tool_choice) = request.tool_choice {
+            body["tool_choice"] = openai_tool_choice(tool_choice, "required");
+        }
+
+        if let Some(ref format) = request.response_format {
+            body["response_format"] = openai_response_format(format);
+        }
+
+        if let Some(options) = request.logprobs {
+            body["logprobs"] = serde_json::json!(true);
+            if options.top_logprobs > 0 {
+                body["top_logprobs"] = serde_json::json!(options.top_logprobs);
+            }
+        }
+
+        sampling::apply_openai_params(request, &mut body);
+
         body
     }
 
+    /// Build request body for a chat completion with image and audio parts
+    ///
+    /// Images go in `image_url` parts, local ones as `data:` URIs; audio
+    /// goes in `input_audio` parts, which only take WAV and MP3.
+    async fn build_multimodal_body(&self, request: &MultiModalRequest) -> Result<serde_json::Value, ProviderError> {
+        let mut content = Vec::with_capacity(request.content.parts.len());
+        for part in &request.content.parts {
+            content.push(match part {
+                ContentPart::Text(text) => serde_json::json!({ "type": "text", "text": text.text }),
+                ContentPart::Image(image) => {
+                    let mut image_url = serde_json::json!({
+                        "url": multimodal::image_source(&image.image).await?.into_url(),
+                    });
+                    if let Some(detail) = image.detail {
+                        image_url["detail"] = serde_json::json!(detail);
+                    }
+                    serde_json::json!({ "type": "image_url", "image_url": image_url })
+                }
+                ContentPart::Audio(audio) => {
+                    let (media_type, data) = multimodal::audio_source(&audio.audio).await?;
+                    let format = match media_type.as_str() {
+                        "audio/wav" | "audio/x-wav" => "wav",
+                        "audio/mpeg" | "audio/mp3" => "mp3",
+                        other => {
+                            return Err(ProviderError::InvalidRequest(format!(
+                                "OpenAI accepts WAV or MP3 audio, not {}",
+                                other
+                            )))
+                        }
+                    };
+                    serde_json::json!({ "type": "input_audio", "input_audio": { "data": data, "format": format } })
+                }
+                part => return Err(multimodal::unsupported(self.name(), part)),
+            });
+        }
+
+        let mut messages = Vec::with_capacity(2);
+        if let Some(ref system) = request.system {
+            messages.push(serde_json::json!({ "role": "system", "content": system }));
+        }
+        messages.push(serde_json::json!({ "role": "user", "content": content }));
+
+        let mut body = self.build_request_body(&multimodal::text_request(request), false);
+        body["messages"] = serde_json::json!(messages);
+        Ok(body)
+    }
+
+    /// Build request body for the legacy text completions API
+    ///
+    /// Used to score the prompt: with `echo` the returned log probabilities
+    /// cover the prompt followed by the completion. vLLM and other
+    /// OpenAI-compatible servers support this on all their models.
+    fn build_text_completion_body(&self, request: &CompletionRequest) -> Result<serde_json::Value, ProviderError> {
+        if request.system_prompt().is_some() || !request.messages.is_empty() {
+            return Err(ProviderError::InvalidRequest(
+                "Prompt log probabilities require a plain prompt without system or messages".to_string(),
+            ));
+        }
+        if request.completions() > 1 {
+            return Err(ProviderError::InvalidRequest(
+                "Prompt log probabilities require a single completion".to_string(),
+            ));
+        }
+        if request.reasoning_effort.is_some() {
+            return Err(ProviderError::InvalidRequest(
+                "Prompt log probabilities are not available for reasoning models".to_string(),
+            ));
+        }
+
+        let top_logprobs = request.logprobs.map(|options| options.top_logprobs).unwrap_or(0);
+        let mut body = serde_json::json!({
+            "model": request.model,
+            "prompt": request.prompt,
+            "echo": true,
+            "logprobs": top_logprobs,
+            "max_tokens": request.max_tokens.unwrap_or(16),
+        });
+
+        if let Some(temp) = request.temperature {
+            body["temperature"] = serde_json::json!(temp);
+        }
+
+        if let Some(top_p) = request.top_p {
+            body["top_p"] = serde_json::json!(top_p);
+        }
+
+        if let Some(ref stop) = request.stop {
+            body["stop"] = serde_json::json!(stop);
+        }
+
+        sampling::apply_openai_params(request, &mut body);
+
+        Ok(body)
+    }
+
     /// Parse OpenAI error response
-    fn parse_error_response(status: u16, text: &str) -> ProviderError {
+    ///
+    /// Shared with the OpenAI-compatible provider, whose servers mostly
+    /// mirror OpenAI's error body.
+    pub(super) fn parse_error_response(status: u16, text: &str) -> ProviderError {
         #[derive(Deserialize)]
         struct ErrorResponse {
             error: ErrorDetail,
@@ -163,11 +298,15 @@ impl OpenAIProvider {
         struct Choice {
             message: Message,
             finish_reason: String,
+            #[serde(default)]
+            logprobs: Option<serde_json::Value>,
         }
 
         #[derive(Deserialize)]
         struct Message {
-            content: String,
+            content: Option<String>,
+            #[serde(default)]
+            tool_calls: Vec<OpenAIToolCall>,
         }
 
         #[derive(Deserialize)]
@@ -175,32 +314,135 @@ impl OpenAIProvider {
             prompt_tokens: u32,
             completion_tokens: u32,
             total_tokens: u32,
+            #[serde(default)]
+            prompt_tokens_details: Option<TokenDetails>,
+            #[serde(default)]
+            completion_tokens_details: Option<TokenDetails>,
+        }
+
+        #[derive(Deserialize)]
+        struct TokenDetails {
+            #[serde(default)]
+            cached_tokens: Option<u32>,
+            #[serde(default)]
+            reasoning_tokens: Option<u32>,
         }
 
         let resp: OpenAIResponse = serde_json::from_str(json)?;
 
-        let choice = resp.choices.first()
+        let logprobs = resp.choices.first()
+            .and_then(|choice| choice.logprobs.as_ref())
+            .and_then(logprobs::parse);
+
+        let choices = resp.choices.into_iter().enumerate()
+            .map(|(index, choice)| CompletionChoice {
+                index,
+                content: choice.message.content.unwrap_or_default(),
+                finish_reason: match choice.finish_reason.as_str() {
+                    "stop" => FinishReason::Stop,
+                    "length" => FinishReason::Length,
+                    "content_filter" => FinishReason::ContentFilter,
+                    "tool_calls" | "function_call" => FinishReason::ToolCalls,
+                    _ => FinishReason::Error,
+                },
+                tool_calls: choice.message.tool_calls.into_iter().map(Into::into).collect(),
+            })
+            .collect();
+
+        let (first, choices) = sampling::split_choices(choices)?;
+
+        Ok(CompletionResponse {
+            id: resp.id,
+            content: first.content,
+            model: resp.model,
+            usage: TokenUsage {
+                prompt_tokens: resp.usage.prompt_tokens as usize,
+                completion_tokens: resp.usage.completion_tokens as usize,
+                total_tokens: resp.usage.total_tokens as usize,
+                reasoning_tokens: resp.usage.completion_tokens_details
+                    .and_then(|details| details.reasoning_tokens)
+                    .unwrap_or(0) as usize,
+                cached_tokens: resp.usage.prompt_tokens_details
+                    .and_then(|details| details.cached_tokens)
+                    .unwrap_or(0) as usize,
+                    cache_write_tokens: 0,
+            },
+            finish_reason: first.finish_reason,
+            created_at: chrono::Utc::now(),
+            tool_calls: first.tool_calls,
+            schema_violations: Vec::new(),
+            logprobs,
+            prompt_logprobs: None,
+            choices,
+            metadata: HashMap::new(),
+        })
+    }
+
+    /// Parse a legacy text completions response to an echoed prompt
+    ///
+    /// Tokens whose character offset falls inside `prompt` belong to the
+    /// prompt; the echoed prompt is removed from the content.
+    fn parse_text_completion_response(&self, json: &str, prompt: &str) -> Result<CompletionResponse, ProviderError> {
+        #[derive(Deserialize)]
+        struct TextCompletionResponse {
+            id: String,
+            model: String,
+            choices: Vec<TextChoice>,
+            usage: Usage,
+        }
+
+        #[derive(Deserialize)]
+        struct TextChoice {
+            text: String,
+            finish_reason: Option<String>,
+            #[serde(default)]
+            logprobs: serde_json::Value,
+        }
+
+        #[derive(Deserialize)]
+        struct Usage {
+            prompt_tokens: u32,
+            #[serde(default)]
+            completion_tokens: u32,
+            total_tokens: u32,
+        }
+
+        let resp: TextCompletionResponse = serde_json::from_str(json)?;
+
+        let choice = resp.choices.into_iter().next()
             .ok_or_else(|| ProviderError::ApiError { status: 500, message: "No choices in response".to_string() })?;
 
-        let finish_reason = match choice.finish_reason.as_str() {
-            "stop" => FinishReason::Stop,
-            "length" => FinishReason::Length,
-            "content_filter" => FinishReason::ContentFilter,
-            "tool_calls" | "function_call" => FinishReason::ToolCalls,
-            _ => FinishReason::Error,
+        let prompt_chars = prompt.chars().count();
+        let (prompt_tokens, completion_tokens): (Vec<_>, Vec<_>) = logprobs::parse_legacy(&choice.logprobs)
+            .into_iter()
+            .partition(|(offset, _)| *offset < prompt_chars);
+
+        let finish_reason = match choice.finish_reason.as_deref() {
+            Some("length") => FinishReason::Length,
+            Some("content_filter") => FinishReason::ContentFilter,
+            _ => FinishReason::Stop,
         };
 
         Ok(CompletionResponse {
             id: resp.id,
-            content: choice.message.content.clone(),
+            content: choice.text.strip_prefix(prompt).unwrap_or(&choice.text).to_string(),
             model: resp.model,
             usage: TokenUsage {
                 prompt_tokens: resp.usage.prompt_tokens as usize,
                 completion_tokens: resp.usage.completion_tokens as usize,
                 total_tokens: resp.usage.total_tokens as usize,
+                reasoning_tokens: 0,
+                cached_tokens: 0,
+                cache_write_tokens: 0,
             },
             finish_reason,
             created_at: chrono::Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: Some(completion_tokens.into_iter().filter_map(|(_, token)| token).collect()),
+            prompt_logprobs: Some(prompt_tokens.into_iter().filter_map(|(_, token)| token).collect()),
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         })
     }
 
@@ -230,8 +472,13 @@ impl OpenAIProvider {
     async fn complete_once(&self, request: &CompletionRequest) -> Result<CompletionResponse, ProviderError> {
         debug!("OpenAI completion request: model={}, prompt_len={}", request.model, request.prompt.len());
 
-        let url = format!("{}/chat/completions", self.base_url);
-        let body = self.build_request_body(request, false);
+        // Only the text completions API can echo the prompt with its log probabilities
+        let score_prompt = request.logprobs.is_some_and(|options| options.include_prompt);
+        let (url, body) = if score_prompt {
+            (format!("{}/completions", self.base_url), self.build_text_completion_body(request)?)
+        } else {
+            (format!("{}/chat/completions", self.base_url), self.build_request_body(request, false))
+        };
 
         let response = self.client
             .post(&url)
@@ -250,7 +497,11 @@ impl OpenAIProvider {
         }
 
         debug!("OpenAI completion response received, parsing...");
-        self.parse_completion_response(&text)
+        if score_prompt {
+            self.parse_text_completion_response(&text, &request.prompt)
+        } else {
+            self.parse_completion_response(&text)
+        }
     }
 
     /// Make a completion request with retry logic
@@ -283,16 +534,89 @@ impl OpenAIProvider {
             ProviderError::InternalError("Retry loop completed without error".to_string())
         }))
     }
+
+    /// Sends a Batch or Files API request and returns the response body
+    async fn send_batch_api(&self, request: reqwest::RequestBuilder) -> Result<String, ProviderError> {
+        let response = request
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .send()
+            .await?;
+
+        let status = response.status();
+        let text = response.text().await?;
+
+        if !status.is_success() {
+            error!("OpenAI Batch API error: status={}, response={}", status, text);
+            return Err(Self::parse_error_response(status.as_u16(), &text));
+        }
+
+        Ok(text)
+    }
+
+    /// Fetches a batch object, along with its output and error file IDs
+    async fn fetch_batch(&self, batch_id: &str) -> Result<OpenAIBatch, ProviderError> {
+        let url = format!("{}/batches/{}", self.base_url, batch_id);
+        let text = self.send_batch_api(self.client.get(&url)).await?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Parses the results in a batch output or error file
+    ///
+    /// Each line holds either the HTTP response to one request or the error
+    /// that prevented it from being sent.
+    fn parse_batch_results(&self, text: &str, batch_id: &str) -> Result<Vec<BatchResult>, ProviderError> {
+        #[derive(Deserialize)]
+        struct ResultLine {
+            custom_id: String,
+            #[serde(default)]
+            response: Option<ResultResponse>,
+            #[serde(default)]
+            error: Option<serde_json::Value>,
+        }
+
+        #[derive(Deserialize)]
+        struct ResultResponse {
+            status_code: u16,
+            body: serde_json::Value,
+        }
+
+        let lines: Vec<ResultLine> = batch::parse_jsonl(text)?;
+        Ok(lines
+            .into_iter()
+            .map(|line| {
+                let response = match (line.response, line.error) {
+                    (Some(response), _) if response.status_code == 200 => self
+                        .parse_completion_response(&response.body.to_string())
+                        .map(|mut completion| {
+                            completion.metadata.insert("batch_id".to_string(), batch_id.to_string());
+                            completion
+                        })
+                        .map_err(|e| e.to_string()),
+                    (Some(response), _) => Err(batch::error_message(&response.body)),
+                    (None, Some(error)) => Err(batch::error_message(&error)),
+                    (None, None) => Err("No response in batch output".to_string()),
+                };
+                BatchResult {
+                    custom_id: line.custom_id,
+                    response,
+                }
+            })
+            .collect())
+    }
 }
 
 #[async_trait]
 impl Provider for OpenAIProvider {
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
-        self.complete_with_retry(&request).await
+        SamplingSupport::OPENAI.check(self.name(), &request)?;
+        let mut response = self.complete_with_retry(&request).await?;
+        structured::check_response(&request, &mut response);
+        Ok(response)
     }
 
     async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
         debug!("OpenAI streaming request: model={}, prompt_len={}", request.model, request.prompt.len());
+        SamplingSupport::OPENAI.check_stream(self.name(), &request)?;
 
         let url = format!("{}/chat/completions", self.base_url);
         let body = self.build_request_body(&request, true);
@@ -303,63 +627,15 @@ impl Provider for OpenAIProvider {
             .header("Content-Type", "application/json")
             .json(&body);
 
-        let event_source = EventSource::new(req_builder)
-            .map_err(|e| ProviderError::InternalError(format!("Failed to create event source: {}", e)))?;
-
-        let stream = event_source.filter_map(move |event| async move {
-            match event {
-                Ok(Event::Message(msg)) => {
-                    if msg.data == "[DONE]" {
-                        return None;
-                    }
-
-                    #[derive(Deserialize)]
-                    struct StreamResponse {
-                        choices: Vec<StreamChoice>,
-                    }
-
-                    #[derive(Deserialize)]
-                    struct StreamChoice {
-                        delta: Delta,
-                        finish_reason: Option<String>,
-                    }
-
-                    #[derive(Deserialize)]
-                    struct Delta {
-                        content: Option<String>,
-                    }
-
-                    match serde_json::from_str::<StreamResponse>(&msg.data) {
-                        Ok(resp) => {
-                            if let Some(choice) = resp.choices.first() {
-                                let content = choice.delta.content.clone().unwrap_or_default();
-                                if !content.is_empty() {
-                                    Some(Ok(content))
-                                } else {
-                                    None
-                                }
-                            } else {
-                                Some(Err(ProviderError::InternalError("No choices in stream chunk".to_string())))
-                            }
-                        }
-                        Err(e) => {
-                            error!("Failed to parse stream chunk: {}", e);
-                            Some(Err(ProviderError::ParseError(e)))
-                        }
-                    }
-                }
-                Ok(Event::Open) => {
-                    debug!("Stream opened");
-                    None
-                }
-                Err(e) => {
-                    error!("Stream error: {}", e);
-                    Some(Err(ProviderError::InternalError(format!("{}", e))))
-                }
-            }
-        });
+        let response = req_builder.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await?;
+            error!("OpenAI API error: status={}, response={}", status, text);
+            return Err(Self::parse_error_response(status.as_u16(), &text));
+        }
 
-        Ok(Box::pin(stream))
+        Ok(streaming::openai_stream(response))
     }
 
     fn supported_models(&self) -> Vec<ModelInfo> {
@@ -370,6 +646,8 @@ impl Provider for OpenAIProvider {
                 max_tokens: 8192,
                 supports_streaming: true,
                 supports_function_calling: true,
+                supports_vision: false,
+                supports_audio: false,
             },
             ModelInfo {
                 id: "gpt-4-turbo".to_string(),
@@ -377,6 +655,8 @@ impl Provider for OpenAIProvider {
                 max_tokens: 128000,
                 supports_streaming: true,
                 supports_function_calling: true,
+                supports_vision: true,
+                supports_audio: false,
             },
             ModelInfo {
                 id: "gpt-4-turbo-preview".to_string(),
@@ -384,6 +664,26 @@ impl Provider for OpenAIProvider {
                 max_tokens: 128000,
                 supports_streaming: true,
                 supports_function_calling: true,
+                supports_vision: false,
+                supports_audio: false,
+            },
+            ModelInfo {
+                id: "gpt-4o".to_string(),
+                name: "GPT-4o".to_string(),
+                max_tokens: 128000,
+                supports_streaming: true,
+                supports_function_calling: true,
+                supports_vision: true,
+                supports_audio: false,
+            },
+            ModelInfo {
+                id: "gpt-4o-audio-preview".to_string(),
+                name: "GPT-4o Audio Preview".to_string(),
+                max_tokens: 128000,
+                supports_streaming: true,
+                supports_function_calling: true,
+                supports_vision: false,
+                supports_audio: true,
             },
             ModelInfo {
                 id: "gpt-3.5-turbo".to_string(),
@@ -391,10 +691,19 @@ impl Provider for OpenAIProvider {
                 max_tokens: 16385,
                 supports_streaming: true,
                 supports_function_calling: true,
+                supports_vision: false,
+                supports_audio: false,
             },
         ]
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        let request = self.client
+            .get(format!("{}/models", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key));
+        Ok(discovery::parse_openai_models(&discovery::fetch_json(request).await?))
+    }
+
     fn max_context_length(&self, model: &str) -> Option<usize> {
         self.supported_models()
             .iter()
@@ -413,21 +722,201 @@ impl Provider for OpenAIProvider {
         Ok(())
     }
 
-    fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
-        // Simple estimation: ~4 characters per token
-        Ok(text.len() / 4)
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        Ok(Tokenizer::for_model(model).count_tokens(text))
+    }
+}
+
+#[async_trait]
+impl MultiModalProvider for OpenAIProvider {
+    async fn complete_multimodal(&self, request: MultiModalRequest) -> Result<MultiModalResponse, ProviderError> {
+        debug!("OpenAI multi-modal request: model={}, parts={}", request.model, request.content.parts.len());
+
+        let body = self.build_multimodal_body(&request).await?;
+
+        let response = self.client
+            .post(format!("{}/chat/completions", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let text = response.text().await?;
+
+        if !status.is_success() {
+            error!("OpenAI API error: status={}, response={}", status, text);
+            return Err(Self::parse_error_response(status.as_u16(), &text));
+        }
+
+        self.parse_completion_response(&text).map(Into::into)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAIProvider {
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, ProviderError> {
+        debug!("OpenAI embedding request: model={}, inputs={}", request.model, request.inputs.len());
+
+        let response = self.client
+            .post(format!("{}/embeddings", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(&embeddings::openai_embedding_body(&request))
+            .send()
+            .await?;
+
+        let status = response.status();
+        let text = response.text().await?;
+
+        if !status.is_success() {
+            error!("OpenAI API error: status={}, response={}", status, text);
+            return Err(Self::parse_error_response(status.as_u16(), &text));
+        }
+
+        embeddings::parse_openai_embeddings(&text, &request)
+    }
+
+    fn embedding_models(&self) -> Vec<EmbeddingModel> {
+        vec![
+            EmbeddingModel::new("text-embedding-3-small", 1536, 8191),
+            EmbeddingModel::new("text-embedding-3-large", 3072, 8191),
+            EmbeddingModel::new("text-embedding-ada-002", 1536, 8191),
+        ]
+    }
+
+    fn max_batch_size(&self) -> usize {
+        2048
+    }
+}
+
+/// A batch object from the Batch API
+#[derive(Debug, Deserialize)]
+struct OpenAIBatch {
+    id: String,
+    status: String,
+    #[serde(default)]
+    request_counts: Option<OpenAIBatchCounts>,
+    #[serde(default)]
+    output_file_id: Option<String>,
+    #[serde(default)]
+    error_file_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIBatchCounts {
+    total: usize,
+    completed: usize,
+    failed: usize,
+}
+
+impl From<OpenAIBatch> for BatchJob {
+    fn from(batch: OpenAIBatch) -> Self {
+        let status = match batch.status.as_str() {
+            "validating" => BatchStatus::Validating,
+            "in_progress" => BatchStatus::InProgress,
+            "finalizing" => BatchStatus::Finalizing,
+            "completed" => BatchStatus::Completed,
+            "expired" => BatchStatus::Expired,
+            "cancelling" => BatchStatus::Cancelling,
+            "cancelled" => BatchStatus::Cancelled,
+            _ => BatchStatus::Failed,
+        };
+        let counts = batch.request_counts.map_or_else(BatchCounts::default, |counts| BatchCounts {
+            total: counts.total,
+            completed: counts.completed,
+            failed: counts.failed,
+        });
+
+        BatchJob { id: batch.id, status, counts }
+    }
+}
+
+/// Batches go through the Files API: the requests are uploaded as a JSON
+/// Lines file and run against `/v1/chat/completions`.
+#[async_trait]
+impl BatchProvider for OpenAIProvider {
+    async fn submit_batch(&self, requests: Vec<BatchRequest>) -> Result<BatchJob, ProviderError> {
+        batch::check_custom_ids(&requests)?;
+
+        let mut input = String::new();
+        for request in &requests {
+            SamplingSupport::OPENAI.check(self.name(), &request.request)?;
+            let line = serde_json::json!({
+                "custom_id": request.custom_id,
+                "method": "POST",
+                "url": "/v1/chat/completions",
+                "body": self.build_request_body(&request.request, false),
+            });
+            input.push_str(&line.to_string());
+            input.push('\n');
+        }
+
+        debug!("OpenAI batch upload: requests={}, bytes={}", requests.len(), input.len());
+
+        let file = Part::bytes(input.into_bytes())
+            .file_name("batch.jsonl")
+            .mime_str("application/jsonl")?;
+        let form = Form::new().text("purpose", "batch").part("file", file);
+        let text = self
+            .send_batch_api(self.client.post(format!("{}/files", self.base_url)).multipart(form))
+            .await?;
+
+        #[derive(Deserialize)]
+        struct UploadedFile {
+            id: String,
+        }
+        let uploaded: UploadedFile = serde_json::from_str(&text)?;
+
+        let body = serde_json::json!({
+            "input_file_id": uploaded.id,
+            "endpoint": "/v1/chat/completions",
+            "completion_window": "24h",
+        });
+        let text = self
+            .send_batch_api(self.client.post(format!("{}/batches", self.base_url)).json(&body))
+            .await?;
+        let batch: OpenAIBatch = serde_json::from_str(&text)?;
+        Ok(batch.into())
+    }
+
+    async fn batch_status(&self, batch_id: &str) -> Result<BatchJob, ProviderError> {
+        Ok(self.fetch_batch(batch_id).await?.into())
+    }
+
+    async fn batch_results(&self, batch_id: &str) -> Result<Vec<BatchResult>, ProviderError> {
+        let batch = self.fetch_batch(batch_id).await?;
+        let files = [batch.output_file_id.clone(), batch.error_file_id.clone()];
+        batch::require_terminal(&BatchJob::from(batch))?;
+
+        let mut results = Vec::new();
+        for file_id in files.iter().flatten() {
+            let url = format!("{}/files/{}/content", self.base_url, file_id);
+            let text = self.send_batch_api(self.client.get(&url)).await?;
+            results.extend(self.parse_batch_results(&text, batch_id)?);
+        }
+        Ok(results)
+    }
+
+    async fn cancel_batch(&self, batch_id: &str) -> Result<BatchJob, ProviderError> {
+        let url = format!("{}/batches/{}/cancel", self.base_url, batch_id);
+        let text = self.send_batch_api(self.client.post(&url)).await?;
+        let batch: OpenAIBatch = serde_json::from_str(&text)?;
+        Ok(batch.into())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::providers::ChatMessage;
 
     #[test]
     fn test_openai_provider_creation() {
         let provider = OpenAIProvider::new("test_key".to_string()).unwrap();
         assert_eq!(provider.name(), "OpenAI");
-        assert_eq!(provider.supported_models().len(), 4);
+        assert_eq!(provider.supported_models().len(), 6);
     }
 
     #[test]
@@ -444,11 +933,26 @@ mod tests {
         let request = CompletionRequest {
             model: "gpt-4".to_string(),
             prompt: "Hello".to_string(),
+            system: None,
+            messages: Vec::new(),
             temperature: Some(0.7),
             max_tokens: Some(100),
             top_p: Some(0.9),
             stop: Some(vec!["STOP".to_string()]),
             stream: false,
+            tools: Vec::new(),
+            tool_choice: None,
+            response_format: None,
+            logprobs: None,
+            seed: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+            top_k: None,
+            n: None,
+            logit_bias: HashMap::new(),
+            reasoning_effort: None,
+            thinking_budget: None,
+            cache_points: Vec::new(),
         };
 
         let body = provider.build_request_body(&request, false);
@@ -459,6 +963,26 @@ mod tests {
         assert_eq!(body["stream"], false);
     }
 
+    #[test]
+    fn test_build_request_body_with_system_and_history() {
+        let provider = OpenAIProvider::new("test_key".to_string()).unwrap();
+        let request = CompletionRequest::new("gpt-4", "And in French?")
+            .with_system("You are a translator.")
+            .with_message(ChatMessage::user("Translate 'cat' to Spanish."))
+            .with_message(ChatMessage::assistant("gato"));
+
+        let body = provider.build_request_body(&request, false);
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(messages[0]["content"], "You are a translator.");
+        assert_eq!(messages[1]["role"], "user");
+        assert_eq!(messages[2]["role"], "assistant");
+        assert_eq!(messages[2]["content"], "gato");
+        assert_eq!(messages[3]["role"], "user");
+        assert_eq!(messages[3]["content"], "And in French?");
+    }
+
     #[test]
     fn test_is_retryable() {
         assert!(OpenAIProvider::is_retryable(&ProviderError::RateLimitExceeded {
@@ -542,7 +1066,7 @@ mod tests {
     fn test_estimate_tokens() {
         let provider = OpenAIProvider::new("test_key".to_string()).unwrap();
 
-        // "Hello, world!" is ~13 chars, should be ~4 tokens
+        // "Hello" "," " world" "!" in cl100k_base
         let count = provider.estimate_tokens("Hello, world!", "gpt-4").unwrap();
         assert_eq!(count, 4);
 
@@ -550,10 +1074,14 @@ mod tests {
         let count = provider.estimate_tokens("", "gpt-4").unwrap();
         assert_eq!(count, 0);
 
-        // Longer text
-        let long_text = "a".repeat(400); // 400 chars = ~100 tokens
+        // Repeated characters merge into long tokens
+        let long_text = "a".repeat(400);
         let count = provider.estimate_tokens(&long_text, "gpt-4").unwrap();
-        assert_eq!(count, 100);
+        assert_eq!(count, 50);
+
+        // GPT-4o uses o200k_base
+        let count = provider.estimate_tokens("Hello, world!", "gpt-4o").unwrap();
+        assert_eq!(count, 4);
     }
 
     #[test]
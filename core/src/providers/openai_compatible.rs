@@ -0,0 +1,399 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Generic provider for servers that speak the OpenAI chat completions API
+//!
+//! vLLM, llama.cpp server, LM Studio, LiteLLM and similar servers expose
+//! `/chat/completions` at their own base URL, often without authentication
+//! or behind a custom header. Unlike the vendor providers, this one has no
+//! built-in name or model list: both come from configuration, so the same
+//! kind can be registered several times under different names.
+//!
+//! ```toml
+//! [providers.local-vllm]
+//! kind = "openai-compatible"
+//! api_key_env = "VLLM_API_KEY"
+//! base_url = "http://localhost:8000/v1"
+//! default_model = "meta-llama/Meta-Llama-3-8B-Instruct"
+//! timeout_seconds = 120
+//! max_retries = 0
+//! models = ["meta-llama/Meta-Llama-3-8B-Instruct"]
+//! context_length = 8192
+//!
+//! [providers.local-vllm.headers]
+//! X-Api-Key = "{api_key}"
+//! ```
+//!
+//! Header values are templates in which `{api_key}` is replaced by the value
+//! of `api_key_env`. Without any `headers`, `Authorization: Bearer {api_key}`
+//! is sent when the variable is set and no authentication otherwise.
+
+use super::discovery;
+use super::embeddings::{self, EmbeddingProvider, EmbeddingRequest, EmbeddingResponse};
+use super::logprobs;
+use super::openai::OpenAIProvider;
+use super::sampling::{self, SamplingSupport};
+use super::streaming;
+use super::structured::{self, openai_response_format};
+use super::tools::{openai_tool_choice, openai_tools, OpenAIToolCall};
+use super::{CompletionChoice, CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use crate::tokenizer::Tokenizer;
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{debug, error};
+
+/// Placeholder for the API key in header templates
+pub const API_KEY_PLACEHOLDER: &str = "{api_key}";
+
+/// Expands a header template, substituting the API key
+///
+/// Fails with `ProviderError::InvalidApiKey` if the template needs a key and
+/// none is available.
+pub fn expand_header_template(template: &str, api_key: Option<&str>) -> Result<String, ProviderError> {
+    if !template.contains(API_KEY_PLACEHOLDER) {
+        return Ok(template.to_string());
+    }
+    match api_key {
+        Some(key) if !key.is_empty() => Ok(template.replace(API_KEY_PLACEHOLDER, key)),
+        _ => Err(ProviderError::InvalidApiKey),
+    }
+}
+
+/// Provider for self-hosted and proxy servers with an OpenAI-compatible API
+pub struct OpenAICompatibleProvider {
+    client: reqwest::Client,
+    name: String,
+    base_url: String,
+    headers: HeaderMap,
+    models: Vec<ModelInfo>,
+}
+
+impl OpenAICompatibleProvider {
+    /// Creates a provider called `name` for the server at `base_url`
+    ///
+    /// `base_url` includes the API version prefix, e.g. `http://localhost:8000/v1`.
+    pub fn new(name: impl Into<String>, base_url: impl Into<String>) -> Result<Self, ProviderError> {
+        Self::with_timeout(name, base_url, Duration::from_secs(120))
+    }
+
+    /// Creates a provider with a custom request timeout
+    pub fn with_timeout(
+        name: impl Into<String>,
+        base_url: impl Into<String>,
+        timeout: Duration,
+    ) -> Result<Self, ProviderError> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .use_rustls_tls()
+            .build()
+            .map_err(|e| ProviderError::InternalError(format!("Failed to build HTTP client: {}", e)))?;
+
+        Ok(Self {
+            client,
+            name: name.into(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            headers: HeaderMap::new(),
+            models: Vec::new(),
+        })
+    }
+
+    /// Uses `client` for every request, e.g. one built from transport settings
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Sends `value` in header `name` with every request
+    pub fn with_header(mut self, name: &str, value: &str) -> Result<Self, ProviderError> {
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| ProviderError::InvalidRequest(format!("Invalid header name '{}': {}", name, e)))?;
+        let mut header_value = HeaderValue::from_str(value)
+            .map_err(|e| ProviderError::InvalidRequest(format!("Invalid value for header '{}': {}", name, e)))?;
+        header_value.set_sensitive(true);
+        self.headers.insert(header_name, header_value);
+        Ok(self)
+    }
+
+    /// Authenticates with `Authorization: Bearer <api_key>`
+    pub fn with_api_key(self, api_key: &str) -> Result<Self, ProviderError> {
+        if api_key.is_empty() {
+            return Err(ProviderError::InvalidApiKey);
+        }
+        self.with_header("Authorization", &format!("Bearer {}", api_key))
+    }
+
+    /// Adds a model served by this server
+    pub fn with_model(mut self, id: impl Into<String>, context_length: usize) -> Self {
+        let id = id.into();
+        self.models.push(ModelInfo::new(id.clone(), id, context_length, true, true));
+        self
+    }
+
+    fn build_request_body(&self, request: &CompletionRequest, stream: bool) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "model": request.model,
+            "messages": request.chat_messages(),
+            "stream": stream,
+        });
+
+        if stream {
+            body["stream_options"] = serde_json::json!({ "include_usage": true });
+        }
+        if let Some(temp) = request.temperature {
+            body["temperature"] = serde_json::json!(temp);
+        }
+        if let Some(max_tokens) = request.max_tokens {
+            body["max_tokens"] = serde_json::json!(max_tokens);
+        }
+        if let Some(top_p) = request.top_p {
+            body["top_p"] = serde_json::json!(top_p);
+        }
+        if let Some(ref stop) = request.stop {
+            body["stop"] = serde_json::json!(stop);
+        }
+        if !request.tools.is_empty() {
+            body["tools"] = openai_tools(&request.tools);
+        }
+        if let Some(ref tool_choice) = request.tool_choice {
+            body["tool_choice"] = openai_tool_choice(tool_choice, "required");
+        }
+        if let Some(ref format) = request.response_format {
+            body["response_format"] = openai_response_format(format);
+        }
+        if let Some(options) = request.logprobs {
+            body["logprobs"] = serde_json::json!(true);
+            if options.top_logprobs > 0 {
+                body["top_logprobs"] = serde_json::json!(options.top_logprobs);
+            }
+        }
+        // Servers ignore or reject what they do not support, so pass everything on
+        sampling::apply_openai_params(request, &mut body);
+
+        body
+    }
+
+    /// Posts `body` to `path` under the base URL
+    async fn send(&self, path: &str, body: &serde_json::Value) -> Result<reqwest::Response, ProviderError> {
+        let url = format!("{}{}", self.base_url, path);
+        let response = self.client
+            .post(&url)
+            .headers(self.headers.clone())
+            .json(body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            error!("{} API error ({}): {}", self.name, status, text);
+            return Err(OpenAIProvider::parse_error_response(status.as_u16(), &text));
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl Provider for OpenAICompatibleProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        debug!("Sending request to {} at {}", self.name, self.base_url);
+
+        let body = self.build_request_body(&request, false);
+        let text = self.send("/chat/completions", &body).await?.text().await?;
+
+        #[derive(Deserialize)]
+        struct ChatResponse {
+            #[serde(default)]
+            id: String,
+            #[serde(default)]
+            model: Option<String>,
+            choices: Vec<Choice>,
+            #[serde(default)]
+            usage: Option<Usage>,
+        }
+
+        #[derive(Deserialize)]
+        struct Choice {
+            message: Message,
+            finish_reason: Option<String>,
+            #[serde(default)]
+            logprobs: Option<serde_json::Value>,
+        }
+
+        #[derive(Deserialize)]
+        struct Message {
+            content: Option<String>,
+            #[serde(default)]
+            tool_calls: Vec<OpenAIToolCall>,
+        }
+
+        #[derive(Deserialize)]
+        struct Usage {
+            prompt_tokens: usize,
+            #[serde(default)]
+            completion_tokens: usize,
+        }
+
+        let resp: ChatResponse = serde_json::from_str(&text)?;
+        let logprobs = resp.choices.first()
+            .and_then(|choice| choice.logprobs.as_ref())
+            .and_then(logprobs::parse);
+
+        let choices = resp.choices.into_iter().enumerate()
+            .map(|(index, choice)| CompletionChoice {
+                index,
+                content: choice.message.content.unwrap_or_default(),
+                finish_reason: match choice.finish_reason.as_deref() {
+                    Some("length") => FinishReason::Length,
+                    Some("content_filter") => FinishReason::ContentFilter,
+                    Some("tool_calls") | Some("function_call") => FinishReason::ToolCalls,
+                    _ => FinishReason::Stop,
+                },
+                tool_calls: choice.message.tool_calls.into_iter().map(Into::into).collect(),
+            })
+            .collect();
+        let (first, choices) = sampling::split_choices(choices)?;
+
+        // Some servers omit usage; fall back to local estimates
+        let usage = match resp.usage {
+            Some(usage) => TokenUsage::new(usage.prompt_tokens, usage.completion_tokens),
+            None => {
+                let tokenizer = Tokenizer::for_model(&request.model);
+                let completion_tokens = if choices.is_empty() {
+                    tokenizer.count_tokens(&first.content)
+                } else {
+                    choices.iter().map(|choice| tokenizer.count_tokens(&choice.content)).sum()
+                };
+                TokenUsage::new(tokenizer.count_tokens(&request.prompt), completion_tokens)
+            }
+        };
+
+        let mut response = CompletionResponse {
+            id: resp.id,
+            content: first.content,
+            model: resp.model.unwrap_or_else(|| request.model.clone()),
+            usage,
+            finish_reason: first.finish_reason,
+            created_at: chrono::Utc::now(),
+            tool_calls: first.tool_calls,
+            schema_violations: Vec::new(),
+            logprobs,
+            prompt_logprobs: None,
+            choices,
+            metadata: HashMap::new(),
+        };
+        structured::check_response(&request, &mut response);
+        Ok(response)
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+        // Every sampling parameter is accepted, but a stream is one completion
+        SamplingSupport::ALL.check_stream(self.name(), &request)?;
+
+        let body = self.build_request_body(&request, true);
+        let response = self.send("/chat/completions", &body).await?;
+        Ok(streaming::openai_stream(response))
+    }
+
+    fn supported_models(&self) -> Vec<ModelInfo> {
+        self.models.clone()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        let request = self.client
+            .get(format!("{}/models", self.base_url))
+            .headers(self.headers.clone());
+        let mut models = discovery::parse_openai_models(&discovery::fetch_json(request).await?);
+        // Servers rarely report context windows; use the configured ones
+        for model in &mut models {
+            if model.max_tokens == 0 {
+                model.max_tokens = self.max_context_length(&model.id).unwrap_or(0);
+            }
+        }
+        Ok(models)
+    }
+
+    fn max_context_length(&self, model: &str) -> Option<usize> {
+        self.models.iter().find(|m| m.id == model).map(|m| m.max_tokens)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn validate_config(&self) -> Result<(), ProviderError> {
+        // Every compatible server lists its models; this checks reachability and auth
+        let url = format!("{}/models", self.base_url);
+        let response = self.client.get(&url).headers(self.headers.clone()).send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let text = response.text().await.unwrap_or_default();
+        Err(OpenAIProvider::parse_error_response(status.as_u16(), &text))
+    }
+
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        Ok(Tokenizer::for_model(model).count_tokens(text))
+    }
+}
+
+/// Servers such as vLLM, llama.cpp and Text Embeddings Inference serve
+/// `/embeddings` alongside chat completions
+#[async_trait]
+impl EmbeddingProvider for OpenAICompatibleProvider {
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, ProviderError> {
+        debug!("Sending embedding request to {} at {}", self.name, self.base_url);
+
+        let body = embeddings::openai_embedding_body(&request);
+        let text = self.send("/embeddings", &body).await?.text().await?;
+        embeddings::parse_openai_embeddings(&text, &request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_header_template() {
+        assert_eq!(expand_header_template("Bearer {api_key}", Some("sk-1")).unwrap(), "Bearer sk-1");
+        assert_eq!(expand_header_template("static", None).unwrap(), "static");
+        assert!(matches!(
+            expand_header_template("{api_key}", None),
+            Err(ProviderError::InvalidApiKey)
+        ));
+        assert!(matches!(
+            expand_header_template("{api_key}", Some("")),
+            Err(ProviderError::InvalidApiKey)
+        ));
+    }
+
+    #[test]
+    fn test_models_come_from_configuration() {
+        let provider = OpenAICompatibleProvider::new("lm-studio", "http://localhost:1234/v1/")
+            .unwrap()
+            .with_model("qwen2.5-7b-instruct", 32768);
+
+        assert_eq!(provider.name(), "lm-studio");
+        assert_eq!(provider.base_url, "http://localhost:1234/v1");
+        assert_eq!(provider.supported_models().len(), 1);
+        assert_eq!(provider.max_context_length("qwen2.5-7b-instruct"), Some(32768));
+        assert_eq!(provider.max_context_length("other"), None);
+    }
+
+    #[test]
+    fn test_invalid_header_is_rejected() {
+        let provider = OpenAICompatibleProvider::new("local", "http://localhost:8000/v1").unwrap();
+        assert!(matches!(
+            provider.with_header("bad header", "value"),
+            Err(ProviderError::InvalidRequest(_))
+        ));
+    }
+}
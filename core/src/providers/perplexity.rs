@@ -6,13 +6,26 @@
 
 //! Perplexity AI provider implementation (OpenAI-compatible)
 
+use super::sampling::{self, SamplingSupport};
+use super::streaming;
+use super::structured::{self, ResponseFormat};
 use super::{CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use crate::tokenizer::Tokenizer;
 use async_trait::async_trait;
-use futures::stream::StreamExt;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::time::Duration;
 use tracing::{debug, error};
 
+/// Perplexity takes `top_k` and penalties, but no seed, logit bias or
+/// multiple completions
+const PERPLEXITY_SAMPLING: SamplingSupport = SamplingSupport {
+    presence_penalty: true,
+    frequency_penalty: true,
+    top_k: true,
+    ..SamplingSupport::NONE
+};
+
 /// Perplexity AI provider
 pub struct PerplexityProvider {
     client: reqwest::Client,
@@ -39,10 +52,16 @@ impl PerplexityProvider {
         Ok(Self { client, api_key, base_url })
     }
 
+    /// Uses `client` for every request, e.g. one built from transport settings
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
     fn build_request_body(&self, request: &CompletionRequest, stream: bool) -> serde_json::Value {
         let mut body = serde_json::json!({
             "model": request.model,
-            "messages": [{"role": "user", "content": request.prompt}],
+            "messages": request.chat_messages(),
             "stream": stream,
         });
 
@@ -56,6 +75,21 @@ impl PerplexityProvider {
             body["top_p"] = serde_json::json!(top_p);
         }
 
+        // Perplexity only accepts schemas, so JSON mode asks for any object
+        let schema = match request.response_format {
+            Some(ResponseFormat::JsonObject) => Some(serde_json::json!({ "type": "object" })),
+            Some(ResponseFormat::JsonSchema { ref schema, .. }) => Some(schema.clone()),
+            Some(ResponseFormat::Text) | None => None,
+        };
+        if let Some(schema) = schema {
+            body["response_format"] = serde_json::json!({
+                "type": "json_schema",
+                "json_schema": { "schema": schema },
+            });
+        }
+
+        sampling::apply_openai_params(request, &mut body);
+
         body
     }
 }
@@ -63,6 +97,8 @@ impl PerplexityProvider {
 #[async_trait]
 impl Provider for PerplexityProvider {
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        PERPLEXITY_SAMPLING.check(self.name(), &request)?;
+
         let url = format!("{}/chat/completions", self.base_url);
         let body = self.build_request_body(&request, false);
 
@@ -115,7 +151,7 @@ impl Provider for PerplexityProvider {
         let choice = resp.choices.first()
             .ok_or_else(|| ProviderError::ApiError { status: 500, message: "No choices".to_string() })?;
 
-        Ok(CompletionResponse {
+        let mut response = CompletionResponse {
             id: resp.id,
             content: choice.message.content.clone(),
             model: resp.model,
@@ -123,6 +159,9 @@ impl Provider for PerplexityProvider {
                 prompt_tokens: resp.usage.prompt_tokens as usize,
                 completion_tokens: resp.usage.completion_tokens as usize,
                 total_tokens: resp.usage.total_tokens as usize,
+                reasoning_tokens: 0,
+                cached_tokens: 0,
+                cache_write_tokens: 0,
             },
             finish_reason: match choice.finish_reason.as_str() {
                 "stop" => FinishReason::Stop,
@@ -130,10 +169,20 @@ impl Provider for PerplexityProvider {
                 _ => FinishReason::Stop,
             },
             created_at: chrono::Utc::now(),
-        })
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
+        };
+        structured::check_response(&request, &mut response);
+        Ok(response)
     }
 
     async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+        PERPLEXITY_SAMPLING.check_stream(self.name(), &request)?;
+
         let url = format!("{}/chat/completions", self.base_url);
         let body = self.build_request_body(&request, true);
 
@@ -151,30 +200,7 @@ impl Provider for PerplexityProvider {
             return Err(ProviderError::ApiError { status, message: text });
         }
 
-        let stream = response.bytes_stream().map(move |result| {
-            match result {
-                Ok(bytes) => {
-                    let text = String::from_utf8_lossy(&bytes);
-                    for line in text.lines() {
-                        if line.starts_with("data: ") {
-                            let json_str = line.strip_prefix("data: ").unwrap_or("");
-                            if json_str == "[DONE]" {
-                                continue;
-                            }
-                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(json_str) {
-                                if let Some(content) = json["choices"][0]["delta"]["content"].as_str() {
-                                    return Ok(content.to_string());
-                                }
-                            }
-                        }
-                    }
-                    Ok(String::new())
-                }
-                Err(e) => Err(ProviderError::NetworkError(e)),
-            }
-        });
-
-        Ok(Box::pin(stream))
+        Ok(streaming::openai_stream(response))
     }
 
     fn supported_models(&self) -> Vec<ModelInfo> {
@@ -208,7 +234,7 @@ impl Provider for PerplexityProvider {
         Ok(())
     }
 
-    fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
-        Ok((text.len() / 4).max(1))
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        Ok(Tokenizer::for_model(model).count_tokens(text))
     }
 }
@@ -0,0 +1,533 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Client-side rate limiting.
+//!
+//! [`RateLimitedProvider`] wraps any provider with a [`RateLimiter`] holding
+//! two token buckets: one for requests per minute and one for tokens per
+//! minute. Every request waits until both buckets can cover it. When the
+//! provider still answers with [`ProviderError::RateLimitExceeded`], all
+//! requests sharing the limiter pause for the `retry_after` period.
+//!
+//! [`ProviderFactory::create`](super::ProviderFactory::create) adds the
+//! wrapper automatically when `rate_limit_rpm` or `rate_limit_tpm` is set in
+//! the provider's configuration. Limiters are shared per provider endpoint,
+//! so every task and every provider instance created for the same provider
+//! draws from the same budget.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use llm_test_bench_core::providers::{OpenAIProvider, RateLimitedProvider, RateLimiter};
+//! use std::sync::Arc;
+//!
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let limiter = Arc::new(RateLimiter::new(Some(500), Some(30_000)));
+//! let provider = RateLimitedProvider::new(Box::new(OpenAIProvider::new("api-key".to_string())?), limiter);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{CompletionRequest, CompletionResponse, ModelInfo, Provider, ProviderError, ResponseStream};
+use super::streaming::{StreamEvent, StreamEventKind};
+use crate::tokenizer;
+use async_trait::async_trait;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+/// Pause applied after a rate limit error that carries no `retry_after`.
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// A token bucket refilled continuously at `limit` per minute.
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    available: f64,
+    per_second: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(per_minute: u32) -> Self {
+        let per_minute = f64::from(per_minute.max(1));
+        // Hold at most ten seconds' worth, so a full bucket does not release
+        // a minute of traffic at once and trip per-second enforcement
+        let capacity = (per_minute / 6.0).max(1.0);
+        Self {
+            capacity,
+            available: capacity,
+            per_second: per_minute / 60.0,
+            updated_at: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.available = (self.available + elapsed * self.per_second).min(self.capacity);
+        self.updated_at = now;
+    }
+
+    /// Time until `amount` is available (amounts above the capacity only
+    /// wait for a full bucket).
+    fn wait_for(&self, amount: f64) -> Duration {
+        let missing = amount.min(self.capacity) - self.available;
+        if missing <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(missing / self.per_second)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    requests: Option<TokenBucket>,
+    tokens: Option<TokenBucket>,
+    paused_until: Option<Instant>,
+}
+
+/// Requests-per-minute and tokens-per-minute limits shared by provider calls.
+///
+/// Token usage is not known until a request completes, so each request
+/// reserves its estimate up front (prompt tokens plus `max_tokens`) and the
+/// difference is settled with [`record_usage`](Self::record_usage). Failed
+/// requests return their whole reservation.
+#[derive(Debug)]
+pub struct RateLimiter {
+    state: Mutex<LimiterState>,
+}
+
+impl RateLimiter {
+    /// Creates a limiter; `None` leaves that dimension unlimited.
+    pub fn new(requests_per_minute: Option<u32>, tokens_per_minute: Option<u32>) -> Self {
+        Self {
+            state: Mutex::new(LimiterState {
+                requests: requests_per_minute.map(TokenBucket::new),
+                tokens: tokens_per_minute.map(TokenBucket::new),
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// Returns the limiter registered under `key`, creating it on first use.
+    ///
+    /// The limits of the first call win; later calls share its buckets.
+    pub fn shared(key: &str, requests_per_minute: Option<u32>, tokens_per_minute: Option<u32>) -> Arc<Self> {
+        static LIMITERS: OnceLock<Mutex<HashMap<String, Arc<RateLimiter>>>> = OnceLock::new();
+
+        let mut limiters = LIMITERS
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        Arc::clone(
+            limiters
+                .entry(key.to_string())
+                .or_insert_with(|| Arc::new(Self::new(requests_per_minute, tokens_per_minute))),
+        )
+    }
+
+    /// Waits until a request estimated at `tokens` tokens may be sent, then
+    /// reserves one request and the tokens.
+    pub async fn acquire(&self, tokens: usize) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+                let now = Instant::now();
+
+                let paused = state
+                    .paused_until
+                    .map(|until| until.saturating_duration_since(now))
+                    .unwrap_or_default();
+
+                let mut wait = paused;
+                if let Some(requests) = state.requests.as_mut() {
+                    requests.refill(now);
+                    wait = wait.max(requests.wait_for(1.0));
+                }
+                if let Some(bucket) = state.tokens.as_mut() {
+                    bucket.refill(now);
+                    wait = wait.max(bucket.wait_for(tokens as f64));
+                }
+
+                if wait.is_zero() {
+                    state.paused_until = None;
+                    if let Some(requests) = state.requests.as_mut() {
+                        requests.available -= 1.0;
+                    }
+                    if let Some(bucket) = state.tokens.as_mut() {
+                        bucket.available -= tokens as f64;
+                    }
+                    return;
+                }
+                wait
+            };
+
+            debug!("Rate limiter delaying request by {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Settles a request's token reservation with its actual usage.
+    ///
+    /// Tokens reserved but not used are returned; usage above the estimate is
+    /// taken from the bucket, delaying later requests.
+    pub fn record_usage(&self, reserved: usize, used: usize) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(bucket) = state.tokens.as_mut() {
+            bucket.refill(Instant::now());
+            bucket.available = (bucket.available + reserved as f64 - used as f64).min(bucket.capacity);
+        }
+    }
+
+    /// Backs off after the provider rejected a request for exceeding its limits.
+    ///
+    /// No request is released until `retry_after` (or one second) has passed,
+    /// and the request bucket is emptied so traffic resumes gradually.
+    pub fn on_rate_limited(&self, retry_after: Option<Duration>) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let until = Instant::now() + retry_after.unwrap_or(DEFAULT_BACKOFF);
+        state.paused_until = Some(state.paused_until.map_or(until, |current| current.max(until)));
+        if let Some(requests) = state.requests.as_mut() {
+            requests.available = requests.available.min(0.0);
+        }
+    }
+}
+
+/// A provider that waits for its [`RateLimiter`] before every request.
+pub struct RateLimitedProvider {
+    inner: Box<dyn Provider>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitedProvider {
+    /// Wraps `inner` with `limiter`.
+    pub fn new(inner: Box<dyn Provider>, limiter: Arc<RateLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+
+    /// The limiter used by this provider.
+    pub fn limiter(&self) -> &Arc<RateLimiter> {
+        &self.limiter
+    }
+
+    /// Tokens to reserve for a request: the prompt plus the requested maximum
+    /// output for each completion.
+    fn reservation(&self, request: &CompletionRequest) -> usize {
+        let prompt: String = request.chat_messages().iter().map(|m| m.content.as_str()).collect();
+        let prompt_tokens = self
+            .inner
+            .estimate_tokens(&prompt, &request.model)
+            .unwrap_or_else(|_| tokenizer::count_tokens(&request.model, &prompt));
+        prompt_tokens + request.max_tokens.unwrap_or(0) * request.completions() as usize
+    }
+
+    fn observe_error(&self, error: &ProviderError) {
+        if let ProviderError::RateLimitExceeded { retry_after } = error {
+            warn!("{} rate limit exceeded, pausing requests", self.inner.name());
+            self.limiter.on_rate_limited(*retry_after);
+        }
+    }
+}
+
+/// The token reservation of a streamed request
+///
+/// Each usage report replaces what the request is charged. A stream that
+/// ends, fails or is dropped without reporting usage returns its whole
+/// reservation.
+struct StreamReservation {
+    limiter: Arc<RateLimiter>,
+    charged: usize,
+    reported: bool,
+}
+
+impl StreamReservation {
+    fn observe(&mut self, event: &Result<StreamEvent, ProviderError>) {
+        match event {
+            Ok(event) => {
+                if let StreamEventKind::Usage(usage) = &event.kind {
+                    self.limiter.record_usage(self.charged, usage.total_tokens);
+                    self.charged = usage.total_tokens;
+                    self.reported = true;
+                }
+            }
+            Err(ProviderError::RateLimitExceeded { retry_after }) => self.limiter.on_rate_limited(*retry_after),
+            Err(_) => {}
+        }
+    }
+}
+
+impl Drop for StreamReservation {
+    fn drop(&mut self) {
+        if !self.reported {
+            self.limiter.record_usage(self.charged, 0);
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for RateLimitedProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        let reserved = self.reservation(&request);
+        self.limiter.acquire(reserved).await;
+
+        match self.inner.complete(request).await {
+            Ok(response) => {
+                self.limiter.record_usage(reserved, response.usage.total_tokens);
+                Ok(response)
+            }
+            Err(e) => {
+                self.limiter.record_usage(reserved, 0);
+                self.observe_error(&e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+        let reserved = self.reservation(&request);
+        self.limiter.acquire(reserved).await;
+
+        let stream = match self.inner.stream(request).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                self.limiter.record_usage(reserved, 0);
+                self.observe_error(&e);
+                return Err(e);
+            }
+        };
+
+        let mut reservation = StreamReservation {
+            limiter: Arc::clone(&self.limiter),
+            charged: reserved,
+            reported: false,
+        };
+        Ok(Box::pin(stream.inspect(move |event| reservation.observe(event))))
+    }
+
+    fn supported_models(&self) -> Vec<ModelInfo> {
+        self.inner.supported_models()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        self.inner.list_models().await
+    }
+
+    fn max_context_length(&self, model: &str) -> Option<usize> {
+        self.inner.max_context_length(model)
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn validate_config(&self) -> Result<(), ProviderError> {
+        self.inner.validate_config().await
+    }
+
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        self.inner.estimate_tokens(text, model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{FinishReason, TokenUsage};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Outcome {
+        Success,
+        RateLimited,
+        ServerError,
+        /// Succeeds, but streams report no usage
+        NoStreamUsage,
+    }
+
+    struct MockProvider {
+        calls: AtomicUsize,
+        outcome: Outcome,
+    }
+
+    impl MockProvider {
+        fn new(outcome: Outcome) -> Self {
+            Self { calls: AtomicUsize::new(0), outcome }
+        }
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        async fn complete(&self, _request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match self.outcome {
+                Outcome::RateLimited => {
+                    return Err(ProviderError::RateLimitExceeded { retry_after: Some(Duration::from_secs(20)) });
+                }
+                Outcome::ServerError => {
+                    return Err(ProviderError::ApiError { status: 500, message: "boom".to_string() });
+                }
+                Outcome::Success | Outcome::NoStreamUsage => {}
+            }
+            Ok(CompletionResponse {
+                id: "mock".to_string(),
+                model: "mock-model".to_string(),
+                content: "ok".to_string(),
+                usage: TokenUsage::new(10, 10),
+                finish_reason: FinishReason::Stop,
+                created_at: chrono::Utc::now(),
+                tool_calls: Vec::new(),
+                schema_violations: Vec::new(),
+                logprobs: None,
+                prompt_logprobs: None,
+                choices: Vec::new(),
+                metadata: HashMap::new(),
+            })
+        }
+
+        async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+            let response = self.complete(request).await?;
+            let mut events = crate::providers::streaming::response_events(&response);
+            if self.outcome == Outcome::NoStreamUsage {
+                events.retain(|event| !matches!(event.kind, StreamEventKind::Usage(_)));
+            }
+            Ok(Box::pin(futures::stream::iter(events.into_iter().map(Ok))))
+        }
+
+        fn supported_models(&self) -> Vec<ModelInfo> {
+            Vec::new()
+        }
+
+        fn max_context_length(&self, _model: &str) -> Option<usize> {
+            None
+        }
+
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        async fn validate_config(&self) -> Result<(), ProviderError> {
+            Ok(())
+        }
+
+        fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
+            Ok(text.split_whitespace().count())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_requests_per_minute() {
+        // 60 RPM: a burst of 10, then one per second
+        let limiter = RateLimiter::new(Some(60), None);
+        let start = Instant::now();
+
+        for _ in 0..10 {
+            limiter.acquire(0).await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(1));
+
+        limiter.acquire(0).await;
+        limiter.acquire(0).await;
+        assert_eq!(start.elapsed().as_secs(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tokens_per_minute() {
+        // 6000 TPM: 1000 tokens of burst, refilled at 100 per second
+        let limiter = RateLimiter::new(None, Some(6000));
+        let start = Instant::now();
+
+        limiter.acquire(800).await;
+        // Only 300 of the reservation were used
+        limiter.record_usage(800, 300);
+        limiter.acquire(700).await;
+        assert!(start.elapsed() < Duration::from_millis(1));
+
+        // The bucket is now at 0: 500 tokens take 5 seconds
+        limiter.acquire(500).await;
+        assert_eq!(start.elapsed().as_secs(), 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_oversized_request_waits_for_full_bucket() {
+        let limiter = RateLimiter::new(None, Some(600));
+        let start = Instant::now();
+
+        limiter.acquire(50).await;
+        // 5000 tokens exceed the 100-token bucket: wait until it is full again
+        limiter.acquire(5000).await;
+        assert!(start.elapsed() <= Duration::from_secs(5));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limited_response_pauses_requests() {
+        let limiter = Arc::new(RateLimiter::new(Some(600), None));
+        let provider = RateLimitedProvider::new(Box::new(MockProvider::new(Outcome::RateLimited)), Arc::clone(&limiter));
+        let start = Instant::now();
+
+        let result = provider.complete(CompletionRequest::new("mock-model", "Hello")).await;
+        assert!(matches!(result, Err(ProviderError::RateLimitExceeded { .. })));
+
+        // Every user of the limiter now waits out the retry-after period
+        limiter.acquire(0).await;
+        assert!(start.elapsed() >= Duration::from_secs(20));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_provider_settles_usage() {
+        let limiter = Arc::new(RateLimiter::new(None, Some(600)));
+        let provider = RateLimitedProvider::new(Box::new(MockProvider::new(Outcome::Success)), Arc::clone(&limiter));
+        let start = Instant::now();
+
+        // Reserves 2 + 50 tokens, uses 20
+        let request = CompletionRequest::new("mock-model", "Hello there").with_max_tokens(50);
+        provider.complete(request.clone()).await.unwrap();
+        let stream = provider.stream(request).await.unwrap();
+        stream.collect::<Vec<_>>().await;
+
+        // 100 - 20 - 20 = 60 tokens left
+        limiter.acquire(60).await;
+        assert!(start.elapsed() < Duration::from_millis(1));
+        assert_eq!(provider.name(), "mock");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failed_requests_return_reservation() {
+        let limiter = Arc::new(RateLimiter::new(None, Some(600)));
+        let start = Instant::now();
+
+        // Each request reserves 2 + 50 tokens and gives them all back
+        let request = CompletionRequest::new("mock-model", "Hello there").with_max_tokens(50);
+        let failing = RateLimitedProvider::new(Box::new(MockProvider::new(Outcome::ServerError)), Arc::clone(&limiter));
+        for _ in 0..3 {
+            assert!(failing.complete(request.clone()).await.is_err());
+            assert!(failing.stream(request.clone()).await.is_err());
+        }
+        let silent = RateLimitedProvider::new(Box::new(MockProvider::new(Outcome::NoStreamUsage)), Arc::clone(&limiter));
+        for _ in 0..3 {
+            let stream = silent.stream(request.clone()).await.unwrap();
+            stream.collect::<Vec<_>>().await;
+        }
+        // A stream dropped before it ends returns its reservation too
+        drop(silent.stream(request).await.unwrap());
+
+        limiter.acquire(100).await;
+        assert!(start.elapsed() < Duration::from_millis(1));
+    }
+
+    #[test]
+    fn test_shared_limiters() {
+        let a = RateLimiter::shared("test|https://a.example.com", Some(10), None);
+        let b = RateLimiter::shared("test|https://a.example.com", Some(99), None);
+        let c = RateLimiter::shared("test|https://c.example.com", Some(10), None);
+
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+    }
+}
@@ -6,13 +6,35 @@
 
 //! Replicate provider implementation
 
+use super::discovery;
+use super::sampling::SamplingSupport;
+use super::streaming::{self, SseDecoder, SseEvent, StreamEvent};
+use super::structured;
 use super::{CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use crate::tokenizer::Tokenizer;
 use async_trait::async_trait;
-use futures::stream::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::Duration;
 use tracing::{debug, error};
 
+/// The hosted language models take these as inputs and return one completion
+const REPLICATE_SAMPLING: SamplingSupport = SamplingSupport { n: false, logit_bias: false, ..SamplingSupport::ALL };
+
+/// A prediction as returned when it is created
+#[derive(Deserialize)]
+struct Prediction {
+    id: String,
+    urls: PredictionUrls,
+}
+
+#[derive(Deserialize)]
+struct PredictionUrls {
+    get: String,
+    /// Server-sent events with the output, for predictions created with `stream`
+    stream: Option<String>,
+}
+
 /// Replicate provider
 pub struct ReplicateProvider {
     client: reqwest::Client,
@@ -39,13 +61,23 @@ impl ReplicateProvider {
         Ok(Self { client, api_key, base_url })
     }
 
+    /// Uses `client` for every request, e.g. one built from transport settings
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
     fn build_request_body(&self, request: &CompletionRequest) -> serde_json::Value {
         let version = self.get_model_version(&request.model);
 
         let mut input = serde_json::json!({
-            "prompt": request.prompt,
+            "prompt": request.render_prompt(),
         });
 
+        if let Some(system) = request.system_prompt() {
+            input["system_prompt"] = serde_json::json!(system);
+        }
+
         if let Some(temp) = request.temperature {
             input["temperature"] = serde_json::json!(temp);
         }
@@ -55,6 +87,18 @@ impl ReplicateProvider {
         if let Some(top_p) = request.top_p {
             input["top_p"] = serde_json::json!(top_p);
         }
+        if let Some(top_k) = request.top_k {
+            input["top_k"] = serde_json::json!(top_k);
+        }
+        if let Some(seed) = request.seed {
+            input["seed"] = serde_json::json!(seed);
+        }
+        if let Some(penalty) = request.presence_penalty {
+            input["presence_penalty"] = serde_json::json!(penalty);
+        }
+        if let Some(penalty) = request.frequency_penalty {
+            input["frequency_penalty"] = serde_json::json!(penalty);
+        }
 
         serde_json::json!({
             "version": version,
@@ -70,21 +114,17 @@ impl ReplicateProvider {
             _ => model,
         }.to_string()
     }
-}
 
-#[async_trait]
-impl Provider for ReplicateProvider {
-    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+    /// Starts a prediction
+    async fn create_prediction(&self, body: &serde_json::Value) -> Result<Prediction, ProviderError> {
         let url = format!("{}/predictions", self.base_url);
-        let body = self.build_request_body(&request);
 
         debug!("Sending request to Replicate");
 
-        // Start prediction
         let response = self.client
             .post(&url)
             .header("Authorization", format!("Token {}", self.api_key))
-            .json(&body)
+            .json(body)
             .send()
             .await
             .map_err(|e| ProviderError::NetworkError(e))?;
@@ -97,19 +137,47 @@ impl Provider for ReplicateProvider {
         }
 
         let text = response.text().await.map_err(|e| ProviderError::NetworkError(e))?;
+        Ok(serde_json::from_str(&text)?)
+    }
 
-        #[derive(Deserialize)]
-        struct PredictionResponse {
-            id: String,
-            urls: Urls,
-        }
+    /// Maps an event from a prediction's stream URL to stream events
+    ///
+    /// `output` events carry raw text, `error` events a JSON `detail` and
+    /// `done` ends the stream, with a `reason` when the prediction did not
+    /// succeed. `content` collects the output to estimate usage.
+    fn stream_chunk_events(event: &SseEvent, model: &str, content: &mut String) -> Result<Vec<StreamEvent>, ProviderError> {
+        match event.event.as_deref() {
+            Some("output") if !event.data.is_empty() => {
+                content.push_str(&event.data);
+                Ok(vec![StreamEvent::content(event.data.clone())])
+            }
+            Some("error") => {
+                let detail = serde_json::from_str::<serde_json::Value>(&event.data)
+                    .ok()
+                    .and_then(|json| json["detail"].as_str().map(str::to_string))
+                    .unwrap_or_else(|| event.data.clone());
+                Err(ProviderError::ApiError { status: 500, message: detail })
+            }
+            Some("done") => {
+                let done: serde_json::Value = serde_json::from_str(&event.data).unwrap_or_default();
+                if let Some(reason) = done["reason"].as_str().filter(|reason| !reason.is_empty()) {
+                    return Err(ProviderError::ApiError { status: 500, message: format!("Prediction {}", reason) });
+                }
 
-        #[derive(Deserialize)]
-        struct Urls {
-            get: String,
+                // The stream does not report usage
+                let tokens = Tokenizer::for_model(model).count_tokens(content).max(1);
+                Ok(vec![
+                    StreamEvent::usage(TokenUsage::new(0, tokens)),
+                    StreamEvent::finish(FinishReason::Stop),
+                ])
+            }
+            _ => Ok(Vec::new()),
         }
+    }
 
-        let pred: PredictionResponse = serde_json::from_str(&text)?;
+    /// Run a single completion request
+    async fn complete_once(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        let pred = self.create_prediction(&self.build_request_body(&request)).await?;
 
         // Poll for result
         let mut attempts = 0;
@@ -152,7 +220,7 @@ impl Provider for ReplicateProvider {
                         output.to_string()
                     };
 
-                    let tokens = (content.len() / 4).max(1);
+                    let tokens = Tokenizer::for_model(&request.model).count_tokens(&content).max(1);
 
                     return Ok(CompletionResponse {
                         id: pred.id,
@@ -162,9 +230,18 @@ impl Provider for ReplicateProvider {
                             prompt_tokens: 0,
                             completion_tokens: tokens,
                             total_tokens: tokens,
+                            reasoning_tokens: 0,
+                            cached_tokens: 0,
+                            cache_write_tokens: 0,
                         },
                         finish_reason: FinishReason::Stop,
                         created_at: chrono::Utc::now(),
+                        tool_calls: Vec::new(),
+                        schema_violations: Vec::new(),
+                        logprobs: None,
+                        prompt_logprobs: None,
+                        choices: Vec::new(),
+                        metadata: HashMap::new(),
                     });
                 }
                 "failed" | "canceled" => {
@@ -180,17 +257,52 @@ impl Provider for ReplicateProvider {
             }
         }
     }
+}
+
+#[async_trait]
+impl Provider for ReplicateProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+        REPLICATE_SAMPLING.check(self.name(), &request)?;
+
+        // Replicate models take free-form inputs, so response formats are
+        // enforced via the prompt
+        structured::complete_with_repair(request, |request| self.complete_once(request)).await
+    }
 
     async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
-        // Replicate doesn't support true streaming, return single result
-        let response = self.complete(request).await?;
-        let content = response.content.clone();
+        REPLICATE_SAMPLING.check_stream(self.name(), &request)?;
 
-        let stream = futures::stream::once(async move {
-            Ok(content)
-        });
+        // Predictions created with `stream` publish their output as
+        // server-sent events on a separate URL
+        let request = structured::with_format_instructions(&request);
+        let mut body = self.build_request_body(&request);
+        body["stream"] = serde_json::json!(true);
+        let prediction = self.create_prediction(&body).await?;
+
+        let url = prediction.urls.stream.ok_or_else(|| {
+            ProviderError::InvalidRequest(format!("Model '{}' does not support streaming", request.model))
+        })?;
 
-        Ok(Box::pin(stream))
+        let response = self.client
+            .get(&url)
+            .header("Authorization", format!("Token {}", self.api_key))
+            .header("Accept", "text/event-stream")
+            .header("Cache-Control", "no-store")
+            .send()
+            .await
+            .map_err(|e| ProviderError::NetworkError(e))?;
+
+        let status = response.status().as_u16();
+        if !response.status().is_success() {
+            let text = response.text().await.unwrap_or_default();
+            error!("Replicate streaming error ({}): {}", status, text);
+            return Err(ProviderError::ApiError { status, message: text });
+        }
+
+        let mut content = String::new();
+        Ok(streaming::decode_stream(response.bytes_stream(), SseDecoder::default(), move |event| {
+            Self::stream_chunk_events(&event, &request.model, &mut content)
+        }))
     }
 
     fn supported_models(&self) -> Vec<ModelInfo> {
@@ -202,6 +314,22 @@ impl Provider for ReplicateProvider {
         ]
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        // Replicate hosts every kind of model; its language-model collection lists the text ones
+        let request = self.client
+            .get(format!("{}/collections/language-models", self.base_url))
+            .header("Authorization", format!("Token {}", self.api_key));
+        let json = discovery::fetch_json(request).await?;
+        Ok(json["models"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|model| Some(format!("{}/{}", model["owner"].as_str()?, model["name"].as_str()?)))
+            .map(|id| ModelInfo::new(id.clone(), id, 0, true, false))
+            .collect())
+    }
+
     fn max_context_length(&self, model: &str) -> Option<usize> {
         if model.contains("llama-2") {
             Some(4096)
@@ -223,7 +351,7 @@ impl Provider for ReplicateProvider {
         Ok(())
     }
 
-    fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
-        Ok((text.len() / 4).max(1))
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        Ok(Tokenizer::for_model(model).count_tokens(text))
     }
 }
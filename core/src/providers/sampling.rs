@@ -0,0 +1,220 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Extended sampling controls
+//!
+//! Beyond `temperature` and `top_p`, a [`CompletionRequest`] can set a
+//! `seed`, presence and frequency penalties, `top_k`, the number of
+//! completions `n` and a `logit_bias`. Support differs between APIs, so each
+//! provider declares a [`SamplingSupport`] and rejects requests using
+//! anything else with [`ProviderError::InvalidRequest`] before sending them,
+//! rather than silently ignoring a parameter a reproducibility study relies
+//! on.
+//!
+//! The reasoning controls are checked the same way. OpenAI and Azure accept
+//! a `reasoning_effort`; Anthropic and Google accept a `thinking_budget`.
+//!
+//! | Provider          | seed | penalties | top_k | n | logit_bias |
+//! |-------------------|------|-----------|-------|---|------------|
+//! | OpenAI, Azure     | ✓    | ✓         |       | ✓ | ✓          |
+//! | Anthropic         |      |           | ✓     |   |            |
+//! | Google            | ✓    | ✓         | ✓     | ✓ |            |
+//! | Cohere            | ✓    | ✓         | ✓     |   |            |
+//! | Mistral           | ✓    | ✓         |       | ✓ |            |
+//! | Groq              | ✓    | ✓         |       |   |            |
+//! | Together          | ✓    | ✓         | ✓     | ✓ | ✓          |
+//! | Hugging Face      | ✓    |           | ✓     |   |            |
+//! | Ollama            | ✓    | ✓         | ✓     |   |            |
+//! | Bedrock Claude    |      |           | ✓     |   |            |
+//! | Bedrock Command R | ✓    | ✓         | ✓     |   |            |
+//! | Replicate         | ✓    | ✓         | ✓     |   |            |
+//! | Perplexity        |      | ✓         | ✓     |   |            |
+//! | OpenAI-compatible | ✓    | ✓         | ✓     | ✓ | ✓          |
+//!
+//! Of Bedrock's other model families, the original Command models accept
+//! `top_k` and Titan and Llama accept none. `n = 1` is accepted everywhere.
+//! Streaming always produces a single completion.
+
+use super::{CompletionChoice, CompletionRequest, ProviderError};
+
+/// Sampling parameters a provider accepts beyond `temperature` and `top_p`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SamplingSupport {
+    /// Deterministic sampling seed
+    pub seed: bool,
+    /// `presence_penalty`
+    pub presence_penalty: bool,
+    /// `frequency_penalty`
+    pub frequency_penalty: bool,
+    /// `top_k`
+    pub top_k: bool,
+    /// More than one completion per request
+    pub n: bool,
+    /// `logit_bias`
+    pub logit_bias: bool,
+    /// `reasoning_effort`
+    pub reasoning_effort: bool,
+    /// `thinking_budget`
+    pub thinking_budget: bool,
+}
+
+impl SamplingSupport {
+    /// None of the extended parameters
+    pub const NONE: Self = Self {
+        seed: false,
+        presence_penalty: false,
+        frequency_penalty: false,
+        top_k: false,
+        n: false,
+        logit_bias: false,
+        reasoning_effort: false,
+        thinking_budget: false,
+    };
+
+    /// Every extended sampling parameter, but no reasoning controls
+    pub const ALL: Self = Self {
+        seed: true,
+        presence_penalty: true,
+        frequency_penalty: true,
+        top_k: true,
+        n: true,
+        logit_bias: true,
+        reasoning_effort: false,
+        thinking_budget: false,
+    };
+
+    /// The OpenAI chat completions API, which has no `top_k`
+    pub const OPENAI: Self = Self { top_k: false, reasoning_effort: true, ..Self::ALL };
+
+    /// Fails with [`ProviderError::InvalidRequest`] naming the first
+    /// parameter set on `request` that `provider` does not accept
+    pub fn check(&self, provider: &str, request: &CompletionRequest) -> Result<(), ProviderError> {
+        let unsupported = [
+            ("seed", request.seed.is_some() && !self.seed),
+            ("presence_penalty", request.presence_penalty.is_some() && !self.presence_penalty),
+            ("frequency_penalty", request.frequency_penalty.is_some() && !self.frequency_penalty),
+            ("top_k", request.top_k.is_some() && !self.top_k),
+            ("n", request.completions() > 1 && !self.n),
+            ("logit_bias", !request.logit_bias.is_empty() && !self.logit_bias),
+            ("reasoning_effort", request.reasoning_effort.is_some() && !self.reasoning_effort),
+            ("thinking_budget", request.thinking_budget.is_some() && !self.thinking_budget),
+        ];
+
+        match unsupported.iter().find(|(_, rejected)| *rejected) {
+            Some((parameter, _)) => Err(ProviderError::InvalidRequest(format!(
+                "{} does not support the {} sampling parameter",
+                provider, parameter
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Like [`check`](Self::check), but also rejects `n > 1`, as streams
+    /// carry a single completion
+    pub fn check_stream(&self, provider: &str, request: &CompletionRequest) -> Result<(), ProviderError> {
+        if request.completions() > 1 {
+            return Err(ProviderError::InvalidRequest(format!(
+                "{} cannot stream more than one completion; set n to 1",
+                provider
+            )));
+        }
+        self.check(provider, request)
+    }
+}
+
+/// Copies the extended sampling parameters set on `request` into an
+/// OpenAI-style request body
+///
+/// Providers call [`SamplingSupport::check`] first, so only parameters they
+/// accept are present.
+pub(crate) fn apply_openai_params(request: &CompletionRequest, body: &mut serde_json::Value) {
+    if let Some(seed) = request.seed {
+        body["seed"] = serde_json::json!(seed);
+    }
+    if let Some(penalty) = request.presence_penalty {
+        body["presence_penalty"] = serde_json::json!(penalty);
+    }
+    if let Some(penalty) = request.frequency_penalty {
+        body["frequency_penalty"] = serde_json::json!(penalty);
+    }
+    if let Some(top_k) = request.top_k {
+        body["top_k"] = serde_json::json!(top_k);
+    }
+    if request.completions() > 1 {
+        body["n"] = serde_json::json!(request.completions());
+    }
+    if !request.logit_bias.is_empty() {
+        body["logit_bias"] = serde_json::json!(request.logit_bias);
+    }
+    if let Some(effort) = request.reasoning_effort {
+        body["reasoning_effort"] = serde_json::json!(effort);
+    }
+}
+
+/// Splits parsed choices into the first one, which fills the top-level
+/// response fields, and the `choices` list, which stays empty for a single
+/// completion
+///
+/// Fails with an API error when the response has no choices at all.
+pub(crate) fn split_choices(
+    mut choices: Vec<CompletionChoice>,
+) -> Result<(CompletionChoice, Vec<CompletionChoice>), ProviderError> {
+    let first = choices.first().cloned().ok_or_else(|| ProviderError::ApiError {
+        status: 500,
+        message: "No choices in response".to_string(),
+    })?;
+    if choices.len() == 1 {
+        choices.clear();
+    }
+    Ok((first, choices))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::ReasoningEffort;
+
+    #[test]
+    fn test_check() {
+        let request = CompletionRequest::new("model", "Hi").with_seed(7).with_top_k(40);
+        assert!(SamplingSupport::ALL.check("together", &request).is_ok());
+
+        match SamplingSupport::OPENAI.check("openai", &request) {
+            Err(ProviderError::InvalidRequest(msg)) => assert!(msg.contains("top_k")),
+            other => panic!("Expected InvalidRequest, got {:?}", other),
+        }
+
+        // n = 1 is the default everywhere
+        let request = CompletionRequest::new("model", "Hi").with_n(1);
+        assert!(SamplingSupport::NONE.check("anthropic", &request).is_ok());
+        assert!(SamplingSupport::NONE.check("anthropic", &request.clone().with_n(3)).is_err());
+        assert!(SamplingSupport::ALL.check_stream("openai", &request.with_n(3)).is_err());
+
+        let request = CompletionRequest::new("model", "Hi").with_thinking_budget(2048);
+        assert!(SamplingSupport::OPENAI.check("openai", &request).is_err());
+    }
+
+    #[test]
+    fn test_apply_openai_params() {
+        let request = CompletionRequest::new("gpt-4", "Hi")
+            .with_seed(42)
+            .with_presence_penalty(0.5)
+            .with_frequency_penalty(-0.5)
+            .with_n(2)
+            .with_logit_bias(50256, -100.0)
+            .with_reasoning_effort(ReasoningEffort::Low);
+        let mut body = serde_json::json!({});
+        apply_openai_params(&request, &mut body);
+
+        assert_eq!(body["seed"], 42);
+        assert_eq!(body["presence_penalty"], 0.5);
+        assert_eq!(body["frequency_penalty"], -0.5);
+        assert_eq!(body["n"], 2);
+        assert_eq!(body["logit_bias"]["50256"], -100.0);
+        assert_eq!(body["reasoning_effort"], "low");
+        assert!(body.get("top_k").is_none());
+    }
+}
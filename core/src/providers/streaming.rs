@@ -0,0 +1,797 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Streaming response events.
+//!
+//! [`Provider::stream`](super::Provider::stream) yields [`StreamEvent`]s:
+//! content and tool-call deltas as the model generates them, followed by the
+//! token usage and finish reason once the provider reports them. Every event
+//! is timestamped when it is received, so a [`StreamAccumulator`] can rebuild
+//! the full [`CompletionResponse`] and derive [`StreamMetrics`] such as time
+//! to first token.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use futures::StreamExt;
+//! use llm_test_bench_core::providers::{CompletionRequest, OpenAIProvider, Provider, StreamAccumulator};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let provider = OpenAIProvider::new("api-key".to_string())?;
+//! let request = CompletionRequest::new("gpt-4o", "Write a haiku").with_streaming();
+//!
+//! let mut accumulator = StreamAccumulator::new("gpt-4o");
+//! let mut stream = provider.stream(request).await?;
+//! while let Some(event) = stream.next().await {
+//!     let event = event?;
+//!     if let Some(text) = event.text() {
+//!         print!("{}", text);
+//!     }
+//!     accumulator.push(&event);
+//! }
+//!
+//! let metrics = accumulator.metrics();
+//! println!("\nTTFT: {:?} ms", metrics.time_to_first_token_ms);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{CompletionResponse, FinishReason, ProviderError, ResponseStream, TokenUsage, ToolCall};
+use crate::tokenizer::Tokenizer;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// An event in a streaming response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamEvent {
+    /// What the event carries.
+    #[serde(flatten)]
+    pub kind: StreamEventKind,
+
+    /// When the event was received.
+    pub timestamp: DateTime<Utc>,
+}
+
+/// The payload of a [`StreamEvent`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEventKind {
+    /// Generated text.
+    ContentDelta {
+        /// The text fragment.
+        text: String,
+    },
+
+    /// A fragment of a tool call.
+    ToolCallDelta(ToolCallDelta),
+
+    /// Token usage for the whole completion.
+    ///
+    /// Some providers report usage more than once; the last report wins.
+    Usage(TokenUsage),
+
+    /// The model stopped generating.
+    Finish {
+        /// Why generation stopped.
+        reason: FinishReason,
+
+        /// Details added by provider wrappers, copied into the assembled
+        /// response's `metadata`.
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        metadata: HashMap<String, String>,
+    },
+}
+
+/// A fragment of a streamed tool call.
+///
+/// The first fragment of a call carries its `id` and `name`; later fragments
+/// with the same `index` append to its JSON `arguments`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallDelta {
+    /// Position of the call, shared by all of its fragments.
+    pub index: usize,
+
+    /// Provider-assigned call identifier, if present in this fragment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// Tool name, if present in this fragment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Fragment of the JSON-encoded arguments.
+    #[serde(default)]
+    pub arguments: String,
+}
+
+impl StreamEvent {
+    /// Creates an event received now.
+    pub fn new(kind: StreamEventKind) -> Self {
+        Self { kind, timestamp: Utc::now() }
+    }
+
+    /// Creates a content delta received now.
+    pub fn content(text: impl Into<String>) -> Self {
+        Self::new(StreamEventKind::ContentDelta { text: text.into() })
+    }
+
+    /// Creates a tool-call delta received now.
+    pub fn tool_call(delta: ToolCallDelta) -> Self {
+        Self::new(StreamEventKind::ToolCallDelta(delta))
+    }
+
+    /// Creates a usage report received now.
+    pub fn usage(usage: TokenUsage) -> Self {
+        Self::new(StreamEventKind::Usage(usage))
+    }
+
+    /// Creates a finish event received now.
+    pub fn finish(reason: FinishReason) -> Self {
+        Self::new(StreamEventKind::Finish { reason, metadata: HashMap::new() })
+    }
+
+    /// The text of a content delta, or `None` for other events.
+    pub fn text(&self) -> Option<&str> {
+        match &self.kind {
+            StreamEventKind::ContentDelta { text } => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Whether the event carries generated output (text or a tool call).
+    pub fn is_delta(&self) -> bool {
+        matches!(
+            self.kind,
+            StreamEventKind::ContentDelta { .. } | StreamEventKind::ToolCallDelta(_)
+        )
+    }
+}
+
+/// Latency and throughput of a streamed completion.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StreamMetrics {
+    /// Time from sending the request to the first generated delta, in
+    /// milliseconds. `None` if nothing was generated.
+    pub time_to_first_token_ms: Option<f64>,
+
+    /// Average time per generated token after the first, in milliseconds.
+    /// `None` with fewer than two completion tokens.
+    pub inter_token_latency_ms: Option<f64>,
+
+    /// Completion tokens per second, from sending the request to the last
+    /// generated delta. `None` if nothing was generated.
+    pub tokens_per_second: Option<f64>,
+
+    /// Number of content and tool-call deltas received.
+    pub chunks: usize,
+
+    /// Completion tokens, as reported by the provider or estimated from the
+    /// content when it sends no usage.
+    pub completion_tokens: usize,
+}
+
+/// Builds a [`CompletionResponse`] and [`StreamMetrics`] from stream events.
+#[derive(Debug, Clone)]
+pub struct StreamAccumulator {
+    model: String,
+    started_at: DateTime<Utc>,
+    content: String,
+    tool_calls: BTreeMap<usize, ToolCallDelta>,
+    usage: Option<TokenUsage>,
+    finish_reason: Option<FinishReason>,
+    metadata: HashMap<String, String>,
+    first_delta_at: Option<DateTime<Utc>>,
+    last_delta_at: Option<DateTime<Utc>>,
+    chunks: usize,
+}
+
+impl StreamAccumulator {
+    /// Creates an accumulator for a request sent now.
+    ///
+    /// Create it just before calling `stream` so time to first token includes
+    /// connection setup.
+    pub fn new(model: impl Into<String>) -> Self {
+        Self::started_at(model, Utc::now())
+    }
+
+    /// Creates an accumulator for a request sent at `started_at`.
+    pub fn started_at(model: impl Into<String>, started_at: DateTime<Utc>) -> Self {
+        Self {
+            model: model.into(),
+            started_at,
+            content: String::new(),
+            tool_calls: BTreeMap::new(),
+            usage: None,
+            finish_reason: None,
+            metadata: HashMap::new(),
+            first_delta_at: None,
+            last_delta_at: None,
+            chunks: 0,
+        }
+    }
+
+    /// Consumes a stream, returning the assembled response and its metrics.
+    ///
+    /// Stops at the first error.
+    pub async fn collect(
+        model: impl Into<String>,
+        mut stream: ResponseStream,
+    ) -> Result<(CompletionResponse, StreamMetrics), ProviderError> {
+        let mut accumulator = Self::new(model);
+        while let Some(event) = stream.next().await {
+            accumulator.push(&event?);
+        }
+        let metrics = accumulator.metrics();
+        Ok((accumulator.into_response(), metrics))
+    }
+
+    /// Records an event.
+    pub fn push(&mut self, event: &StreamEvent) {
+        if event.is_delta() {
+            self.first_delta_at.get_or_insert(event.timestamp);
+            self.last_delta_at = Some(event.timestamp);
+            self.chunks += 1;
+        }
+
+        match &event.kind {
+            StreamEventKind::ContentDelta { text } => self.content.push_str(text),
+            StreamEventKind::ToolCallDelta(delta) => {
+                let call = self.tool_calls.entry(delta.index).or_insert_with(|| ToolCallDelta {
+                    index: delta.index,
+                    id: None,
+                    name: None,
+                    arguments: String::new(),
+                });
+                if delta.id.is_some() {
+                    call.id.clone_from(&delta.id);
+                }
+                if delta.name.is_some() {
+                    call.name.clone_from(&delta.name);
+                }
+                call.arguments.push_str(&delta.arguments);
+            }
+            StreamEventKind::Usage(usage) => self.usage = Some(*usage),
+            StreamEventKind::Finish { reason, metadata } => {
+                self.finish_reason = Some(*reason);
+                self.metadata.extend(metadata.clone());
+            }
+        }
+    }
+
+    /// The text received so far.
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// The usage reported by the provider, if any.
+    pub fn usage(&self) -> Option<TokenUsage> {
+        self.usage
+    }
+
+    /// Computes latency metrics from the events received so far.
+    pub fn metrics(&self) -> StreamMetrics {
+        let completion_tokens = self.completion_tokens();
+        let ms = |from: DateTime<Utc>, to: DateTime<Utc>| {
+            (to - from).num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0
+        };
+
+        let time_to_first_token_ms = self.first_delta_at.map(|first| ms(self.started_at, first));
+
+        let inter_token_latency_ms = match (self.first_delta_at, self.last_delta_at) {
+            (Some(first), Some(last)) if completion_tokens > 1 => {
+                Some(ms(first, last) / (completion_tokens - 1) as f64)
+            }
+            _ => None,
+        };
+
+        let tokens_per_second = self.last_delta_at.map(|last| {
+            let seconds = ms(self.started_at, last) / 1000.0;
+            completion_tokens as f64 / seconds.max(0.001)
+        });
+
+        StreamMetrics {
+            time_to_first_token_ms,
+            inter_token_latency_ms,
+            tokens_per_second,
+            chunks: self.chunks,
+            completion_tokens,
+        }
+    }
+
+    /// Assembles the response.
+    ///
+    /// Token counts are estimated when the provider reported no usage. The
+    /// prompt cannot be seen from the stream, so its count is 0 in that case.
+    pub fn into_response(self) -> CompletionResponse {
+        let usage = self.usage.unwrap_or_else(|| TokenUsage::new(0, self.completion_tokens()));
+
+        let tool_calls: Vec<ToolCall> = self
+            .tool_calls
+            .into_values()
+            .map(|call| {
+                let arguments = if call.arguments.is_empty() {
+                    serde_json::json!({})
+                } else {
+                    serde_json::from_str(&call.arguments)
+                        .unwrap_or(serde_json::Value::String(call.arguments))
+                };
+                ToolCall::new(
+                    call.id.unwrap_or_else(|| format!("call_{}", call.index)),
+                    call.name.unwrap_or_default(),
+                    arguments,
+                )
+            })
+            .collect();
+
+        let finish_reason = self.finish_reason.unwrap_or(if tool_calls.is_empty() {
+            FinishReason::Stop
+        } else {
+            FinishReason::ToolCalls
+        });
+
+        CompletionResponse {
+            id: format!("stream-{}", self.started_at.timestamp_millis()),
+            model: self.model,
+            content: self.content,
+            usage,
+            finish_reason,
+            created_at: self.started_at,
+            tool_calls,
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: self.metadata,
+        }
+    }
+
+    fn completion_tokens(&self) -> usize {
+        match self.usage {
+            Some(usage) => usage.completion_tokens,
+            None => Tokenizer::for_model(&self.model).count_tokens(&self.content),
+        }
+    }
+}
+
+/// Replays a complete response as stream events
+///
+/// For providers without incremental output: the whole content arrives as a
+/// single delta, followed by the tool calls, usage and finish reason.
+pub(crate) fn response_events(response: &CompletionResponse) -> Vec<StreamEvent> {
+    let mut events = Vec::with_capacity(response.tool_calls.len() + 3);
+    if !response.content.is_empty() {
+        events.push(StreamEvent::content(response.content.clone()));
+    }
+    events.extend(response.tool_calls.iter().enumerate().map(|(index, call)| {
+        StreamEvent::tool_call(ToolCallDelta {
+            index,
+            id: Some(call.id.clone()),
+            name: Some(call.name.clone()),
+            arguments: call.arguments.to_string(),
+        })
+    }));
+    events.push(StreamEvent::usage(response.usage));
+    events.push(StreamEvent::finish(response.finish_reason));
+    events
+}
+
+/// Wraps a complete response as a [`ResponseStream`]
+pub(crate) fn from_response(response: &CompletionResponse) -> ResponseStream {
+    Box::pin(stream::iter(response_events(response).into_iter().map(Ok)))
+}
+
+/// Splits a response body into frames
+pub(crate) trait FrameDecoder: Send + 'static {
+    /// A decoded frame
+    type Frame: Send;
+
+    /// Appends received bytes
+    fn push(&mut self, bytes: &[u8]);
+
+    /// Returns the next complete frame, if one is buffered
+    fn next_frame(&mut self) -> Result<Option<Self::Frame>, ProviderError>;
+
+    /// Returns a trailing frame left unterminated when the body ended
+    fn finish(&mut self) -> Option<Self::Frame> {
+        None
+    }
+}
+
+/// A server-sent event
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct SseEvent {
+    /// The `event:` field, if present
+    pub event: Option<String>,
+    /// The `data:` lines, joined with newlines
+    pub data: String,
+}
+
+/// Incremental decoder for `text/event-stream` bodies
+#[derive(Debug, Default)]
+pub(crate) struct SseDecoder {
+    buffer: Vec<u8>,
+}
+
+impl SseDecoder {
+    fn parse(block: &str) -> Option<SseEvent> {
+        let mut event = SseEvent::default();
+        let mut has_data = false;
+
+        for line in block.lines() {
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => event.event = Some(value.to_string()),
+                "data" => {
+                    if has_data {
+                        event.data.push('\n');
+                    }
+                    event.data.push_str(value);
+                    has_data = true;
+                }
+                // Comments (`: keep-alive`), `id` and `retry` carry nothing we use
+                _ => {}
+            }
+        }
+
+        has_data.then_some(event)
+    }
+}
+
+impl FrameDecoder for SseDecoder {
+    type Frame = SseEvent;
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn next_frame(&mut self) -> Result<Option<SseEvent>, ProviderError> {
+        loop {
+            // Events end with a blank line; servers may use CRLF line endings
+            let end = self
+                .buffer
+                .windows(2)
+                .position(|w| w == b"\n\n")
+                .map(|i| (i, 2))
+                .into_iter()
+                .chain(self.buffer.windows(4).position(|w| w == b"\r\n\r\n").map(|i| (i, 4)))
+                .min_by_key(|(i, _)| *i);
+
+            let Some((end, separator)) = end else {
+                return Ok(None);
+            };
+
+            let block: Vec<u8> = self.buffer.drain(..end + separator).collect();
+            let block = String::from_utf8_lossy(&block[..end]);
+            if let Some(event) = Self::parse(&block) {
+                return Ok(Some(event));
+            }
+        }
+    }
+
+    fn finish(&mut self) -> Option<SseEvent> {
+        let block = String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).into_owned();
+        Self::parse(&block)
+    }
+}
+
+/// Incremental decoder for newline-delimited JSON bodies
+#[derive(Debug, Default)]
+pub(crate) struct JsonLinesDecoder {
+    buffer: Vec<u8>,
+}
+
+impl FrameDecoder for JsonLinesDecoder {
+    type Frame = serde_json::Value;
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn next_frame(&mut self) -> Result<Option<serde_json::Value>, ProviderError> {
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim();
+            if !line.is_empty() {
+                return Ok(Some(serde_json::from_str(line)?));
+            }
+        }
+        Ok(None)
+    }
+
+    fn finish(&mut self) -> Option<serde_json::Value> {
+        let rest = std::mem::take(&mut self.buffer);
+        serde_json::from_slice(&rest).ok()
+    }
+}
+
+/// Decodes a response body into stream events
+///
+/// Each frame is passed to `handle`, which returns the events it carries.
+/// The stream ends after the first error.
+pub(crate) fn decode_stream<S, B, D, F>(body: S, decoder: D, handle: F) -> ResponseStream
+where
+    S: Stream<Item = reqwest::Result<B>> + Send + 'static,
+    B: AsRef<[u8]>,
+    D: FrameDecoder,
+    F: FnMut(D::Frame) -> Result<Vec<StreamEvent>, ProviderError> + Send + 'static,
+{
+    struct State<D, F> {
+        body: futures::stream::BoxStream<'static, reqwest::Result<Vec<u8>>>,
+        decoder: D,
+        handle: F,
+        pending: VecDeque<StreamEvent>,
+        done: bool,
+    }
+
+    let state = State {
+        body: body.map(|chunk| chunk.map(|bytes| bytes.as_ref().to_vec())).boxed(),
+        decoder,
+        handle,
+        pending: VecDeque::new(),
+        done: false,
+    };
+
+    Box::pin(stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(event) = state.pending.pop_front() {
+                return Some((Ok(event), state));
+            }
+            if state.done {
+                return None;
+            }
+
+            let frame = match state.decoder.next_frame() {
+                Ok(Some(frame)) => Some(frame),
+                Ok(None) => match state.body.next().await {
+                    Some(Ok(chunk)) => {
+                        state.decoder.push(&chunk);
+                        continue;
+                    }
+                    Some(Err(e)) => {
+                        state.done = true;
+                        return Some((Err(ProviderError::NetworkError(e)), state));
+                    }
+                    None => {
+                        state.done = true;
+                        state.decoder.finish()
+                    }
+                },
+                Err(e) => {
+                    state.done = true;
+                    return Some((Err(e), state));
+                }
+            };
+
+            if let Some(frame) = frame {
+                match (state.handle)(frame) {
+                    Ok(events) => state.pending.extend(events),
+                    Err(e) => {
+                        state.done = true;
+                        return Some((Err(e), state));
+                    }
+                }
+            }
+        }
+    }))
+}
+
+/// Maps an OpenAI-style `finish_reason`
+pub(crate) fn openai_finish_reason(reason: &str) -> FinishReason {
+    match reason {
+        "length" => FinishReason::Length,
+        "content_filter" => FinishReason::ContentFilter,
+        "tool_calls" | "function_call" => FinishReason::ToolCalls,
+        "error" => FinishReason::Error,
+        _ => FinishReason::Stop,
+    }
+}
+
+/// Extracts the events from an OpenAI-compatible chat completion chunk
+///
+/// Usage is read from the top-level `usage` (OpenAI with
+/// `stream_options.include_usage`, Mistral, Together, Perplexity) or from
+/// Groq's `x_groq.usage`.
+pub(crate) fn openai_chunk_events(chunk: &serde_json::Value) -> Result<Vec<StreamEvent>, ProviderError> {
+    if let Some(error) = chunk.get("error") {
+        return Err(ProviderError::ApiError {
+            status: 500,
+            message: error["message"].as_str().map(str::to_string).unwrap_or_else(|| error.to_string()),
+        });
+    }
+
+    let mut events = Vec::new();
+    let choice = &chunk["choices"][0];
+
+    if let Some(text) = choice["delta"]["content"].as_str().filter(|text| !text.is_empty()) {
+        events.push(StreamEvent::content(text));
+    }
+
+    for (position, call) in choice["delta"]["tool_calls"].as_array().into_iter().flatten().enumerate() {
+        events.push(StreamEvent::tool_call(ToolCallDelta {
+            index: call["index"].as_u64().map(|i| i as usize).unwrap_or(position),
+            id: call["id"].as_str().map(str::to_string),
+            name: call["function"]["name"].as_str().map(str::to_string),
+            arguments: call["function"]["arguments"].as_str().unwrap_or_default().to_string(),
+        }));
+    }
+
+    let usage = chunk
+        .get("usage")
+        .filter(|usage| !usage.is_null())
+        .or_else(|| chunk["x_groq"].get("usage"));
+    if let Some(usage) = usage {
+        let count = |key: &str| usage[key].as_u64().unwrap_or(0) as usize;
+        let detail = |key: &str, detail: &str| usage[key][detail].as_u64().unwrap_or(0) as usize;
+        events.push(StreamEvent::usage(TokenUsage {
+            prompt_tokens: count("prompt_tokens"),
+            completion_tokens: count("completion_tokens"),
+            total_tokens: count("total_tokens"),
+            reasoning_tokens: detail("completion_tokens_details", "reasoning_tokens"),
+            cached_tokens: detail("prompt_tokens_details", "cached_tokens"),
+            cache_write_tokens: 0,
+        }));
+    }
+
+    if let Some(reason) = choice["finish_reason"].as_str() {
+        events.push(StreamEvent::finish(openai_finish_reason(reason)));
+    }
+
+    Ok(events)
+}
+
+/// Streams an OpenAI-compatible chat completions SSE response
+pub(crate) fn openai_stream(response: reqwest::Response) -> ResponseStream {
+    decode_stream(response.bytes_stream(), SseDecoder::default(), |event| {
+        if event.data == "[DONE]" {
+            return Ok(Vec::new());
+        }
+        openai_chunk_events(&serde_json::from_str(&event.data)?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use serde_json::json;
+
+    fn decode_all<D: FrameDecoder>(decoder: &mut D, chunks: &[&str]) -> Vec<D::Frame> {
+        let mut frames = Vec::new();
+        for chunk in chunks {
+            decoder.push(chunk.as_bytes());
+            while let Some(frame) = decoder.next_frame().unwrap() {
+                frames.push(frame);
+            }
+        }
+        frames.extend(decoder.finish());
+        frames
+    }
+
+    #[test]
+    fn test_sse_decoder_split_chunks() {
+        let mut decoder = SseDecoder::default();
+        let events = decode_all(
+            &mut decoder,
+            &["event: message_start\nda", "ta: {\"a\":1}\n\n: keep-alive\n\n", "data: x\r\ndata: y\r\n\r\ndata: [DONE]"],
+        );
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].event.as_deref(), Some("message_start"));
+        assert_eq!(events[0].data, "{\"a\":1}");
+        assert_eq!(events[1].data, "x\ny");
+        assert_eq!(events[2].data, "[DONE]");
+    }
+
+    #[test]
+    fn test_json_lines_decoder() {
+        let mut decoder = JsonLinesDecoder::default();
+        let frames = decode_all(&mut decoder, &["{\"a\":", "1}\n\n{\"b\":2}\n{\"c\":3}"]);
+        assert_eq!(frames, vec![json!({"a": 1}), json!({"b": 2}), json!({"c": 3})]);
+
+        let mut decoder = JsonLinesDecoder::default();
+        decoder.push(b"not json\n");
+        assert!(decoder.next_frame().is_err());
+    }
+
+    #[test]
+    fn test_openai_chunk_events() {
+        let events = openai_chunk_events(&json!({
+            "choices": [{
+                "delta": {
+                    "tool_calls": [{ "index": 0, "id": "call_1", "function": { "name": "f", "arguments": "{\"x\"" } }]
+                },
+                "finish_reason": null
+            }]
+        }))
+        .unwrap();
+        assert!(matches!(&events[0].kind, StreamEventKind::ToolCallDelta(d) if d.id.as_deref() == Some("call_1")));
+
+        let events = openai_chunk_events(&json!({
+            "choices": [{ "delta": {}, "finish_reason": "stop" }],
+            "x_groq": { "usage": { "prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7 } }
+        }))
+        .unwrap();
+        assert_eq!(events[0].kind, StreamEventKind::Usage(TokenUsage::new(5, 2)));
+        assert_eq!(events[1].kind, StreamEvent::finish(FinishReason::Stop).kind);
+
+        assert!(openai_chunk_events(&json!({ "error": { "message": "overloaded" } })).is_err());
+    }
+
+    #[test]
+    fn test_accumulator_assembles_response() {
+        let mut accumulator = StreamAccumulator::new("gpt-4o");
+        let deltas = [
+            StreamEvent::content("Let me check. "),
+            StreamEvent::tool_call(ToolCallDelta {
+                index: 0,
+                id: Some("call_1".to_string()),
+                name: Some("get_weather".to_string()),
+                arguments: "{\"city\":".to_string(),
+            }),
+            StreamEvent::tool_call(ToolCallDelta {
+                index: 0,
+                id: None,
+                name: None,
+                arguments: "\"Paris\"}".to_string(),
+            }),
+            StreamEvent::usage(TokenUsage::new(20, 12)),
+            StreamEvent::finish(FinishReason::ToolCalls),
+        ];
+        for event in &deltas {
+            accumulator.push(event);
+        }
+
+        let response = accumulator.into_response();
+        assert_eq!(response.content, "Let me check. ");
+        assert_eq!(response.tool_calls, vec![ToolCall::new("call_1", "get_weather", json!({"city": "Paris"}))]);
+        assert_eq!(response.usage.total_tokens, 32);
+        assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    }
+
+    #[test]
+    fn test_accumulator_metrics() {
+        let start = Utc::now();
+        let mut accumulator = StreamAccumulator::started_at("test-model", start);
+
+        // First token after 200ms, then one token every 50ms
+        for i in 0..5 {
+            let mut event = StreamEvent::content("tok");
+            event.timestamp = start + Duration::milliseconds(200 + 50 * i);
+            accumulator.push(&event);
+        }
+        let mut usage = StreamEvent::usage(TokenUsage::new(10, 5));
+        usage.timestamp = start + Duration::milliseconds(500);
+        accumulator.push(&usage);
+
+        let metrics = accumulator.metrics();
+        assert_eq!(metrics.time_to_first_token_ms, Some(200.0));
+        assert_eq!(metrics.inter_token_latency_ms, Some(50.0));
+        assert_eq!(metrics.tokens_per_second, Some(12.5));
+        assert_eq!(metrics.chunks, 5);
+        assert_eq!(metrics.completion_tokens, 5);
+    }
+
+    #[test]
+    fn test_metrics_without_output() {
+        let metrics = StreamAccumulator::new("test-model").metrics();
+        assert_eq!(metrics.time_to_first_token_ms, None);
+        assert_eq!(metrics.inter_token_latency_ms, None);
+        assert_eq!(metrics.tokens_per_second, None);
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let event = StreamEvent::content("Hi");
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "content_delta");
+        assert_eq!(json["text"], "Hi");
+        assert_eq!(serde_json::from_value::<StreamEvent>(json).unwrap(), event);
+    }
+}
@@ -0,0 +1,464 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Structured output (JSON mode and JSON Schema).
+//!
+//! A [`ResponseFormat`] on a [`CompletionRequest`] asks the model for a JSON
+//! object or for JSON matching a schema. Providers with a native structured
+//! output option (OpenAI `response_format`, Gemini `responseSchema`, Ollama
+//! `format`, ...) use it directly. The others are instructed through the
+//! system prompt, and if their answer does not validate they are asked once
+//! to repair it.
+//!
+//! Either way the response content is validated, and any remaining problems
+//! are reported in [`CompletionResponse::schema_violations`].
+//!
+//! # Examples
+//!
+//! ```
+//! use llm_test_bench_core::providers::{CompletionRequest, ResponseFormat};
+//! use serde_json::json;
+//!
+//! let request = CompletionRequest::new("gpt-4o", "Extract the person: Ada Lovelace, 36")
+//!     .with_response_format(ResponseFormat::json_schema(
+//!         "person",
+//!         json!({
+//!             "type": "object",
+//!             "properties": {
+//!                 "name": { "type": "string" },
+//!                 "age": { "type": "integer" }
+//!             },
+//!             "required": ["name", "age"],
+//!             "additionalProperties": false
+//!         }),
+//!     ));
+//! assert!(request.response_format.unwrap().is_json());
+//! ```
+
+use super::{ChatMessage, CompletionRequest, CompletionResponse, MessageRole, ProviderError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::Future;
+use tracing::debug;
+
+/// The format the model's output must follow.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Free-form text (the default).
+    Text,
+
+    /// Any valid JSON object.
+    JsonObject,
+
+    /// JSON conforming to a schema.
+    JsonSchema {
+        /// Name of the schema, sent to providers that require one.
+        name: String,
+
+        /// The JSON Schema the output must satisfy.
+        schema: Value,
+
+        /// Ask the provider to enforce the schema exactly, where supported.
+        #[serde(default = "default_strict")]
+        strict: bool,
+    },
+}
+
+fn default_strict() -> bool {
+    true
+}
+
+impl ResponseFormat {
+    /// Creates a strict JSON Schema format.
+    pub fn json_schema(name: impl Into<String>, schema: Value) -> Self {
+        ResponseFormat::JsonSchema {
+            name: name.into(),
+            schema,
+            strict: true,
+        }
+    }
+
+    /// Whether the format requires JSON output.
+    pub fn is_json(&self) -> bool {
+        !matches!(self, ResponseFormat::Text)
+    }
+
+    /// The JSON Schema, for [`ResponseFormat::JsonSchema`].
+    pub fn schema(&self) -> Option<&Value> {
+        match self {
+            ResponseFormat::JsonSchema { schema, .. } => Some(schema),
+            _ => None,
+        }
+    }
+}
+
+/// A way in which response content failed to match its [`ResponseFormat`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// JSON Pointer to the offending value (empty for the document root).
+    pub path: String,
+
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl SchemaViolation {
+    fn root(message: impl Into<String>) -> Self {
+        Self {
+            path: String::new(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Validates `content` against `format`.
+///
+/// JSON wrapped in a Markdown code fence or surrounded by prose is accepted;
+/// see [`extract_json`].
+///
+/// # Examples
+///
+/// ```
+/// use llm_test_bench_core::providers::structured::{validate, ResponseFormat};
+/// use serde_json::json;
+///
+/// let format = ResponseFormat::json_schema("answer", json!({
+///     "type": "object",
+///     "properties": { "answer": { "type": "integer" } },
+///     "required": ["answer"]
+/// }));
+///
+/// assert!(validate(&format, r#"{"answer": 42}"#).is_empty());
+///
+/// let violations = validate(&format, r#"{"answer": "42"}"#);
+/// assert_eq!(violations[0].path, "/answer");
+/// ```
+pub fn validate(format: &ResponseFormat, content: &str) -> Vec<SchemaViolation> {
+    if !format.is_json() {
+        return Vec::new();
+    }
+
+    let value: Value = match serde_json::from_str(extract_json(content)) {
+        Ok(value) => value,
+        Err(e) => return vec![SchemaViolation::root(format!("Invalid JSON: {}", e))],
+    };
+
+    match format {
+        ResponseFormat::Text => Vec::new(),
+        ResponseFormat::JsonObject if value.is_object() => Vec::new(),
+        ResponseFormat::JsonObject => vec![SchemaViolation::root("Expected a JSON object")],
+        ResponseFormat::JsonSchema { schema, .. } => match jsonschema::validator_for(schema) {
+            Ok(validator) => validator
+                .iter_errors(&value)
+                .map(|error| SchemaViolation {
+                    path: error.instance_path.to_string(),
+                    message: error.to_string(),
+                })
+                .collect(),
+            Err(e) => vec![SchemaViolation::root(format!("Invalid schema: {}", e))],
+        },
+    }
+}
+
+/// Extracts the JSON document from model output.
+///
+/// Strips a surrounding Markdown code fence, or failing that any prose
+/// before the first `{`/`[` and after the last `}`/`]`.
+pub fn extract_json(content: &str) -> &str {
+    let trimmed = content.trim();
+
+    if let Some(fenced) = trimmed.strip_prefix("```") {
+        // Skip the language tag on the opening fence
+        let body = fenced.split_once('\n').map_or("", |(_, body)| body);
+        if let Some(body) = body.trim_end().strip_suffix("```") {
+            return body.trim();
+        }
+    }
+
+    match (trimmed.find(['{', '[']), trimmed.rfind(['}', ']'])) {
+        (Some(start), Some(end)) if start < end => &trimmed[start..=end],
+        _ => trimmed,
+    }
+}
+
+/// Validates a response against the request's format.
+///
+/// Records violations on the response and, when the JSON had to be
+/// extracted from surrounding text, replaces the content with the bare JSON.
+pub(crate) fn check_response(request: &CompletionRequest, response: &mut CompletionResponse) {
+    let Some(format) = request.response_format.as_ref().filter(|f| f.is_json()) else {
+        return;
+    };
+
+    let json = extract_json(&response.content);
+    if json.len() != response.content.len() && serde_json::from_str::<Value>(json).is_ok() {
+        response.content = json.to_string();
+    }
+    response.schema_violations = validate(format, &response.content);
+}
+
+/// Builds the OpenAI `response_format` parameter.
+pub(crate) fn openai_response_format(format: &ResponseFormat) -> Value {
+    match format {
+        ResponseFormat::Text => serde_json::json!({ "type": "text" }),
+        ResponseFormat::JsonObject => serde_json::json!({ "type": "json_object" }),
+        ResponseFormat::JsonSchema { name, schema, strict } => serde_json::json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": name,
+                "schema": schema,
+                "strict": strict,
+            }
+        }),
+    }
+}
+
+/// Adds output format instructions to the system prompt, for providers
+/// without a native structured output option.
+pub(crate) fn with_format_instructions(request: &CompletionRequest) -> CompletionRequest {
+    let instructions = match &request.response_format {
+        Some(ResponseFormat::JsonObject) => {
+            "Respond only with a valid JSON object. Do not include explanations or Markdown code fences.".to_string()
+        }
+        Some(ResponseFormat::JsonSchema { schema, .. }) => format!(
+            "Respond only with JSON that conforms to the following JSON Schema. \
+             Do not include explanations or Markdown code fences.\n\n{}",
+            serde_json::to_string_pretty(schema).unwrap_or_else(|_| schema.to_string())
+        ),
+        Some(ResponseFormat::Text) | None => return request.clone(),
+    };
+
+    let system = match request.system_prompt() {
+        Some(system) => format!("{}\n\n{}", system, instructions),
+        None => instructions,
+    };
+
+    // System messages are merged into `system`, so drop them to avoid
+    // sending the original instructions twice
+    let mut enforced = request.clone();
+    enforced.messages.retain(|m| m.role != MessageRole::System);
+    enforced.system = Some(system);
+    enforced
+}
+
+/// Completes a request using prompt-based format enforcement.
+///
+/// The format is described in the system prompt. If the answer does not
+/// validate, the model is shown the violations and asked to try again once;
+/// token usage covers both attempts.
+pub(crate) async fn complete_with_repair<F, Fut>(
+    request: CompletionRequest,
+    complete: F,
+) -> Result<CompletionResponse, ProviderError>
+where
+    F: Fn(CompletionRequest) -> Fut,
+    Fut: Future<Output = Result<CompletionResponse, ProviderError>>,
+{
+    if !request.response_format.as_ref().is_some_and(ResponseFormat::is_json) {
+        return complete(request).await;
+    }
+
+    let enforced = with_format_instructions(&request);
+    let mut response = complete(enforced.clone()).await?;
+    check_response(&request, &mut response);
+    if response.schema_violations.is_empty() {
+        return Ok(response);
+    }
+
+    debug!(
+        "Response violated the requested format ({} violations), asking for a repair",
+        response.schema_violations.len()
+    );
+
+    let mut messages = enforced.messages.clone();
+    if !enforced.prompt.is_empty() {
+        messages.push(ChatMessage::user(enforced.prompt.clone()));
+    }
+    messages.push(ChatMessage::assistant(response.content.clone()));
+
+    let violations = response
+        .schema_violations
+        .iter()
+        .map(|v| format!("- {}", v))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let repair = CompletionRequest {
+        prompt: format!(
+            "Your previous response did not match the required format:\n{}\n\n\
+             Respond again with only the corrected JSON.",
+            violations
+        ),
+        messages,
+        ..enforced
+    };
+
+    let first_usage = response.usage;
+    let mut repaired = complete(repair).await?;
+    check_response(&request, &mut repaired);
+    repaired.usage.accumulate(&first_usage);
+    Ok(repaired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{FinishReason, TokenUsage};
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    fn person_format() -> ResponseFormat {
+        ResponseFormat::json_schema(
+            "person",
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "age": { "type": "integer", "minimum": 0 }
+                },
+                "required": ["name", "age"]
+            }),
+        )
+    }
+
+    fn response(content: &str) -> CompletionResponse {
+        CompletionResponse {
+            id: "test".to_string(),
+            model: "test-model".to_string(),
+            content: content.to_string(),
+            usage: TokenUsage::new(10, 5).with_reasoning_tokens(2).with_cached_tokens(4),
+            finish_reason: FinishReason::Stop,
+            created_at: chrono::Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_response_format_serialization() {
+        let format: ResponseFormat = serde_json::from_value(json!({
+            "type": "json_schema",
+            "name": "person",
+            "schema": { "type": "object" }
+        }))
+        .unwrap();
+        assert!(matches!(format, ResponseFormat::JsonSchema { strict: true, .. }));
+
+        assert_eq!(
+            serde_json::to_value(ResponseFormat::JsonObject).unwrap(),
+            json!({ "type": "json_object" })
+        );
+    }
+
+    #[test]
+    fn test_validate_json_object() {
+        let format = ResponseFormat::JsonObject;
+        assert!(validate(&format, r#"{"a": 1}"#).is_empty());
+        assert_eq!(validate(&format, "[1, 2]")[0].message, "Expected a JSON object");
+        assert!(validate(&format, "not json")[0].message.starts_with("Invalid JSON"));
+        assert!(validate(&ResponseFormat::Text, "not json").is_empty());
+    }
+
+    #[test]
+    fn test_validate_json_schema() {
+        let format = person_format();
+        assert!(validate(&format, r#"{"name": "Ada", "age": 36}"#).is_empty());
+
+        let violations = validate(&format, r#"{"name": "Ada", "age": -1}"#);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].path, "/age");
+
+        let violations = validate(&format, r#"{"name": 7}"#);
+        assert_eq!(violations.len(), 2);
+    }
+
+    #[test]
+    fn test_extract_json() {
+        assert_eq!(extract_json("```json\n{\"a\": 1}\n```"), "{\"a\": 1}");
+        assert_eq!(extract_json("```\n[1]\n```"), "[1]");
+        assert_eq!(extract_json("Here you go: {\"a\": 1}. Done."), "{\"a\": 1}");
+        assert_eq!(extract_json("  plain  "), "plain");
+    }
+
+    #[test]
+    fn test_check_response_strips_fences() {
+        let request = CompletionRequest::new("m", "p").with_response_format(ResponseFormat::JsonObject);
+        let mut resp = response("```json\n{\"a\": 1}\n```");
+        check_response(&request, &mut resp);
+        assert_eq!(resp.content, "{\"a\": 1}");
+        assert!(resp.schema_violations.is_empty());
+
+        // Text requests are left alone
+        let mut resp = response("```json\n{\"a\": 1}\n```");
+        check_response(&CompletionRequest::new("m", "p"), &mut resp);
+        assert!(resp.content.starts_with("```"));
+    }
+
+    #[test]
+    fn test_format_instructions() {
+        let request = CompletionRequest::new("m", "Extract")
+            .with_system("Be precise.")
+            .with_response_format(person_format());
+        let enforced = with_format_instructions(&request);
+        let system = enforced.system.clone().unwrap();
+        assert!(system.starts_with("Be precise.\n\n"));
+        assert!(system.contains("\"required\""));
+        assert_eq!(enforced.conversation(), request.conversation());
+
+        let plain = CompletionRequest::new("m", "Hi");
+        assert_eq!(with_format_instructions(&plain), plain);
+    }
+
+    #[tokio::test]
+    async fn test_repair_retry() {
+        let request = CompletionRequest::new("m", "Who?").with_response_format(person_format());
+        let seen = Mutex::new(Vec::new());
+        let replies = Mutex::new(vec![r#"{"name": "Ada", "age": 36}"#, r#"{"name": "Ada"}"#]);
+
+        let result = complete_with_repair(request, |req| {
+            seen.lock().unwrap().push(req);
+            let content = replies.lock().unwrap().pop().unwrap();
+            async move { Ok(response(content)) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(result.content, r#"{"name": "Ada", "age": 36}"#);
+        assert!(result.schema_violations.is_empty());
+        assert_eq!(result.usage, TokenUsage::new(20, 10).with_reasoning_tokens(4).with_cached_tokens(8));
+
+        let seen = seen.into_inner().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[1].messages[0], ChatMessage::user("Who?"));
+        assert_eq!(seen[1].messages[1], ChatMessage::assistant(r#"{"name": "Ada"}"#));
+        assert!(seen[1].prompt.contains("\"age\" is a required property"));
+    }
+
+    #[tokio::test]
+    async fn test_repair_reports_remaining_violations() {
+        let request = CompletionRequest::new("m", "Who?").with_response_format(ResponseFormat::JsonObject);
+        let result = complete_with_repair(request, |_| async { Ok(response("no")) })
+            .await
+            .unwrap();
+
+        assert_eq!(result.schema_violations.len(), 1);
+    }
+}
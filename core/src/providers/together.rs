@@ -6,10 +6,17 @@
 
 //! Together AI provider implementation (OpenAI-compatible)
 
-use super::{CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use super::discovery;
+use super::embeddings::{self, EmbeddingModel, EmbeddingProvider, EmbeddingRequest, EmbeddingResponse};
+use super::sampling::{self, SamplingSupport};
+use super::streaming;
+use super::logprobs;
+use super::structured::{self, ResponseFormat};
+use super::{CompletionChoice, CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, ProviderError, ResponseStream, TokenUsage};
+use crate::tokenizer::Tokenizer;
 use async_trait::async_trait;
-use futures::stream::StreamExt;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::time::Duration;
 use tracing::{debug, error};
 
@@ -39,10 +46,16 @@ impl TogetherProvider {
         Ok(Self { client, api_key, base_url })
     }
 
+    /// Uses `client` for every request, e.g. one built from transport settings
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
     fn build_request_body(&self, request: &CompletionRequest, stream: bool) -> serde_json::Value {
         let mut body = serde_json::json!({
             "model": request.model,
-            "messages": [{"role": "user", "content": request.prompt}],
+            "messages": request.chat_messages(),
             "stream": stream,
         });
 
@@ -56,15 +69,56 @@ impl TogetherProvider {
             body["top_p"] = serde_json::json!(top_p);
         }
 
+        // Together's JSON mode takes the schema alongside the type
+        match request.response_format {
+            Some(ResponseFormat::JsonObject) => {
+                body["response_format"] = serde_json::json!({ "type": "json_object" });
+            }
+            Some(ResponseFormat::JsonSchema { ref schema, .. }) => {
+                body["response_format"] = serde_json::json!({ "type": "json_object", "schema": schema });
+            }
+            Some(ResponseFormat::Text) | None => {}
+        }
+
+        // `logprobs` is the number of alternatives; 1 returns only the chosen token
+        if let Some(options) = request.logprobs {
+            body["logprobs"] = serde_json::json!(options.top_logprobs.max(1));
+        }
+
+        sampling::apply_openai_params(request, &mut body);
+
         body
     }
+
+    /// Builds a raw completions request that echoes the prompt's log probabilities
+    ///
+    /// The chat endpoint would score the chat template's tokens as well, so
+    /// prompt scoring goes through `/completions` with the plain prompt.
+    fn build_text_completion_body(&self, request: &CompletionRequest) -> Result<serde_json::Value, ProviderError> {
+        if request.system_prompt().is_some() || !request.messages.is_empty() {
+            return Err(ProviderError::InvalidRequest(
+                "Prompt log probabilities require a plain prompt without system or messages".to_string(),
+            ));
+        }
+
+        let mut body = self.build_request_body(request, false);
+        if let Some(object) = body.as_object_mut() {
+            object.remove("messages");
+            object.insert("prompt".to_string(), serde_json::json!(request.prompt));
+            object.insert("echo".to_string(), serde_json::json!(true));
+        }
+        Ok(body)
+    }
 }
 
 #[async_trait]
 impl Provider for TogetherProvider {
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
-        let url = format!("{}/chat/completions", self.base_url);
-        let body = self.build_request_body(&request, false);
+        let (url, body) = if request.logprobs.is_some_and(|options| options.include_prompt) {
+            (format!("{}/completions", self.base_url), self.build_text_completion_body(&request)?)
+        } else {
+            (format!("{}/chat/completions", self.base_url), self.build_request_body(&request, false))
+        };
 
         debug!("Sending request to Together AI");
 
@@ -91,12 +145,24 @@ impl Provider for TogetherProvider {
             model: String,
             choices: Vec<Choice>,
             usage: Usage,
+            #[serde(default)]
+            prompt: Vec<EchoedPrompt>,
         }
 
         #[derive(Deserialize)]
         struct Choice {
-            message: Message,
+            // Chat completions return a message, raw completions plain text
+            message: Option<Message>,
+            text: Option<String>,
             finish_reason: String,
+            #[serde(default)]
+            logprobs: Option<serde_json::Value>,
+        }
+
+        #[derive(Deserialize)]
+        struct EchoedPrompt {
+            #[serde(default)]
+            logprobs: Option<serde_json::Value>,
         }
 
         #[derive(Deserialize)]
@@ -112,28 +178,58 @@ impl Provider for TogetherProvider {
         }
 
         let resp: TogetherResponse = serde_json::from_str(&text)?;
-        let choice = resp.choices.first()
-            .ok_or_else(|| ProviderError::ApiError { status: 500, message: "No choices".to_string() })?;
+        let logprobs = resp.choices.first()
+            .and_then(|choice| choice.logprobs.as_ref())
+            .and_then(logprobs::parse);
+
+        let choices = resp.choices.into_iter().enumerate()
+            .map(|(index, choice)| CompletionChoice {
+                index,
+                content: match (choice.message, choice.text) {
+                    (Some(message), _) => message.content,
+                    (None, text) => text.unwrap_or_default(),
+                },
+                finish_reason: match choice.finish_reason.as_str() {
+                    "stop" | "eos" => FinishReason::Stop,
+                    "length" => FinishReason::Length,
+                    _ => FinishReason::Stop,
+                },
+                tool_calls: Vec::new(),
+            })
+            .collect();
+        let (first, choices) = sampling::split_choices(choices)?;
 
-        Ok(CompletionResponse {
+        let mut response = CompletionResponse {
             id: resp.id,
-            content: choice.message.content.clone(),
+            content: first.content,
             model: resp.model,
             usage: TokenUsage {
                 prompt_tokens: resp.usage.prompt_tokens as usize,
                 completion_tokens: resp.usage.completion_tokens as usize,
                 total_tokens: resp.usage.total_tokens as usize,
+                reasoning_tokens: 0,
+                cached_tokens: 0,
+                cache_write_tokens: 0,
             },
-            finish_reason: match choice.finish_reason.as_str() {
-                "stop" | "eos" => FinishReason::Stop,
-                "length" => FinishReason::Length,
-                _ => FinishReason::Stop,
-            },
+            finish_reason: first.finish_reason,
             created_at: chrono::Utc::now(),
-        })
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs,
+            prompt_logprobs: resp.prompt.first()
+                .and_then(|prompt| prompt.logprobs.as_ref())
+                .and_then(logprobs::parse),
+            choices,
+            metadata: HashMap::new(),
+        };
+        structured::check_response(&request, &mut response);
+        Ok(response)
     }
 
     async fn stream(&self, request: CompletionRequest) -> Result<ResponseStream, ProviderError> {
+        // Every sampling parameter is accepted, but a stream is one completion
+        SamplingSupport::ALL.check_stream(self.name(), &request)?;
+
         let url = format!("{}/chat/completions", self.base_url);
         let body = self.build_request_body(&request, true);
 
@@ -151,30 +247,7 @@ impl Provider for TogetherProvider {
             return Err(ProviderError::ApiError { status, message: text });
         }
 
-        let stream = response.bytes_stream().map(move |result| {
-            match result {
-                Ok(bytes) => {
-                    let text = String::from_utf8_lossy(&bytes);
-                    for line in text.lines() {
-                        if line.starts_with("data: ") {
-                            let json_str = line.strip_prefix("data: ").unwrap_or("");
-                            if json_str == "[DONE]" {
-                                continue;
-                            }
-                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(json_str) {
-                                if let Some(content) = json["choices"][0]["delta"]["content"].as_str() {
-                                    return Ok(content.to_string());
-                                }
-                            }
-                        }
-                    }
-                    Ok(String::new())
-                }
-                Err(e) => Err(ProviderError::NetworkError(e)),
-            }
-        });
-
-        Ok(Box::pin(stream))
+        Ok(streaming::openai_stream(response))
     }
 
     fn supported_models(&self) -> Vec<ModelInfo> {
@@ -187,6 +260,13 @@ impl Provider for TogetherProvider {
         ]
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        let request = self.client
+            .get(format!("{}/models", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key));
+        Ok(discovery::parse_openai_models(&discovery::fetch_json(request).await?))
+    }
+
     fn max_context_length(&self, model: &str) -> Option<usize> {
         if model.contains("Llama-2") {
             Some(4096)
@@ -212,7 +292,51 @@ impl Provider for TogetherProvider {
         Ok(())
     }
 
-    fn estimate_tokens(&self, text: &str, _model: &str) -> Result<usize, ProviderError> {
-        Ok((text.len() / 4).max(1))
+    fn estimate_tokens(&self, text: &str, model: &str) -> Result<usize, ProviderError> {
+        Ok(Tokenizer::for_model(model).count_tokens(text))
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for TogetherProvider {
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, ProviderError> {
+        embeddings::require_native_dimensions("together", &request)?;
+
+        let url = format!("{}/embeddings", self.base_url);
+        let body = serde_json::json!({
+            "model": request.model,
+            "input": request.inputs,
+        });
+
+        debug!("Sending embedding request to Together AI");
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| ProviderError::NetworkError(e))?;
+
+        let status = response.status().as_u16();
+        if !response.status().is_success() {
+            let text = response.text().await.unwrap_or_default();
+            error!("Together AI API error ({}): {}", status, text);
+            return Err(ProviderError::ApiError { status, message: text });
+        }
+
+        // Together does not report usage for embeddings, so it is estimated
+        let text = response.text().await.map_err(|e| ProviderError::NetworkError(e))?;
+        embeddings::parse_openai_embeddings(&text, &request)
+    }
+
+    fn embedding_models(&self) -> Vec<EmbeddingModel> {
+        vec![
+            EmbeddingModel::new("togethercomputer/m2-bert-80M-8k-retrieval", 768, 8192),
+            EmbeddingModel::new("togethercomputer/m2-bert-80M-32k-retrieval", 768, 32768),
+            EmbeddingModel::new("BAAI/bge-large-en-v1.5", 1024, 512),
+            EmbeddingModel::new("BAAI/bge-base-en-v1.5", 768, 512),
+            EmbeddingModel::new("WhereIsAI/UAE-Large-V1", 1024, 512),
+        ]
     }
 }
@@ -0,0 +1,238 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Tool (function) calling types.
+//!
+//! Tools are declared on a [`CompletionRequest`](super::CompletionRequest)
+//! with a JSON Schema describing their parameters. When the model decides to
+//! invoke one, the provider returns the structured [`ToolCall`]s on the
+//! [`CompletionResponse`](super::CompletionResponse).
+//!
+//! # Examples
+//!
+//! ```
+//! use llm_test_bench_core::providers::{CompletionRequest, ToolChoice, ToolDefinition};
+//! use serde_json::json;
+//!
+//! let weather = ToolDefinition::new(
+//!     "get_weather",
+//!     "Get the current weather for a city",
+//!     json!({
+//!         "type": "object",
+//!         "properties": { "city": { "type": "string" } },
+//!         "required": ["city"]
+//!     }),
+//! );
+//!
+//! let request = CompletionRequest::new("gpt-4", "What's the weather in Paris?")
+//!     .with_tools(vec![weather])
+//!     .with_tool_choice(ToolChoice::Auto);
+//! assert_eq!(request.tools.len(), 1);
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+/// A tool the model may call.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolDefinition {
+    /// Name of the tool, as the model will refer to it.
+    pub name: String,
+
+    /// What the tool does. Models use this to decide when to call it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// JSON Schema for the tool's arguments.
+    pub parameters: serde_json::Value,
+}
+
+impl ToolDefinition {
+    /// Creates a new tool definition.
+    pub fn new(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: serde_json::Value,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            description: Some(description.into()),
+            parameters,
+        }
+    }
+}
+
+/// Controls whether and which tools the model calls.
+///
+/// # Examples
+///
+/// ```
+/// use llm_test_bench_core::providers::ToolChoice;
+///
+/// let choice: ToolChoice = serde_json::from_str(r#"{"type":"tool","name":"search"}"#).unwrap();
+/// assert_eq!(choice, ToolChoice::tool("search"));
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ToolChoice {
+    /// The model decides whether to call a tool.
+    Auto,
+
+    /// The model must not call any tool.
+    None,
+
+    /// The model must call at least one tool.
+    Required,
+
+    /// The model must call the named tool.
+    Tool {
+        /// Name of the tool to call.
+        name: String,
+    },
+}
+
+impl ToolChoice {
+    /// Forces a call to the named tool.
+    pub fn tool(name: impl Into<String>) -> Self {
+        ToolChoice::Tool { name: name.into() }
+    }
+}
+
+/// A tool invocation requested by the model.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolCall {
+    /// Provider-assigned identifier for this call.
+    ///
+    /// Providers that don't assign IDs (e.g. Gemini) get a synthetic
+    /// `call_<index>` identifier.
+    pub id: String,
+
+    /// Name of the tool to invoke.
+    pub name: String,
+
+    /// Arguments for the tool, as produced by the model.
+    ///
+    /// If the model emitted arguments that aren't valid JSON, the raw text is
+    /// kept as a JSON string so it can still be inspected.
+    pub arguments: serde_json::Value,
+}
+
+impl ToolCall {
+    /// Creates a new tool call.
+    pub fn new(id: impl Into<String>, name: impl Into<String>, arguments: serde_json::Value) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            arguments,
+        }
+    }
+}
+
+/// Tool call as returned by OpenAI-compatible chat completion APIs.
+#[derive(Debug, Deserialize)]
+pub(crate) struct OpenAIToolCall {
+    #[serde(default)]
+    pub id: String,
+    pub function: OpenAIFunctionCall,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct OpenAIFunctionCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: String,
+}
+
+impl From<OpenAIToolCall> for ToolCall {
+    fn from(call: OpenAIToolCall) -> Self {
+        let arguments = serde_json::from_str(&call.function.arguments)
+            .unwrap_or(serde_json::Value::String(call.function.arguments));
+        ToolCall::new(call.id, call.function.name, arguments)
+    }
+}
+
+/// Builds the `tools` array for OpenAI-compatible chat completion APIs.
+pub(crate) fn openai_tools(tools: &[ToolDefinition]) -> serde_json::Value {
+    tools
+        .iter()
+        .map(|tool| {
+            let mut function = serde_json::json!({
+                "name": tool.name,
+                "parameters": tool.parameters,
+            });
+            if let Some(ref description) = tool.description {
+                function["description"] = serde_json::json!(description);
+            }
+            serde_json::json!({ "type": "function", "function": function })
+        })
+        .collect()
+}
+
+/// Builds the `tool_choice` value for OpenAI-compatible chat completion APIs.
+///
+/// `required` is the keyword for [`ToolChoice::Required`]; Mistral spells it
+/// `any`.
+pub(crate) fn openai_tool_choice(choice: &ToolChoice, required: &str) -> serde_json::Value {
+    match choice {
+        ToolChoice::Auto => serde_json::json!("auto"),
+        ToolChoice::None => serde_json::json!("none"),
+        ToolChoice::Required => serde_json::json!(required),
+        ToolChoice::Tool { name } => serde_json::json!({
+            "type": "function",
+            "function": { "name": name },
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_openai_tools_format() {
+        let tools = vec![ToolDefinition::new(
+            "search",
+            "Search the web",
+            json!({"type": "object", "properties": {"q": {"type": "string"}}}),
+        )];
+
+        let value = openai_tools(&tools);
+        assert_eq!(value[0]["type"], "function");
+        assert_eq!(value[0]["function"]["name"], "search");
+        assert_eq!(value[0]["function"]["description"], "Search the web");
+        assert_eq!(value[0]["function"]["parameters"]["type"], "object");
+    }
+
+    #[test]
+    fn test_openai_tool_choice() {
+        assert_eq!(openai_tool_choice(&ToolChoice::Auto, "required"), json!("auto"));
+        assert_eq!(openai_tool_choice(&ToolChoice::Required, "any"), json!("any"));
+        assert_eq!(
+            openai_tool_choice(&ToolChoice::tool("search"), "required"),
+            json!({"type": "function", "function": {"name": "search"}})
+        );
+    }
+
+    #[test]
+    fn test_openai_tool_call_arguments() {
+        let call: OpenAIToolCall = serde_json::from_value(json!({
+            "id": "call_1",
+            "type": "function",
+            "function": {"name": "search", "arguments": "{\"q\":\"rust\"}"}
+        }))
+        .unwrap();
+        let call = ToolCall::from(call);
+        assert_eq!(call.arguments["q"], "rust");
+
+        let call: OpenAIToolCall = serde_json::from_value(json!({
+            "id": "call_2",
+            "function": {"name": "search", "arguments": "{not json"}
+        }))
+        .unwrap();
+        let call = ToolCall::from(call);
+        assert_eq!(call.arguments, json!("{not json"));
+    }
+}
@@ -120,8 +120,9 @@ pub trait Provider: Send + Sync {
     ///
     /// # Returns
     ///
-    /// Returns a `ResponseStream` that yields text chunks as they're generated,
-    /// or a `ProviderError` if the stream cannot be initiated.
+    /// Returns a `ResponseStream` that yields content and tool-call deltas as
+    /// they're generated, then the usage and finish reason, or a
+    /// `ProviderError` if the stream cannot be initiated.
     ///
     /// # Errors
     ///
@@ -141,7 +142,7 @@ pub trait Provider: Send + Sync {
     /// let mut stream = provider.stream(request).await?;
     /// while let Some(result) = stream.next().await {
     ///     match result {
-    ///         Ok(chunk) => print!("{}", chunk),
+    ///         Ok(event) => print!("{}", event.text().unwrap_or_default()),
     ///         Err(e) => eprintln!("Stream error: {}", e),
     ///     }
     /// }
@@ -173,6 +174,39 @@ pub trait Provider: Send + Sync {
     /// ```
     fn supported_models(&self) -> Vec<ModelInfo>;
 
+    /// Queries the provider's API for the models it currently serves.
+    ///
+    /// Unlike [`supported_models`](Provider::supported_models), which is a
+    /// built-in list, this reflects what the API reports right now. Context
+    /// windows the API does not report are 0. Use
+    /// [`ModelCatalog`](super::ModelCatalog) to cache results and merge them
+    /// with static pricing and capability data.
+    ///
+    /// The default implementation returns [`supported_models`](Provider::supported_models)
+    /// for providers without a list-models endpoint.
+    ///
+    /// # Errors
+    ///
+    /// - `ProviderError::AuthenticationError` - The API rejected the credentials
+    /// - `ProviderError::NetworkError` - Cannot reach provider
+    /// - `ProviderError::ApiError` - Provider-specific API error
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use llm_test_bench_core::providers::{Provider, OpenAIProvider};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let provider = OpenAIProvider::new("key".to_string())?;
+    /// for model in provider.list_models().await? {
+    ///     println!("{}", model.id);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        Ok(self.supported_models())
+    }
+
     /// Returns the maximum context length for a specific model.
     ///
     /// This method looks up the maximum number of tokens that can be processed
@@ -332,8 +366,8 @@ pub trait RetryableProvider: Provider {
     }
 }
 
-// Blanket implementation for all Provider types
-impl<T: Provider> RetryableProvider for T {}
+// Blanket implementation for all Provider types, including `dyn Provider`
+impl<T: Provider + ?Sized> RetryableProvider for T {}
 
 /// Calculates the exponential backoff delay for a retry attempt.
 ///
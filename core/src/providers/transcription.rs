@@ -0,0 +1,536 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Speech-to-text transcription
+//!
+//! [`TranscriptionProvider`] turns an [`AudioInput`] into text, honoring
+//! [`TranscriptionOptions`]. Two backends are provided:
+//!
+//! - [`WhisperProvider`] posts multipart uploads to OpenAI's
+//!   `/audio/transcriptions` endpoint, which Groq and most self-hosted
+//!   Whisper servers also implement, or to a local whisper.cpp server's
+//!   `/inference` endpoint via [`WhisperProvider::whisper_cpp`]
+//! - [`GoogleSpeechProvider`] calls Google Cloud Speech-to-Text
+//!
+//! # Example
+//!
+//! ```no_run
+//! use llm_test_bench_core::multimodal::{AudioInput, TranscriptionOptions};
+//! use llm_test_bench_core::providers::{TranscriptionProvider, WhisperProvider};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let provider = WhisperProvider::new("your-api-key".to_string())?;
+//! let audio = AudioInput::from_path("speech.mp3").await?;
+//!
+//! let options = TranscriptionOptions { language: Some("en".to_string()), ..Default::default() };
+//! let transcription = provider.transcribe("whisper-1", &audio, &options).await?;
+//! println!("{}", transcription.text);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::google::GoogleProvider;
+use super::openai::OpenAIProvider;
+use super::ProviderError;
+use crate::multimodal::{AudioInput, TranscriptionOptions};
+use async_trait::async_trait;
+use reqwest::multipart::{Form, Part};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{debug, error};
+
+/// A transcript produced by a [`TranscriptionProvider`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transcription {
+    /// The transcribed text
+    pub text: String,
+
+    /// Language of the speech, as reported by the backend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+
+    /// Duration of the audio in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+
+    /// Timed segments, when timestamps were requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub segments: Vec<TranscriptSegment>,
+
+    /// Timed words, when word timestamps were requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<TranscriptWord>,
+}
+
+impl Transcription {
+    /// Creates a transcript with only its text
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            language: None,
+            duration: None,
+            segments: Vec::new(),
+            words: Vec::new(),
+        }
+    }
+}
+
+/// A timed span of a transcript, in seconds from the start of the audio
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptSegment {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+/// A timed word of a transcript, in seconds from the start of the audio
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptWord {
+    pub start: f64,
+    pub end: f64,
+    pub word: String,
+}
+
+/// A speech-to-text backend
+#[async_trait]
+pub trait TranscriptionProvider: Send + Sync {
+    /// Transcribes `audio` with `model`
+    async fn transcribe(
+        &self,
+        model: &str,
+        audio: &AudioInput,
+        options: &TranscriptionOptions,
+    ) -> Result<Transcription, ProviderError>;
+
+    /// Name of the backend, used in reports
+    fn name(&self) -> &str;
+}
+
+/// Whisper-compatible transcription over multipart uploads
+pub struct WhisperProvider {
+    client: reqwest::Client,
+    api_key: Option<String>,
+    base_url: String,
+    endpoint: &'static str,
+    name: &'static str,
+}
+
+impl WhisperProvider {
+    /// Create a provider for OpenAI's transcription API
+    pub fn new(api_key: String) -> Result<Self, ProviderError> {
+        Self::with_base_url(api_key, "https://api.openai.com/v1".to_string())
+    }
+
+    /// Create a provider for any server implementing `/audio/transcriptions`
+    pub fn with_base_url(api_key: String, base_url: String) -> Result<Self, ProviderError> {
+        if api_key.is_empty() {
+            return Err(ProviderError::InvalidApiKey);
+        }
+        Self::build(Some(api_key), base_url, "/audio/transcriptions", "whisper")
+    }
+
+    /// Create a provider for a local whisper.cpp server
+    ///
+    /// The server transcribes with the model it was started with, so the
+    /// model passed to [`transcribe`](TranscriptionProvider::transcribe)
+    /// only labels the results.
+    pub fn whisper_cpp(base_url: String) -> Result<Self, ProviderError> {
+        Self::build(None, base_url, "/inference", "whisper.cpp")
+    }
+
+    fn build(
+        api_key: Option<String>,
+        base_url: String,
+        endpoint: &'static str,
+        name: &'static str,
+    ) -> Result<Self, ProviderError> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(300))  // Long recordings take a while
+            .build()
+            .map_err(|e| ProviderError::InternalError(format!("Failed to build HTTP client: {}", e)))?;
+
+        Ok(Self {
+            client,
+            api_key,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            endpoint,
+            name,
+        })
+    }
+
+    /// Build the multipart form for a transcription request
+    ///
+    /// Timestamps need `verbose_json`, which overrides the requested
+    /// response format.
+    async fn build_form(
+        &self,
+        model: &str,
+        audio: &AudioInput,
+        options: &TranscriptionOptions,
+    ) -> Result<(Form, String), ProviderError> {
+        let bytes = audio.read_bytes().await.map_err(audio_error)?;
+        let mut file = Part::bytes(bytes).file_name(file_name(audio));
+        if let Some(media_type) = audio.media_type() {
+            file = file.mime_str(media_type)?;
+        }
+
+        let response_format = if options.timestamps || options.word_timestamps {
+            "verbose_json".to_string()
+        } else {
+            options.response_format.clone().unwrap_or_else(|| "json".to_string())
+        };
+
+        let mut form = Form::new()
+            .part("file", file)
+            .text("model", model.to_string())
+            .text("response_format", response_format.clone());
+
+        if let Some(ref language) = options.language {
+            form = form.text("language", language.clone());
+        }
+        if let Some(ref prompt) = options.prompt {
+            form = form.text("prompt", prompt.clone());
+        }
+        if let Some(temperature) = options.temperature {
+            form = form.text("temperature", temperature.to_string());
+        }
+        if options.timestamps {
+            form = form.text("timestamp_granularities[]", "segment");
+        }
+        if options.word_timestamps {
+            form = form.text("timestamp_granularities[]", "word");
+        }
+
+        Ok((form, response_format))
+    }
+
+    /// Parse a transcription response body
+    ///
+    /// `text`, `srt` and `vtt` responses are returned verbatim.
+    fn parse_response(body: &str, response_format: &str) -> Result<Transcription, ProviderError> {
+        #[derive(Deserialize)]
+        struct WhisperResponse {
+            text: String,
+            #[serde(default)]
+            language: Option<String>,
+            #[serde(default)]
+            duration: Option<f64>,
+            #[serde(default)]
+            segments: Vec<TranscriptSegment>,
+            #[serde(default)]
+            words: Vec<TranscriptWord>,
+        }
+
+        if !matches!(response_format, "json" | "verbose_json") {
+            return Ok(Transcription::new(body.trim()));
+        }
+
+        let resp: WhisperResponse = serde_json::from_str(body)?;
+        Ok(Transcription {
+            text: resp.text.trim().to_string(),
+            language: resp.language,
+            duration: resp.duration,
+            segments: resp.segments,
+            words: resp.words,
+        })
+    }
+}
+
+#[async_trait]
+impl TranscriptionProvider for WhisperProvider {
+    async fn transcribe(
+        &self,
+        model: &str,
+        audio: &AudioInput,
+        options: &TranscriptionOptions,
+    ) -> Result<Transcription, ProviderError> {
+        debug!("{} transcription request: model={}", self.name, model);
+
+        let (form, response_format) = self.build_form(model, audio, options).await?;
+
+        let mut request = self.client
+            .post(format!("{}{}", self.base_url, self.endpoint))
+            .multipart(form);
+        if let Some(ref api_key) = self.api_key {
+            request = request.header("Authorization", format!("Bearer {}", api_key));
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        let text = response.text().await?;
+
+        if !status.is_success() {
+            error!("{} transcription error: status={}, response={}", self.name, status, text);
+            return Err(OpenAIProvider::parse_error_response(status.as_u16(), &text));
+        }
+
+        Self::parse_response(&text, &response_format)
+    }
+
+    fn name(&self) -> &str {
+        self.name
+    }
+}
+
+/// Google Cloud Speech-to-Text
+///
+/// The model is Speech-to-Text's recognition model, e.g. `latest_long`;
+/// `default` leaves the choice to the API. Without a language hint
+/// `en-US` is assumed, since the API requires one. `prompt` is sent as a
+/// phrase hint; `temperature` and `response_format` do not apply.
+pub struct GoogleSpeechProvider {
+    client: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+impl GoogleSpeechProvider {
+    /// Create a new Speech-to-Text provider
+    pub fn new(api_key: String) -> Result<Self, ProviderError> {
+        Self::with_base_url(api_key, "https://speech.googleapis.com/v1".to_string())
+    }
+
+    /// Create a new Speech-to-Text provider with custom base URL
+    pub fn with_base_url(api_key: String, base_url: String) -> Result<Self, ProviderError> {
+        if api_key.is_empty() {
+            return Err(ProviderError::InvalidApiKey);
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(300))
+            .build()
+            .map_err(|e| ProviderError::InternalError(format!("Failed to build HTTP client: {}", e)))?;
+
+        Ok(Self { client, api_key, base_url })
+    }
+
+    /// Build request body for `speech:recognize`
+    ///
+    /// `gs://` URLs are passed by reference; everything else is inlined.
+    async fn build_request_body(
+        &self,
+        model: &str,
+        audio: &AudioInput,
+        options: &TranscriptionOptions,
+    ) -> Result<serde_json::Value, ProviderError> {
+        let mut config = serde_json::json!({
+            "languageCode": options.language.as_deref().unwrap_or("en-US"),
+            "enableAutomaticPunctuation": true,
+        });
+
+        if !model.is_empty() && model != "default" {
+            config["model"] = serde_json::json!(model);
+        }
+        if options.word_timestamps {
+            config["enableWordTimeOffsets"] = serde_json::json!(true);
+        }
+        if let Some(ref prompt) = options.prompt {
+            config["speechContexts"] = serde_json::json!([{ "phrases": [prompt] }]);
+        }
+        // WAV headers are read by the API; compressed formats must be named
+        let encoding = match audio.media_type() {
+            Some("audio/flac") => Some("FLAC"),
+            Some("audio/mpeg") => Some("MP3"),
+            Some("audio/ogg") | Some("audio/opus") => Some("OGG_OPUS"),
+            Some("audio/webm") => Some("WEBM_OPUS"),
+            _ => None,
+        };
+        if let Some(encoding) = encoding {
+            config["encoding"] = serde_json::json!(encoding);
+        }
+
+        let audio = match audio {
+            AudioInput::Url { url, .. } if url.starts_with("gs://") => serde_json::json!({ "uri": url }),
+            _ => serde_json::json!({ "content": audio.to_base64().await.map_err(audio_error)? }),
+        };
+
+        Ok(serde_json::json!({ "config": config, "audio": audio }))
+    }
+
+    /// Parse a `speech:recognize` response
+    ///
+    /// Each result covers a consecutive stretch of audio and becomes one
+    /// segment; the transcript joins their top alternatives.
+    fn parse_response(json: &str) -> Result<Transcription, ProviderError> {
+        #[derive(Deserialize)]
+        struct RecognizeResponse {
+            #[serde(default)]
+            results: Vec<SpeechResult>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct SpeechResult {
+            #[serde(default)]
+            alternatives: Vec<Alternative>,
+            result_end_time: Option<String>,
+            language_code: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct Alternative {
+            #[serde(default)]
+            transcript: String,
+            #[serde(default)]
+            words: Vec<WordInfo>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct WordInfo {
+            start_time: Option<String>,
+            end_time: Option<String>,
+            word: String,
+        }
+
+        let resp: RecognizeResponse = serde_json::from_str(json)?;
+
+        let mut transcription = Transcription::new("");
+        let mut texts = Vec::new();
+        let mut start = 0.0;
+        for result in resp.results {
+            let end = result.result_end_time.as_deref().and_then(parse_seconds).unwrap_or(start);
+            if transcription.language.is_none() {
+                transcription.language = result.language_code;
+            }
+
+            if let Some(alternative) = result.alternatives.into_iter().next() {
+                let text = alternative.transcript.trim().to_string();
+                transcription.words.extend(alternative.words.into_iter().map(|word| TranscriptWord {
+                    start: word.start_time.as_deref().and_then(parse_seconds).unwrap_or(start),
+                    end: word.end_time.as_deref().and_then(parse_seconds).unwrap_or(end),
+                    word: word.word,
+                }));
+                transcription.segments.push(TranscriptSegment { start, end, text: text.clone() });
+                texts.push(text);
+            }
+
+            transcription.duration = Some(end);
+            start = end;
+        }
+
+        transcription.text = texts.join(" ");
+        Ok(transcription)
+    }
+}
+
+#[async_trait]
+impl TranscriptionProvider for GoogleSpeechProvider {
+    async fn transcribe(
+        &self,
+        model: &str,
+        audio: &AudioInput,
+        options: &TranscriptionOptions,
+    ) -> Result<Transcription, ProviderError> {
+        let body = self.build_request_body(model, audio, options).await?;
+
+        debug!("Sending transcription request to Google Speech-to-Text: model={}", model);
+
+        let response = self.client
+            .post(format!("{}/speech:recognize?key={}", self.base_url, self.api_key))
+            .json(&body)
+            .send()
+            .await?;
+
+        let status = response.status().as_u16();
+        let text = response.text().await?;
+
+        if !(200..300).contains(&status) {
+            error!("Google Speech-to-Text error ({}): {}", status, text);
+            return Err(GoogleProvider::parse_error_response(status, &text));
+        }
+
+        let mut transcription = Self::parse_response(&text)?;
+        if !options.timestamps {
+            transcription.segments.clear();
+        }
+        Ok(transcription)
+    }
+
+    fn name(&self) -> &str {
+        "google-speech"
+    }
+}
+
+/// File name for the upload; Whisper servers detect the format from it
+fn file_name(audio: &AudioInput) -> String {
+    let named = match audio {
+        AudioInput::Path { path, .. } => path.file_name().and_then(|name| name.to_str()),
+        AudioInput::Url { url, .. } => url.split(['?', '#']).next().and_then(|path| path.rsplit('/').next()),
+        AudioInput::Base64 { .. } => None,
+    };
+
+    match named {
+        Some(name) if name.contains('.') => name.to_string(),
+        _ => {
+            let extension = match audio.media_type() {
+                Some("audio/mpeg") | None => "mp3",
+                Some(media_type) => media_type.rsplit('/').next().unwrap_or("mp3"),
+            };
+            format!("audio.{}", extension)
+        }
+    }
+}
+
+/// Parse a protobuf duration such as `"1.500s"`
+fn parse_seconds(duration: &str) -> Option<f64> {
+    duration.strip_suffix('s')?.parse().ok()
+}
+
+fn audio_error(error: anyhow::Error) -> ProviderError {
+    ProviderError::InvalidRequest(format!("Failed to read audio: {:#}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multimodal::AudioFormat;
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name(&AudioInput::from_base64("AA==".to_string(), AudioFormat::Wav)), "audio.wav");
+        assert_eq!(file_name(&AudioInput::from_base64("AA==".to_string(), AudioFormat::Mp3)), "audio.mp3");
+        assert_eq!(file_name(&AudioInput::from_url("https://example.com/clips/a.flac?sig=1")), "a.flac");
+    }
+
+    #[test]
+    fn test_parse_whisper_response() {
+        let transcription = WhisperProvider::parse_response(
+            r#"{"task":"transcribe","language":"english","duration":2.5,"text":" Hello world.",
+                "segments":[{"id":0,"start":0.0,"end":2.5,"text":" Hello world.","tokens":[1,2]}],
+                "words":[{"word":"Hello","start":0.1,"end":0.6},{"word":"world","start":0.7,"end":1.2}]}"#,
+            "verbose_json",
+        )
+        .unwrap();
+        assert_eq!(transcription.text, "Hello world.");
+        assert_eq!(transcription.language.as_deref(), Some("english"));
+        assert_eq!(transcription.segments.len(), 1);
+        assert_eq!(transcription.words[1].word, "world");
+
+        let transcription = WhisperProvider::parse_response("1\n00:00:00,000 --> 00:00:02,500\nHello world.\n", "srt").unwrap();
+        assert!(transcription.text.starts_with("1\n00:00:00,000"));
+    }
+
+    #[test]
+    fn test_parse_google_response() {
+        let transcription = GoogleSpeechProvider::parse_response(
+            r#"{"results":[
+                {"alternatives":[{"transcript":"Hello world","confidence":0.9,
+                    "words":[{"startTime":"0s","endTime":"0.500s","word":"Hello"},{"startTime":"0.500s","endTime":"1s","word":"world"}]}],
+                 "resultEndTime":"1.200s","languageCode":"en-us"},
+                {"alternatives":[{"transcript":" how are you"}],"resultEndTime":"2.400s","languageCode":"en-us"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(transcription.text, "Hello world how are you");
+        assert_eq!(transcription.duration, Some(2.4));
+        assert_eq!(transcription.segments[1].start, 1.2);
+        assert_eq!(transcription.words[1].end, 1.0);
+
+        // Silence produces no results
+        assert_eq!(GoogleSpeechProvider::parse_response("{}").unwrap().text, "");
+    }
+}
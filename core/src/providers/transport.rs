@@ -0,0 +1,141 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! HTTP clients built from a provider's transport settings.
+//!
+//! Each provider builds its own `reqwest::Client` with defaults suited to
+//! its API. When a provider's configuration has a `transport` section,
+//! [`ProviderFactory`](super::ProviderFactory) replaces that client with one
+//! from [`http_client`], so proxies, extra headers, a corporate CA bundle,
+//! pool sizing and the HTTP version apply the same way to every provider.
+//!
+//! # Examples
+//!
+//! ```toml
+//! [providers.openai.transport]
+//! proxy = "http://proxy.corp:3128"
+//! no_proxy = "localhost"
+//! ca_bundle = "/etc/ssl/certs/corp-ca.pem"
+//! pool_max_idle_per_host = 32
+//! http2 = false
+//!
+//! [providers.openai.transport.headers]
+//! OpenAI-Organization = "org-123"
+//! OpenAI-Project = "proj-456"
+//! ```
+
+use super::ProviderError;
+use crate::config::models::TransportConfig;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::time::Duration;
+
+/// Builds an HTTP client from transport settings
+///
+/// `timeout` bounds each request, including reading the whole response.
+///
+/// # Errors
+///
+/// `ProviderError::InvalidRequest` when the proxy URL, a header or the CA
+/// bundle is invalid, or the CA bundle cannot be read.
+pub fn http_client(transport: &TransportConfig, timeout: Duration) -> Result<reqwest::Client, ProviderError> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(timeout)
+        .use_rustls_tls()
+        .default_headers(default_headers(transport)?);
+
+    if let Some(ref url) = transport.proxy {
+        let proxy = reqwest::Proxy::all(url)
+            .map_err(|e| ProviderError::InvalidRequest(format!("Invalid proxy '{}': {}", url, e)))?
+            .no_proxy(transport.no_proxy.as_deref().and_then(reqwest::NoProxy::from_string));
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(ref path) = transport.ca_bundle {
+        let pem = std::fs::read(path).map_err(|e| {
+            ProviderError::InvalidRequest(format!("Failed to read CA bundle {}: {}", path.display(), e))
+        })?;
+        let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| ProviderError::InvalidRequest(format!("Invalid CA bundle {}: {}", path.display(), e)))?;
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    if let Some(max_idle) = transport.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max_idle);
+    }
+    if let Some(seconds) = transport.pool_idle_timeout_seconds {
+        builder = builder.pool_idle_timeout(Duration::from_secs(seconds));
+    }
+
+    builder = match transport.http2 {
+        Some(true) => builder.http2_prior_knowledge(),
+        Some(false) => builder.http1_only(),
+        None => builder,
+    };
+
+    builder
+        .build()
+        .map_err(|e| ProviderError::InternalError(format!("Failed to build HTTP client: {}", e)))
+}
+
+/// The configured headers; values are marked sensitive so they are not logged
+fn default_headers(transport: &TransportConfig) -> Result<HeaderMap, ProviderError> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &transport.headers {
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| ProviderError::InvalidRequest(format!("Invalid header name '{}': {}", name, e)))?;
+        let mut header_value = HeaderValue::from_str(value)
+            .map_err(|e| ProviderError::InvalidRequest(format!("Invalid value for header '{}': {}", name, e)))?;
+        header_value.set_sensitive(true);
+        headers.insert(header_name, header_value);
+    }
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    #[test]
+    fn test_default_transport() {
+        assert!(http_client(&TransportConfig::default(), Duration::from_secs(30)).is_ok());
+    }
+
+    #[test]
+    fn test_full_transport() {
+        let transport = TransportConfig {
+            proxy: Some("http://proxy.example.com:3128".to_string()),
+            no_proxy: Some("localhost,127.0.0.1".to_string()),
+            headers: HashMap::from([("OpenAI-Organization".to_string(), "org-123".to_string())]),
+            ca_bundle: None,
+            pool_max_idle_per_host: Some(4),
+            pool_idle_timeout_seconds: Some(30),
+            http2: Some(false),
+        };
+        assert!(http_client(&transport, Duration::from_secs(30)).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_transport() {
+        let invalid = |transport: TransportConfig| {
+            matches!(http_client(&transport, Duration::from_secs(30)), Err(ProviderError::InvalidRequest(_)))
+        };
+
+        assert!(invalid(TransportConfig { proxy: Some("not a url".to_string()), ..Default::default() }));
+        assert!(invalid(TransportConfig {
+            headers: HashMap::from([("Bad Header".to_string(), "value".to_string())]),
+            ..Default::default()
+        }));
+        assert!(invalid(TransportConfig { ca_bundle: Some("/nonexistent/ca.pem".into()), ..Default::default() }));
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "-----BEGIN CERTIFICATE-----\nnot base64\n-----END CERTIFICATE-----").unwrap();
+        assert!(invalid(TransportConfig { ca_bundle: Some(file.path().to_path_buf()), ..Default::default() }));
+    }
+}
@@ -12,9 +12,14 @@
 use chrono::{DateTime, Utc};
 use futures::Stream;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::pin::Pin;
 
 use super::error::ProviderError;
+use super::logprobs::{LogprobOptions, TokenLogprob};
+use super::streaming::StreamEvent;
+use super::structured::{ResponseFormat, SchemaViolation};
+use super::tools::{ToolCall, ToolChoice, ToolDefinition};
 
 /// A completion request to send to an LLM provider.
 ///
@@ -26,15 +31,31 @@ use super::error::ProviderError;
 ///
 /// ```
 /// use llm_test_bench_core::providers::types::CompletionRequest;
+/// use std::collections::HashMap;
 ///
 /// let request = CompletionRequest {
 ///     model: "gpt-4".to_string(),
 ///     prompt: "Explain Rust ownership".to_string(),
+///     system: Some("You are a concise Rust tutor.".to_string()),
+///     messages: Vec::new(),
 ///     max_tokens: Some(100),
 ///     temperature: Some(0.7),
 ///     top_p: Some(0.9),
 ///     stop: Some(vec!["\n\n".to_string()]),
 ///     stream: false,
+///     tools: Vec::new(),
+///     tool_choice: None,
+///     response_format: None,
+///     logprobs: None,
+///     seed: Some(42),
+///     presence_penalty: None,
+///     frequency_penalty: None,
+///     top_k: None,
+///     n: None,
+///     logit_bias: HashMap::new(),
+///     reasoning_effort: None,
+///     thinking_budget: None,
+///     cache_points: Vec::new(),
 /// };
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -43,8 +64,27 @@ pub struct CompletionRequest {
     pub model: String,
 
     /// The prompt or input text to send to the model.
+    ///
+    /// When `messages` is non-empty, the prompt is sent as the final user
+    /// turn after them. It may be left empty if `messages` already ends with
+    /// the user turn to answer.
     pub prompt: String,
 
+    /// System instructions for the model.
+    ///
+    /// Providers map this to their native system prompt field (e.g. the
+    /// `system` parameter for Anthropic or a leading `system` message for
+    /// OpenAI-compatible APIs).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+
+    /// Conversation turns preceding the prompt.
+    ///
+    /// Use this for multi-turn chats and few-shot examples. Messages with the
+    /// [`MessageRole::System`] role are merged into the system prompt.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<ChatMessage>,
+
     /// Maximum number of tokens to generate in the completion.
     ///
     /// If `None`, the provider's default will be used.
@@ -77,6 +117,83 @@ pub struct CompletionRequest {
     /// the provider should return a complete response.
     #[serde(default)]
     pub stream: bool,
+
+    /// Tools the model may call.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolDefinition>,
+
+    /// Controls how the model uses `tools`.
+    ///
+    /// If `None`, the provider's default (usually "auto") will be used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+
+    /// Format the output must follow (free text, a JSON object, or JSON
+    /// matching a schema).
+    ///
+    /// If `None`, the model answers in free text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+
+    /// Per-token log probabilities to return.
+    ///
+    /// If `None`, no log probabilities are requested. Providers without
+    /// log probability support ignore this.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<LogprobOptions>,
+
+    /// Seed for reproducible sampling.
+    ///
+    /// Providers that support it make a best effort to return the same
+    /// output for the same request and seed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+
+    /// Penalty between -2.0 and 2.0 for tokens that already appeared.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+
+    /// Penalty between -2.0 and 2.0 scaled by how often tokens appeared.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+
+    /// Sample only from the `top_k` most likely tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+
+    /// Number of completions to generate.
+    ///
+    /// If `None`, one completion is generated. With more than one, the
+    /// response lists them all in `choices`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+
+    /// Bias between -100 and 100 added to the logits of token IDs.
+    ///
+    /// Token IDs are those of the model's tokenizer.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub logit_bias: HashMap<u32, f32>,
+
+    /// How much a reasoning model should think before answering.
+    ///
+    /// Sent as OpenAI's `reasoning_effort`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<ReasoningEffort>,
+
+    /// Maximum number of tokens a reasoning model may spend thinking.
+    ///
+    /// Enables Anthropic's extended thinking with this `budget_tokens`, or
+    /// sets Gemini's `thinkingBudget`. Anthropic requires at least 1,024
+    /// and less than `max_tokens`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<u32>,
+
+    /// Ends of prompt prefixes the provider should cache.
+    ///
+    /// Sent as Anthropic `cache_control` breakpoints. Providers that cache
+    /// long prompts automatically, such as OpenAI, ignore this.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cache_points: Vec<CachePoint>,
 }
 
 impl CompletionRequest {
@@ -98,14 +215,155 @@ impl CompletionRequest {
         Self {
             model: model.into(),
             prompt: prompt.into(),
+            system: None,
+            messages: Vec::new(),
             max_tokens: None,
             temperature: None,
             top_p: None,
             stop: None,
             stream: false,
+            tools: Vec::new(),
+            tool_choice: None,
+            response_format: None,
+            logprobs: None,
+            seed: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+            top_k: None,
+            n: None,
+            logit_bias: HashMap::new(),
+            reasoning_effort: None,
+            thinking_budget: None,
+            cache_points: Vec::new(),
         }
     }
 
+    /// Creates a new completion request from a list of chat messages.
+    ///
+    /// The prompt is left empty, so the last message should be the user turn
+    /// the model is expected to answer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use llm_test_bench_core::providers::types::{ChatMessage, CompletionRequest};
+    ///
+    /// let request = CompletionRequest::from_messages(
+    ///     "gpt-4",
+    ///     vec![
+    ///         ChatMessage::system("Answer with a single word."),
+    ///         ChatMessage::user("What color is the sky?"),
+    ///     ],
+    /// );
+    /// assert_eq!(request.system_prompt().as_deref(), Some("Answer with a single word."));
+    /// assert_eq!(request.conversation().len(), 1);
+    /// ```
+    pub fn from_messages(model: impl Into<String>, messages: Vec<ChatMessage>) -> Self {
+        Self::new(model, String::new()).with_messages(messages)
+    }
+
+    /// Sets the system instructions.
+    pub fn with_system(mut self, system: impl Into<String>) -> Self {
+        self.system = Some(system.into());
+        self
+    }
+
+    /// Sets the conversation turns preceding the prompt.
+    pub fn with_messages(mut self, messages: Vec<ChatMessage>) -> Self {
+        self.messages = messages;
+        self
+    }
+
+    /// Appends a single conversation turn.
+    pub fn with_message(mut self, message: ChatMessage) -> Self {
+        self.messages.push(message);
+        self
+    }
+
+    /// Returns the combined system prompt, if any.
+    ///
+    /// The `system` field comes first, followed by the content of any
+    /// system-role entries in `messages`, separated by blank lines.
+    pub fn system_prompt(&self) -> Option<String> {
+        let parts: Vec<&str> = self
+            .system
+            .iter()
+            .map(String::as_str)
+            .chain(
+                self.messages
+                    .iter()
+                    .filter(|m| m.role == MessageRole::System)
+                    .map(|m| m.content.as_str()),
+            )
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join("\n\n"))
+        }
+    }
+
+    /// Returns the user and assistant turns, ending with the prompt.
+    ///
+    /// System messages are excluded; use [`system_prompt`](Self::system_prompt)
+    /// for those. The prompt is appended as a user turn when non-empty.
+    pub fn conversation(&self) -> Vec<ChatMessage> {
+        let mut turns: Vec<ChatMessage> = self
+            .messages
+            .iter()
+            .filter(|m| m.role != MessageRole::System)
+            .cloned()
+            .collect();
+
+        if !self.prompt.is_empty() {
+            turns.push(ChatMessage::user(self.prompt.clone()));
+        }
+
+        turns
+    }
+
+    /// Returns the full message list in OpenAI chat format order.
+    ///
+    /// This is the system prompt (as a single system message) followed by
+    /// the [`conversation`](Self::conversation).
+    pub fn chat_messages(&self) -> Vec<ChatMessage> {
+        let mut messages = Vec::with_capacity(self.messages.len() + 2);
+        if let Some(system) = self.system_prompt() {
+            messages.push(ChatMessage::system(system));
+        }
+        messages.extend(self.conversation());
+        messages
+    }
+
+    /// Renders the conversation as a single plain-text prompt.
+    ///
+    /// Used by providers whose APIs only accept raw text. A lone user turn is
+    /// returned verbatim; longer conversations are rendered as a
+    /// `User:`/`Assistant:` transcript ending with an open assistant turn.
+    /// The system prompt is not included.
+    pub fn render_prompt(&self) -> String {
+        let turns = self.conversation();
+
+        if let [only] = turns.as_slice() {
+            if only.role == MessageRole::User {
+                return only.content.clone();
+            }
+        }
+
+        let mut rendered = turns
+            .iter()
+            .map(|m| match m.role {
+                MessageRole::Assistant => format!("Assistant: {}", m.content),
+                _ => format!("User: {}", m.content),
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        rendered.push_str("\n\nAssistant:");
+        rendered
+    }
+
     /// Sets the maximum number of tokens to generate.
     pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
         self.max_tokens = Some(max_tokens);
@@ -135,6 +393,181 @@ impl CompletionRequest {
         self.stream = true;
         self
     }
+
+    /// Sets the tools the model may call.
+    pub fn with_tools(mut self, tools: Vec<ToolDefinition>) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Sets the tool choice.
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    /// Sets the response format.
+    pub fn with_response_format(mut self, response_format: ResponseFormat) -> Self {
+        self.response_format = Some(response_format);
+        self
+    }
+
+    /// Requests log probabilities for the generated tokens, with up to
+    /// `top_logprobs` alternatives per position.
+    pub fn with_logprobs(mut self, top_logprobs: usize) -> Self {
+        self.logprobs.get_or_insert_with(LogprobOptions::default).top_logprobs = top_logprobs;
+        self
+    }
+
+    /// Requests log probabilities for the prompt's tokens as well.
+    ///
+    /// Only a plain prompt can be scored; `system` and `messages` must be
+    /// empty.
+    pub fn with_prompt_logprobs(mut self) -> Self {
+        self.logprobs.get_or_insert_with(LogprobOptions::default).include_prompt = true;
+        self
+    }
+
+    /// Sets the sampling seed.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Sets the presence penalty.
+    pub fn with_presence_penalty(mut self, penalty: f32) -> Self {
+        self.presence_penalty = Some(penalty);
+        self
+    }
+
+    /// Sets the frequency penalty.
+    pub fn with_frequency_penalty(mut self, penalty: f32) -> Self {
+        self.frequency_penalty = Some(penalty);
+        self
+    }
+
+    /// Restricts sampling to the `top_k` most likely tokens.
+    pub fn with_top_k(mut self, top_k: u32) -> Self {
+        self.top_k = Some(top_k);
+        self
+    }
+
+    /// Sets the number of completions to generate.
+    pub fn with_n(mut self, n: u32) -> Self {
+        self.n = Some(n);
+        self
+    }
+
+    /// Biases the logit of a single token ID.
+    pub fn with_logit_bias(mut self, token: u32, bias: f32) -> Self {
+        self.logit_bias.insert(token, bias);
+        self
+    }
+
+    /// Sets the reasoning effort.
+    pub fn with_reasoning_effort(mut self, effort: ReasoningEffort) -> Self {
+        self.reasoning_effort = Some(effort);
+        self
+    }
+
+    /// Sets the thinking token budget.
+    pub fn with_thinking_budget(mut self, budget_tokens: u32) -> Self {
+        self.thinking_budget = Some(budget_tokens);
+        self
+    }
+
+    /// Marks the prompt up to `point` as cacheable.
+    pub fn with_cache_point(mut self, point: CachePoint) -> Self {
+        if !self.cache_points.contains(&point) {
+            self.cache_points.push(point);
+        }
+        self
+    }
+
+    /// Returns the number of completions requested, at least one.
+    pub fn completions(&self) -> u32 {
+        self.n.unwrap_or(1).max(1)
+    }
+}
+
+/// The role of a message in a chat conversation.
+///
+/// # Examples
+///
+/// ```
+/// use llm_test_bench_core::providers::types::MessageRole;
+///
+/// assert_eq!(MessageRole::Assistant.to_string(), "assistant");
+/// ```
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageRole {
+    /// Instructions that steer the model's behavior.
+    System,
+
+    /// A message written by the user.
+    User,
+
+    /// A message written by the model.
+    Assistant,
+}
+
+impl std::fmt::Display for MessageRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageRole::System => write!(f, "system"),
+            MessageRole::User => write!(f, "user"),
+            MessageRole::Assistant => write!(f, "assistant"),
+        }
+    }
+}
+
+/// A single message in a chat conversation.
+///
+/// Serializes to the `{"role": ..., "content": ...}` shape used by
+/// OpenAI-compatible chat APIs.
+///
+/// # Examples
+///
+/// ```
+/// use llm_test_bench_core::providers::types::{ChatMessage, MessageRole};
+///
+/// let message = ChatMessage::user("Hello!");
+/// assert_eq!(message.role, MessageRole::User);
+/// assert_eq!(message.content, "Hello!");
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChatMessage {
+    /// Who authored the message.
+    pub role: MessageRole,
+
+    /// The text content of the message.
+    pub content: String,
+}
+
+impl ChatMessage {
+    /// Creates a new message with the given role and content.
+    pub fn new(role: MessageRole, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+        }
+    }
+
+    /// Creates a system message.
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(MessageRole::System, content)
+    }
+
+    /// Creates a user message.
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(MessageRole::User, content)
+    }
+
+    /// Creates an assistant message.
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new(MessageRole::Assistant, content)
+    }
 }
 
 /// A completion response from an LLM provider.
@@ -148,6 +581,7 @@ impl CompletionRequest {
 /// ```
 /// use llm_test_bench_core::providers::types::{CompletionResponse, TokenUsage, FinishReason};
 /// use chrono::Utc;
+/// use std::collections::HashMap;
 ///
 /// let response = CompletionResponse {
 ///     id: "cmpl-123".to_string(),
@@ -157,9 +591,18 @@ impl CompletionRequest {
 ///         prompt_tokens: 10,
 ///         completion_tokens: 8,
 ///         total_tokens: 18,
+///         reasoning_tokens: 0,
+///         cached_tokens: 0,
+///         cache_write_tokens: 0,
 ///     },
 ///     finish_reason: FinishReason::Stop,
 ///     created_at: Utc::now(),
+///     tool_calls: Vec::new(),
+///     schema_violations: Vec::new(),
+///     logprobs: None,
+///     prompt_logprobs: None,
+///     choices: Vec::new(),
+///     metadata: HashMap::new(),
 /// };
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -182,10 +625,156 @@ pub struct CompletionResponse {
     /// Timestamp when the completion was created.
     #[serde(with = "chrono::serde::ts_seconds")]
     pub created_at: DateTime<Utc>,
+
+    /// Tool calls requested by the model.
+    ///
+    /// Non-empty when the model decided to invoke one or more of the request's
+    /// tools, typically with [`FinishReason::ToolCalls`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+
+    /// Ways the content fails to match the request's response format.
+    ///
+    /// Empty when free text was requested or the output conforms.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schema_violations: Vec<SchemaViolation>,
+
+    /// Log probabilities of the generated tokens, if requested and supported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<Vec<TokenLogprob>>,
+
+    /// Log probabilities of the prompt's tokens, if requested and supported.
+    ///
+    /// The first prompt token has no preceding context and is omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_logprobs: Option<Vec<TokenLogprob>>,
+
+    /// Every completion, when the request asked for more than one.
+    ///
+    /// `content`, `finish_reason` and `tool_calls` repeat the first choice.
+    /// Empty for single completions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<CompletionChoice>,
+
+    /// Additional details about how the response was produced.
+    ///
+    /// Set by provider wrappers rather than by the API, for example the
+    /// backend that served a request sent through a
+    /// [`FallbackProvider`](super::FallbackProvider).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+}
+
+impl CompletionResponse {
+    /// Deserializes the content as JSON.
+    ///
+    /// Intended for requests with a JSON [`ResponseFormat`]; check
+    /// `schema_violations` first to distinguish malformed output from a
+    /// type mismatch.
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_str(&self.content)
+    }
+}
+
+/// One of several completions generated for a request with `n > 1`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompletionChoice {
+    /// Position of this completion among the choices.
+    pub index: usize,
+
+    /// The generated text content.
+    pub content: String,
+
+    /// The reason the model stopped generating.
+    pub finish_reason: FinishReason,
+
+    /// Tool calls requested by the model.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+/// How much a reasoning model should think before answering.
+///
+/// Lower effort answers faster and spends fewer reasoning tokens.
+///
+/// # Examples
+///
+/// ```
+/// use llm_test_bench_core::providers::types::ReasoningEffort;
+///
+/// assert_eq!(ReasoningEffort::High.to_string(), "high");
+/// assert_eq!("low".parse::<ReasoningEffort>(), Ok(ReasoningEffort::Low));
+/// ```
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    /// Minimal reasoning.
+    Low,
+
+    /// The model's default.
+    Medium,
+
+    /// Extensive reasoning.
+    High,
+}
+
+impl std::fmt::Display for ReasoningEffort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReasoningEffort::Low => write!(f, "low"),
+            ReasoningEffort::Medium => write!(f, "medium"),
+            ReasoningEffort::High => write!(f, "high"),
+        }
+    }
+}
+
+impl std::str::FromStr for ReasoningEffort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "low" => Ok(ReasoningEffort::Low),
+            "medium" => Ok(ReasoningEffort::Medium),
+            "high" => Ok(ReasoningEffort::High),
+            _ => Err(format!("Invalid reasoning effort '{}': expected low, medium or high", s)),
+        }
+    }
+}
+
+/// The end of a cacheable prompt prefix.
+///
+/// Prompts are cached in the order tools, system prompt, conversation, so
+/// each point covers everything before it as well.
+///
+/// # Examples
+///
+/// ```
+/// use llm_test_bench_core::providers::types::{CachePoint, CompletionRequest};
+///
+/// let request = CompletionRequest::new("claude-3-5-sonnet-20241022", "Summarize section 2.")
+///     .with_system("<a long shared document>")
+///     .with_cache_point(CachePoint::System);
+/// assert_eq!(request.cache_points, vec![CachePoint::System]);
+/// ```
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum CachePoint {
+    /// After the tool definitions.
+    Tools,
+
+    /// After the system prompt.
+    System,
+
+    /// After the conversation turns preceding the prompt.
+    Messages,
 }
 
 /// Token usage information for a completion.
 ///
+/// Reasoning tokens are billed as output, so they are counted in
+/// `completion_tokens` as well as in `reasoning_tokens`. Likewise, tokens
+/// read from or written to the prompt cache are part of `prompt_tokens`.
+///
 /// # Examples
 ///
 /// ```
@@ -195,6 +784,9 @@ pub struct CompletionResponse {
 ///     prompt_tokens: 50,
 ///     completion_tokens: 100,
 ///     total_tokens: 150,
+///     reasoning_tokens: 60,
+///     cached_tokens: 0,
+///     cache_write_tokens: 0,
 /// };
 /// assert_eq!(usage.total_tokens, 150);
 /// ```
@@ -208,6 +800,23 @@ pub struct TokenUsage {
 
     /// Total tokens used (prompt + completion).
     pub total_tokens: usize,
+
+    /// Completion tokens the model spent reasoning before its answer.
+    ///
+    /// Zero for providers that do not report them separately, such as
+    /// Anthropic, whose thinking tokens are only part of `completion_tokens`.
+    #[serde(default)]
+    pub reasoning_tokens: usize,
+
+    /// Prompt tokens read from the provider's prompt cache.
+    #[serde(default)]
+    pub cached_tokens: usize,
+
+    /// Prompt tokens written to the provider's prompt cache.
+    ///
+    /// Only reported by providers that bill cache writes, such as Anthropic.
+    #[serde(default)]
+    pub cache_write_tokens: usize,
 }
 
 impl TokenUsage {
@@ -228,11 +837,60 @@ impl TokenUsage {
             prompt_tokens,
             completion_tokens,
             total_tokens: prompt_tokens + completion_tokens,
+            reasoning_tokens: 0,
+            cached_tokens: 0,
+            cache_write_tokens: 0,
         }
     }
 
+    /// Sets the number of reasoning tokens, which are part of the completion.
+    pub fn with_reasoning_tokens(mut self, reasoning_tokens: usize) -> Self {
+        self.reasoning_tokens = reasoning_tokens;
+        self
+    }
+
+    /// Sets the number of cached tokens, which are part of the prompt.
+    pub fn with_cached_tokens(mut self, cached_tokens: usize) -> Self {
+        self.cached_tokens = cached_tokens;
+        self
+    }
+
+    /// Sets the number of tokens written to the cache, which are part of
+    /// the prompt.
+    pub fn with_cache_write_tokens(mut self, cache_write_tokens: usize) -> Self {
+        self.cache_write_tokens = cache_write_tokens;
+        self
+    }
+
+    /// Adds the counts of `other` to this usage, for example to total the
+    /// requests made for one result.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use llm_test_bench_core::providers::types::TokenUsage;
+    ///
+    /// let mut usage = TokenUsage::new(100, 20).with_cached_tokens(80);
+    /// usage.accumulate(&TokenUsage::new(150, 30).with_cache_write_tokens(50));
+    /// assert_eq!(usage.total_tokens, 300);
+    /// assert_eq!((usage.cached_tokens, usage.cache_write_tokens), (80, 50));
+    /// ```
+    pub fn accumulate(&mut self, other: &TokenUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+        self.reasoning_tokens += other.reasoning_tokens;
+        self.cached_tokens += other.cached_tokens;
+        self.cache_write_tokens += other.cache_write_tokens;
+    }
+
     /// Returns the total cost of this usage at the given rates.
     ///
+    /// Reasoning tokens are billed at the completion rate and cached tokens
+    /// at the prompt rate; see
+    /// [`calculate_cost_with_cache`](Self::calculate_cost_with_cache) for
+    /// discounted cache reads and surcharged cache writes.
+    ///
     /// # Arguments
     ///
     /// * `prompt_cost_per_1k` - Cost per 1,000 prompt tokens
@@ -252,6 +910,34 @@ impl TokenUsage {
         let completion_cost = (self.completion_tokens as f64 / 1000.0) * completion_cost_per_1k;
         prompt_cost + completion_cost
     }
+
+    /// Returns the total cost of this usage, billing prompt tokens read from
+    /// and written to the cache at their own rates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use llm_test_bench_core::providers::types::TokenUsage;
+    ///
+    /// let usage = TokenUsage::new(1000, 1000).with_cached_tokens(500);
+    /// let cost = usage.calculate_cost_with_cache(0.002, 0.001, 0.0025, 0.008);
+    /// assert!((cost - 0.0095).abs() < 1e-9); // 500 uncached + 500 cached + 1000 completion
+    /// ```
+    pub fn calculate_cost_with_cache(
+        &self,
+        prompt_cost_per_1k: f64,
+        cached_cost_per_1k: f64,
+        cache_write_cost_per_1k: f64,
+        completion_cost_per_1k: f64,
+    ) -> f64 {
+        let cached = self.cached_tokens.min(self.prompt_tokens);
+        let written = self.cache_write_tokens.min(self.prompt_tokens - cached);
+        let uncached_cost = ((self.prompt_tokens - cached - written) as f64 / 1000.0) * prompt_cost_per_1k;
+        let cached_cost = (cached as f64 / 1000.0) * cached_cost_per_1k;
+        let written_cost = (written as f64 / 1000.0) * cache_write_cost_per_1k;
+        let completion_cost = (self.completion_tokens as f64 / 1000.0) * completion_cost_per_1k;
+        uncached_cost + cached_cost + written_cost + completion_cost
+    }
 }
 
 /// The reason a completion finished.
@@ -308,6 +994,8 @@ impl std::fmt::Display for FinishReason {
 ///     max_tokens: 8192,
 ///     supports_streaming: true,
 ///     supports_function_calling: true,
+///     supports_vision: false,
+///     supports_audio: false,
 /// };
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -326,6 +1014,14 @@ pub struct ModelInfo {
 
     /// Whether this model supports function/tool calling.
     pub supports_function_calling: bool,
+
+    /// Whether this model accepts image inputs.
+    #[serde(default)]
+    pub supports_vision: bool,
+
+    /// Whether this model accepts audio inputs.
+    #[serde(default)]
+    pub supports_audio: bool,
 }
 
 impl ModelInfo {
@@ -358,17 +1054,32 @@ impl ModelInfo {
             max_tokens,
             supports_streaming,
             supports_function_calling,
+            supports_vision: false,
+            supports_audio: false,
         }
     }
+
+    /// Marks the model as accepting image inputs.
+    pub fn with_vision(mut self) -> Self {
+        self.supports_vision = true;
+        self
+    }
+
+    /// Marks the model as accepting audio inputs.
+    pub fn with_audio(mut self) -> Self {
+        self.supports_audio = true;
+        self
+    }
 }
 
-/// A stream of response chunks from a provider.
+/// A stream of response events from a provider.
 ///
-/// This type represents a streaming response where tokens are yielded
-/// incrementally as they're generated by the model.
+/// This type represents a streaming response where content is yielded
+/// incrementally as it's generated by the model, followed by the token usage
+/// and finish reason.
 ///
-/// Each item in the stream is either a chunk of text or an error.
-pub type ResponseStream = Pin<Box<dyn Stream<Item = Result<String, ProviderError>> + Send>>;
+/// Each item in the stream is either a timestamped [`StreamEvent`] or an error.
+pub type ResponseStream = Pin<Box<dyn Stream<Item = Result<StreamEvent, ProviderError>> + Send>>;
 
 #[cfg(test)]
 mod tests {
@@ -400,6 +1111,71 @@ mod tests {
         assert!(request.stream);
     }
 
+    #[test]
+    fn test_completion_request_system_and_messages() {
+        let request = CompletionRequest::new("gpt-4", "And 3 + 3?")
+            .with_system("You are a calculator.")
+            .with_messages(vec![
+                ChatMessage::system("Answer with digits only."),
+                ChatMessage::user("1 + 1?"),
+                ChatMessage::assistant("2"),
+            ]);
+
+        assert_eq!(
+            request.system_prompt().as_deref(),
+            Some("You are a calculator.\n\nAnswer with digits only.")
+        );
+
+        let conversation = request.conversation();
+        assert_eq!(conversation.len(), 3);
+        assert_eq!(conversation[0], ChatMessage::user("1 + 1?"));
+        assert_eq!(conversation[1], ChatMessage::assistant("2"));
+        assert_eq!(conversation[2], ChatMessage::user("And 3 + 3?"));
+
+        let chat = request.chat_messages();
+        assert_eq!(chat.len(), 4);
+        assert_eq!(chat[0].role, MessageRole::System);
+    }
+
+    #[test]
+    fn test_completion_request_from_messages() {
+        let request = CompletionRequest::from_messages("gpt-4", vec![ChatMessage::user("Hi")]);
+        assert!(request.prompt.is_empty());
+        assert_eq!(request.system_prompt(), None);
+        assert_eq!(request.conversation(), vec![ChatMessage::user("Hi")]);
+        assert_eq!(request.chat_messages(), vec![ChatMessage::user("Hi")]);
+    }
+
+    #[test]
+    fn test_render_prompt() {
+        let single = CompletionRequest::new("model", "Hello");
+        assert_eq!(single.render_prompt(), "Hello");
+
+        let multi = CompletionRequest::new("model", "How are you?")
+            .with_message(ChatMessage::user("Hi"))
+            .with_message(ChatMessage::assistant("Hello!"));
+        assert_eq!(
+            multi.render_prompt(),
+            "User: Hi\n\nAssistant: Hello!\n\nUser: How are you?\n\nAssistant:"
+        );
+    }
+
+    #[test]
+    fn test_chat_message_serialization() {
+        let message = ChatMessage::assistant("Sure.");
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json, serde_json::json!({"role": "assistant", "content": "Sure."}));
+
+        let request = CompletionRequest::new("gpt-4", "Hello");
+        let json = serde_json::to_value(&request).unwrap();
+        assert!(json.get("system").is_none());
+        assert!(json.get("messages").is_none());
+
+        let deserialized: CompletionRequest =
+            serde_json::from_str(r#"{"model":"gpt-4","prompt":"Hello"}"#).unwrap();
+        assert_eq!(deserialized, request);
+    }
+
     #[test]
     fn test_token_usage_new() {
         let usage = TokenUsage::new(50, 100);
@@ -415,6 +1191,46 @@ mod tests {
         assert!((cost - 0.15).abs() < 0.001); // Account for floating point
     }
 
+    #[test]
+    fn test_token_usage_reasoning_and_cached_default_to_zero() {
+        let usage: TokenUsage =
+            serde_json::from_str(r#"{"prompt_tokens":1,"completion_tokens":2,"total_tokens":3}"#).unwrap();
+        assert_eq!(usage, TokenUsage::new(1, 2));
+        assert_eq!((usage.reasoning_tokens, usage.cached_tokens, usage.cache_write_tokens), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_token_usage_accumulate() {
+        let mut usage = TokenUsage::new(10, 5).with_reasoning_tokens(2).with_cached_tokens(4);
+        usage.accumulate(&TokenUsage::new(20, 8).with_reasoning_tokens(3).with_cache_write_tokens(6));
+        assert_eq!(
+            usage,
+            TokenUsage::new(30, 13).with_reasoning_tokens(5).with_cached_tokens(4).with_cache_write_tokens(6)
+        );
+    }
+
+    #[test]
+    fn test_token_usage_calculate_cost_with_cache_writes() {
+        // 1,000 fresh, 2,000 read and 1,000 written prompt tokens
+        let usage = TokenUsage::new(4000, 100).with_cached_tokens(2000).with_cache_write_tokens(1000);
+        let cost = usage.calculate_cost_with_cache(0.003, 0.0003, 0.00375, 0.015);
+        assert!((cost - (0.003 + 2.0 * 0.0003 + 0.00375 + 0.1 * 0.015)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_completion_request_cache_points() {
+        let request = CompletionRequest::new("claude-3-5-sonnet-20241022", "Hi")
+            .with_cache_point(CachePoint::Tools)
+            .with_cache_point(CachePoint::System)
+            .with_cache_point(CachePoint::Tools);
+        assert_eq!(request.cache_points, vec![CachePoint::Tools, CachePoint::System]);
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["cache_points"], serde_json::json!(["tools", "system"]));
+        let json = serde_json::to_value(CompletionRequest::new("gpt-4o", "Hi")).unwrap();
+        assert!(json.get("cache_points").is_none());
+    }
+
     #[test]
     fn test_finish_reason_display() {
         assert_eq!(FinishReason::Stop.to_string(), "stop");
@@ -454,6 +1270,12 @@ mod tests {
             usage: TokenUsage::new(10, 5),
             finish_reason: FinishReason::Stop,
             created_at: Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         };
 
         let json = serde_json::to_string(&response).unwrap();
@@ -0,0 +1,231 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! BPE encodings and the model-to-encoding mapping
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use super::TokenizerError;
+
+/// Pre-tokenization pattern shared by `r50k_base` and `p50k_base`
+const GPT2_PATTERN: &str =
+    r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+";
+
+/// Pre-tokenization pattern for `cl100k_base`
+const CL100K_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+
+/// Pre-tokenization pattern for `o200k_base`
+const O200K_PATTERN: &str = concat!(
+    r"[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]*[\p{Ll}\p{Lm}\p{Lo}\p{M}]+(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+    r"|[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]+[\p{Ll}\p{Lm}\p{Lo}\p{M}]*(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+    r"|\p{N}{1,3}",
+    r"| ?[^\s\p{L}\p{N}]+[\r\n/]*",
+    r"|\s*[\r\n]+",
+    r"|\s+(?!\S)",
+    r"|\s+",
+);
+
+/// A byte-pair encoding vocabulary
+///
+/// All four vocabularies are bundled with the crate. A `{name}.tiktoken`
+/// file in the directory named by [`TOKENIZER_DIR_ENV`](super::TOKENIZER_DIR_ENV)
+/// takes precedence over the bundled copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// GPT-2 and the original GPT-3 models
+    R50kBase,
+    /// Codex and `text-davinci-002`/`003`
+    P50kBase,
+    /// GPT-3.5, GPT-4 and the `text-embedding` models
+    Cl100kBase,
+    /// GPT-4o, GPT-4.1, GPT-5 and the o-series reasoning models
+    O200kBase,
+}
+
+impl Encoding {
+    /// All supported encodings
+    pub const ALL: [Encoding; 4] = [
+        Encoding::R50kBase,
+        Encoding::P50kBase,
+        Encoding::Cl100kBase,
+        Encoding::O200kBase,
+    ];
+
+    /// Returns the encoding's canonical name (e.g. `cl100k_base`)
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::R50kBase => "r50k_base",
+            Encoding::P50kBase => "p50k_base",
+            Encoding::Cl100kBase => "cl100k_base",
+            Encoding::O200kBase => "o200k_base",
+        }
+    }
+
+    /// Returns the encoding used by an OpenAI model, if known
+    ///
+    /// Accepts dated snapshots (`gpt-4o-2024-08-06`), fine-tuned model ids
+    /// (`ft:gpt-4o-mini:org::id`), routing prefixes (`openai/gpt-4o`) and
+    /// Azure's `gpt-35-turbo` spelling.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use llm_test_bench_core::tokenizer::Encoding;
+    ///
+    /// assert_eq!(Encoding::for_model("gpt-4o-mini"), Some(Encoding::O200kBase));
+    /// assert_eq!(Encoding::for_model("gpt-4-turbo"), Some(Encoding::Cl100kBase));
+    /// assert_eq!(Encoding::for_model("claude-3-opus-20240229"), None);
+    /// ```
+    pub fn for_model(model: &str) -> Option<Encoding> {
+        let model = model.trim().to_ascii_lowercase();
+        let model = match model.strip_prefix("ft:") {
+            Some(rest) => rest.split(':').next().unwrap_or(rest),
+            None => model.rsplit('/').next().unwrap_or(&model),
+        };
+
+        const O200K: &[&str] = &[
+            "gpt-4o", "chatgpt-4o", "gpt-4.1", "gpt-4.5", "gpt-5", "gpt-oss", "o1", "o3", "o4",
+        ];
+        const CL100K: &[&str] = &[
+            "gpt-4",
+            "gpt-3.5",
+            "gpt-35",
+            "text-embedding-ada-002",
+            "text-embedding-3",
+            "davinci-002",
+            "babbage-002",
+        ];
+        const P50K: &[&str] = &["text-davinci-002", "text-davinci-003", "code-davinci", "code-cushman"];
+        const R50K: &[&str] = &[
+            "text-davinci-001",
+            "text-curie",
+            "text-babbage",
+            "text-ada",
+            "davinci",
+            "curie",
+            "babbage",
+            "ada",
+            "gpt2",
+        ];
+
+        // Order matters: `gpt-4o` must be checked before `gpt-4`
+        [
+            (O200K, Encoding::O200kBase),
+            (CL100K, Encoding::Cl100kBase),
+            (P50K, Encoding::P50kBase),
+            (R50K, Encoding::R50kBase),
+        ]
+        .into_iter()
+        .find(|(families, _)| families.iter().any(|family| in_family(model, family)))
+        .map(|(_, encoding)| encoding)
+    }
+
+    pub(crate) fn pattern(&self) -> &'static str {
+        match self {
+            Encoding::R50kBase | Encoding::P50kBase => GPT2_PATTERN,
+            Encoding::Cl100kBase => CL100K_PATTERN,
+            Encoding::O200kBase => O200K_PATTERN,
+        }
+    }
+
+    pub(crate) fn special_tokens(&self) -> &'static [(&'static str, u32)] {
+        match self {
+            Encoding::R50kBase => &[("<|endoftext|>", 50256)],
+            Encoding::P50kBase => &[("<|endoftext|>", 50256)],
+            Encoding::Cl100kBase => &[
+                ("<|endoftext|>", 100257),
+                ("<|fim_prefix|>", 100258),
+                ("<|fim_middle|>", 100259),
+                ("<|fim_suffix|>", 100260),
+                ("<|endofprompt|>", 100276),
+            ],
+            Encoding::O200kBase => &[("<|endoftext|>", 199999), ("<|endofprompt|>", 200018)],
+        }
+    }
+}
+
+/// `model` is `family` itself or one of its variants (`family-…`, `family.…`)
+fn in_family(model: &str, family: &str) -> bool {
+    match model.strip_prefix(family) {
+        Some(rest) => rest.is_empty() || rest.starts_with('-') || rest.starts_with('.'),
+        None => false,
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = TokenizerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Encoding::ALL
+            .into_iter()
+            .find(|encoding| encoding.name() == s)
+            .ok_or_else(|| TokenizerError::UnknownEncoding(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openai_model_mapping() {
+        let cases = [
+            ("gpt-4o", Encoding::O200kBase),
+            ("gpt-4o-2024-08-06", Encoding::O200kBase),
+            ("gpt-4.1-mini", Encoding::O200kBase),
+            ("o1-preview", Encoding::O200kBase),
+            ("o3", Encoding::O200kBase),
+            ("gpt-4", Encoding::Cl100kBase),
+            ("gpt-4-turbo-preview", Encoding::Cl100kBase),
+            ("gpt-3.5-turbo", Encoding::Cl100kBase),
+            ("text-embedding-3-small", Encoding::Cl100kBase),
+            ("text-davinci-003", Encoding::P50kBase),
+            ("code-davinci-002", Encoding::P50kBase),
+            ("text-davinci-001", Encoding::R50kBase),
+            ("davinci", Encoding::R50kBase),
+        ];
+
+        for (model, expected) in cases {
+            assert_eq!(Encoding::for_model(model), Some(expected), "{}", model);
+        }
+    }
+
+    #[test]
+    fn test_model_name_variants() {
+        assert_eq!(Encoding::for_model("gpt-35-turbo"), Some(Encoding::Cl100kBase));
+        assert_eq!(Encoding::for_model("openai/gpt-4o"), Some(Encoding::O200kBase));
+        assert_eq!(Encoding::for_model("GPT-4"), Some(Encoding::Cl100kBase));
+        assert_eq!(
+            Encoding::for_model("ft:gpt-4o-mini-2024-07-18:acme::abc123"),
+            Some(Encoding::O200kBase)
+        );
+    }
+
+    #[test]
+    fn test_unknown_models() {
+        assert_eq!(Encoding::for_model("claude-3-5-sonnet-20241022"), None);
+        assert_eq!(Encoding::for_model("llama3-70b-8192"), None);
+        assert_eq!(Encoding::for_model("o1x"), None);
+        assert_eq!(Encoding::for_model("adamw"), None);
+    }
+
+    #[test]
+    fn test_name_round_trip() {
+        for encoding in Encoding::ALL {
+            assert_eq!(encoding.name().parse::<Encoding>().unwrap(), encoding);
+        }
+        assert!("gpt2_base".parse::<Encoding>().is_err());
+    }
+}
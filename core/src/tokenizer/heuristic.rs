@@ -0,0 +1,141 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Calibrated token count heuristic
+//!
+//! Used for models whose tokenizer vocabulary is not available. The text is
+//! split into runs of the same character class and each run is charged a
+//! number of tokens. The ratios below were fitted against `cl100k_base` on
+//! English prose, Markdown, Rust source and JSON (all within a few percent),
+//! and on short Chinese, Japanese, Korean, Hindi and European-language
+//! samples (within roughly 15%).
+
+/// Characters per token for ASCII word runs (letters and underscores)
+const WORD_CHARS_PER_TOKEN: f64 = 8.0;
+
+/// Digits per token; BPE vocabularies split numbers into groups of three
+const DIGITS_PER_TOKEN: f64 = 3.0;
+
+/// Characters per token for runs of ASCII punctuation and symbols
+const SYMBOL_CHARS_PER_TOKEN: f64 = 3.0;
+
+/// Characters per token for letters of other alphabetic scripts
+const LETTER_CHARS_PER_TOKEN: f64 = 2.5;
+
+/// Tokens per character for scripts encoded at about one token per character
+const DENSE_TOKENS_PER_CHAR: f64 = 1.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Word,
+    Digit,
+    Symbol,
+    Letter,
+    Dense,
+    Space,
+    Newline,
+}
+
+impl CharClass {
+    fn of(c: char) -> Self {
+        match c {
+            'a'..='z' | 'A'..='Z' | '_' => CharClass::Word,
+            '0'..='9' => CharClass::Digit,
+            '\n' | '\r' => CharClass::Newline,
+            c if c.is_whitespace() => CharClass::Space,
+            c if c.is_ascii() => CharClass::Symbol,
+            c if is_dense_script(c) => CharClass::Dense,
+            c if c.is_alphabetic() => CharClass::Letter,
+            _ => CharClass::Symbol,
+        }
+    }
+}
+
+/// Scripts that BPE vocabularies trained mostly on English text encode at
+/// roughly one token per character
+fn is_dense_script(c: char) -> bool {
+    matches!(c as u32,
+        0x0900..=0x0DFF      // Indic scripts
+        | 0x0E00..=0x0EFF    // Thai, Lao
+        | 0x3040..=0x30FF    // Hiragana, Katakana
+        | 0x3400..=0x4DBF    // CJK Extension A
+        | 0x4E00..=0x9FFF    // CJK Unified Ideographs
+        | 0xAC00..=0xD7AF    // Hangul syllables
+        | 0xF900..=0xFAFF    // CJK Compatibility Ideographs
+        | 0x20000..=0x2FA1F) // CJK Extensions B-F
+}
+
+/// Estimates the number of tokens in `text` without a vocabulary
+pub(crate) fn estimate(text: &str) -> usize {
+    let mut tokens = 0.0;
+    let mut previous = None;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let class = CharClass::of(c);
+        let mut len = 1usize;
+        while chars.peek().is_some_and(|&next| CharClass::of(next) == class) {
+            chars.next();
+            len += 1;
+        }
+
+        let len = len as f64;
+        tokens += match class {
+            CharClass::Word => (len / WORD_CHARS_PER_TOKEN).ceil(),
+            CharClass::Digit => (len / DIGITS_PER_TOKEN).ceil(),
+            CharClass::Symbol => (len / SYMBOL_CHARS_PER_TOKEN).ceil(),
+            CharClass::Letter => (len / LETTER_CHARS_PER_TOKEN).ceil(),
+            CharClass::Dense => (len * DENSE_TOKENS_PER_CHAR).ceil(),
+            // A single space is merged into the following token
+            CharClass::Space if len > 1.0 => 1.0,
+            CharClass::Space => 0.0,
+            // Line breaks are merged into a preceding punctuation run
+            CharClass::Newline if previous == Some(CharClass::Symbol) => 0.0,
+            CharClass::Newline => 1.0,
+        };
+        previous = Some(class);
+    }
+
+    tokens as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_text() {
+        assert_eq!(estimate(""), 0);
+    }
+
+    #[test]
+    fn test_english_words() {
+        // One token per short word, single spaces are free
+        assert_eq!(estimate("the quick brown fox"), 4);
+        assert_eq!(estimate("Hello, world!"), 4);
+    }
+
+    #[test]
+    fn test_numbers_split_in_groups_of_three() {
+        assert_eq!(estimate("1234567"), 3);
+    }
+
+    #[test]
+    fn test_punctuation_absorbs_newline() {
+        assert_eq!(estimate("foo();\nbar();\n"), 4);
+    }
+
+    #[test]
+    fn test_dense_scripts() {
+        assert_eq!(estimate("人工智能"), 5);
+        assert!(estimate("大規模言語モデル") >= 8);
+    }
+
+    #[test]
+    fn test_other_alphabetic_scripts() {
+        assert_eq!(estimate("модели"), 3);
+    }
+}
@@ -0,0 +1,303 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Token counting for prompt and completion text
+//!
+//! Models with a known byte-pair encoding (the OpenAI families) are counted
+//! exactly with the matching vocabulary. The `r50k_base`, `p50k_base`,
+//! `cl100k_base` and `o200k_base` vocabularies are bundled; a local copy can
+//! be supplied instead by placing `{encoding}.tiktoken` files in the directory
+//! named by `LLM_TEST_BENCH_TOKENIZER_DIR`. Every other model is counted with
+//! a heuristic calibrated against `cl100k_base`.
+//!
+//! # Examples
+//!
+//! ```
+//! use llm_test_bench_core::tokenizer::{Encoding, Tokenizer};
+//!
+//! let tokenizer = Tokenizer::for_model("gpt-4");
+//! assert_eq!(tokenizer.encoding(), Some(Encoding::Cl100kBase));
+//! assert_eq!(tokenizer.count_tokens("Hello, world!"), 4);
+//!
+//! // No vocabulary is known for Claude models, so the heuristic is used
+//! let tokenizer = Tokenizer::for_model("claude-3-opus-20240229");
+//! assert!(!tokenizer.is_exact());
+//! assert!(tokenizer.count_tokens("Hello, world!") > 0);
+//! ```
+
+mod encoding;
+mod heuristic;
+
+pub use encoding::Encoding;
+
+use base64::Engine;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use thiserror::Error;
+use tiktoken_rs::CoreBPE;
+use tracing::{debug, warn};
+
+/// Environment variable naming a directory of `{encoding}.tiktoken` files
+/// that override the bundled vocabularies
+pub const TOKENIZER_DIR_ENV: &str = "LLM_TEST_BENCH_TOKENIZER_DIR";
+
+/// Tokenizer errors
+#[derive(Error, Debug)]
+pub enum TokenizerError {
+    /// Vocabulary file could not be read
+    #[error("Failed to read vocabulary file {path}: {source}")]
+    Io {
+        /// Path of the vocabulary file
+        path: PathBuf,
+        /// Underlying I/O error
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// Vocabulary file is malformed or the encoder could not be built
+    #[error("Invalid vocabulary: {0}")]
+    InvalidVocabulary(String),
+
+    /// Encoding name not recognized
+    #[error("Unknown encoding: {0}")]
+    UnknownEncoding(String),
+}
+
+/// Counts tokens for a model
+///
+/// Cheap to clone; loaded vocabularies are shared process-wide.
+#[derive(Clone)]
+pub struct Tokenizer {
+    backend: Backend,
+}
+
+#[derive(Clone)]
+enum Backend {
+    Bpe { encoding: Encoding, bpe: Arc<CoreBPE> },
+    Heuristic,
+}
+
+impl Tokenizer {
+    /// Returns the tokenizer for a model
+    ///
+    /// Falls back to the calibrated heuristic when the model has no known
+    /// encoding or its vocabulary fails to load.
+    pub fn for_model(model: &str) -> Self {
+        let Some(encoding) = Encoding::for_model(model) else {
+            return Self::heuristic();
+        };
+
+        match Self::for_encoding(encoding) {
+            Ok(tokenizer) => tokenizer,
+            Err(e) => {
+                warn!("Falling back to heuristic token counts for {}: {}", model, e);
+                Self::heuristic()
+            }
+        }
+    }
+
+    /// Returns the tokenizer for an encoding
+    ///
+    /// Vocabularies are loaded once per process, from
+    /// [`TOKENIZER_DIR_ENV`] if it contains a file for the encoding and from
+    /// the bundled copy otherwise.
+    pub fn for_encoding(encoding: Encoding) -> Result<Self, TokenizerError> {
+        static CACHE: OnceLock<Mutex<HashMap<Encoding, Arc<CoreBPE>>>> = OnceLock::new();
+
+        let mut cache = CACHE
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let bpe = match cache.get(&encoding) {
+            Some(bpe) => Arc::clone(bpe),
+            None => {
+                let bpe = Arc::new(load(encoding)?);
+                cache.insert(encoding, Arc::clone(&bpe));
+                bpe
+            }
+        };
+
+        Ok(Self { backend: Backend::Bpe { encoding, bpe } })
+    }
+
+    /// Loads an encoding's vocabulary from a `.tiktoken` file
+    ///
+    /// Each line holds a base64-encoded token and its rank, separated by a
+    /// space. The encoding determines the pre-tokenization pattern and the
+    /// special tokens.
+    pub fn from_file(encoding: Encoding, path: impl AsRef<Path>) -> Result<Self, TokenizerError> {
+        let bpe = Arc::new(load_file(encoding, path.as_ref())?);
+        Ok(Self { backend: Backend::Bpe { encoding, bpe } })
+    }
+
+    /// Returns the calibrated heuristic tokenizer
+    pub fn heuristic() -> Self {
+        Self { backend: Backend::Heuristic }
+    }
+
+    /// The BPE encoding, or `None` for the heuristic
+    pub fn encoding(&self) -> Option<Encoding> {
+        match &self.backend {
+            Backend::Bpe { encoding, .. } => Some(*encoding),
+            Backend::Heuristic => None,
+        }
+    }
+
+    /// Whether counts are exact rather than estimated
+    pub fn is_exact(&self) -> bool {
+        matches!(self.backend, Backend::Bpe { .. })
+    }
+
+    /// Counts the tokens in `text`
+    ///
+    /// Special tokens such as `<|endoftext|>` appearing in the text are
+    /// counted as ordinary text.
+    pub fn count_tokens(&self, text: &str) -> usize {
+        match &self.backend {
+            Backend::Bpe { bpe, .. } => bpe.encode_ordinary(text).len(),
+            Backend::Heuristic => heuristic::estimate(text),
+        }
+    }
+
+    /// Encodes `text` into token ids, or `None` for the heuristic
+    pub fn encode(&self, text: &str) -> Option<Vec<u32>> {
+        match &self.backend {
+            Backend::Bpe { bpe, .. } => Some(bpe.encode_ordinary(text)),
+            Backend::Heuristic => None,
+        }
+    }
+}
+
+impl fmt::Debug for Tokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tokenizer")
+            .field("encoding", &self.encoding())
+            .finish()
+    }
+}
+
+/// Counts the tokens in `text` using the tokenizer for `model`
+pub fn count_tokens(model: &str, text: &str) -> usize {
+    Tokenizer::for_model(model).count_tokens(text)
+}
+
+fn load(encoding: Encoding) -> Result<CoreBPE, TokenizerError> {
+    if let Some(dir) = std::env::var_os(TOKENIZER_DIR_ENV) {
+        let path = Path::new(&dir).join(format!("{}.tiktoken", encoding.name()));
+        if path.is_file() {
+            debug!("Loading {} vocabulary from {}", encoding, path.display());
+            return load_file(encoding, &path);
+        }
+    }
+
+    let bpe = match encoding {
+        Encoding::R50kBase => tiktoken_rs::r50k_base(),
+        Encoding::P50kBase => tiktoken_rs::p50k_base(),
+        Encoding::Cl100kBase => tiktoken_rs::cl100k_base(),
+        Encoding::O200kBase => tiktoken_rs::o200k_base(),
+    };
+    bpe.map_err(|e| TokenizerError::InvalidVocabulary(e.to_string()))
+}
+
+fn load_file(encoding: Encoding, path: &Path) -> Result<CoreBPE, TokenizerError> {
+    let contents = std::fs::read_to_string(path).map_err(|source| TokenizerError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    build(encoding, &contents)
+}
+
+fn build(encoding: Encoding, vocabulary: &str) -> Result<CoreBPE, TokenizerError> {
+    let invalid = |line: usize| {
+        TokenizerError::InvalidVocabulary(format!("malformed entry on line {}", line + 1))
+    };
+
+    let encoder = vocabulary
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let (token, rank) = line.trim().split_once(' ').ok_or_else(|| invalid(i))?;
+            let token = base64::engine::general_purpose::STANDARD
+                .decode(token)
+                .map_err(|_| invalid(i))?;
+            let rank = rank.parse().map_err(|_| invalid(i))?;
+            Ok((token, rank))
+        })
+        .collect::<Result<_, TokenizerError>>()?;
+
+    let special_tokens = encoding
+        .special_tokens()
+        .iter()
+        .map(|(token, rank)| (token.to_string(), *rank))
+        .collect();
+
+    CoreBPE::new(encoder, special_tokens, encoding.pattern())
+        .map_err(|e| TokenizerError::InvalidVocabulary(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_exact_counts() {
+        let cl100k = Tokenizer::for_encoding(Encoding::Cl100kBase).unwrap();
+        assert_eq!(cl100k.count_tokens("Hello, world!"), 4);
+        assert_eq!(cl100k.count_tokens(""), 0);
+        assert_eq!(cl100k.encode("hello world").unwrap(), vec![15339, 1917]);
+
+        let o200k = Tokenizer::for_encoding(Encoding::O200kBase).unwrap();
+        assert_eq!(o200k.encode("hello world").unwrap(), vec![24912, 2375]);
+    }
+
+    #[test]
+    fn test_for_model() {
+        assert_eq!(Tokenizer::for_model("gpt-4o").encoding(), Some(Encoding::O200kBase));
+        assert_eq!(Tokenizer::for_model("gpt-3.5-turbo").encoding(), Some(Encoding::Cl100kBase));
+
+        let fallback = Tokenizer::for_model("mistral-large-latest");
+        assert!(!fallback.is_exact());
+        assert_eq!(fallback.encode("hello"), None);
+        assert_eq!(fallback.count_tokens("Hello, world!"), 4);
+    }
+
+    #[test]
+    fn test_special_tokens_counted_as_text() {
+        let tokenizer = Tokenizer::for_model("gpt-4");
+        assert!(tokenizer.count_tokens("<|endoftext|>") > 1);
+    }
+
+    #[test]
+    fn test_from_file() {
+        // "a", "b" and the merged "ab"
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "YQ== 0\nYg== 1\nYWI= 2").unwrap();
+
+        let tokenizer = Tokenizer::from_file(Encoding::R50kBase, file.path()).unwrap();
+        assert_eq!(tokenizer.encode("ab").unwrap(), vec![2]);
+        assert_eq!(tokenizer.count_tokens("abba"), 3);
+    }
+
+    #[test]
+    fn test_from_file_errors() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "YQ== 0\nnot-a-rank").unwrap();
+        assert!(matches!(
+            Tokenizer::from_file(Encoding::Cl100kBase, file.path()),
+            Err(TokenizerError::InvalidVocabulary(_))
+        ));
+
+        assert!(matches!(
+            Tokenizer::from_file(Encoding::Cl100kBase, "/nonexistent/cl100k_base.tiktoken"),
+            Err(TokenizerError::Io { .. })
+        ));
+    }
+}
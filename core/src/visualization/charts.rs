@@ -301,6 +301,12 @@ mod tests {
                 usage: TokenUsage::new(10, 5),
                 finish_reason: FinishReason::Stop,
                 created_at: Utc::now(),
+                tool_calls: Vec::new(),
+                schema_violations: Vec::new(),
+                logprobs: None,
+                prompt_logprobs: None,
+                choices: Vec::new(),
+                metadata: HashMap::new(),
             },
             Duration::from_millis(duration_ms),
         )
@@ -0,0 +1,374 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for batch jobs
+//!
+//! Each test mocks the provider's batch endpoints with wiremock and walks a
+//! job through submission, polling and result retrieval, either directly or
+//! through `BenchmarkRunner::run_batch`.
+
+use llm_test_bench_core::benchmarks::{BenchmarkConfig, BenchmarkRunner, TestStatus};
+use llm_test_bench_core::providers::{
+    AnthropicProvider, BatchProvider, BatchRequest, BatchStatus, CompletionRequest, OpenAIProvider,
+};
+use llm_test_bench_datasets::{Dataset, TestCase};
+use serde_json::json;
+use std::sync::Arc;
+use wiremock::matchers::{body_partial_json, body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn openai_batch(id: &str, status: &str, completed: usize, failed: usize) -> serde_json::Value {
+    json!({
+        "id": id,
+        "object": "batch",
+        "endpoint": "/v1/chat/completions",
+        "status": status,
+        "output_file_id": if status == "completed" { json!("file-out") } else { json!(null) },
+        "error_file_id": if status == "completed" { json!("file-err") } else { json!(null) },
+        "request_counts": { "total": 2, "completed": completed, "failed": failed }
+    })
+}
+
+fn openai_output_line(custom_id: &str, content: &str) -> String {
+    json!({
+        "id": "batch_req_1",
+        "custom_id": custom_id,
+        "response": {
+            "status_code": 200,
+            "body": {
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "model": "gpt-4o-mini",
+                "choices": [{ "index": 0, "message": { "role": "assistant", "content": content }, "finish_reason": "stop" }],
+                "usage": { "prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6 }
+            }
+        },
+        "error": null
+    })
+    .to_string()
+}
+
+#[tokio::test]
+async fn test_openai_batch_protocol() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/files"))
+        .and(body_string_contains("name=\"purpose\""))
+        .and(body_string_contains("\"custom_id\":\"q2\""))
+        .and(body_string_contains("\"url\":\"/v1/chat/completions\""))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "file-in",
+            "object": "file",
+            "purpose": "batch"
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/batches"))
+        .and(body_partial_json(json!({
+            "input_file_id": "file-in",
+            "endpoint": "/v1/chat/completions",
+            "completion_window": "24h"
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_batch("batch_1", "validating", 0, 0)))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/batches/batch_1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_batch("batch_1", "completed", 1, 1)))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/files/file-out/content"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!("{}\n", openai_output_line("q1", "4"))))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/files/file-err/content"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            json!({
+                "custom_id": "q2",
+                "response": {
+                    "status_code": 400,
+                    "body": { "error": { "message": "Invalid model", "type": "invalid_request_error" } }
+                },
+                "error": null
+            })
+            .to_string(),
+        ))
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let job = provider
+        .submit_batch(vec![
+            BatchRequest::new("q1", CompletionRequest::new("gpt-4o-mini", "What is 2 + 2?")),
+            BatchRequest::new("q2", CompletionRequest::new("gpt-4o-mini", "What is 3 + 3?")),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(job.id, "batch_1");
+    assert_eq!(job.status, BatchStatus::Validating);
+
+    let job = provider.batch_status("batch_1").await.unwrap();
+    assert_eq!(job.status, BatchStatus::Completed);
+    assert_eq!((job.counts.total, job.counts.completed, job.counts.failed), (2, 1, 1));
+
+    let results = provider.batch_results("batch_1").await.unwrap();
+    assert_eq!(results.len(), 2);
+    let response = results[0].response.as_ref().unwrap();
+    assert_eq!(results[0].custom_id, "q1");
+    assert_eq!(response.content, "4");
+    assert_eq!(response.metadata["batch_id"], "batch_1");
+    assert_eq!(results[1].custom_id, "q2");
+    assert_eq!(results[1].response.as_ref().unwrap_err(), "Invalid model");
+}
+
+#[tokio::test]
+async fn test_anthropic_batch_protocol() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/messages/batches"))
+        .and(body_string_contains("\"custom_id\":\"q1\""))
+        .and(body_string_contains("\"model\":\"claude-3-5-haiku-20241022\""))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msgbatch_1",
+            "type": "message_batch",
+            "processing_status": "in_progress",
+            "request_counts": { "processing": 3, "succeeded": 0, "errored": 0, "canceled": 0, "expired": 0 },
+            "results_url": null
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/messages/batches/msgbatch_1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msgbatch_1",
+            "type": "message_batch",
+            "processing_status": "ended",
+            "request_counts": { "processing": 0, "succeeded": 1, "errored": 1, "canceled": 0, "expired": 1 },
+            "results_url": format!("{}/results/msgbatch_1", mock_server.uri())
+        })))
+        .mount(&mock_server)
+        .await;
+    let results = [
+        json!({
+            "custom_id": "q1",
+            "result": {
+                "type": "succeeded",
+                "message": {
+                    "id": "msg_01",
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [{ "type": "text", "text": "4" }],
+                    "model": "claude-3-5-haiku-20241022",
+                    "stop_reason": "end_turn",
+                    "usage": { "input_tokens": 10, "output_tokens": 1 }
+                }
+            }
+        }),
+        json!({
+            "custom_id": "q2",
+            "result": {
+                "type": "errored",
+                "error": { "type": "error", "error": { "type": "invalid_request_error", "message": "prompt is too long" } }
+            }
+        }),
+        json!({ "custom_id": "q3", "result": { "type": "expired" } }),
+    ];
+    let body: Vec<String> = results.iter().map(|line| line.to_string()).collect();
+    Mock::given(method("GET"))
+        .and(path("/results/msgbatch_1"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(body.join("\n")))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/messages/batches/msgbatch_1/cancel"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msgbatch_1",
+            "processing_status": "canceling",
+            "request_counts": { "processing": 3 }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = AnthropicProvider::with_base_url("test-key".to_string(), mock_server.uri());
+    let requests = ["q1", "q2", "q3"]
+        .into_iter()
+        .map(|id| BatchRequest::new(id, CompletionRequest::new("claude-3-5-haiku-20241022", "What is 2 + 2?")))
+        .collect();
+    let job = provider.submit_batch(requests).await.unwrap();
+    assert_eq!(job.status, BatchStatus::InProgress);
+    assert_eq!(job.counts.total, 3);
+
+    assert_eq!(provider.cancel_batch("msgbatch_1").await.unwrap().status, BatchStatus::Cancelling);
+
+    let job = provider.batch_status("msgbatch_1").await.unwrap();
+    assert_eq!(job.status, BatchStatus::Completed);
+    assert_eq!((job.counts.completed, job.counts.failed), (1, 2));
+
+    let results = provider.batch_results("msgbatch_1").await.unwrap();
+    assert_eq!(results[0].response.as_ref().unwrap().content, "4");
+    assert_eq!(results[1].response.as_ref().unwrap_err(), "prompt is too long");
+    assert!(results[2].response.is_err());
+}
+
+fn dataset() -> Dataset {
+    let mut dataset = Dataset::new("arithmetic".to_string(), "Batch test".to_string());
+    dataset.add_test_case(TestCase::new("add", "What is 2 + 2?"));
+    dataset.add_test_case(TestCase::new("sub", "What is 3 - 1?"));
+    dataset
+}
+
+#[tokio::test]
+async fn test_run_batch_submits_and_maps_results() {
+    let mock_server = MockServer::start().await;
+    let output = tempfile::tempdir().unwrap();
+
+    Mock::given(method("POST"))
+        .and(path("/files"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "file-in" })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/batches"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_batch("batch_2", "validating", 0, 0)))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    // The first poll finds the job still running
+    Mock::given(method("GET"))
+        .and(path("/batches/batch_2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_batch("batch_2", "in_progress", 1, 0)))
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/batches/batch_2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_batch("batch_2", "completed", 2, 0)))
+        .mount(&mock_server)
+        .await;
+    let output_lines = [openai_output_line("test-1", "2"), openai_output_line("test-0", "4")];
+    Mock::given(method("GET"))
+        .and(path("/files/file-out/content"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(output_lines.join("\n")))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/files/file-err/content"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(""))
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let config = BenchmarkConfig::new()
+        .with_output_dir(output.path().to_path_buf())
+        .with_save_responses(false)
+        .with_batch_poll_interval_ms(10);
+    let results = BenchmarkRunner::new(config).run_batch(&dataset(), Arc::new(provider)).await.unwrap();
+
+    assert_eq!(results.total_tests, 2);
+    assert_eq!(results.summary.succeeded, 2);
+    assert_eq!(results.results[0].test_id, "add");
+    assert_eq!(results.results[0].response.as_ref().unwrap().content, "4");
+    assert_eq!(results.results[1].test_id, "sub");
+    assert_eq!(results.results[1].response.as_ref().unwrap().content, "2");
+
+    // The state file is removed once the job has ended
+    assert_eq!(std::fs::read_dir(output.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn test_run_batch_resumes_persisted_job() {
+    let mock_server = MockServer::start().await;
+    let output = tempfile::tempdir().unwrap();
+
+    let state_path = output.path().join("batch-openai-arithmetic.json");
+    std::fs::write(
+        &state_path,
+        json!({
+            "batch_id": "batch_9",
+            "test_ids": ["add", "sub"],
+            "submitted_at": "2026-10-17T08:00:00Z"
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/batches/batch_9"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "batch_9",
+            "status": "expired",
+            "output_file_id": "file-out",
+            "request_counts": { "total": 2, "completed": 1, "failed": 0 }
+        })))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/files/file-out/content"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(openai_output_line("test-0", "4")))
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let config = BenchmarkConfig::new()
+        .with_output_dir(output.path().to_path_buf())
+        .with_save_responses(false);
+    let results = BenchmarkRunner::new(config).run_batch(&dataset(), Arc::new(provider)).await.unwrap();
+
+    assert_eq!(results.results[0].status, TestStatus::Success);
+    assert_eq!(results.results[1].status, TestStatus::Failure);
+    assert!(results.results[1].error.as_ref().unwrap().contains("batch_9"));
+    assert!(!state_path.exists());
+}
+
+#[tokio::test]
+async fn test_run_batch_stops_waiting_at_deadline() {
+    let mock_server = MockServer::start().await;
+    let output = tempfile::tempdir().unwrap();
+
+    Mock::given(method("POST"))
+        .and(path("/files"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "file-in" })))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/batches"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_batch("batch_3", "in_progress", 0, 0)))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/batches/batch_3"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_batch("batch_3", "in_progress", 1, 0)))
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let config = BenchmarkConfig::new()
+        .with_output_dir(output.path().to_path_buf())
+        .with_save_responses(false)
+        .with_batch_poll_interval_ms(10)
+        .with_batch_max_wait_ms(50);
+    let error = BenchmarkRunner::new(config).run_batch(&dataset(), Arc::new(provider)).await.unwrap_err();
+    assert!(error.to_string().contains("batch_3"));
+
+    // The job is still running, so the next run picks it up again
+    let state = std::fs::read_to_string(output.path().join("batch-openai-arithmetic.json")).unwrap();
+    assert!(state.contains("batch_3"));
+}
@@ -0,0 +1,214 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for the AWS Bedrock provider
+//!
+//! The Bedrock runtime API is mocked with wiremock, including the binary
+//! event-stream framing used by `invoke-with-response-stream`.
+
+use base64::Engine;
+use futures::StreamExt;
+use llm_test_bench_core::providers::{
+    BedrockProvider, CompletionRequest, FinishReason, Provider, ProviderError, StreamEvent, StreamEventKind, TokenUsage,
+};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, header, header_exists, header_regex, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn create_provider(base_url: String) -> BedrockProvider {
+    BedrockProvider::with_base_url(
+        "us-east-1".to_string(),
+        "AKIDTEST".to_string(),
+        "test-secret".to_string(),
+        base_url,
+    )
+    .unwrap()
+}
+
+// Encode a Bedrock `chunk` event in the AWS event-stream binary format
+fn chunk_frame(chunk: serde_json::Value) -> Vec<u8> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(chunk.to_string());
+    let payload = json!({ "bytes": encoded }).to_string();
+
+    let mut headers = Vec::new();
+    for (name, value) in [
+        (":event-type", "chunk"),
+        (":content-type", "application/json"),
+        (":message-type", "event"),
+    ] {
+        headers.push(name.len() as u8);
+        headers.extend_from_slice(name.as_bytes());
+        headers.push(7);
+        headers.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        headers.extend_from_slice(value.as_bytes());
+    }
+
+    let total_len = (12 + headers.len() + payload.len() + 4) as u32;
+    let mut frame = Vec::new();
+    frame.extend_from_slice(&total_len.to_be_bytes());
+    frame.extend_from_slice(&(headers.len() as u32).to_be_bytes());
+    let prelude_crc = crc32fast::hash(&frame);
+    frame.extend_from_slice(&prelude_crc.to_be_bytes());
+    frame.extend_from_slice(&headers);
+    frame.extend_from_slice(payload.as_bytes());
+    let message_crc = crc32fast::hash(&frame);
+    frame.extend_from_slice(&message_crc.to_be_bytes());
+    frame
+}
+
+#[tokio::test]
+async fn test_bedrock_anthropic_completion() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/model/anthropic.claude-3-sonnet-20240229-v1%3A0/invoke"))
+        .and(header_regex(
+            "authorization",
+            r"^AWS4-HMAC-SHA256 Credential=AKIDTEST/\d{8}/us-east-1/bedrock/aws4_request, SignedHeaders=accept;content-type;host;x-amz-date, Signature=[0-9a-f]{64}$",
+        ))
+        .and(header_exists("x-amz-date"))
+        .and(header("content-type", "application/json"))
+        .and(body_partial_json(json!({
+            "anthropic_version": "bedrock-2023-05-31",
+            "system": "Be brief.",
+            "messages": [{ "role": "user", "content": "Hello" }]
+        })))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("x-amzn-bedrock-input-token-count", "12")
+                .insert_header("x-amzn-bedrock-output-token-count", "4")
+                .set_body_json(json!({
+                    "id": "msg_bdrk_01",
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [{ "type": "text", "text": "Hi there!" }],
+                    "model": "claude-3-sonnet-20240229",
+                    "stop_reason": "end_turn",
+                    "usage": { "input_tokens": 12, "output_tokens": 4 }
+                })),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = create_provider(mock_server.uri());
+    let request = CompletionRequest::new("anthropic.claude-3-sonnet-20240229-v1:0", "Hello")
+        .with_system("Be brief.");
+
+    let response = provider.complete(request).await.unwrap();
+
+    assert_eq!(response.id, "msg_bdrk_01");
+    assert_eq!(response.content, "Hi there!");
+    assert_eq!(response.model, "anthropic.claude-3-sonnet-20240229-v1:0");
+    assert_eq!(response.usage.prompt_tokens, 12);
+    assert_eq!(response.usage.completion_tokens, 4);
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+}
+
+#[tokio::test]
+async fn test_bedrock_session_token_is_signed() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/model/amazon.titan-text-express-v1/invoke"))
+        .and(header("x-amz-security-token", "session-token"))
+        .and(header_regex("authorization", "SignedHeaders=accept;content-type;host;x-amz-date;x-amz-security-token,"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "inputTextTokenCount": 3,
+            "results": [{ "tokenCount": 2, "outputText": "Hello!", "completionReason": "FINISH" }]
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = create_provider(mock_server.uri()).with_session_token("session-token");
+    let response = provider
+        .complete(CompletionRequest::new("amazon.titan-text-express-v1", "Hi"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.content, "Hello!");
+    assert_eq!(response.usage.prompt_tokens, 3);
+    assert_eq!(response.usage.completion_tokens, 2);
+}
+
+#[tokio::test]
+async fn test_bedrock_llama_streaming() {
+    let mock_server = MockServer::start().await;
+
+    let mut body = Vec::new();
+    body.extend(chunk_frame(json!({ "generation": "Hello", "prompt_token_count": 8 })));
+    body.extend(chunk_frame(json!({ "generation": ", world" })));
+    body.extend(chunk_frame(json!({
+        "generation": "",
+        "stop_reason": "stop",
+        "amazon-bedrock-invocationMetrics": { "inputTokenCount": 8, "outputTokenCount": 3 }
+    })));
+
+    Mock::given(method("POST"))
+        .and(path("/model/meta.llama3-8b-instruct-v1%3A0/invoke-with-response-stream"))
+        .and(header("accept", "application/vnd.amazon.eventstream"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "application/vnd.amazon.eventstream")
+                .set_body_bytes(body),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = create_provider(mock_server.uri());
+    let request = CompletionRequest::new("meta.llama3-8b-instruct-v1:0", "Say hello").with_streaming();
+
+    let mut stream = provider.stream(request).await.unwrap();
+    let mut events = Vec::new();
+    while let Some(event) = stream.next().await {
+        events.push(event.unwrap().kind);
+    }
+
+    assert_eq!(
+        events,
+        vec![
+            StreamEventKind::ContentDelta { text: "Hello".to_string() },
+            StreamEventKind::ContentDelta { text: ", world".to_string() },
+            StreamEventKind::Usage(TokenUsage::new(8, 3)),
+            StreamEvent::finish(FinishReason::Stop).kind,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_bedrock_throttling_error() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/model/cohere.command-text-v14/invoke"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("x-amzn-errortype", "ThrottlingException:http://internal.amazon.com/coral/com.amazon.bedrock/")
+                .set_body_json(json!({ "message": "Too many requests, please wait before trying again." })),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = create_provider(mock_server.uri());
+    let result = provider
+        .complete(CompletionRequest::new("cohere.command-text-v14", "Hi"))
+        .await;
+
+    assert!(matches!(result, Err(ProviderError::RateLimitExceeded { .. })));
+}
+
+#[tokio::test]
+async fn test_bedrock_unsupported_model_family() {
+    let provider = create_provider("http://127.0.0.1:1".to_string());
+    let result = provider
+        .complete(CompletionRequest::new("ai21.j2-ultra-v1", "Hi"))
+        .await;
+
+    assert!(matches!(result, Err(ProviderError::InvalidRequest(_))));
+}
@@ -0,0 +1,177 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for credential sources and key rotation
+//!
+//! Providers are built through the `ProviderFactory` from configurations
+//! using each credential source, and a wiremock server checks which key
+//! every request was authenticated with.
+
+use llm_test_bench_core::config::models::{KeyRotation, ProviderConfig, TransportConfig};
+use llm_test_bench_core::providers::{CompletionRequest, ProviderFactory};
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::Write;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn provider_config(kind: &str, base_url: String) -> ProviderConfig {
+    ProviderConfig {
+        api_key_env: String::new(),
+        api_key_file: None,
+        api_key_cmd: None,
+        api_keys_env: Vec::new(),
+        key_rotation: KeyRotation::default(),
+        azure_ad: false,
+        base_url,
+        default_model: "gpt-4o".to_string(),
+        timeout_seconds: 30,
+        max_retries: 0,
+        rate_limit_rpm: None,
+        rate_limit_tpm: None,
+        fallback: Vec::new(),
+        circuit_breaker: None,
+        kind: Some(kind.to_string()),
+        headers: HashMap::new(),
+        models: Vec::new(),
+        context_length: None,
+        script: None,
+        transport: TransportConfig::default(),
+    }
+}
+
+fn chat_response(model: &str) -> serde_json::Value {
+    json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": model,
+        "choices": [{ "index": 0, "message": { "role": "assistant", "content": "Hello!" }, "finish_reason": "stop" }],
+        "usage": { "prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7 }
+    })
+}
+
+#[tokio::test]
+async fn test_rotates_to_next_key_on_429() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(header("authorization", "Bearer key-one"))
+        .respond_with(ResponseTemplate::new(429).set_body_string("Too many requests"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(header("authorization", "Bearer key-two"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(chat_response("mistral-small-latest")))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    std::env::set_var("CREDENTIALS_TEST_KEY_ONE", "key-one");
+    std::env::set_var("CREDENTIALS_TEST_KEY_TWO", "key-two");
+    let mut config = provider_config("mistral", mock_server.uri());
+    config.api_keys_env = vec!["CREDENTIALS_TEST_KEY_ONE".to_string(), "CREDENTIALS_TEST_KEY_TWO".to_string()];
+    config.key_rotation = KeyRotation::OnRateLimit;
+
+    let provider = ProviderFactory::new().create("mistral", &config).unwrap();
+
+    // The first key is rate limited, so both requests are served by the second
+    for _ in 0..2 {
+        let response = provider
+            .complete(CompletionRequest::new("mistral-small-latest", "Hi"))
+            .await
+            .unwrap();
+        assert_eq!(response.content, "Hello!");
+    }
+}
+
+#[tokio::test]
+async fn test_api_key_file() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(header("authorization", "Bearer file-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(chat_response("mistral-small-latest")))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    writeln!(file, "file-key").unwrap();
+    let mut config = provider_config("mistral", mock_server.uri());
+    config.api_key_file = Some(file.path().to_path_buf());
+
+    let provider = ProviderFactory::new().create("mistral", &config).unwrap();
+    provider
+        .complete(CompletionRequest::new("mistral-small-latest", "Hi"))
+        .await
+        .unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_azure_ad_token_from_command() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/openai/deployments/gpt-4o/chat/completions"))
+        .and(header("authorization", "Bearer ad-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(chat_response("gpt-4o")))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut config = provider_config("azure-openai", mock_server.uri());
+    config.api_key_cmd = Some("printf 'ad-token\\n'".to_string());
+    config.azure_ad = true;
+
+    let provider = ProviderFactory::new().create("azure", &config).unwrap();
+    let response = provider.complete(CompletionRequest::new("gpt-4o", "Hi")).await.unwrap();
+    assert_eq!(response.content, "Hello!");
+
+    let requests = mock_server.received_requests().await.unwrap();
+    assert!(requests[0].headers.get("api-key").is_none());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_azure_ad_token_refreshed_after_401() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/openai/deployments/gpt-4o/chat/completions"))
+        .and(header("authorization", "Bearer expired-token"))
+        .respond_with(ResponseTemplate::new(401).set_body_string("Token expired"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/openai/deployments/gpt-4o/chat/completions"))
+        .and(header("authorization", "Bearer fresh-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(chat_response("gpt-4o")))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let token_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(token_file.path(), "expired-token\n").unwrap();
+    let mut config = provider_config("azure-openai", mock_server.uri());
+    config.api_key_cmd = Some(format!("cat {}", token_file.path().display()));
+    config.azure_ad = true;
+
+    let provider = ProviderFactory::new().create("azure", &config).unwrap();
+
+    // The token issued when the provider was built has since expired
+    std::fs::write(token_file.path(), "fresh-token\n").unwrap();
+    for _ in 0..2 {
+        let response = provider.complete(CompletionRequest::new("gpt-4o", "Hi")).await.unwrap();
+        assert_eq!(response.content, "Hello!");
+    }
+}
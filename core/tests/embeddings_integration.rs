@@ -0,0 +1,201 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for embeddings
+//!
+//! Each test mocks the provider's embeddings endpoint with wiremock and
+//! checks the request body, the order of the vectors and the usage.
+
+use llm_test_bench_core::providers::{
+    CohereProvider, EmbeddingInputType, EmbeddingProvider, EmbeddingRequest, HuggingFaceProvider, MistralProvider,
+    OllamaProvider, OpenAICompatibleProvider, OpenAIProvider, ProviderError, TogetherProvider,
+};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn openai_embeddings(model: &str, vectors: &[[f32; 2]], usage: Option<usize>) -> serde_json::Value {
+    let data: Vec<_> = vectors
+        .iter()
+        .enumerate()
+        .map(|(index, embedding)| json!({ "object": "embedding", "index": index, "embedding": embedding }))
+        .collect();
+    let mut body = json!({ "object": "list", "data": data, "model": model });
+    if let Some(tokens) = usage {
+        body["usage"] = json!({ "prompt_tokens": tokens, "total_tokens": tokens });
+    }
+    body
+}
+
+#[tokio::test]
+async fn test_openai_embeddings() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/embeddings"))
+        .and(header("authorization", "Bearer test-key"))
+        .and(body_partial_json(json!({
+            "model": "text-embedding-3-small",
+            "input": ["The cat sat", "A cat was sitting"],
+            "dimensions": 2
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_embeddings(
+            "text-embedding-3-small",
+            &[[0.6, 0.8], [0.8, 0.6]],
+            Some(9),
+        )))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    assert_eq!(provider.embedding_dimensions("text-embedding-3-large"), Some(3072));
+
+    let request = EmbeddingRequest::new("text-embedding-3-small", ["The cat sat", "A cat was sitting"]).with_dimensions(2);
+    let response = provider.embed(request).await.unwrap();
+    assert_eq!(response.dimensions(), Some(2));
+    assert_eq!(response.usage.prompt_tokens, 9);
+    assert!((response.similarity(0, 1).unwrap() - 0.96).abs() < 1e-6);
+}
+
+#[tokio::test]
+async fn test_cohere_embed_input_type() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/embed"))
+        .and(body_partial_json(json!({
+            "model": "embed-english-v3.0",
+            "texts": ["what is rust?"],
+            "input_type": "search_query",
+            "embedding_types": ["float"]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "emb-123",
+            "embeddings": { "float": [[0.1, 0.2, 0.3]] },
+            "texts": ["what is rust?"],
+            "meta": { "billed_units": { "input_tokens": 4 } }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = CohereProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let request = EmbeddingRequest::new("embed-english-v3.0", ["what is rust?"])
+        .with_input_type(EmbeddingInputType::SearchQuery);
+    let response = provider.embed(request).await.unwrap();
+    assert_eq!(response.embeddings, vec![vec![0.1, 0.2, 0.3]]);
+    assert_eq!(response.usage.prompt_tokens, 4);
+
+    // v3 models have a fixed size; nothing is sent
+    let request = EmbeddingRequest::new("embed-english-v3.0", ["hi"]).with_dimensions(256);
+    assert!(matches!(provider.embed(request).await, Err(ProviderError::InvalidRequest(_))));
+}
+
+#[tokio::test]
+async fn test_mistral_and_together_embeddings() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/mistral/embeddings"))
+        .and(body_partial_json(json!({ "model": "codestral-embed", "output_dimension": 2 })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_embeddings("codestral-embed", &[[1.0, 0.0]], Some(3))))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/together/embeddings"))
+        .and(body_partial_json(json!({ "model": "BAAI/bge-base-en-v1.5", "input": ["hello world"] })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_embeddings("BAAI/bge-base-en-v1.5", &[[0.0, 1.0]], None)))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mistral = MistralProvider::with_base_url("test-key".to_string(), format!("{}/mistral", mock_server.uri())).unwrap();
+    let request = EmbeddingRequest::new("codestral-embed", ["fn main() {}"]).with_dimensions(2);
+    assert_eq!(mistral.embed(request).await.unwrap().usage.prompt_tokens, 3);
+
+    // Together omits usage, so it is estimated
+    let together = TogetherProvider::with_base_url("test-key".to_string(), format!("{}/together", mock_server.uri())).unwrap();
+    let response = together.embed(EmbeddingRequest::new("BAAI/bge-base-en-v1.5", ["hello world"])).await.unwrap();
+    assert_eq!(response.embeddings, vec![vec![0.0, 1.0]]);
+    assert!(response.usage.prompt_tokens > 0);
+}
+
+#[tokio::test]
+async fn test_ollama_embed() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/embed"))
+        .and(body_partial_json(json!({ "model": "nomic-embed-text", "input": ["a", "b"] })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "model": "nomic-embed-text",
+            "embeddings": [[0.1, 0.2], [0.3, 0.4]],
+            "total_duration": 14143917,
+            "load_duration": 1019500,
+            "prompt_eval_count": 8
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OllamaProvider::with_base_url(mock_server.uri()).unwrap();
+    let response = provider.embed(EmbeddingRequest::new("nomic-embed-text", ["a", "b"])).await.unwrap();
+    assert_eq!(response.embeddings.len(), 2);
+    assert_eq!(response.usage.prompt_tokens, 8);
+}
+
+#[tokio::test]
+async fn test_huggingface_feature_extraction() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/sentence-transformers/all-MiniLM-L6-v2"))
+        .and(body_partial_json(json!({ "inputs": ["hello"], "options": { "wait_for_model": true } })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([[0.5, 0.5]])))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    // Plain encoders return one vector per token
+    Mock::given(method("POST"))
+        .and(path("/bert-base-uncased"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([[[1.0, 0.0], [0.0, 1.0]]])))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = HuggingFaceProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+
+    let response = provider
+        .embed(EmbeddingRequest::new("sentence-transformers/all-MiniLM-L6-v2", ["hello"]))
+        .await
+        .unwrap();
+    assert_eq!(response.embeddings, vec![vec![0.5, 0.5]]);
+
+    let response = provider.embed(EmbeddingRequest::new("bert-base-uncased", ["hi"])).await.unwrap();
+    assert_eq!(response.embeddings, vec![vec![0.5, 0.5]]);
+}
+
+#[tokio::test]
+async fn test_embed_all_splits_batches() {
+    let mock_server = MockServer::start().await;
+
+    // Every batch gets the same one-vector reply, so 100 inputs at the
+    // default batch size of 96 take two requests
+    Mock::given(method("POST"))
+        .and(path("/v1/embeddings"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_embeddings("bge-m3", &[[1.0, 0.0]], Some(5))))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAICompatibleProvider::new("tei", format!("{}/v1", mock_server.uri())).unwrap();
+    let inputs: Vec<String> = (0..100).map(|i| format!("document {}", i)).collect();
+    let response = provider.embed_all(EmbeddingRequest::new("bge-m3", inputs)).await.unwrap();
+    assert_eq!(response.embeddings.len(), 2);
+    assert_eq!(response.usage.prompt_tokens, 10);
+}
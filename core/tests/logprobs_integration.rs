@@ -0,0 +1,230 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for token log probabilities
+//!
+//! Covers the request parameters and response formats of OpenAI chat, the
+//! vLLM-style text completions endpoint used for prompt scoring, Together AI
+//! and Ollama, and the perplexity evaluator built on top of them.
+
+use llm_test_bench_core::evaluators::PerplexityEvaluator;
+use llm_test_bench_core::providers::{
+    CompletionRequest, OllamaProvider, OpenAIProvider, Provider, ProviderError, TogetherProvider,
+};
+use serde_json::json;
+use std::sync::Arc;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// A vLLM `/completions` response echoing `"The cat sat"` followed by `" down"`
+fn echoed_completion() -> serde_json::Value {
+    json!({
+        "id": "cmpl-1",
+        "object": "text_completion",
+        "model": "meta-llama/Llama-3.1-8B",
+        "choices": [{
+            "index": 0,
+            "text": "The cat sat down",
+            "finish_reason": "length",
+            "logprobs": {
+                "tokens": ["The", " cat", " sat", " down"],
+                "token_logprobs": [null, -4.0, -2.0, -0.5],
+                "top_logprobs": [null, { " cat": -4.0, " dog": -3.5 }, { " sat": -2.0 }, { " down": -0.5 }],
+                "text_offset": [0, 3, 7, 11]
+            }
+        }],
+        "usage": { "prompt_tokens": 3, "completion_tokens": 1, "total_tokens": 4 }
+    })
+}
+
+#[tokio::test]
+async fn test_openai_chat_logprobs() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({ "logprobs": true, "top_logprobs": 2 })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "Yes" },
+                "logprobs": {
+                    "content": [{
+                        "token": "Yes",
+                        "logprob": -0.01,
+                        "bytes": [89, 101, 115],
+                        "top_logprobs": [
+                            { "token": "Yes", "logprob": -0.01, "bytes": [89, 101, 115] },
+                            { "token": "No", "logprob": -4.6, "bytes": [78, 111] }
+                        ]
+                    }],
+                    "refusal": null
+                },
+                "finish_reason": "stop"
+            }],
+            "usage": { "prompt_tokens": 12, "completion_tokens": 1, "total_tokens": 13 }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("gpt-4o", "Is water wet?").with_logprobs(2);
+    let response = provider.complete(request).await.unwrap();
+
+    let logprobs = response.logprobs.unwrap();
+    assert_eq!(logprobs.len(), 1);
+    assert_eq!(logprobs[0].token, "Yes");
+    assert_eq!(logprobs[0].top_logprobs[1].token, "No");
+    assert!(response.prompt_logprobs.is_none());
+}
+
+#[tokio::test]
+async fn test_openai_compatible_prompt_scoring() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/completions"))
+        .and(body_partial_json(json!({
+            "prompt": "The cat sat",
+            "echo": true,
+            "logprobs": 1,
+            "max_tokens": 1
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(echoed_completion()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("EMPTY".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("meta-llama/Llama-3.1-8B", "The cat sat")
+        .with_max_tokens(1)
+        .with_logprobs(1)
+        .with_prompt_logprobs();
+    let response = provider.complete(request).await.unwrap();
+
+    // The echoed prompt is split off the content and the completion's tokens
+    assert_eq!(response.content, " down");
+    let prompt = response.prompt_logprobs.unwrap();
+    assert_eq!(prompt.iter().map(|t| t.token.as_str()).collect::<Vec<_>>(), [" cat", " sat"]);
+    assert_eq!(prompt[0].top_logprobs[0].token, " dog");
+    assert_eq!(response.logprobs.unwrap()[0].token, " down");
+}
+
+#[tokio::test]
+async fn test_prompt_scoring_rejects_chat_requests() {
+    let provider = OpenAIProvider::with_base_url("EMPTY".to_string(), "http://127.0.0.1:9".to_string()).unwrap();
+    let request = CompletionRequest::new("meta-llama/Llama-3.1-8B", "The cat sat")
+        .with_system("Be terse.")
+        .with_prompt_logprobs();
+
+    let result = provider.complete(request).await;
+    assert!(matches!(result, Err(ProviderError::InvalidRequest(_))));
+}
+
+#[tokio::test]
+async fn test_together_logprobs_with_echo() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/completions"))
+        .and(body_partial_json(json!({ "prompt": "Hello world", "echo": true, "logprobs": 1 })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "together-1",
+            "model": "meta-llama/Llama-3-8b-hf",
+            "prompt": [{
+                "text": "Hello world",
+                "logprobs": { "tokens": ["Hello", " world"], "token_logprobs": [null, -3.1] }
+            }],
+            "choices": [{
+                "text": "!",
+                "finish_reason": "length",
+                "logprobs": { "tokens": ["!"], "token_logprobs": [-1.2] }
+            }],
+            "usage": { "prompt_tokens": 2, "completion_tokens": 1, "total_tokens": 3 }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = TogetherProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("meta-llama/Llama-3-8b-hf", "Hello world")
+        .with_max_tokens(1)
+        .with_prompt_logprobs();
+    let response = provider.complete(request).await.unwrap();
+
+    assert_eq!(response.content, "!");
+    assert_eq!(response.prompt_logprobs.unwrap()[0].logprob, -3.1);
+    assert_eq!(response.logprobs.unwrap()[0].token, "!");
+}
+
+#[tokio::test]
+async fn test_ollama_logprobs() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/generate"))
+        .and(body_partial_json(json!({ "logprobs": true, "top_logprobs": 3 })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "model": "llama3.2",
+            "response": "Blue",
+            "done": true,
+            "prompt_eval_count": 8,
+            "eval_count": 1,
+            "logprobs": [{
+                "token": "Blue",
+                "logprob": -0.2,
+                "top_logprobs": [
+                    { "token": "Blue", "logprob": -0.2 },
+                    { "token": "The", "logprob": -1.9 },
+                    { "token": "It", "logprob": -3.3 }
+                ]
+            }]
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OllamaProvider::with_base_url(mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("llama3.2", "What color is the sky?").with_logprobs(3);
+    let response = provider.complete(request).await.unwrap();
+
+    let logprobs = response.logprobs.unwrap();
+    assert_eq!(logprobs[0].top_logprobs.len(), 3);
+
+    // Ollama cannot score the prompt
+    let request = CompletionRequest::new("llama3.2", "What color is the sky?").with_prompt_logprobs();
+    assert!(matches!(provider.complete(request).await, Err(ProviderError::InvalidRequest(_))));
+}
+
+#[tokio::test]
+async fn test_perplexity_evaluator_scores_prompt_tokens() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/completions"))
+        .and(body_partial_json(json!({ "prompt": "The cat sat", "echo": true })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(echoed_completion()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("EMPTY".to_string(), mock_server.uri()).unwrap();
+    let evaluator = PerplexityEvaluator::new(Arc::new(provider), "meta-llama/Llama-3.1-8B".to_string())
+        .with_token_details();
+
+    let result = evaluator.evaluate_detailed("The cat sat").await.unwrap();
+
+    // Only the prompt's scored tokens count: exp(-(-4.0 + -2.0) / 2)
+    assert_eq!(result.token_count, 2);
+    assert!((result.perplexity - 3.0f64.exp()).abs() < 1e-9);
+    let details = result.token_level_details.unwrap();
+    assert_eq!(details[0].token, " cat");
+    assert_eq!(details[1].token, " sat");
+}
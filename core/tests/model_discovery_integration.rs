@@ -0,0 +1,132 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for live model discovery
+//!
+//! Each provider's list-models endpoint is served by wiremock and the
+//! results are run through the on-disk `ModelCatalog`.
+
+use llm_test_bench_core::providers::{
+    GroqProvider, ModelCatalog, OllamaProvider, OpenAIProvider, Provider, ProviderError, TogetherProvider,
+};
+use serde_json::json;
+use tempfile::TempDir;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_openai_list_models_merged_with_pricing() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/models"))
+        .and(header("authorization", "Bearer test-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "object": "list",
+            "data": [
+                { "id": "gpt-4o", "object": "model", "owned_by": "system" },
+                { "id": "gpt-4o-mini", "object": "model", "owned_by": "system" },
+                { "id": "ft:gpt-4o-mini:acme::abc123", "object": "model", "owned_by": "acme" }
+            ]
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let dir = TempDir::new().unwrap();
+    let catalog = ModelCatalog::new().with_cache_dir(dir.path());
+
+    let models = catalog.list_models(&provider).await.unwrap();
+    assert_eq!(models.len(), 3);
+
+    let mini = models.iter().find(|m| m.info.id == "gpt-4o-mini").unwrap();
+    assert_eq!(mini.info.max_tokens, 128_000);
+    assert_eq!(mini.cost_per_1k_input, Some(0.00015));
+    assert_eq!(mini.cost_per_1k_output, Some(0.0006));
+
+    let finetune = models.iter().find(|m| m.info.id.starts_with("ft:")).unwrap();
+    assert_eq!(finetune.cost_per_1k_input, None);
+
+    // Served from disk the second time; the mock expects a single call
+    let cached = catalog.list_models(&provider).await.unwrap();
+    assert_eq!(cached, models);
+}
+
+#[tokio::test]
+async fn test_groq_and_together_context_windows() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/groq/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "object": "list",
+            "data": [{ "id": "llama-3.3-70b-versatile", "object": "model", "context_window": 131072 }]
+        })))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/together/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            { "id": "Qwen/Qwen2.5-72B-Instruct-Turbo", "type": "chat", "display_name": "Qwen 2.5 72B", "context_length": 32768 },
+            { "id": "black-forest-labs/FLUX.1-schnell", "type": "image" }
+        ])))
+        .mount(&mock_server)
+        .await;
+
+    let groq = GroqProvider::with_base_url("key".to_string(), format!("{}/groq", mock_server.uri())).unwrap();
+    let models = groq.list_models().await.unwrap();
+    assert_eq!(models[0].id, "llama-3.3-70b-versatile");
+    assert_eq!(models[0].max_tokens, 131072);
+
+    let together = TogetherProvider::with_base_url("key".to_string(), format!("{}/together", mock_server.uri())).unwrap();
+    let models = together.list_models().await.unwrap();
+    assert_eq!(models.len(), 1);
+    assert_eq!(models[0].name, "Qwen 2.5 72B");
+    assert_eq!(models[0].max_tokens, 32768);
+}
+
+#[tokio::test]
+async fn test_ollama_tags() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/tags"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "models": [
+                { "name": "llama3.1:8b", "model": "llama3.1:8b", "size": 4920753328u64 },
+                { "name": "mistral:latest", "model": "mistral:latest", "size": 4113301824u64 }
+            ]
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let provider = OllamaProvider::with_base_url(mock_server.uri()).unwrap();
+    let models = ModelCatalog::new().without_cache().list_models(&provider).await.unwrap();
+
+    assert_eq!(models.len(), 2);
+    assert_eq!(models[0].info.id, "llama3.1:8b");
+    // Ollama does not report context windows; the provider's static table fills them in
+    assert_eq!(models[1].info.max_tokens, 32768);
+    assert_eq!(models[1].provider, "ollama");
+}
+
+#[tokio::test]
+async fn test_list_models_auth_error() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/models"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "error": { "message": "Incorrect API key provided", "type": "invalid_request_error" }
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("bad-key".to_string(), mock_server.uri()).unwrap();
+    let result = ModelCatalog::new().without_cache().list_models(&provider).await;
+    assert!(matches!(result, Err(ProviderError::AuthenticationError(_))));
+}
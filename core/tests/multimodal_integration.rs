@@ -0,0 +1,270 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for multi-modal completions
+//!
+//! Each test mocks the provider's API with wiremock and checks that images
+//! and audio are encoded in the provider's native request format.
+
+use llm_test_bench_core::multimodal::types::ImageDetail;
+use llm_test_bench_core::multimodal::{
+    AudioFormat, AudioInput, ContentPart, ImageFormat, ImageInput, ImagePart, MultiModalRequest,
+};
+use llm_test_bench_core::providers::{
+    AnthropicProvider, GoogleProvider, MultiModalProvider, OllamaProvider, OpenAIProvider, ProviderError,
+};
+use serde_json::json;
+use tempfile::TempDir;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+// Base64 of the 8-byte PNG signature
+const PNG: &str = "iVBORw0KGgo=";
+
+fn openai_response(model: &str) -> serde_json::Value {
+    json!({
+        "id": "chatcmpl-123",
+        "object": "chat.completion",
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": "A cat on a sofa." },
+            "finish_reason": "stop"
+        }],
+        "usage": { "prompt_tokens": 800, "completion_tokens": 6, "total_tokens": 806 }
+    })
+}
+
+#[tokio::test]
+async fn test_openai_image_parts() {
+    let mock_server = MockServer::start().await;
+
+    let mut request = MultiModalRequest::new("gpt-4o")
+        .with_system("Describe images briefly.")
+        .with_text("What's in these images?")
+        .with_image(ImageInput::from_url("https://example.com/cat.jpg"))
+        .with_max_tokens(100);
+    request.content.parts.push(ContentPart::Image(
+        ImagePart::new(ImageInput::from_base64(PNG.to_string(), ImageFormat::Png)).with_detail(ImageDetail::Low),
+    ));
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({
+            "model": "gpt-4o",
+            "max_tokens": 100,
+            "messages": [
+                { "role": "system", "content": "Describe images briefly." },
+                {
+                    "role": "user",
+                    "content": [
+                        { "type": "text", "text": "What's in these images?" },
+                        { "type": "image_url", "image_url": { "url": "https://example.com/cat.jpg" } },
+                        {
+                            "type": "image_url",
+                            "image_url": { "url": format!("data:image/png;base64,{}", PNG), "detail": "low" }
+                        }
+                    ]
+                }
+            ]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_response("gpt-4o")))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    assert!(provider.supports_vision("gpt-4o"));
+
+    let response = provider.complete_multimodal(request).await.unwrap();
+    assert_eq!(response.text(), "A cat on a sofa.");
+    assert_eq!(response.usage.prompt_tokens, 800);
+}
+
+#[tokio::test]
+async fn test_openai_input_audio() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({
+            "messages": [{
+                "role": "user",
+                "content": [
+                    { "type": "text", "text": "Transcribe this." },
+                    { "type": "input_audio", "input_audio": { "data": "UklGRg==", "format": "wav" } }
+                ]
+            }]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_response("gpt-4o-audio-preview")))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    assert!(provider.supports_audio("gpt-4o-audio-preview"));
+
+    let request = MultiModalRequest::new("gpt-4o-audio-preview")
+        .with_text("Transcribe this.")
+        .with_audio(AudioInput::from_base64("UklGRg==".to_string(), AudioFormat::Wav));
+    provider.complete_multimodal(request).await.unwrap();
+
+    // Chat completions only take WAV and MP3
+    let request = MultiModalRequest::new("gpt-4o-audio-preview")
+        .with_audio(AudioInput::from_base64("T2dnUw==".to_string(), AudioFormat::Ogg));
+    assert!(matches!(
+        provider.complete_multimodal(request).await,
+        Err(ProviderError::InvalidRequest(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_anthropic_image_blocks() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .and(body_partial_json(json!({
+            "model": "claude-3-haiku-20240307",
+            "system": "Be concise.",
+            "messages": [{
+                "role": "user",
+                "content": [
+                    { "type": "image", "source": { "type": "base64", "media_type": "image/png", "data": PNG } },
+                    { "type": "image", "source": { "type": "url", "url": "https://example.com/dog.webp" } },
+                    { "type": "text", "text": "Compare the two animals." }
+                ]
+            }]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_123",
+            "type": "message",
+            "role": "assistant",
+            "content": [{ "type": "text", "text": "A cat and a dog." }],
+            "model": "claude-3-haiku-20240307",
+            "stop_reason": "end_turn",
+            "usage": { "input_tokens": 1200, "output_tokens": 7 }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = AnthropicProvider::with_base_url("test-key".to_string(), mock_server.uri());
+    assert!(provider.supports_vision("claude-3-haiku-20240307"));
+    assert!(!provider.supports_audio("claude-3-haiku-20240307"));
+
+    let request = MultiModalRequest::new("claude-3-haiku-20240307")
+        .with_system("Be concise.")
+        .with_image(ImageInput::from_base64(PNG.to_string(), ImageFormat::Png))
+        .with_image(ImageInput::from_url("https://example.com/dog.webp"))
+        .with_text("Compare the two animals.");
+    let response = provider.complete_multimodal(request).await.unwrap();
+    assert_eq!(response.text(), "A cat and a dog.");
+    assert_eq!(response.usage.total_tokens, 1207);
+
+    // Claude has no audio input; nothing is sent
+    let request = MultiModalRequest::new("claude-3-haiku-20240307")
+        .with_audio(AudioInput::from_base64("UklGRg==".to_string(), AudioFormat::Wav));
+    assert!(matches!(
+        provider.complete_multimodal(request).await,
+        Err(ProviderError::InvalidRequest(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_google_inline_data() {
+    let mock_server = MockServer::start().await;
+
+    // Gemini takes media inline, so remote images are downloaded first
+    Mock::given(method("GET"))
+        .and(path("/images/cat.png"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let dir = TempDir::new().unwrap();
+    let audio_path = dir.path().join("question.mp3");
+    std::fs::write(&audio_path, b"ID3").unwrap();
+
+    Mock::given(method("POST"))
+        .and(path("/models/gemini-1.5-flash:generateContent"))
+        .and(body_partial_json(json!({
+            "systemInstruction": { "parts": [{ "text": "Answer in one word." }] },
+            "contents": [{
+                "role": "user",
+                "parts": [
+                    { "inlineData": { "mimeType": "image/png", "data": PNG } },
+                    { "inlineData": { "mimeType": "audio/mpeg", "data": "SUQz" } },
+                    { "text": "Answer the question about the image." }
+                ]
+            }],
+            "generationConfig": { "temperature": 0.0 }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{
+                "content": { "parts": [{ "text": "Cat" }], "role": "model" },
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": { "promptTokenCount": 300, "candidatesTokenCount": 1, "totalTokenCount": 301 }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = GoogleProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    assert!(provider.supports_vision("gemini-1.5-flash"));
+    assert!(provider.supports_audio("gemini-1.5-flash"));
+
+    let request = MultiModalRequest::new("gemini-1.5-flash")
+        .with_system("Answer in one word.")
+        .with_image(ImageInput::from_url(format!("{}/images/cat.png", mock_server.uri())))
+        .with_audio(AudioInput::from_path(&audio_path).await.unwrap())
+        .with_text("Answer the question about the image.")
+        .with_temperature(0.0);
+    let response = provider.complete_multimodal(request).await.unwrap();
+    assert_eq!(response.text(), "Cat");
+    assert_eq!(response.usage.prompt_tokens, 300);
+}
+
+#[tokio::test]
+async fn test_ollama_llava_images() {
+    let mock_server = MockServer::start().await;
+
+    let dir = TempDir::new().unwrap();
+    let image_path = dir.path().join("cat.png");
+    std::fs::write(&image_path, [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]).unwrap();
+
+    Mock::given(method("POST"))
+        .and(path("/api/generate"))
+        .and(body_partial_json(json!({
+            "model": "llava",
+            "prompt": "What is this?",
+            "stream": false,
+            "images": [PNG]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "model": "llava",
+            "response": "A cat.",
+            "done": true,
+            "prompt_eval_count": 600,
+            "eval_count": 3
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OllamaProvider::with_base_url(mock_server.uri()).unwrap();
+    assert!(provider.supports_vision("llava"));
+    assert!(!provider.supports_vision("mistral"));
+
+    let request = MultiModalRequest::new("llava")
+        .with_text("What is this?")
+        .with_image(ImageInput::from_path(&image_path).await.unwrap());
+    let response = provider.complete_multimodal(request).await.unwrap();
+    assert_eq!(response.text(), "A cat.");
+    assert_eq!(response.usage.total_tokens, 603);
+}
@@ -0,0 +1,237 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for the OpenAI-compatible provider
+//!
+//! A wiremock server stands in for vLLM / llama.cpp / LiteLLM, and the
+//! provider is built from configuration through the `ProviderFactory`.
+
+use futures::StreamExt;
+use llm_test_bench_core::config::models::{KeyRotation, ProviderConfig, TransportConfig};
+use llm_test_bench_core::providers::{
+    CompletionRequest, FinishReason, ProviderError, ProviderFactory, StreamAccumulator,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+fn compatible_config(base_url: String, api_key_env: &str) -> ProviderConfig {
+    ProviderConfig {
+        api_key_env: api_key_env.to_string(),
+        api_key_file: None,
+        api_key_cmd: None,
+        api_keys_env: Vec::new(),
+        key_rotation: KeyRotation::default(),
+        azure_ad: false,
+        base_url,
+        default_model: "llama-3-8b".to_string(),
+        timeout_seconds: 30,
+        max_retries: 0,
+        rate_limit_rpm: None,
+        rate_limit_tpm: None,
+        fallback: Vec::new(),
+        circuit_breaker: None,
+        kind: Some("openai-compatible".to_string()),
+        headers: HashMap::new(),
+        models: vec!["llama-3-8b".to_string()],
+        context_length: Some(8192),
+        script: None,
+        transport: TransportConfig::default(),
+    }
+}
+
+fn chat_response() -> serde_json::Value {
+    json!({
+        "id": "cmpl-local-1",
+        "object": "chat.completion",
+        "model": "llama-3-8b",
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": "Hello from vLLM" },
+            "finish_reason": "stop"
+        }],
+        "usage": { "prompt_tokens": 9, "completion_tokens": 4, "total_tokens": 13 }
+    })
+}
+
+#[tokio::test]
+async fn test_completion_with_header_template() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header("x-litellm-key", "team-secret"))
+        .and(header("x-team", "bench"))
+        .and(body_partial_json(json!({
+            "model": "llama-3-8b",
+            "messages": [{ "role": "user", "content": "Hi" }],
+            "stream": false
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(chat_response()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    std::env::set_var("OPENAI_COMPATIBLE_TEST_LITELLM_KEY", "team-secret");
+    let mut config = compatible_config(format!("{}/v1", mock_server.uri()), "OPENAI_COMPATIBLE_TEST_LITELLM_KEY");
+    config.headers.insert("X-LiteLLM-Key".to_string(), "{api_key}".to_string());
+    config.headers.insert("X-Team".to_string(), "bench".to_string());
+
+    let provider = ProviderFactory::new().create("litellm", &config).unwrap();
+    let response = provider.complete(CompletionRequest::new("llama-3-8b", "Hi")).await.unwrap();
+
+    assert_eq!(provider.name(), "litellm");
+    assert_eq!(response.id, "cmpl-local-1");
+    assert_eq!(response.content, "Hello from vLLM");
+    assert_eq!(response.usage.prompt_tokens, 9);
+    assert_eq!(response.usage.completion_tokens, 4);
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+}
+
+#[tokio::test]
+async fn test_bearer_auth_only_when_key_is_set() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(|request: &Request| {
+            let authorization = request
+                .headers
+                .get("authorization")
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or("none")
+                .to_string();
+            let mut body = chat_response();
+            body["choices"][0]["message"]["content"] = json!(authorization);
+            ResponseTemplate::new(200).set_body_json(body)
+        })
+        .mount(&mock_server)
+        .await;
+
+    let base_url = format!("{}/v1", mock_server.uri());
+    let factory = ProviderFactory::new();
+
+    let anonymous = factory
+        .create("llama-cpp", &compatible_config(base_url.clone(), "OPENAI_COMPATIBLE_TEST_UNSET_KEY"))
+        .unwrap();
+    let response = anonymous.complete(CompletionRequest::new("llama-3-8b", "Hi")).await.unwrap();
+    assert_eq!(response.content, "none");
+
+    std::env::set_var("OPENAI_COMPATIBLE_TEST_VLLM_KEY", "vllm-token");
+    let authenticated = factory
+        .create("vllm", &compatible_config(base_url, "OPENAI_COMPATIBLE_TEST_VLLM_KEY"))
+        .unwrap();
+    let response = authenticated.complete(CompletionRequest::new("llama-3-8b", "Hi")).await.unwrap();
+    assert_eq!(response.content, "Bearer vllm-token");
+}
+
+#[tokio::test]
+async fn test_missing_usage_is_estimated() {
+    let mock_server = MockServer::start().await;
+
+    let mut body = chat_response();
+    body.as_object_mut().unwrap().remove("usage");
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(body))
+        .mount(&mock_server)
+        .await;
+
+    let config = compatible_config(format!("{}/v1", mock_server.uri()), "OPENAI_COMPATIBLE_TEST_UNSET_KEY");
+    let provider = ProviderFactory::new().create("lm-studio", &config).unwrap();
+    let response = provider.complete(CompletionRequest::new("llama-3-8b", "Hi there")).await.unwrap();
+
+    assert!(response.usage.prompt_tokens > 0);
+    assert!(response.usage.completion_tokens > 0);
+    assert_eq!(
+        response.usage.total_tokens,
+        response.usage.prompt_tokens + response.usage.completion_tokens
+    );
+}
+
+#[tokio::test]
+async fn test_streaming() {
+    let mock_server = MockServer::start().await;
+
+    let chunk = |delta: serde_json::Value, finish_reason: serde_json::Value| {
+        json!({
+            "id": "cmpl-local-2",
+            "object": "chat.completion.chunk",
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }]
+        })
+    };
+    let events = [
+        chunk(json!({ "role": "assistant", "content": "Hel" }), json!(null)),
+        chunk(json!({ "content": "lo" }), json!("stop")),
+        json!({
+            "id": "cmpl-local-2",
+            "object": "chat.completion.chunk",
+            "choices": [],
+            "usage": { "prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7 }
+        }),
+    ];
+    let mut sse: String = events.iter().map(|event| format!("data: {}\n\n", event)).collect();
+    sse.push_str("data: [DONE]\n\n");
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({ "stream": true })))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(sse),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let config = compatible_config(format!("{}/v1", mock_server.uri()), "OPENAI_COMPATIBLE_TEST_UNSET_KEY");
+    let provider = ProviderFactory::new().create("vllm", &config).unwrap();
+    let mut stream = provider
+        .stream(CompletionRequest::new("llama-3-8b", "Hi").with_streaming())
+        .await
+        .unwrap();
+
+    let mut accumulator = StreamAccumulator::new("llama-3-8b");
+    while let Some(event) = stream.next().await {
+        accumulator.push(&event.unwrap());
+    }
+    let response = accumulator.into_response();
+
+    assert_eq!(response.content, "Hello");
+    assert_eq!(response.usage.prompt_tokens, 5);
+    assert_eq!(response.usage.completion_tokens, 2);
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+}
+
+#[tokio::test]
+async fn test_error_mapping_and_validation() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(429).set_body_json(json!({
+            "error": { "message": "Too many requests", "type": "rate_limit_error" }
+        })))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "object": "list",
+            "data": [{ "id": "llama-3-8b", "object": "model" }]
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let config = compatible_config(format!("{}/v1", mock_server.uri()), "OPENAI_COMPATIBLE_TEST_UNSET_KEY");
+    let provider = ProviderFactory::new().create("vllm", &config).unwrap();
+
+    let result = provider.complete(CompletionRequest::new("llama-3-8b", "Hi")).await;
+    assert!(matches!(result, Err(ProviderError::RateLimitExceeded { .. })));
+    assert!(provider.validate_config().await.is_ok());
+}
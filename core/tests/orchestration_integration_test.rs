@@ -15,6 +15,7 @@ use llm_test_bench_core::orchestration::{
 use llm_test_bench_core::providers::error::ProviderError;
 use llm_test_bench_core::providers::traits::Provider;
 use llm_test_bench_core::providers::types::{CompletionRequest, CompletionResponse, FinishReason, ModelInfo, ResponseStream, TokenUsage};
+use std::collections::HashMap;
 use std::sync::Arc;
 use async_trait::async_trait;
 use chrono::Utc;
@@ -41,6 +42,12 @@ impl Provider for TestProvider {
             usage: TokenUsage::new(100, 200),
             finish_reason: FinishReason::Stop,
             created_at: Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         })
     }
 
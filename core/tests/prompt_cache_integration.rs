@@ -0,0 +1,191 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for prompt caching
+//!
+//! A wiremock server checks that cache points are sent as Anthropic
+//! `cache_control` blocks, and that cache reads and writes reported by the
+//! API end up in `TokenUsage`.
+
+use futures::StreamExt;
+use llm_test_bench_core::providers::{
+    models, AnthropicProvider, CachePoint, ChatMessage, CompletionRequest, OpenAIProvider, Provider, StreamAccumulator,
+};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const MODEL: &str = "claude-3-5-sonnet-20241022";
+
+fn claude_response(usage: serde_json::Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "id": "msg_01",
+        "type": "message",
+        "role": "assistant",
+        "content": [{ "type": "text", "text": "Section 2 covers caching." }],
+        "model": MODEL,
+        "stop_reason": "end_turn",
+        "usage": usage
+    }))
+}
+
+#[tokio::test]
+async fn test_anthropic_cache_control_and_usage() {
+    let mock_server = MockServer::start().await;
+
+    let cached_system = json!({
+        "system": [{ "type": "text", "text": "<long document>", "cache_control": { "type": "ephemeral" } }]
+    });
+
+    // The first request writes the prefix, the second reads it
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .and(body_partial_json(cached_system.clone()))
+        .respond_with(claude_response(json!({
+            "input_tokens": 12,
+            "output_tokens": 8,
+            "cache_creation_input_tokens": 4000,
+            "cache_read_input_tokens": 0
+        })))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .and(body_partial_json(cached_system))
+        .respond_with(claude_response(json!({
+            "input_tokens": 12,
+            "output_tokens": 8,
+            "cache_creation_input_tokens": 0,
+            "cache_read_input_tokens": 4000
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = AnthropicProvider::with_base_url("test-key".to_string(), mock_server.uri());
+    let request = CompletionRequest::new(MODEL, "Summarize section 2.")
+        .with_system("<long document>")
+        .with_cache_point(CachePoint::System);
+
+    let cold = provider.complete(request.clone()).await.unwrap().usage;
+    assert_eq!(cold.prompt_tokens, 4012);
+    assert_eq!(cold.cache_write_tokens, 4000);
+    assert_eq!(cold.cached_tokens, 0);
+
+    let warm = provider.complete(request).await.unwrap().usage;
+    assert_eq!(warm.prompt_tokens, 4012);
+    assert_eq!(warm.cache_write_tokens, 0);
+    assert_eq!(warm.cached_tokens, 4000);
+
+    // Writes cost more than plain input, reads much less
+    let cold_cost = models::estimate_cost(MODEL, &cold).unwrap();
+    let warm_cost = models::estimate_cost(MODEL, &warm).unwrap();
+    assert!(warm_cost < cold_cost / 5.0);
+}
+
+#[tokio::test]
+async fn test_anthropic_stream_cache_usage() {
+    let mock_server = MockServer::start().await;
+
+    let body = [
+        json!({
+            "type": "message_start",
+            "message": {
+                "id": "msg_01",
+                "type": "message",
+                "role": "assistant",
+                "content": [],
+                "model": MODEL,
+                "usage": {
+                    "input_tokens": 12,
+                    "output_tokens": 1,
+                    "cache_creation_input_tokens": 4000,
+                    "cache_read_input_tokens": 0
+                }
+            }
+        }),
+        json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "Done." } }),
+        json!({ "type": "message_delta", "delta": { "stop_reason": "end_turn" }, "usage": { "output_tokens": 3 } }),
+        json!({ "type": "message_stop" }),
+    ]
+    .iter()
+    .map(|event| format!("event: {}\ndata: {}\n\n", event["type"].as_str().unwrap(), event))
+    .collect::<String>();
+
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .and(body_partial_json(json!({
+            "stream": true,
+            "messages": [
+                { "role": "user", "content": "Summarize section 2." },
+                {
+                    "role": "assistant",
+                    "content": [{ "type": "text", "text": "Section 2 covers caching.", "cache_control": { "type": "ephemeral" } }]
+                },
+                { "role": "user", "content": "And section 3?" }
+            ]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = AnthropicProvider::with_base_url("test-key".to_string(), mock_server.uri());
+    let request = CompletionRequest::new(MODEL, "And section 3?")
+        .with_message(ChatMessage::user("Summarize section 2."))
+        .with_message(ChatMessage::assistant("Section 2 covers caching."))
+        .with_cache_point(CachePoint::Messages)
+        .with_streaming();
+    let mut stream = provider.stream(request).await.unwrap();
+
+    let mut accumulator = StreamAccumulator::new(MODEL);
+    while let Some(event) = stream.next().await {
+        accumulator.push(&event.unwrap());
+    }
+    let usage = accumulator.usage().unwrap();
+    assert_eq!(usage.prompt_tokens, 4012);
+    assert_eq!(usage.completion_tokens, 3);
+    assert_eq!(usage.cache_write_tokens, 4000);
+}
+
+#[tokio::test]
+async fn test_openai_ignores_cache_points() {
+    let mock_server = MockServer::start().await;
+
+    // OpenAI caches long prompts automatically and only reports reads
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [{ "index": 0, "message": { "role": "assistant", "content": "Done." }, "finish_reason": "stop" }],
+            "usage": {
+                "prompt_tokens": 4012,
+                "completion_tokens": 3,
+                "total_tokens": 4015,
+                "prompt_tokens_details": { "cached_tokens": 3968 }
+            }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("gpt-4o", "Summarize section 2.")
+        .with_system("<long document>")
+        .with_cache_point(CachePoint::System);
+    let usage = provider.complete(request).await.unwrap().usage;
+    assert_eq!(usage.cached_tokens, 3968);
+    assert_eq!(usage.cache_write_tokens, 0);
+
+    let requests = mock_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert!(body.get("cache_points").is_none());
+    assert_eq!(body["messages"][0]["content"], "<long document>");
+}
@@ -0,0 +1,191 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for reasoning controls and token accounting
+//!
+//! Each test mocks the provider's endpoint with wiremock and checks how the
+//! reasoning effort or thinking budget is sent, and how reasoning and
+//! cached tokens are read back into `TokenUsage`.
+
+use futures::StreamExt;
+use llm_test_bench_core::providers::{
+    AnthropicProvider, CompletionRequest, GoogleProvider, OpenAIProvider, Provider, ProviderError, ReasoningEffort,
+    StreamAccumulator,
+};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_openai_reasoning_effort() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({ "model": "o3-mini", "reasoning_effort": "high" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "model": "o3-mini",
+            "choices": [{ "index": 0, "message": { "role": "assistant", "content": "42" }, "finish_reason": "stop" }],
+            "usage": {
+                "prompt_tokens": 1200,
+                "completion_tokens": 900,
+                "total_tokens": 2100,
+                "prompt_tokens_details": { "cached_tokens": 1024 },
+                "completion_tokens_details": { "reasoning_tokens": 896 }
+            }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("o3-mini", "What is 6 * 7?").with_reasoning_effort(ReasoningEffort::High);
+    let response = provider.complete(request).await.unwrap();
+
+    assert_eq!(response.usage.completion_tokens, 900);
+    assert_eq!(response.usage.reasoning_tokens, 896);
+    assert_eq!(response.usage.cached_tokens, 1024);
+
+    // OpenAI takes an effort, not a token budget
+    let request = CompletionRequest::new("o3-mini", "Hi").with_thinking_budget(2048);
+    match provider.complete(request).await {
+        Err(ProviderError::InvalidRequest(msg)) => assert!(msg.contains("thinking_budget")),
+        other => panic!("Expected InvalidRequest, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_openai_stream_reasoning_usage() {
+    let mock_server = MockServer::start().await;
+
+    let body = [
+        json!({ "choices": [{ "index": 0, "delta": { "content": "42" }, "finish_reason": null }] }),
+        json!({ "choices": [{ "index": 0, "delta": {}, "finish_reason": "stop" }] }),
+        json!({
+            "choices": [],
+            "usage": {
+                "prompt_tokens": 20,
+                "completion_tokens": 300,
+                "total_tokens": 320,
+                "completion_tokens_details": { "reasoning_tokens": 256 }
+            }
+        }),
+    ]
+    .iter()
+    .map(|chunk| format!("data: {}\n\n", chunk))
+    .collect::<String>()
+        + "data: [DONE]\n\n";
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("o3-mini", "What is 6 * 7?").with_reasoning_effort(ReasoningEffort::Low);
+    let mut stream = provider.stream(request).await.unwrap();
+
+    let mut accumulator = StreamAccumulator::new("o3-mini");
+    while let Some(event) = stream.next().await {
+        accumulator.push(&event.unwrap());
+    }
+    let usage = accumulator.usage().unwrap();
+    assert_eq!(usage.reasoning_tokens, 256);
+    assert_eq!(usage.cached_tokens, 0);
+}
+
+#[tokio::test]
+async fn test_anthropic_thinking_budget() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .and(body_partial_json(json!({
+            "max_tokens": 3072,
+            "thinking": { "type": "enabled", "budget_tokens": 2048 }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_01",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                { "type": "thinking", "thinking": "6 * 7 is 42.", "signature": "sig" },
+                { "type": "redacted_thinking", "data": "abc" },
+                { "type": "text", "text": "42" }
+            ],
+            "model": "claude-sonnet-4-20250514",
+            "stop_reason": "end_turn",
+            "usage": {
+                "input_tokens": 50,
+                "output_tokens": 400,
+                "cache_read_input_tokens": 2000,
+                "cache_creation_input_tokens": 0
+            }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = AnthropicProvider::with_base_url("test-key".to_string(), mock_server.uri());
+    let request = CompletionRequest::new("claude-sonnet-4-20250514", "What is 6 * 7?").with_thinking_budget(2048);
+    let response = provider.complete(request).await.unwrap();
+
+    assert_eq!(response.content, "42");
+    assert_eq!(response.usage.prompt_tokens, 2050);
+    assert_eq!(response.usage.cached_tokens, 2000);
+    assert_eq!(response.usage.completion_tokens, 400);
+    assert_eq!(response.usage.total_tokens, 2450);
+
+    // Budgets below the API minimum or not below max_tokens are rejected
+    let request = CompletionRequest::new("claude-sonnet-4-20250514", "Hi").with_thinking_budget(512);
+    assert!(matches!(provider.complete(request).await, Err(ProviderError::InvalidRequest(_))));
+    let request = CompletionRequest::new("claude-sonnet-4-20250514", "Hi")
+        .with_thinking_budget(2048)
+        .with_max_tokens(1024);
+    assert!(matches!(provider.complete(request).await, Err(ProviderError::InvalidRequest(_))));
+
+    let request = CompletionRequest::new("claude-sonnet-4-20250514", "Hi").with_reasoning_effort(ReasoningEffort::Low);
+    assert!(matches!(provider.complete(request).await, Err(ProviderError::InvalidRequest(_))));
+}
+
+#[tokio::test]
+async fn test_google_thinking_budget() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/models/gemini-2.5-flash:generateContent"))
+        .and(body_partial_json(json!({
+            "generationConfig": { "thinkingConfig": { "thinkingBudget": 1024 } }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [
+                { "content": { "role": "model", "parts": [{ "text": "42" }] }, "finishReason": "STOP" }
+            ],
+            "usageMetadata": {
+                "promptTokenCount": 10,
+                "candidatesTokenCount": 2,
+                "thoughtsTokenCount": 300,
+                "cachedContentTokenCount": 4,
+                "totalTokenCount": 312
+            }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = GoogleProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("gemini-2.5-flash", "What is 6 * 7?").with_thinking_budget(1024);
+    let response = provider.complete(request).await.unwrap();
+
+    // Thinking tokens are billed as output, so they count as completion
+    assert_eq!(response.usage.completion_tokens, 302);
+    assert_eq!(response.usage.reasoning_tokens, 300);
+    assert_eq!(response.usage.cached_tokens, 4);
+    assert_eq!(response.usage.total_tokens, 312);
+}
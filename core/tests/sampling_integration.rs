@@ -0,0 +1,172 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for the extended sampling controls
+//!
+//! Each test mocks the provider's endpoint with wiremock and checks the
+//! parameter names sent, the `choices` of multi-sample responses, and that
+//! unsupported parameters are rejected before anything is sent.
+
+use llm_test_bench_core::providers::{
+    AnthropicProvider, CompletionRequest, FinishReason, GoogleProvider, MistralProvider, OllamaProvider,
+    OpenAIProvider, Provider, ProviderError,
+};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_openai_multiple_completions() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({
+            "seed": 42,
+            "n": 2,
+            "frequency_penalty": 0.5,
+            "logit_bias": { "50256": -100.0 }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "model": "gpt-4o",
+            "choices": [
+                { "index": 0, "message": { "role": "assistant", "content": "def f(): return 1" }, "finish_reason": "stop" },
+                { "index": 1, "message": { "role": "assistant", "content": "def f(): return" }, "finish_reason": "length" }
+            ],
+            "usage": { "prompt_tokens": 12, "completion_tokens": 14, "total_tokens": 26 }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("gpt-4o", "Write f")
+        .with_seed(42)
+        .with_n(2)
+        .with_frequency_penalty(0.5)
+        .with_logit_bias(50256, -100.0);
+    let response = provider.complete(request.clone()).await.unwrap();
+
+    assert_eq!(response.content, "def f(): return 1");
+    assert_eq!(response.choices.len(), 2);
+    assert_eq!(response.choices[1].index, 1);
+    assert_eq!(response.choices[1].finish_reason, FinishReason::Length);
+
+    // Streams carry one completion, and OpenAI has no top_k
+    assert!(matches!(provider.stream(request).await, Err(ProviderError::InvalidRequest(_))));
+    let request = CompletionRequest::new("gpt-4o", "Write f").with_top_k(40);
+    assert!(matches!(provider.complete(request).await, Err(ProviderError::InvalidRequest(_))));
+}
+
+#[tokio::test]
+async fn test_anthropic_rejects_seed() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .and(body_partial_json(json!({ "top_k": 5 })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_01",
+            "type": "message",
+            "role": "assistant",
+            "content": [{ "type": "text", "text": "Hello" }],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "end_turn",
+            "usage": { "input_tokens": 10, "output_tokens": 2 }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = AnthropicProvider::with_base_url("test-key".to_string(), mock_server.uri());
+    let request = CompletionRequest::new("claude-3-5-sonnet-20241022", "Hi").with_top_k(5);
+    assert_eq!(provider.complete(request).await.unwrap().content, "Hello");
+
+    let request = CompletionRequest::new("claude-3-5-sonnet-20241022", "Hi").with_seed(7);
+    match provider.complete(request).await {
+        Err(ProviderError::InvalidRequest(msg)) => assert!(msg.contains("seed")),
+        other => panic!("Expected InvalidRequest, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_google_candidates() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/models/gemini-1.5-pro:generateContent"))
+        .and(body_partial_json(json!({
+            "generationConfig": { "seed": 1, "topK": 20, "candidateCount": 2, "presencePenalty": 0.25 }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [
+                { "content": { "role": "model", "parts": [{ "text": "A" }] }, "finishReason": "STOP" },
+                { "content": { "role": "model", "parts": [{ "text": "B" }] }, "finishReason": "MAX_TOKENS" }
+            ],
+            "usageMetadata": { "promptTokenCount": 4, "candidatesTokenCount": 2, "totalTokenCount": 6 }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = GoogleProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("gemini-1.5-pro", "Pick a letter")
+        .with_seed(1)
+        .with_top_k(20)
+        .with_n(2)
+        .with_presence_penalty(0.25);
+    let response = provider.complete(request).await.unwrap();
+
+    assert_eq!(response.content, "A");
+    let contents: Vec<_> = response.choices.iter().map(|c| c.content.as_str()).collect();
+    assert_eq!(contents, ["A", "B"]);
+}
+
+#[tokio::test]
+async fn test_mistral_random_seed_and_ollama_options() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/mistral/chat/completions"))
+        .and(body_partial_json(json!({ "random_seed": 9, "presence_penalty": 0.5 })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "cmpl-1",
+            "model": "mistral-small-latest",
+            "choices": [{ "index": 0, "message": { "role": "assistant", "content": "Hi" }, "finish_reason": "stop" }],
+            "usage": { "prompt_tokens": 3, "completion_tokens": 1, "total_tokens": 4 }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/generate"))
+        .and(body_partial_json(json!({ "options": { "seed": 9, "top_k": 10 } })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "model": "llama3",
+            "response": "Hi",
+            "done": true,
+            "prompt_eval_count": 3,
+            "eval_count": 1
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mistral = MistralProvider::with_base_url("test-key".to_string(), format!("{}/mistral", mock_server.uri())).unwrap();
+    let request = CompletionRequest::new("mistral-small-latest", "Hi").with_seed(9).with_presence_penalty(0.5);
+    let response = mistral.complete(request).await.unwrap();
+    assert!(response.choices.is_empty());
+
+    let ollama = OllamaProvider::with_base_url(mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("llama3", "Hi").with_seed(9).with_top_k(10);
+    assert_eq!(ollama.complete(request).await.unwrap().content, "Hi");
+
+    // Ollama generates one completion per request
+    let request = CompletionRequest::new("llama3", "Hi").with_n(4);
+    assert!(matches!(ollama.complete(request).await, Err(ProviderError::InvalidRequest(_))));
+}
@@ -0,0 +1,438 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for streaming events
+//!
+//! Each provider's wire format (OpenAI and Anthropic SSE, Gemini SSE, Ollama
+//! JSON lines, Hugging Face TGI and Replicate SSE) is served by a mock server and decoded into typed events,
+//! which are then assembled into a response with latency metrics.
+
+use futures::StreamExt;
+use llm_test_bench_core::providers::{
+    AnthropicProvider, CompletionRequest, FinishReason, GoogleProvider, HuggingFaceProvider, OllamaProvider,
+    OpenAIProvider, Provider, ProviderError, ReplicateProvider, StreamAccumulator, StreamEvent, StreamEventKind, TokenUsage, ToolCall,
+};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Render server-sent events, one `data:` payload per event
+fn sse(events: &[serde_json::Value]) -> String {
+    events.iter().map(|event| format!("data: {}\n\n", event)).collect()
+}
+
+/// Render named server-sent events as sent by Anthropic
+fn named_sse(events: &[serde_json::Value]) -> String {
+    events
+        .iter()
+        .map(|event| format!("event: {}\ndata: {}\n\n", event["type"].as_str().unwrap(), event))
+        .collect()
+}
+
+fn sse_response(body: String) -> ResponseTemplate {
+    ResponseTemplate::new(200)
+        .insert_header("content-type", "text/event-stream")
+        .set_body_string(body)
+}
+
+#[tokio::test]
+async fn test_openai_stream_events() {
+    let mock_server = MockServer::start().await;
+
+    let chunk = |delta: serde_json::Value, finish_reason: serde_json::Value| {
+        json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }]
+        })
+    };
+    let mut body = sse(&[
+        chunk(json!({ "role": "assistant", "content": "" }), json!(null)),
+        chunk(json!({ "content": "Checking" }), json!(null)),
+        chunk(
+            json!({ "tool_calls": [{
+                "index": 0,
+                "id": "call_abc",
+                "type": "function",
+                "function": { "name": "get_weather", "arguments": "" }
+            }] }),
+            json!(null),
+        ),
+        chunk(json!({ "tool_calls": [{ "index": 0, "function": { "arguments": "{\"city\":" } }] }), json!(null)),
+        chunk(json!({ "tool_calls": [{ "index": 0, "function": { "arguments": "\"Paris\"}" } }] }), json!(null)),
+        chunk(json!({}), json!("tool_calls")),
+        json!({
+            "id": "chatcmpl-1",
+            "choices": [],
+            "usage": { "prompt_tokens": 42, "completion_tokens": 9, "total_tokens": 51 }
+        }),
+    ]);
+    body.push_str("data: [DONE]\n\n");
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({ "stream": true, "stream_options": { "include_usage": true } })))
+        .respond_with(sse_response(body))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("gpt-4o", "Weather in Paris?").with_streaming();
+
+    let mut accumulator = StreamAccumulator::new("gpt-4o");
+    let mut stream = provider.stream(request).await.unwrap();
+    let mut kinds = Vec::new();
+    while let Some(event) = stream.next().await {
+        let event = event.unwrap();
+        accumulator.push(&event);
+        kinds.push(event.kind);
+    }
+
+    assert_eq!(kinds.len(), 6);
+    assert_eq!(kinds[0], StreamEventKind::ContentDelta { text: "Checking".to_string() });
+    assert!(matches!(&kinds[1], StreamEventKind::ToolCallDelta(delta) if delta.name.as_deref() == Some("get_weather")));
+    assert_eq!(kinds[4], StreamEvent::finish(FinishReason::ToolCalls).kind);
+    assert_eq!(kinds[5], StreamEventKind::Usage(TokenUsage::new(42, 9)));
+
+    let metrics = accumulator.metrics();
+    assert!(metrics.time_to_first_token_ms.is_some());
+    assert_eq!(metrics.chunks, 4);
+    assert_eq!(metrics.completion_tokens, 9);
+
+    let response = accumulator.into_response();
+    assert_eq!(response.content, "Checking");
+    assert_eq!(response.tool_calls, vec![ToolCall::new("call_abc", "get_weather", json!({ "city": "Paris" }))]);
+    assert_eq!(response.usage.total_tokens, 51);
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+}
+
+#[tokio::test]
+async fn test_openai_stream_error_status() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "error": { "message": "Incorrect API key provided", "type": "invalid_request_error" }
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("bad-key".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("gpt-4o", "Hello").with_streaming();
+
+    assert!(matches!(
+        provider.stream(request).await,
+        Err(ProviderError::InvalidApiKey | ProviderError::AuthenticationError(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_anthropic_stream_events() {
+    let mock_server = MockServer::start().await;
+
+    let body = named_sse(&[
+        json!({
+            "type": "message_start",
+            "message": {
+                "id": "msg_1",
+                "type": "message",
+                "role": "assistant",
+                "content": [],
+                "model": "claude-3-5-sonnet-20241022",
+                "usage": { "input_tokens": 25, "output_tokens": 1 }
+            }
+        }),
+        json!({ "type": "content_block_start", "index": 0, "content_block": { "type": "text", "text": "" } }),
+        json!({ "type": "ping" }),
+        json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "Let me look." } }),
+        json!({ "type": "content_block_stop", "index": 0 }),
+        json!({
+            "type": "content_block_start",
+            "index": 1,
+            "content_block": { "type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {} }
+        }),
+        json!({
+            "type": "content_block_delta",
+            "index": 1,
+            "delta": { "type": "input_json_delta", "partial_json": "{\"city\": \"Par" }
+        }),
+        json!({
+            "type": "content_block_delta",
+            "index": 1,
+            "delta": { "type": "input_json_delta", "partial_json": "is\"}" }
+        }),
+        json!({ "type": "content_block_stop", "index": 1 }),
+        json!({
+            "type": "message_delta",
+            "delta": { "stop_reason": "tool_use", "stop_sequence": null },
+            "usage": { "output_tokens": 30 }
+        }),
+        json!({ "type": "message_stop" }),
+    ]);
+
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .and(body_partial_json(json!({ "stream": true })))
+        .respond_with(sse_response(body))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = AnthropicProvider::with_base_url("test-key".to_string(), mock_server.uri());
+    let request = CompletionRequest::new("claude-3-5-sonnet-20241022", "Weather in Paris?").with_streaming();
+
+    let stream = provider.stream(request).await.unwrap();
+    let (response, metrics) = StreamAccumulator::collect("claude-3-5-sonnet-20241022", stream).await.unwrap();
+
+    assert_eq!(response.content, "Let me look.");
+    assert_eq!(response.tool_calls, vec![ToolCall::new("toolu_1", "get_weather", json!({ "city": "Paris" }))]);
+    assert_eq!(response.usage, TokenUsage::new(25, 30));
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(metrics.chunks, 4);
+}
+
+#[tokio::test]
+async fn test_anthropic_stream_error_event() {
+    let mock_server = MockServer::start().await;
+
+    let body = named_sse(&[
+        json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "Hi" } }),
+        json!({ "type": "error", "error": { "type": "overloaded_error", "message": "Overloaded" } }),
+    ]);
+
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .respond_with(sse_response(body))
+        .mount(&mock_server)
+        .await;
+
+    let provider = AnthropicProvider::with_base_url("test-key".to_string(), mock_server.uri());
+    let request = CompletionRequest::new("claude-3-5-sonnet-20241022", "Hello").with_streaming();
+
+    let mut stream = provider.stream(request).await.unwrap();
+    assert_eq!(stream.next().await.unwrap().unwrap().text(), Some("Hi"));
+    assert!(matches!(stream.next().await, Some(Err(ProviderError::ApiError { status: 529, .. }))));
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_google_stream_events() {
+    let mock_server = MockServer::start().await;
+
+    let body = sse(&[
+        json!({ "candidates": [{ "content": { "role": "model", "parts": [{ "text": "Blue" }] } }] }),
+        json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": " sky." }] }, "finishReason": "STOP" }],
+            "usageMetadata": { "promptTokenCount": 6, "candidatesTokenCount": 3, "totalTokenCount": 9 }
+        }),
+    ]);
+
+    Mock::given(method("POST"))
+        .and(path("/models/gemini-1.5-pro:streamGenerateContent"))
+        .respond_with(sse_response(body))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = GoogleProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("gemini-1.5-pro", "What color is the sky?").with_streaming();
+
+    let stream = provider.stream(request).await.unwrap();
+    let (response, _) = StreamAccumulator::collect("gemini-1.5-pro", stream).await.unwrap();
+
+    assert_eq!(response.content, "Blue sky.");
+    assert_eq!(response.usage.total_tokens, 9);
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+}
+
+#[tokio::test]
+async fn test_ollama_stream_events() {
+    let mock_server = MockServer::start().await;
+
+    let body = [
+        json!({ "model": "llama3.2", "response": "The", "done": false }),
+        json!({ "model": "llama3.2", "response": " end", "done": false }),
+        json!({
+            "model": "llama3.2",
+            "response": "",
+            "done": true,
+            "done_reason": "length",
+            "prompt_eval_count": 11,
+            "eval_count": 2
+        }),
+    ]
+    .iter()
+    .map(|line| format!("{}\n", line))
+    .collect::<String>();
+
+    Mock::given(method("POST"))
+        .and(path("/api/generate"))
+        .and(body_partial_json(json!({ "stream": true })))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "application/x-ndjson")
+                .set_body_string(body),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OllamaProvider::with_base_url(mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("llama3.2", "Finish the story").with_streaming();
+
+    let stream = provider.stream(request).await.unwrap();
+    let (response, metrics) = StreamAccumulator::collect("llama3.2", stream).await.unwrap();
+
+    assert_eq!(response.content, "The end");
+    assert_eq!(response.usage, TokenUsage::new(11, 2));
+    assert_eq!(response.finish_reason, FinishReason::Length);
+    assert_eq!(metrics.chunks, 2);
+    assert!(metrics.inter_token_latency_ms.is_some());
+}
+
+#[tokio::test]
+async fn test_huggingface_stream_events() {
+    let mock_server = MockServer::start().await;
+
+    let token = |id: u32, text: &str, special: bool| {
+        json!({ "id": id, "text": text, "logprob": -0.1, "special": special })
+    };
+    let body = sse(&[
+        json!({ "index": 1, "token": token(415, "The", false), "generated_text": null, "details": null }),
+        json!({ "index": 2, "token": token(948, " end", false), "generated_text": null, "details": null }),
+        json!({
+            "index": 3,
+            "token": token(2, "</s>", true),
+            "generated_text": "The end",
+            "details": { "finish_reason": "eos_token", "generated_tokens": 3, "seed": null }
+        }),
+    ]);
+
+    Mock::given(method("POST"))
+        .and(path("/mistralai/Mistral-7B-Instruct-v0.2"))
+        .and(header("authorization", "Bearer test-key"))
+        .and(body_partial_json(json!({ "stream": true, "parameters": { "max_new_tokens": 16 } })))
+        .respond_with(sse_response(body))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = HuggingFaceProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("mistralai/Mistral-7B-Instruct-v0.2", "Finish the story")
+        .with_max_tokens(16)
+        .with_streaming();
+
+    let stream = provider.stream(request).await.unwrap();
+    let (response, metrics) = StreamAccumulator::collect("mistralai/Mistral-7B-Instruct-v0.2", stream).await.unwrap();
+
+    // The end-of-sequence token is counted but not part of the text
+    assert_eq!(response.content, "The end");
+    assert_eq!(response.usage.completion_tokens, 3);
+    assert!(response.usage.prompt_tokens > 0);
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+    assert_eq!(metrics.chunks, 2);
+}
+
+#[tokio::test]
+async fn test_huggingface_stream_error_event() {
+    let mock_server = MockServer::start().await;
+
+    let body = sse(&[
+        json!({ "index": 1, "token": { "id": 415, "text": "The", "logprob": -0.1, "special": false }, "details": null }),
+        json!({ "error": "Request failed during generation: Server error: CUDA out of memory", "error_type": "generation" }),
+    ]);
+
+    Mock::given(method("POST"))
+        .and(path("/bigcode/starcoder"))
+        .respond_with(sse_response(body))
+        .mount(&mock_server)
+        .await;
+
+    let provider = HuggingFaceProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("bigcode/starcoder", "def fib(n):").with_streaming();
+
+    let mut stream = provider.stream(request).await.unwrap();
+    assert_eq!(stream.next().await.unwrap().unwrap().text(), Some("The"));
+    match stream.next().await {
+        Some(Err(ProviderError::ApiError { message, .. })) => assert!(message.contains("CUDA out of memory")),
+        other => panic!("Expected ApiError, got {:?}", other),
+    }
+    assert!(stream.next().await.is_none());
+}
+
+/// Mount a prediction whose output is streamed from `/stream/{id}`
+async fn mount_replicate_prediction(mock_server: &MockServer, id: &str, events: String) {
+    Mock::given(method("POST"))
+        .and(path("/predictions"))
+        .and(header("authorization", "Token test-key"))
+        .and(body_partial_json(json!({ "stream": true })))
+        .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+            "id": id,
+            "status": "starting",
+            "urls": {
+                "get": format!("{}/predictions/{}", mock_server.uri(), id),
+                "cancel": format!("{}/predictions/{}/cancel", mock_server.uri(), id),
+                "stream": format!("{}/stream/{}", mock_server.uri(), id)
+            }
+        })))
+        .expect(1)
+        .mount(mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path(format!("/stream/{}", id)))
+        .and(header("accept", "text/event-stream"))
+        .respond_with(sse_response(events))
+        .expect(1)
+        .mount(mock_server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_replicate_stream_events() {
+    let mock_server = MockServer::start().await;
+
+    // Output events carry raw text; a multi-line chunk spans several `data:` lines
+    let events = "event: output\nid: 1\ndata: Roses are red,\n\n\
+                  event: output\nid: 2\ndata: \ndata: violets are blue.\n\n\
+                  event: done\ndata: {}\n\n"
+        .to_string();
+    mount_replicate_prediction(&mock_server, "pred-1", events).await;
+
+    let provider = ReplicateProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("meta/llama-2-70b-chat", "Write a poem").with_streaming();
+
+    let stream = provider.stream(request).await.unwrap();
+    let (response, metrics) = StreamAccumulator::collect("meta/llama-2-70b-chat", stream).await.unwrap();
+
+    assert_eq!(response.content, "Roses are red,\nviolets are blue.");
+    assert!(response.usage.completion_tokens > 0);
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+    assert_eq!(metrics.chunks, 2);
+}
+
+#[tokio::test]
+async fn test_replicate_stream_error_event() {
+    let mock_server = MockServer::start().await;
+
+    let events = "event: output\ndata: Roses\n\n\
+                  event: error\ndata: {\"detail\": \"Prediction interrupted; please retry\"}\n\n"
+        .to_string();
+    mount_replicate_prediction(&mock_server, "pred-2", events).await;
+
+    let provider = ReplicateProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("meta/llama-2-70b-chat", "Write a poem").with_streaming();
+
+    let mut stream = provider.stream(request).await.unwrap();
+    assert_eq!(stream.next().await.unwrap().unwrap().text(), Some("Roses"));
+    match stream.next().await {
+        Some(Err(ProviderError::ApiError { message, .. })) => assert_eq!(message, "Prediction interrupted; please retry"),
+        other => panic!("Expected ApiError, got {:?}", other),
+    }
+    assert!(stream.next().await.is_none());
+}
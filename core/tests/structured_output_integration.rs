@@ -0,0 +1,239 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for structured output (`response_format`)
+//!
+//! Providers with a native JSON mode are checked for the parameter they send;
+//! Anthropic is checked for prompt-based enforcement and the repair retry.
+
+use llm_test_bench_core::providers::{
+    AnthropicProvider, CompletionRequest, GoogleProvider, OllamaProvider, OpenAIProvider, Provider,
+    ResponseFormat,
+};
+use serde::Deserialize;
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Person {
+    name: String,
+    age: u32,
+}
+
+fn person_format() -> ResponseFormat {
+    ResponseFormat::json_schema(
+        "person",
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "age": { "type": "integer" }
+            },
+            "required": ["name", "age"],
+            "additionalProperties": false
+        }),
+    )
+}
+
+fn person_request(model: &str) -> CompletionRequest {
+    CompletionRequest::new(model, "Extract the person: Ada Lovelace, aged 36.")
+        .with_response_format(person_format())
+}
+
+fn openai_response(content: &str) -> serde_json::Value {
+    json!({
+        "id": "chatcmpl-123",
+        "object": "chat.completion",
+        "model": "gpt-4o",
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": content },
+            "finish_reason": "stop"
+        }],
+        "usage": { "prompt_tokens": 30, "completion_tokens": 10, "total_tokens": 40 }
+    })
+}
+
+fn claude_response(text: &str) -> serde_json::Value {
+    json!({
+        "id": "msg_01",
+        "type": "message",
+        "role": "assistant",
+        "content": [{ "type": "text", "text": text }],
+        "model": "claude-3-5-sonnet-20241022",
+        "stop_reason": "end_turn",
+        "usage": { "input_tokens": 50, "output_tokens": 10 }
+    })
+}
+
+#[tokio::test]
+async fn test_openai_json_schema() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({
+            "response_format": {
+                "type": "json_schema",
+                "json_schema": {
+                    "name": "person",
+                    "strict": true,
+                    "schema": { "required": ["name", "age"] }
+                }
+            }
+        })))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(openai_response(r#"{"name":"Ada Lovelace","age":36}"#)),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let response = provider.complete(person_request("gpt-4o")).await.unwrap();
+
+    assert!(response.schema_violations.is_empty());
+    assert_eq!(
+        response.json::<Person>().unwrap(),
+        Person { name: "Ada Lovelace".to_string(), age: 36 }
+    );
+}
+
+#[tokio::test]
+async fn test_openai_reports_schema_violations() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({ "response_format": { "type": "json_object" } })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_response("[1, 2, 3]")))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("gpt-4o", "List three numbers")
+        .with_response_format(ResponseFormat::JsonObject);
+    let response = provider.complete(request).await.unwrap();
+
+    assert_eq!(response.schema_violations.len(), 1);
+    assert_eq!(response.schema_violations[0].message, "Expected a JSON object");
+}
+
+#[tokio::test]
+async fn test_anthropic_prompt_enforcement_and_repair() {
+    let mock_server = MockServer::start().await;
+
+    // First attempt: the schema is in the system prompt, the answer is incomplete
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .and(body_string_contains("conforms to the following JSON Schema"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(claude_response(
+            "Sure! ```json\n{\"name\": \"Ada Lovelace\"}\n```",
+        )))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    // Repair: the previous answer and the violation are sent back
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .and(body_string_contains("did not match the required format"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(claude_response(r#"{"name": "Ada Lovelace", "age": 36}"#)),
+        )
+        .with_priority(1)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = AnthropicProvider::with_base_url("test-key".to_string(), mock_server.uri());
+    let response = provider
+        .complete(person_request("claude-3-5-sonnet-20241022"))
+        .await
+        .unwrap();
+
+    assert!(response.schema_violations.is_empty());
+    assert_eq!(response.json::<Person>().unwrap().age, 36);
+    // Usage covers both attempts
+    assert_eq!(response.usage.prompt_tokens, 100);
+    assert_eq!(response.usage.completion_tokens, 20);
+
+    let requests = mock_server.received_requests().await.unwrap();
+    let repair: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    let messages = repair["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 3);
+    assert_eq!(messages[0]["content"], "Extract the person: Ada Lovelace, aged 36.");
+    assert_eq!(messages[1]["role"], "assistant");
+    assert_eq!(messages[1]["content"], "{\"name\": \"Ada Lovelace\"}");
+    assert!(messages[2]["content"].as_str().unwrap().contains("\"age\" is a required property"));
+}
+
+#[tokio::test]
+async fn test_google_response_schema() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/models/gemini-1.5-pro:generateContent"))
+        .and(body_partial_json(json!({
+            "generationConfig": {
+                "responseMimeType": "application/json",
+                "responseSchema": {
+                    "type": "object",
+                    "properties": { "age": { "type": "integer" } },
+                    "required": ["name", "age"]
+                }
+            }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{
+                "content": { "role": "model", "parts": [{ "text": "{\"name\": \"Ada Lovelace\", \"age\": 36}" }] },
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": { "promptTokenCount": 20, "candidatesTokenCount": 8, "totalTokenCount": 28 }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = GoogleProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let response = provider.complete(person_request("gemini-1.5-pro")).await.unwrap();
+
+    assert!(response.schema_violations.is_empty());
+
+    // Gemini rejects `additionalProperties`, so it must not be sent
+    let requests = mock_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert!(body["generationConfig"]["responseSchema"].get("additionalProperties").is_none());
+}
+
+#[tokio::test]
+async fn test_ollama_format() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/generate"))
+        .and(body_partial_json(json!({ "format": "json" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "model": "llama3",
+            "response": "{\"name\": 5}",
+            "done": true,
+            "prompt_eval_count": 12,
+            "eval_count": 6
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OllamaProvider::with_base_url(mock_server.uri()).unwrap();
+    let request = CompletionRequest::new("llama3", "Who?").with_response_format(ResponseFormat::JsonObject);
+    let response = provider.complete(request).await.unwrap();
+
+    // Valid JSON object, so JSON mode is satisfied even though the shape is odd
+    assert!(response.schema_violations.is_empty());
+}
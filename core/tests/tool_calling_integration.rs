@@ -0,0 +1,273 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for tool calling across providers
+//!
+//! Each test mocks the provider's API with wiremock, checks that tool
+//! definitions and tool choice are sent in the provider's native format, and
+//! that tool calls in the response are mapped back to `ToolCall`s.
+
+use llm_test_bench_core::providers::{
+    AnthropicProvider, CompletionRequest, FinishReason, GoogleProvider, GroqProvider,
+    MistralProvider, OpenAIProvider, Provider, ToolChoice, ToolDefinition,
+};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn weather_tool() -> ToolDefinition {
+    ToolDefinition::new(
+        "get_weather",
+        "Get the current weather for a city",
+        json!({
+            "type": "object",
+            "properties": { "city": { "type": "string" } },
+            "required": ["city"]
+        }),
+    )
+}
+
+fn tool_request(model: &str, choice: ToolChoice) -> CompletionRequest {
+    CompletionRequest::new(model, "What's the weather in Paris?")
+        .with_tools(vec![weather_tool()])
+        .with_tool_choice(choice)
+}
+
+// OpenAI-compatible chat completion response containing a single tool call
+fn openai_style_tool_call_response(model: &str) -> serde_json::Value {
+    json!({
+        "id": "chatcmpl-123",
+        "object": "chat.completion",
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": {
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [{
+                    "id": "call_abc",
+                    "type": "function",
+                    "function": {
+                        "name": "get_weather",
+                        "arguments": "{\"city\":\"Paris\"}"
+                    }
+                }]
+            },
+            "finish_reason": "tool_calls"
+        }],
+        "usage": { "prompt_tokens": 20, "completion_tokens": 10, "total_tokens": 30 }
+    })
+}
+
+#[tokio::test]
+async fn test_openai_tool_calling() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({
+            "tools": [{
+                "type": "function",
+                "function": {
+                    "name": "get_weather",
+                    "description": "Get the current weather for a city",
+                    "parameters": { "required": ["city"] }
+                }
+            }],
+            "tool_choice": "required"
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_style_tool_call_response("gpt-4")))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let response = provider.complete(tool_request("gpt-4", ToolChoice::Required)).await.unwrap();
+
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(response.content, "");
+    assert_eq!(response.tool_calls.len(), 1);
+    assert_eq!(response.tool_calls[0].id, "call_abc");
+    assert_eq!(response.tool_calls[0].name, "get_weather");
+    assert_eq!(response.tool_calls[0].arguments, json!({"city": "Paris"}));
+}
+
+#[tokio::test]
+async fn test_openai_forced_tool_choice() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({
+            "tool_choice": { "type": "function", "function": { "name": "get_weather" } }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_style_tool_call_response("gpt-4")))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = OpenAIProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let response = provider
+        .complete(tool_request("gpt-4", ToolChoice::tool("get_weather")))
+        .await
+        .unwrap();
+
+    assert_eq!(response.tool_calls[0].name, "get_weather");
+}
+
+#[tokio::test]
+async fn test_mistral_tool_calling() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({
+            "tools": [{ "type": "function", "function": { "name": "get_weather" } }],
+            "tool_choice": "any"
+        })))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(openai_style_tool_call_response("mistral-large-latest")),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = MistralProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let response = provider
+        .complete(tool_request("mistral-large-latest", ToolChoice::Required))
+        .await
+        .unwrap();
+
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(response.tool_calls.len(), 1);
+    assert_eq!(response.tool_calls[0].arguments["city"], "Paris");
+}
+
+#[tokio::test]
+async fn test_groq_tool_calling() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({
+            "tools": [{ "type": "function", "function": { "name": "get_weather" } }],
+            "tool_choice": "auto"
+        })))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(openai_style_tool_call_response("llama3-70b-8192")),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = GroqProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let response = provider
+        .complete(tool_request("llama3-70b-8192", ToolChoice::Auto))
+        .await
+        .unwrap();
+
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(response.tool_calls.len(), 1);
+    assert_eq!(response.tool_calls[0].id, "call_abc");
+}
+
+#[tokio::test]
+async fn test_anthropic_tool_calling() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .and(body_partial_json(json!({
+            "tools": [{
+                "name": "get_weather",
+                "description": "Get the current weather for a city",
+                "input_schema": { "required": ["city"] }
+            }],
+            "tool_choice": { "type": "tool", "name": "get_weather" }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_01",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                { "type": "text", "text": "Let me check." },
+                {
+                    "type": "tool_use",
+                    "id": "toolu_01",
+                    "name": "get_weather",
+                    "input": { "city": "Paris" }
+                }
+            ],
+            "model": "claude-3-5-sonnet-20241022",
+            "stop_reason": "tool_use",
+            "usage": { "input_tokens": 25, "output_tokens": 12 }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = AnthropicProvider::with_base_url("test-key".to_string(), mock_server.uri());
+    let response = provider
+        .complete(tool_request("claude-3-5-sonnet-20241022", ToolChoice::tool("get_weather")))
+        .await
+        .unwrap();
+
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(response.content, "Let me check.");
+    assert_eq!(response.tool_calls.len(), 1);
+    assert_eq!(response.tool_calls[0].id, "toolu_01");
+    assert_eq!(response.tool_calls[0].arguments, json!({"city": "Paris"}));
+}
+
+#[tokio::test]
+async fn test_google_tool_calling() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/models/gemini-pro:generateContent"))
+        .and(body_partial_json(json!({
+            "tools": [{
+                "functionDeclarations": [{
+                    "name": "get_weather",
+                    "description": "Get the current weather for a city"
+                }]
+            }],
+            "toolConfig": { "functionCallingConfig": { "mode": "ANY" } }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [{
+                        "functionCall": { "name": "get_weather", "args": { "city": "Paris" } }
+                    }]
+                },
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {
+                "promptTokenCount": 18,
+                "candidatesTokenCount": 6,
+                "totalTokenCount": 24
+            }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = GoogleProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let response = provider
+        .complete(tool_request("gemini-pro", ToolChoice::Required))
+        .await
+        .unwrap();
+
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(response.tool_calls.len(), 1);
+    assert_eq!(response.tool_calls[0].id, "call_0");
+    assert_eq!(response.tool_calls[0].name, "get_weather");
+    assert_eq!(response.tool_calls[0].arguments["city"], "Paris");
+}
@@ -0,0 +1,221 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for speech-to-text
+//!
+//! Each transcription backend is served by wiremock; the last test runs a
+//! speech benchmark over a small dataset against it.
+
+use llm_test_bench_core::multimodal::{
+    AudioFormat, AudioInput, MultiModalContent, MultiModalDataset, MultiModalExample, MultiModalTask,
+    SpeechBenchmark, TranscriptionOptions,
+};
+use llm_test_bench_core::providers::{
+    GoogleSpeechProvider, ProviderError, TranscriptionProvider, WhisperProvider,
+};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, body_string_contains, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+// Base64 of "RIFF"
+const WAV: &str = "UklGRg==";
+
+#[tokio::test]
+async fn test_whisper_multipart_upload() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/audio/transcriptions"))
+        .and(header("authorization", "Bearer test-key"))
+        .and(body_string_contains("name=\"file\"; filename=\"audio.wav\""))
+        .and(body_string_contains("RIFF"))
+        .and(body_string_contains("whisper-1"))
+        .and(body_string_contains("name=\"language\"\r\n\r\nfr"))
+        .and(body_string_contains("name=\"prompt\"\r\n\r\nBonjour"))
+        .and(body_string_contains("name=\"response_format\"\r\n\r\nverbose_json"))
+        .and(body_string_contains("name=\"timestamp_granularities[]\"\r\n\r\nword"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "task": "transcribe",
+            "language": "french",
+            "duration": 1.5,
+            "text": " Bonjour le monde.",
+            "words": [
+                { "word": "Bonjour", "start": 0.0, "end": 0.6 },
+                { "word": "le", "start": 0.6, "end": 0.8 },
+                { "word": "monde", "start": 0.8, "end": 1.4 }
+            ]
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = WhisperProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let options = TranscriptionOptions {
+        language: Some("fr".to_string()),
+        prompt: Some("Bonjour".to_string()),
+        word_timestamps: true,
+        ..Default::default()
+    };
+    let audio = AudioInput::from_base64(WAV.to_string(), AudioFormat::Wav);
+
+    let transcription = provider.transcribe("whisper-1", &audio, &options).await.unwrap();
+    assert_eq!(transcription.text, "Bonjour le monde.");
+    assert_eq!(transcription.language.as_deref(), Some("french"));
+    assert_eq!(transcription.words.len(), 3);
+    assert_eq!(transcription.duration, Some(1.5));
+}
+
+#[tokio::test]
+async fn test_whisper_text_format_and_errors() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/audio/transcriptions"))
+        .and(body_string_contains("name=\"response_format\"\r\n\r\ntext"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("Hello world.\n"))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/bad/audio/transcriptions"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "error": { "message": "Incorrect API key provided", "type": "invalid_request_error" }
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let audio = AudioInput::from_base64(WAV.to_string(), AudioFormat::Wav);
+    let options = TranscriptionOptions {
+        response_format: Some("text".to_string()),
+        ..Default::default()
+    };
+
+    let provider = WhisperProvider::with_base_url("key".to_string(), format!("{}/v1", mock_server.uri())).unwrap();
+    let transcription = provider.transcribe("whisper-large-v3", &audio, &options).await.unwrap();
+    assert_eq!(transcription.text, "Hello world.");
+
+    let provider = WhisperProvider::with_base_url("key".to_string(), format!("{}/bad", mock_server.uri())).unwrap();
+    let result = provider.transcribe("whisper-1", &audio, &options).await;
+    assert!(matches!(result, Err(ProviderError::InvalidApiKey)));
+}
+
+#[tokio::test]
+async fn test_whisper_cpp_server() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/inference"))
+        .and(body_string_contains("name=\"temperature\"\r\n\r\n0"))
+        .and(body_string_contains("name=\"response_format\"\r\n\r\nverbose_json"))
+        .and(body_string_contains("name=\"timestamp_granularities[]\"\r\n\r\nsegment"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "task": "transcribe",
+            "language": "english",
+            "duration": 2.0,
+            "text": " And so my fellow Americans",
+            "segments": [{ "id": 0, "start": 0.0, "end": 2.0, "text": " And so my fellow Americans" }]
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = WhisperProvider::whisper_cpp(mock_server.uri()).unwrap();
+    assert_eq!(provider.name(), "whisper.cpp");
+
+    let options = TranscriptionOptions { timestamps: true, ..Default::default() };
+    let audio = AudioInput::from_base64(WAV.to_string(), AudioFormat::Wav);
+    let transcription = provider.transcribe("ggml-base.en", &audio, &options).await.unwrap();
+    assert_eq!(transcription.text, "And so my fellow Americans");
+    assert_eq!(transcription.segments[0].end, 2.0);
+}
+
+#[tokio::test]
+async fn test_google_speech_recognize() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/speech:recognize"))
+        .and(query_param("key", "test-key"))
+        .and(body_partial_json(json!({
+            "config": {
+                "languageCode": "en-GB",
+                "model": "latest_long",
+                "enableWordTimeOffsets": true,
+                "encoding": "FLAC",
+                "speechContexts": [{ "phrases": ["Cotswolds"] }]
+            },
+            "audio": { "content": "ZkxhQw==" }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": [{
+                "alternatives": [{
+                    "transcript": "walking in the Cotswolds",
+                    "confidence": 0.93,
+                    "words": [
+                        { "startTime": "0s", "endTime": "0.400s", "word": "walking" },
+                        { "startTime": "0.400s", "endTime": "0.500s", "word": "in" },
+                        { "startTime": "0.500s", "endTime": "0.600s", "word": "the" },
+                        { "startTime": "0.600s", "endTime": "1.300s", "word": "Cotswolds" }
+                    ]
+                }],
+                "resultEndTime": "1.400s",
+                "languageCode": "en-gb"
+            }],
+            "totalBilledTime": "2s"
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let provider = GoogleSpeechProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let options = TranscriptionOptions {
+        language: Some("en-GB".to_string()),
+        prompt: Some("Cotswolds".to_string()),
+        word_timestamps: true,
+        ..Default::default()
+    };
+    let audio = AudioInput::from_base64("ZkxhQw==".to_string(), AudioFormat::Flac);
+
+    let transcription = provider.transcribe("latest_long", &audio, &options).await.unwrap();
+    assert_eq!(transcription.text, "walking in the Cotswolds");
+    assert_eq!(transcription.words[3].end, 1.3);
+    // Segments were not requested
+    assert!(transcription.segments.is_empty());
+}
+
+#[tokio::test]
+async fn test_speech_benchmark_per_model() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/audio/transcriptions"))
+        .and(body_string_contains("whisper-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "text": "The quick brown fox." })))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/audio/transcriptions"))
+        .and(body_string_contains("whisper-tiny"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "text": "the quick brown box" })))
+        .mount(&mock_server)
+        .await;
+
+    let mut content = MultiModalContent::new();
+    content.add_audio(AudioInput::from_base64(WAV.to_string(), AudioFormat::Wav));
+    let mut dataset = MultiModalDataset::new("fox");
+    dataset.add_example(MultiModalExample::new("fox-1", MultiModalTask::SpeechToText, content, "The quick brown fox"));
+
+    let provider = WhisperProvider::with_base_url("test-key".to_string(), mock_server.uri()).unwrap();
+    let report = SpeechBenchmark::new()
+        .run(&dataset, &[(&provider, "whisper-1"), (&provider, "whisper-tiny")])
+        .await;
+
+    assert_eq!(report.models.len(), 2);
+    assert_eq!(report.models[0].mean_wer, Some(0.0));
+    assert_eq!(report.models[1].mean_wer, Some(0.25));
+    assert!(report.models[1].mean_cer.unwrap() > 0.0);
+    assert_eq!(report.best_model().unwrap().model, "whisper-1");
+    assert_eq!(report.models[0].provider, "whisper");
+}
@@ -0,0 +1,153 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for per-provider HTTP transport settings
+//!
+//! Providers are built through the `ProviderFactory` with a `transport`
+//! section. A wiremock server checks the extra headers and requests routed
+//! through a proxy, and a bare TCP server checks the HTTP version.
+
+use llm_test_bench_core::config::models::{KeyRotation, ProviderConfig, TransportConfig};
+use llm_test_bench_core::providers::{CompletionRequest, ProviderError, ProviderFactory};
+use serde_json::json;
+use std::collections::HashMap;
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpListener;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const KEY_ENV: &str = "TRANSPORT_TEST_API_KEY";
+
+fn provider_config(kind: &str, base_url: String, transport: TransportConfig) -> ProviderConfig {
+    std::env::set_var(KEY_ENV, "test-key");
+    ProviderConfig {
+        api_key_env: KEY_ENV.to_string(),
+        api_key_file: None,
+        api_key_cmd: None,
+        api_keys_env: Vec::new(),
+        key_rotation: KeyRotation::default(),
+        azure_ad: false,
+        base_url,
+        default_model: "test-model".to_string(),
+        timeout_seconds: 30,
+        max_retries: 0,
+        rate_limit_rpm: None,
+        rate_limit_tpm: None,
+        fallback: Vec::new(),
+        circuit_breaker: None,
+        kind: Some(kind.to_string()),
+        headers: HashMap::new(),
+        models: Vec::new(),
+        context_length: None,
+        script: None,
+        transport,
+    }
+}
+
+fn chat_response() -> serde_json::Value {
+    json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "test-model",
+        "choices": [{ "index": 0, "message": { "role": "assistant", "content": "Hello!" }, "finish_reason": "stop" }],
+        "usage": { "prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7 }
+    })
+}
+
+#[tokio::test]
+async fn test_default_headers() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(header("openai-organization", "org-123"))
+        .and(header("openai-project", "proj-456"))
+        .and(header("authorization", "Bearer test-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(chat_response()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let transport = TransportConfig {
+        headers: HashMap::from([
+            ("OpenAI-Organization".to_string(), "org-123".to_string()),
+            ("OpenAI-Project".to_string(), "proj-456".to_string()),
+        ]),
+        ..TransportConfig::default()
+    };
+    let config = provider_config("openai", mock_server.uri(), transport);
+
+    let provider = ProviderFactory::new().create("openai", &config).unwrap();
+    let response = provider.complete(CompletionRequest::new("test-model", "Hi")).await.unwrap();
+    assert_eq!(response.content, "Hello!");
+}
+
+#[tokio::test]
+async fn test_requests_go_through_proxy() {
+    let proxy = MockServer::start().await;
+
+    // A plain-HTTP request through a proxy carries the target's absolute URL
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header("host", "llm.internal"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(chat_response()))
+        .expect(1)
+        .mount(&proxy)
+        .await;
+
+    let transport = TransportConfig {
+        proxy: Some(proxy.uri()),
+        ..TransportConfig::default()
+    };
+    let config = provider_config("mistral", "http://llm.internal/v1".to_string(), transport);
+
+    let provider = ProviderFactory::new().create("mistral", &config).unwrap();
+    let response = provider.complete(CompletionRequest::new("test-model", "Hi")).await.unwrap();
+    assert_eq!(response.content, "Hello!");
+}
+
+/// The first bytes a client sends to a bare TCP server
+async fn request_preface(http2: bool) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0; 24];
+        socket.read_exact(&mut buf).await.unwrap();
+        String::from_utf8_lossy(&buf).into_owned()
+    });
+
+    let transport = TransportConfig { http2: Some(http2), ..TransportConfig::default() };
+    let config = provider_config("groq", base_url, transport);
+    let provider = ProviderFactory::new().create("groq", &config).unwrap();
+
+    // The server hangs up without answering
+    assert!(provider.complete(CompletionRequest::new("test-model", "Hi")).await.is_err());
+    server.await.unwrap()
+}
+
+#[tokio::test]
+async fn test_http_version() {
+    assert!(request_preface(true).await.starts_with("PRI * HTTP/2.0"));
+    assert!(request_preface(false).await.starts_with("POST /chat/completions"));
+}
+
+#[test]
+fn test_invalid_transport_is_rejected() {
+    let transport = TransportConfig {
+        ca_bundle: Some("/nonexistent/corp-ca.pem".into()),
+        ..TransportConfig::default()
+    };
+    let config = provider_config("openai", "https://api.openai.com/v1".to_string(), transport);
+
+    match ProviderFactory::new().create("openai", &config) {
+        Err(ProviderError::InvalidRequest(msg)) => assert!(msg.contains("CA bundle")),
+        Err(e) => panic!("Expected InvalidRequest, got {}", e),
+        Ok(_) => panic!("Expected InvalidRequest"),
+    }
+}
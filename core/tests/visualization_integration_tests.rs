@@ -11,6 +11,7 @@ use llm_test_bench_core::providers::{CompletionResponse, FinishReason, TokenUsag
 use llm_test_bench_core::visualization::{
     ChartDataFormatter, DashboardConfig, DashboardGenerator, DashboardType, Theme,
 };
+use std::collections::HashMap;
 use std::time::Duration;
 
 // Helper to create test results
@@ -25,6 +26,12 @@ fn create_test_result(id: &str, latency_ms: u64) -> TestResult {
             usage: TokenUsage::new(100, 50),
             finish_reason: FinishReason::Stop,
             created_at: chrono::Utc::now(),
+            tool_calls: Vec::new(),
+            schema_violations: Vec::new(),
+            logprobs: None,
+            prompt_logprobs: None,
+            choices: Vec::new(),
+            metadata: HashMap::new(),
         },
         Duration::from_millis(latency_ms),
     )